@@ -0,0 +1,40 @@
+use bevy::{app::PluginGroupBuilder, prelude::*, window::close_on_esc};
+use bevy_vulkano::{BenchConfig, BenchPlugin, VulkanoWinitPlugin};
+
+pub struct PluginBundle;
+
+impl PluginGroup for PluginBundle {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<PluginBundle>()
+            // Minimum plugins for the bench
+            .add(bevy::log::LogPlugin::default())
+            .add(bevy::core::TaskPoolPlugin::default())
+            .add(bevy::core::TypeRegistrationPlugin)
+            .add(bevy::core::FrameCountPlugin)
+            .add(bevy::time::TimePlugin)
+            .add(bevy::input::InputPlugin)
+            .add(bevy::window::WindowPlugin::default())
+            .add(VulkanoWinitPlugin::default())
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(PluginBundle.set(WindowPlugin {
+            primary_window: Some(Window {
+                resolution: (512.0, 512.0).into(),
+                title: "Bevy Vulkano Bench".to_string(),
+                present_mode: bevy::window::PresentMode::Immediate,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins(BenchPlugin {
+            config: BenchConfig {
+                windows: 2,
+                ..default()
+            },
+        })
+        .add_systems(Update, close_on_esc)
+        .run();
+}
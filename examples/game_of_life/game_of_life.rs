@@ -10,25 +10,17 @@
 use std::sync::Arc;
 
 use bevy::{math::IVec2, prelude::Resource};
+use bevy_vulkano::ComputePass;
 use rand::Rng;
 use vulkano::{
     buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
     command_buffer::{
         allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
-        PrimaryAutoCommandBuffer,
-    },
-    descriptor_set::{
-        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
     },
     device::{DeviceOwned, Queue},
     format::Format,
     image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
     memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
-    pipeline::{
-        compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
-        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
-        PipelineShaderStageCreateInfo,
-    },
     sync::GpuFuture,
 };
 
@@ -41,8 +33,7 @@ use vulkano::{
 pub struct GameOfLifeComputePipeline {
     compute_queue: Arc<Queue>,
     command_buffer_allocator: StandardCommandBufferAllocator,
-    descriptor_set_allocator: StandardDescriptorSetAllocator,
-    compute_life_pipeline: Arc<ComputePipeline>,
+    compute_life_pass: ComputePass,
     life_in: Subbuffer<[u32]>,
     life_out: Subbuffer<[u32]>,
     image: Arc<ImageView>,
@@ -76,27 +67,14 @@ impl GameOfLifeComputePipeline {
         let life_in = rand_grid(allocator, size);
         let life_out = rand_grid(allocator, size);
 
-        let compute_life_pipeline = {
-            let cs = compute_life_cs::load(allocator.device().clone())
-                .unwrap()
-                .entry_point("main")
-                .unwrap();
-            let stage = PipelineShaderStageCreateInfo::new(cs);
-            let layout = PipelineLayout::new(
-                allocator.device().clone(),
-                PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
-                    .into_pipeline_layout_create_info(allocator.device().clone())
-                    .unwrap(),
-            )
-            .unwrap();
-
-            ComputePipeline::new(
-                allocator.device().clone(),
-                None,
-                ComputePipelineCreateInfo::stage_layout(stage, layout),
-            )
+        let cs = compute_life_cs::load(allocator.device().clone())
             .unwrap()
-        };
+            .entry_point("main")
+            .unwrap();
+        let mut compute_life_pass = ComputePass::new(compute_queue.clone(), cs, [8, 8, 1])
+            .with_binding("image", 0, 0)
+            .with_binding("life_in", 0, 1)
+            .with_binding("life_out", 0, 2);
 
         let image = ImageView::new_default(
             Image::new(
@@ -115,6 +93,7 @@ impl GameOfLifeComputePipeline {
             .unwrap(),
         )
         .unwrap();
+        compute_life_pass.set_image("image", image.clone());
 
         GameOfLifeComputePipeline {
             compute_queue,
@@ -122,11 +101,7 @@ impl GameOfLifeComputePipeline {
                 allocator.device().clone(),
                 Default::default(),
             ),
-            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
-                allocator.device().clone(),
-                Default::default(),
-            ),
-            compute_life_pipeline,
+            compute_life_pass,
             life_in,
             life_out,
             image,
@@ -160,14 +135,29 @@ impl GameOfLifeComputePipeline {
         )
         .unwrap();
 
+        // The descriptor set is only rebuilt when the bound buffers change
+        self.compute_life_pass
+            .set_buffer("life_in", self.life_in.clone());
+        self.compute_life_pass
+            .set_buffer("life_out", self.life_out.clone());
+
         // Dispatch will mutate the builder adding commands which won't be sent before we build the command buffer
         // after dispatches. This will minimize the commands we send to the GPU. For example, we could be doing
         // tens of dispatches here depending on our needs. Maybe we wanted to simulate 10 steps at a time...
-
-        // First compute the next state
-        self.dispatch(&mut builder, life_color, dead_color, 0);
-        // Then color based on the next state
-        self.dispatch(&mut builder, life_color, dead_color, 1);
+        let extent = self.image.image().extent();
+        // First compute the next state, then color based on the next state.
+        // Step determines whether we color or compute life (see branch in the shader)
+        for step in 0..2 {
+            self.compute_life_pass.record(
+                &mut builder,
+                extent,
+                Some(compute_life_cs::PushConstants {
+                    life_color,
+                    dead_color,
+                    step,
+                }),
+            );
+        }
 
         let command_buffer = builder.build().unwrap();
         let finished = before_future
@@ -180,47 +170,6 @@ impl GameOfLifeComputePipeline {
 
         after_pipeline
     }
-
-    /// Build the command for a dispatch.
-    fn dispatch(
-        &mut self,
-        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
-        life_color: [f32; 4],
-        dead_color: [f32; 4],
-        // Step determines whether we color or compute life (see branch in the shader)s
-        step: i32,
-    ) {
-        // Resize image if needed
-        let img_dims = self.image.image().extent();
-        let pipeline_layout = self.compute_life_pipeline.layout();
-        let desc_layout = pipeline_layout.set_layouts().first().unwrap();
-        let set = PersistentDescriptorSet::new(
-            &self.descriptor_set_allocator,
-            desc_layout.clone(),
-            [
-                WriteDescriptorSet::image_view(0, self.image.clone()),
-                WriteDescriptorSet::buffer(1, self.life_in.clone()),
-                WriteDescriptorSet::buffer(2, self.life_out.clone()),
-            ],
-            [],
-        )
-        .unwrap();
-
-        let push_constants = compute_life_cs::PushConstants {
-            life_color,
-            dead_color,
-            step,
-        };
-        builder
-            .bind_pipeline_compute(self.compute_life_pipeline.clone())
-            .unwrap()
-            .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set)
-            .unwrap()
-            .push_constants(pipeline_layout.clone(), 0, push_constants)
-            .unwrap()
-            .dispatch([img_dims[0] / 8, img_dims[1] / 8, 1])
-            .unwrap();
-    }
 }
 
 mod compute_life_cs {
@@ -32,7 +32,7 @@ impl PluginGroup for PluginBundle {
             .add(bevy::window::WindowPlugin::default())
             // Don't add WinitPlugin. This owns "core loop" (runner).
             // Bevy winit and render should be excluded
-            .add(VulkanoWinitPlugin)
+            .add(VulkanoWinitPlugin::default())
     }
 }
 
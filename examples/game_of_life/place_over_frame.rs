@@ -9,6 +9,7 @@
 
 use std::sync::Arc;
 
+use bevy_vulkano::FramebufferCache;
 use vulkano::{
     command_buffer::{
         allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
@@ -18,7 +19,7 @@ use vulkano::{
     format::Format,
     image::view::ImageView,
     memory::allocator::StandardMemoryAllocator,
-    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
+    render_pass::{RenderPass, Subpass},
     sync::GpuFuture,
 };
 
@@ -30,6 +31,7 @@ pub struct RenderPassPlaceOverFrame {
     gfx_queue: Arc<Queue>,
     command_buffer_allocator: StandardCommandBufferAllocator,
     render_pass: Arc<RenderPass>,
+    framebuffers: FramebufferCache,
     pixels_draw_pipeline: PixelsDrawPipeline,
 }
 
@@ -64,6 +66,7 @@ impl RenderPassPlaceOverFrame {
                 Default::default(),
             ),
             render_pass,
+            framebuffers: FramebufferCache::new(),
             pixels_draw_pipeline,
         }
     }
@@ -81,12 +84,8 @@ impl RenderPassPlaceOverFrame {
     {
         // Get dimensions
         let img_dims = target.image().extent();
-        // Create framebuffer (must be in same order as render pass description in `new`
-        let framebuffer = Framebuffer::new(self.render_pass.clone(), FramebufferCreateInfo {
-            attachments: vec![target],
-            ..Default::default()
-        })
-        .unwrap();
+        // Get framebuffer (must be in same order as render pass description in `new`
+        let framebuffer = self.framebuffers.get(&self.render_pass, &[target]);
         // Create primary command buffer builder
         let mut command_buffer_builder = AutoCommandBufferBuilder::primary(
             &self.command_buffer_allocator,
@@ -3,9 +3,7 @@ use bevy::{
     prelude::*,
     window::{close_on_esc, PrimaryWindow, WindowMode},
 };
-use bevy_vulkano::{
-    egui_winit_vulkano::egui, BevyVulkanoSettings, BevyVulkanoWindows, VulkanoWinitPlugin,
-};
+use bevy_vulkano::{egui_winit_vulkano::egui, BevyVulkanoWindows, VulkanoWinitPlugin};
 
 pub struct PluginBundle;
 
@@ -14,7 +12,11 @@ impl PluginGroup for PluginBundle {
         PluginGroupBuilder::start::<PluginBundle>()
             .add(bevy::input::InputPlugin)
             .add(bevy::window::WindowPlugin::default())
-            .add(VulkanoWinitPlugin)
+            .add(
+                VulkanoWinitPlugin::builder()
+                    // Since we're only drawing gui, let's clear each frame
+                    .gui_overlay(true),
+            )
     }
 }
 
@@ -24,11 +26,6 @@ impl PluginGroup for PluginBundle {
 
 fn main() {
     App::new()
-        .insert_non_send_resource(BevyVulkanoSettings {
-            // Since we're only drawing gui, let's clear each frame
-            is_gui_overlay: true,
-            ..BevyVulkanoSettings::default()
-        })
         .add_plugins(PluginBundle.set(WindowPlugin {
             primary_window: Some(Window {
                 resolution: (1920.0, 1080.0).into(),
@@ -71,8 +68,13 @@ pub fn main_render_system_primary_window(
                 }
                 Ok(f) => f,
             };
+            // Windows with `NoGui` have no gui
+            let Some(gui) = &mut vulkano_window.gui else {
+                vulkano_window.renderer.present(before, true);
+                continue;
+            };
             // Egui calls
-            let ctx = vulkano_window.gui.context();
+            let ctx = gui.context();
             egui::Area::new("Window Gui")
                 .fixed_pos(egui::pos2(10.0, 10.0))
                 .show(&ctx, |ui| {
@@ -84,7 +86,7 @@ pub fn main_render_system_primary_window(
                 });
             let final_image = vulkano_window.renderer.swapchain_image_view();
             // Render egui
-            let after = vulkano_window.gui.draw_on_image(before, final_image);
+            let after = gui.draw_on_image(before, final_image);
             // Finish frame
             vulkano_window.renderer.present(after, true);
         }
@@ -29,7 +29,7 @@ fn main() {
                 primary_window: None,
                 ..default()
             },
-            VulkanoWinitPlugin,
+            VulkanoWinitPlugin::default(),
         ))
         .add_systems(Startup, run_compute_shader_once_then_exit)
         .run();
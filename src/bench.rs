@@ -0,0 +1,509 @@
+use std::{
+    fmt::Write as _,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use bevy::{
+    app::AppExit,
+    log::{info, warn},
+    prelude::*,
+    utils::HashMap,
+};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::Device,
+    format::Format,
+    image::view::ImageView,
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    pipeline::{
+        compute::ComputePipelineCreateInfo,
+        graphics::{
+            color_blend::{ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::VertexInputState,
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint,
+        PipelineLayout, PipelineShaderStageCreateInfo,
+    },
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
+    sync::GpuFuture,
+};
+
+use crate::{
+    system::frame_presented_events, BevyVulkanoContext, BevyVulkanoWindows, CommandStats,
+    FramePresented,
+};
+
+/// Configuration of the [`BenchPlugin`].
+#[derive(Resource, Debug, Clone)]
+pub struct BenchConfig {
+    /// Number of windows rendered, including the primary window. Additional windows are spawned by
+    /// the plugin.
+    pub windows: usize,
+    /// Draw calls per window and frame.
+    pub draw_calls: u32,
+    /// Compute dispatches per window and frame.
+    pub dispatches: u32,
+    /// Whether the plugin renders its own workload. Disable to record statistics of your own
+    /// render systems instead.
+    pub render_workload: bool,
+    /// Frames per window which are not recorded, e.g. to exclude pipeline creation.
+    pub warmup_frames: u64,
+    /// Frames recorded per window before the statistics are written and the app exits.
+    pub frames: u64,
+    /// CSV file the statistics are written to.
+    pub output: PathBuf,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        BenchConfig {
+            windows: 1,
+            draw_calls: 1000,
+            dispatches: 100,
+            render_workload: true,
+            warmup_frames: 60,
+            frames: 600,
+            output: PathBuf::from("bench.csv"),
+        }
+    }
+}
+
+/// Runs a reproducible benchmark: renders a configurable number of draw calls and dispatches in a
+/// configurable number of windows, records [`FramePresented`] statistics of each window and writes
+/// them to a CSV file before exiting. Add after [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin).
+///
+/// Use `render_workload: false` to measure your own render systems with the same recording.
+#[derive(Default)]
+pub struct BenchPlugin {
+    pub config: BenchConfig,
+}
+
+impl Plugin for BenchPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone())
+            .init_resource::<BenchRecorder>()
+            .add_systems(Startup, spawn_bench_windows)
+            .add_systems(Last, record_bench_stats.after(frame_presented_events));
+        if self.config.render_workload {
+            app.add_systems(Startup, create_bench_workload)
+                .add_systems(PostUpdate, render_bench_workload);
+        }
+    }
+}
+
+/// Frame statistics recorded by the [`BenchPlugin`].
+#[derive(Resource, Default)]
+pub struct BenchRecorder {
+    frames: Vec<FramePresented>,
+    frames_per_window: HashMap<Entity, u64>,
+    finished: bool,
+}
+
+impl BenchRecorder {
+    /// Recorded frames in the order they were presented.
+    pub fn frames(&self) -> &[FramePresented] {
+        &self.frames
+    }
+
+    /// Writes the recorded frames to a CSV file.
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut csv = String::from("window,frame_index,cpu_time_us,acquire_wait_us,suboptimal\n");
+        for frame in &self.frames {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{}",
+                frame.window.index(),
+                frame.frame_index,
+                frame.cpu_time.as_micros(),
+                frame.acquire_wait.as_micros(),
+                frame.suboptimal
+            );
+        }
+        std::fs::write(path, csv)
+    }
+
+    /// Logs mean CPU time and acquire wait of each window.
+    pub fn log_summary(&self) {
+        for (window, count) in self.frames_per_window.iter() {
+            let frames = self.frames.iter().filter(|frame| frame.window == *window);
+            let (cpu_time, acquire_wait) = frames.fold(
+                (Duration::ZERO, Duration::ZERO),
+                |(cpu_time, acquire_wait), frame| {
+                    (cpu_time + frame.cpu_time, acquire_wait + frame.acquire_wait)
+                },
+            );
+            let count = (*count).max(1) as u32;
+            info!(
+                "Bench window {:?}: {} frames, mean cpu time {:?}, mean acquire wait {:?}",
+                window,
+                count,
+                cpu_time / count,
+                acquire_wait / count
+            );
+        }
+    }
+}
+
+fn spawn_bench_windows(mut commands: Commands, config: Res<BenchConfig>) {
+    for i in 1..config.windows {
+        commands.spawn(Window {
+            title: format!("bevy_vulkano bench {i}"),
+            resolution: (512.0, 512.0).into(),
+            ..default()
+        });
+    }
+}
+
+fn record_bench_stats(
+    config: Res<BenchConfig>,
+    mut recorder: ResMut<BenchRecorder>,
+    mut presented: EventReader<FramePresented>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if recorder.finished {
+        return;
+    }
+    for frame in presented.read() {
+        if frame.frame_index < config.warmup_frames {
+            continue;
+        }
+        let count = recorder.frames_per_window.entry(frame.window).or_default();
+        if *count >= config.frames {
+            continue;
+        }
+        *count += 1;
+        recorder.frames.push(frame.clone());
+    }
+
+    let done = recorder.frames_per_window.len() >= config.windows
+        && recorder
+            .frames_per_window
+            .values()
+            .all(|count| *count >= config.frames);
+    if !done {
+        return;
+    }
+    recorder.finished = true;
+    recorder.log_summary();
+    match recorder.write_csv(&config.output) {
+        Ok(()) => info!("Wrote bench results to {:?}", config.output),
+        Err(e) => warn!("Failed to write bench results to {:?}: {e}", config.output),
+    }
+    exit.send(AppExit);
+}
+
+/// Pipelines and buffers of the built-in bench workload.
+struct BenchWorkload {
+    device: Arc<Device>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    compute_pipeline: Arc<ComputePipeline>,
+    /// Render pass and pipeline for each swapchain format.
+    graphics_pipelines: HashMap<Format, (Arc<RenderPass>, Arc<GraphicsPipeline>)>,
+    /// Storage buffer written by the dispatches of each window.
+    buffers: HashMap<Entity, Subbuffer<[u32]>>,
+}
+
+impl BenchWorkload {
+    fn new(context: &BevyVulkanoContext) -> BenchWorkload {
+        let device = context.context.device().clone();
+        let compute_pipeline = {
+            let cs = bench_cs::load(device.clone())
+                .expect("failed to create shader module")
+                .entry_point("main")
+                .expect("shader entry point not found");
+            let stage = PipelineShaderStageCreateInfo::new(cs);
+            let layout = PipelineLayout::new(
+                device.clone(),
+                PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                    .into_pipeline_layout_create_info(device.clone())
+                    .unwrap(),
+            )
+            .unwrap();
+            ComputePipeline::new(
+                device.clone(),
+                None,
+                ComputePipelineCreateInfo::stage_layout(stage, layout),
+            )
+            .unwrap()
+        };
+        BenchWorkload {
+            memory_allocator: context.context.memory_allocator().clone(),
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            device,
+            compute_pipeline,
+            graphics_pipelines: HashMap::default(),
+            buffers: HashMap::default(),
+        }
+    }
+
+    fn graphics_pipeline(&mut self, format: Format) -> (Arc<RenderPass>, Arc<GraphicsPipeline>) {
+        let device = self.device.clone();
+        self.graphics_pipelines
+            .entry(format)
+            .or_insert_with(|| {
+                let render_pass = vulkano::single_pass_renderpass!(device.clone(),
+                    attachments: {
+                        color: {
+                            format: format,
+                            samples: 1,
+                            load_op: Clear,
+                            store_op: Store,
+                        }
+                    },
+                    pass: {
+                            color: [color],
+                            depth_stencil: {}
+                    }
+                )
+                .unwrap();
+                let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+                let vs = bench_vs::load(device.clone())
+                    .expect("failed to create shader module")
+                    .entry_point("main")
+                    .expect("shader entry point not found");
+                let fs = bench_fs::load(device.clone())
+                    .expect("failed to create shader module")
+                    .entry_point("main")
+                    .expect("shader entry point not found");
+                let stages = [
+                    PipelineShaderStageCreateInfo::new(vs),
+                    PipelineShaderStageCreateInfo::new(fs),
+                ];
+                let layout = PipelineLayout::new(
+                    device.clone(),
+                    PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+                        .into_pipeline_layout_create_info(device.clone())
+                        .unwrap(),
+                )
+                .unwrap();
+                let pipeline =
+                    GraphicsPipeline::new(device.clone(), None, GraphicsPipelineCreateInfo {
+                        stages: stages.into_iter().collect(),
+                        vertex_input_state: Some(VertexInputState::default()),
+                        input_assembly_state: Some(InputAssemblyState::default()),
+                        viewport_state: Some(ViewportState::default()),
+                        rasterization_state: Some(RasterizationState::default()),
+                        multisample_state: Some(MultisampleState::default()),
+                        color_blend_state: Some(ColorBlendState::with_attachment_states(
+                            subpass.num_color_attachments(),
+                            ColorBlendAttachmentState::default(),
+                        )),
+                        dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                        subpass: Some(subpass.into()),
+                        ..GraphicsPipelineCreateInfo::layout(layout)
+                    })
+                    .unwrap();
+                (render_pass, pipeline)
+            })
+            .clone()
+    }
+
+    fn buffer(&mut self, window: Entity) -> Subbuffer<[u32]> {
+        let memory_allocator = self.memory_allocator.clone();
+        self.buffers
+            .entry(window)
+            .or_insert_with(|| {
+                Buffer::from_iter(
+                    memory_allocator,
+                    BufferCreateInfo {
+                        usage: BufferUsage::STORAGE_BUFFER,
+                        ..Default::default()
+                    },
+                    AllocationCreateInfo {
+                        memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                        ..Default::default()
+                    },
+                    0..64u32,
+                )
+                .unwrap()
+            })
+            .clone()
+    }
+
+    /// Records `dispatches` dispatches and `draw_calls` draws into `target`.
+    fn record(
+        &mut self,
+        window: Entity,
+        target: Arc<ImageView>,
+        queue_family_index: u32,
+        draw_calls: u32,
+        dispatches: u32,
+    ) -> AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            queue_family_index,
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        if dispatches > 0 {
+            let layout = self.compute_pipeline.layout().clone();
+            let set = PersistentDescriptorSet::new(
+                &self.descriptor_set_allocator,
+                layout.set_layouts()[0].clone(),
+                [WriteDescriptorSet::buffer(0, self.buffer(window))],
+                [],
+            )
+            .unwrap();
+            builder
+                .bind_pipeline_compute(self.compute_pipeline.clone())
+                .unwrap()
+                .bind_descriptor_sets(PipelineBindPoint::Compute, layout, 0, set)
+                .unwrap();
+            for _ in 0..dispatches {
+                builder.dispatch([1, 1, 1]).unwrap();
+            }
+            CommandStats::record_pipeline_bind();
+            CommandStats::record_dispatches(dispatches as u64);
+        }
+
+        let extent = target.image().extent();
+        let (render_pass, pipeline) = self.graphics_pipeline(target.format());
+        let framebuffer = Framebuffer::new(render_pass, FramebufferCreateInfo {
+            attachments: vec![target],
+            ..Default::default()
+        })
+        .unwrap();
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into())],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassBeginInfo {
+                    contents: SubpassContents::Inline,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .set_viewport(
+                0,
+                [Viewport {
+                    offset: [0.0, 0.0],
+                    extent: [extent[0] as f32, extent[1] as f32],
+                    depth_range: 0.0..=1.0,
+                }]
+                .into_iter()
+                .collect(),
+            )
+            .unwrap()
+            .bind_pipeline_graphics(pipeline)
+            .unwrap();
+        for _ in 0..draw_calls {
+            builder.draw(3, 1, 0, 0).unwrap();
+        }
+        CommandStats::record_pipeline_bind();
+        CommandStats::record_draws(draw_calls as u64);
+        builder.end_render_pass(Default::default()).unwrap();
+        builder
+    }
+}
+
+fn create_bench_workload(world: &mut World) {
+    let workload = BenchWorkload::new(world.resource::<BevyVulkanoContext>());
+    world.insert_non_send_resource(workload);
+}
+
+fn render_bench_workload(
+    config: Res<BenchConfig>,
+    mut workload: NonSendMut<BenchWorkload>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+) {
+    for (window, vulkano_window) in vulkano_windows.iter_mut() {
+        let renderer = &mut vulkano_window.renderer;
+        let before = match renderer.acquire() {
+            Err(e) => {
+                warn!("Failed to start bench frame: {}", e);
+                continue;
+            }
+            Ok(f) => f,
+        };
+        let queue = renderer.graphics_queue();
+        let command_buffer = workload
+            .record(
+                window,
+                renderer.swapchain_image_view(),
+                queue.queue_family_index(),
+                config.draw_calls,
+                config.dispatches,
+            )
+            .build()
+            .unwrap();
+        let after = before
+            .then_execute(queue.clone(), command_buffer)
+            .unwrap()
+            .boxed();
+        renderer.present(after, false);
+    }
+}
+
+mod bench_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: "
+#version 450
+layout(local_size_x = 64) in;
+layout(set = 0, binding = 0) buffer Data {
+    uint data[];
+};
+
+void main() {
+    data[gl_GlobalInvocationID.x] += 1;
+}
+"
+    }
+}
+
+mod bench_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+
+void main() {
+    // Small triangle so the bench measures draw call overhead rather than fill rate
+    vec2 position = vec2(gl_VertexIndex & 1, (gl_VertexIndex >> 1) & 1) * 0.1 - 0.05;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"
+    }
+}
+
+mod bench_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(1.0);
+}
+"
+    }
+}
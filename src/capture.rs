@@ -0,0 +1,237 @@
+use std::{
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError, TryRecvError},
+        Arc,
+    },
+    time::Duration,
+};
+
+use image::RgbaImage;
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        CopyImageToBufferInfo,
+    },
+    device::Queue,
+    format::Format,
+    image::Image,
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    sync::{self, GpuFuture},
+    ValidationError,
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk, GpuMemoryBudget};
+
+/// Whether images of `format` can be converted to [`RgbaImage`] by [`ImageReadback`].
+pub fn supports_readback(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R8G8B8A8_UNORM
+            | Format::R8G8B8A8_SRGB
+            | Format::B8G8R8A8_UNORM
+            | Format::B8G8R8A8_SRGB
+            | Format::R8_UNORM
+            | Format::R16G16B16A16_SFLOAT
+            | Format::R32G32B32A32_SFLOAT
+    )
+}
+
+/// A host visible buffer receiving a copy of an image, which can be converted to an [`RgbaImage`]
+/// once the GPU has finished the copy.
+pub struct ImageReadback {
+    buffer: Subbuffer<[u8]>,
+    extent: [u32; 2],
+    format: Format,
+}
+
+impl ImageReadback {
+    /// Allocates a host visible buffer fitting an image of `extent` and `format`.
+    pub fn new(
+        allocator: Arc<StandardMemoryAllocator>,
+        extent: [u32; 2],
+        format: Format,
+    ) -> ImageReadback {
+        let buffer = Buffer::new_slice::<u8>(
+            allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            (extent[0] as u64 * extent[1] as u64 * format.block_size()).max(1),
+        )
+        .unwrap();
+        GpuMemoryBudget::track_buffer(buffer.buffer());
+        ImageReadback {
+            buffer,
+            extent,
+            format,
+        }
+    }
+
+    /// Records a copy of the first mip level and array layer of `image` into the readback
+    /// buffer. The image must have been created with `TRANSFER_SRC` usage.
+    pub fn record<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        image: Arc<Image>,
+    ) -> Result<(), Box<ValidationError>> {
+        builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(
+            image,
+            self.buffer.clone(),
+        ))?;
+        Ok(())
+    }
+
+    /// Extent of the copied image.
+    pub fn extent(&self) -> [u32; 2] {
+        self.extent
+    }
+
+    /// Format of the copied image.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// The raw copied bytes.
+    pub fn buffer(&self) -> &Subbuffer<[u8]> {
+        &self.buffer
+    }
+
+    /// Converts the copied pixels to RGBA8. Float formats are clamped to `0.0..=1.0` without color
+    /// space conversion. Returns `None` for unsupported formats (see [`supports_readback`]) or if
+    /// the buffer is still in use by the GPU.
+    pub fn to_rgba_image(&self) -> Option<RgbaImage> {
+        let data = self.buffer.read().ok()?;
+        let pixels = (self.extent[0] * self.extent[1]) as usize;
+        let unorm = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let rgba = match self.format {
+            Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => data[..pixels * 4].to_vec(),
+            Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => data[..pixels * 4]
+                .chunks_exact(4)
+                .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+                .collect(),
+            Format::R8_UNORM => data[..pixels]
+                .iter()
+                .flat_map(|&r| [r, r, r, 255])
+                .collect(),
+            Format::R16G16B16A16_SFLOAT => data[..pixels * 8]
+                .chunks_exact(2)
+                .map(|half| unorm(f16_to_f32(u16::from_le_bytes([half[0], half[1]]))))
+                .collect(),
+            Format::R32G32B32A32_SFLOAT => data[..pixels * 16]
+                .chunks_exact(4)
+                .map(|float| unorm(f32::from_le_bytes([float[0], float[1], float[2], float[3]])))
+                .collect(),
+            _ => return None,
+        };
+        RgbaImage::from_raw(self.extent[0], self.extent[1], rgba)
+    }
+}
+
+/// Errors from receiving a [`FrameCapture`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameCaptureError {
+    /// The frame couldn't be captured, e.g. because presenting failed, the window closed or its
+    /// swapchain format or usage doesn't allow readback.
+    Failed,
+    /// No frame was presented within the timeout.
+    Timeout,
+}
+
+impl std::fmt::Display for FrameCaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameCaptureError::Failed => write!(f, "the frame could not be captured"),
+            FrameCaptureError::Timeout => write!(f, "timed out waiting for the frame capture"),
+        }
+    }
+}
+
+impl std::error::Error for FrameCaptureError {}
+
+/// Receives the next presented frame of a window, requested with
+/// [`VulkanoWindow::capture_next_frame`](crate::VulkanoWindow::capture_next_frame).
+///
+/// The frame is copied and converted when it is presented, so poll it with
+/// [`FrameCapture::try_take`] in a later system or frame, or block on it with
+/// [`FrameCapture::wait`] from another thread.
+pub struct FrameCapture {
+    pub(crate) receiver: Receiver<RgbaImage>,
+}
+
+impl FrameCapture {
+    /// Returns the captured frame once presented, `Ok(None)` while it is pending.
+    pub fn try_take(&self) -> Result<Option<RgbaImage>, FrameCaptureError> {
+        match self.receiver.try_recv() {
+            Ok(image) => Ok(Some(image)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(FrameCaptureError::Failed),
+        }
+    }
+
+    /// Blocks until the frame is captured. Don't call this on the thread presenting the window,
+    /// which would wait for itself until the timeout.
+    pub fn wait(self, timeout: Duration) -> Result<RgbaImage, FrameCaptureError> {
+        self.receiver.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => FrameCaptureError::Timeout,
+            RecvTimeoutError::Disconnected => FrameCaptureError::Failed,
+        })
+    }
+}
+
+/// Copies `image` to the host and waits for the copy to finish. Intended for debugging and tools
+/// rather than per frame use.
+pub fn read_image_blocking(
+    queue: Arc<Queue>,
+    allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: &StandardCommandBufferAllocator,
+    image: Arc<Image>,
+) -> Option<RgbaImage> {
+    profile_scope!("bevy_vulkano::read_image_blocking");
+    if !supports_readback(image.format()) {
+        return None;
+    }
+    let extent = image.extent();
+    let readback = ImageReadback::new(allocator, [extent[0], extent[1]], image.format());
+    let mut builder = AutoCommandBufferBuilder::primary(
+        command_buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .unwrap();
+    readback.record(&mut builder, image).ok()?;
+    let command_buffer = builder.build().unwrap();
+    trace_vk!(
+        "submit blocking readback of {:?} {:?} on queue family {}",
+        extent,
+        readback.format(),
+        queue.queue_family_index()
+    );
+    sync::now(queue.device().clone())
+        .then_execute(queue, command_buffer)
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .ok()?
+        .wait(None)
+        .ok()?;
+    readback.to_rgba_image()
+}
+
+/// Converts an IEEE 754 half precision float to `f32`.
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
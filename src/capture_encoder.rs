@@ -0,0 +1,349 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::{App, Last, Plugin, PreUpdate},
+    log::{error, warn},
+    prelude::{
+        Entity, Event, EventReader, EventWriter, IntoSystemConfigs, NonSendMut, ResMut, Resource,
+    },
+};
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    ColorType, DynamicImage, ImageEncoder, RgbaImage,
+};
+
+use crate::{
+    capture::supports_readback,
+    frame_dump::{request_frame_dump, write_frame_dump, FrameDumpState},
+    frame_recorder::{record_frames, FrameRecorder},
+    system::present_window_group,
+    BevyVulkanoWindows, DumpFrame, ImageReadback,
+};
+
+/// How long workers wait for the GPU copy of a capture before giving up.
+const READBACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// File format captures are encoded to by the [`CaptureEncoder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    Png,
+    /// Lossy JPEG with `quality` in `1..=100`. Alpha is dropped.
+    Jpeg {
+        quality: u8,
+    },
+    /// Lossless WebP.
+    WebP,
+}
+
+impl CaptureFormat {
+    /// File extension of the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            CaptureFormat::Png => "png",
+            CaptureFormat::Jpeg {
+                ..
+            } => "jpg",
+            CaptureFormat::WebP => "webp",
+        }
+    }
+}
+
+/// Identifies a capture submitted to the [`CaptureEncoder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CaptureId(u64);
+
+/// Returned when a capture can't be queued because [`CaptureEncoder::max_pending`] captures are
+/// already being encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureQueueFull {
+    pub pending: usize,
+}
+
+/// Send to capture the next presented swapchain image of `window` and encode it to `path` on the
+/// [`CaptureEncoder`] threads. [`CaptureEncoded`] is sent once the file is written.
+#[derive(Event, Debug, Clone)]
+pub struct CaptureWindow {
+    pub window: Entity,
+    pub path: PathBuf,
+    pub format: CaptureFormat,
+}
+
+/// Sent when a capture submitted to the [`CaptureEncoder`] has been written. On failure, `error`
+/// holds the reason.
+#[derive(Event, Debug, Clone)]
+pub struct CaptureEncoded {
+    pub id: CaptureId,
+    pub path: PathBuf,
+    /// Time spent converting and encoding the capture on the worker thread.
+    pub encode_time: Duration,
+    pub error: Option<String>,
+}
+
+struct EncodeJob {
+    id: CaptureId,
+    readback: ImageReadback,
+    path: PathBuf,
+    format: CaptureFormat,
+}
+
+type EncodeResult = (CaptureId, PathBuf, Duration, Result<(), String>);
+
+/// Converts and encodes captures on background threads, so that capturing during gameplay doesn't
+/// spike frame time.
+///
+/// Captures are queued with [`CaptureEncoder::encode`], or by sending [`CaptureWindow`], and
+/// encoded in batches by the worker threads. At most [`CaptureEncoder::max_pending`] captures are
+/// queued at a time; further captures are rejected with [`CaptureQueueFull`] and counted in
+/// [`CaptureEncoder::rejected`] instead of piling up memory.
+#[derive(Resource)]
+pub struct CaptureEncoder {
+    threads: usize,
+    max_pending: usize,
+    job_sender: Option<Sender<EncodeJob>>,
+    result_receiver: Mutex<Receiver<EncodeResult>>,
+    result_sender: Sender<EncodeResult>,
+    /// Window captures requested with [`CaptureWindow`] waiting for their frame to be presented.
+    requested: Vec<CaptureWindow>,
+    pending: usize,
+    rejected: u64,
+    next_id: u64,
+}
+
+impl Default for CaptureEncoder {
+    fn default() -> Self {
+        CaptureEncoder::new(2, 8)
+    }
+}
+
+impl CaptureEncoder {
+    /// Creates an encoder using `threads` worker threads, spawned on first use, and queueing at
+    /// most `max_pending` captures.
+    pub fn new(threads: usize, max_pending: usize) -> CaptureEncoder {
+        let (result_sender, result_receiver) = channel();
+        CaptureEncoder {
+            threads: threads.max(1),
+            max_pending: max_pending.max(1),
+            job_sender: None,
+            result_receiver: Mutex::new(result_receiver),
+            result_sender,
+            requested: vec![],
+            pending: 0,
+            rejected: 0,
+            next_id: 0,
+        }
+    }
+
+    /// Queues `readback` to be converted and encoded to `path`. The readback's copy must have been
+    /// submitted; workers wait for it to finish.
+    pub fn encode(
+        &mut self,
+        readback: ImageReadback,
+        path: impl Into<PathBuf>,
+        format: CaptureFormat,
+    ) -> Result<CaptureId, CaptureQueueFull> {
+        if self.pending >= self.max_pending {
+            self.rejected += 1;
+            return Err(CaptureQueueFull {
+                pending: self.pending,
+            });
+        }
+        let id = CaptureId(self.next_id);
+        self.next_id += 1;
+        self.pending += 1;
+        let sender = self.job_sender.get_or_insert_with(|| {
+            let (job_sender, job_receiver) = channel::<EncodeJob>();
+            let job_receiver = Arc::new(Mutex::new(job_receiver));
+            for i in 0..self.threads {
+                let job_receiver = job_receiver.clone();
+                let result_sender = self.result_sender.clone();
+                thread::Builder::new()
+                    .name(format!("bevy_vulkano capture encoder {i}"))
+                    .spawn(move || loop {
+                        let job = job_receiver.lock().unwrap().recv();
+                        match job {
+                            Ok(job) => {
+                                let start = Instant::now();
+                                let result = encode_readback(&job);
+                                if result_sender
+                                    .send((job.id, job.path, start.elapsed(), result))
+                                    .is_err()
+                                {
+                                    break;
+                                }
+                            }
+                            Err(_) => break,
+                        }
+                    })
+                    .unwrap();
+            }
+            job_sender
+        });
+        sender
+            .send(EncodeJob {
+                id,
+                readback,
+                path: path.into(),
+                format,
+            })
+            .unwrap();
+        Ok(id)
+    }
+
+    /// Number of captures queued or being encoded.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    pub fn max_pending(&self) -> usize {
+        self.max_pending
+    }
+
+    /// Number of captures rejected because the queue was full.
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    /// Collects finished encodes.
+    fn poll(&mut self) -> Vec<EncodeResult> {
+        let results = self
+            .result_receiver
+            .lock()
+            .unwrap()
+            .try_iter()
+            .collect::<Vec<_>>();
+        self.pending -= results.len();
+        results
+    }
+}
+
+/// Waits for the readback's copy and writes it to its path.
+fn encode_readback(job: &EncodeJob) -> Result<(), String> {
+    if !supports_readback(job.readback.format()) {
+        return Err(format!("unsupported format {:?}", job.readback.format()));
+    }
+    let start = Instant::now();
+    let image = loop {
+        // The buffer can't be read until the GPU has finished the copy
+        if let Some(image) = job.readback.to_rgba_image() {
+            break image;
+        }
+        if start.elapsed() > READBACK_TIMEOUT {
+            return Err("timed out waiting for the capture copy".to_string());
+        }
+        thread::sleep(Duration::from_millis(1));
+    };
+    write_image(&image, &job.path, job.format)
+}
+
+fn write_image(image: &RgbaImage, path: &Path, format: CaptureFormat) -> Result<(), String> {
+    let writer = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+    let (width, height) = image.dimensions();
+    match format {
+        CaptureFormat::Png => {
+            PngEncoder::new(writer).write_image(image.as_raw(), width, height, ColorType::Rgba8)
+        }
+        CaptureFormat::Jpeg {
+            quality,
+        } => {
+            let rgb = DynamicImage::ImageRgba8(image.clone()).to_rgb8();
+            JpegEncoder::new_with_quality(writer, quality.clamp(1, 100)).write_image(
+                rgb.as_raw(),
+                width,
+                height,
+                ColorType::Rgb8,
+            )
+        }
+        CaptureFormat::WebP => WebPEncoder::new_lossless(writer).write_image(
+            image.as_raw(),
+            width,
+            height,
+            ColorType::Rgba8,
+        ),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Requests swapchain captures of windows for which [`CaptureWindow`] is received.
+fn request_window_captures(
+    mut capture_events: EventReader<CaptureWindow>,
+    mut encoder: ResMut<CaptureEncoder>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+) {
+    for capture in capture_events.read() {
+        let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(capture.window) else {
+            warn!("Can't capture {:?}, it has no renderer", capture.window);
+            continue;
+        };
+        vulkano_window.renderer.request_capture();
+        encoder.requested.push(capture.clone());
+    }
+}
+
+/// Queues presented window captures for encoding and sends [`CaptureEncoded`] for finished ones.
+fn encode_window_captures(
+    mut encoder: ResMut<CaptureEncoder>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    mut encoded_events: EventWriter<CaptureEncoded>,
+) {
+    for capture in std::mem::take(&mut encoder.requested) {
+        let Some(readback) = vulkano_windows
+            .get_vulkano_window_mut(capture.window)
+            .and_then(|vulkano_window| vulkano_window.renderer.take_capture())
+        else {
+            warn!(
+                "No frame of {:?} was presented to capture to {:?}",
+                capture.window, capture.path
+            );
+            continue;
+        };
+        if let Err(full) = encoder.encode(readback, capture.path.clone(), capture.format) {
+            warn!(
+                "Dropped capture to {:?}, {} captures are already being encoded",
+                capture.path, full.pending
+            );
+        }
+    }
+    for (id, path, encode_time, result) in encoder.poll() {
+        if let Err(e) = &result {
+            error!("Failed to write capture {:?}: {}", path, e);
+        }
+        encoded_events.send(CaptureEncoded {
+            id,
+            path,
+            encode_time,
+            error: result.err(),
+        });
+    }
+}
+
+/// Registers window captures, [`DumpFrame`](crate::DumpFrame), the [`CaptureEncoder`] and the
+/// [`FrameRecorder`]. Added by [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin) with feature
+/// `capture`.
+pub struct VulkanoCapturePlugin;
+
+impl Plugin for VulkanoCapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameDumpState>()
+            .init_resource::<CaptureEncoder>()
+            .init_resource::<FrameRecorder>()
+            .add_event::<DumpFrame>()
+            .add_event::<CaptureWindow>()
+            .add_event::<CaptureEncoded>()
+            .add_systems(PreUpdate, (request_frame_dump, request_window_captures))
+            .add_systems(
+                Last,
+                (write_frame_dump, encode_window_captures, record_frames)
+                    .after(present_window_group),
+            );
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bevy::prelude::{ResMut, Resource};
+
+static DRAW_CALLS: AtomicU64 = AtomicU64::new(0);
+static DISPATCHES: AtomicU64 = AtomicU64::new(0);
+static PIPELINE_BINDS: AtomicU64 = AtomicU64::new(0);
+static SECONDARY_BUFFERS: AtomicU64 = AtomicU64::new(0);
+
+/// Counts of recorded commands, reported per frame in [`FrameCommandStats`]. Compare them between
+/// builds to spot batching regressions.
+///
+/// The crate's passes and helpers ([`FullscreenPass`](crate::FullscreenPass),
+/// [`ComputePass`](crate::ComputePass), the indirect draw buffers etc.) count their commands.
+/// Count your own with [`CommandStats::record_draws`] and friends next to recording them. The
+/// counters are global, so commands can't be attributed to a window.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CommandStats {
+    /// Draw commands, including indirect draws.
+    pub draw_calls: u64,
+    /// Dispatch commands, including indirect dispatches.
+    pub dispatches: u64,
+    /// Graphics and compute pipelines bound.
+    pub pipeline_binds: u64,
+    /// Secondary command buffers executed.
+    pub secondary_buffers: u64,
+}
+
+impl CommandStats {
+    /// Counts `count` draw commands.
+    #[inline]
+    pub fn record_draws(count: u64) {
+        DRAW_CALLS.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Counts `count` dispatch commands.
+    #[inline]
+    pub fn record_dispatches(count: u64) {
+        DISPATCHES.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Counts a bound pipeline.
+    #[inline]
+    pub fn record_pipeline_bind() {
+        PIPELINE_BINDS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts `count` executed secondary command buffers.
+    #[inline]
+    pub fn record_secondary_buffers(count: u64) {
+        SECONDARY_BUFFERS.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Commands counted since the start of the app.
+    pub fn total() -> CommandStats {
+        CommandStats {
+            draw_calls: DRAW_CALLS.load(Ordering::Relaxed),
+            dispatches: DISPATCHES.load(Ordering::Relaxed),
+            pipeline_binds: PIPELINE_BINDS.load(Ordering::Relaxed),
+            secondary_buffers: SECONDARY_BUFFERS.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Commands counted since `earlier` was taken with [`CommandStats::total`].
+    pub fn since(earlier: CommandStats) -> CommandStats {
+        let total = CommandStats::total();
+        CommandStats {
+            draw_calls: total.draw_calls.saturating_sub(earlier.draw_calls),
+            dispatches: total.dispatches.saturating_sub(earlier.dispatches),
+            pipeline_binds: total.pipeline_binds.saturating_sub(earlier.pipeline_binds),
+            secondary_buffers: total
+                .secondary_buffers
+                .saturating_sub(earlier.secondary_buffers),
+        }
+    }
+}
+
+/// Commands recorded during the last frame by all windows together, updated at the end of every
+/// frame after the windows presented.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct FrameCommandStats {
+    /// Commands of the last frame.
+    pub last_frame: CommandStats,
+    total_at_frame_end: CommandStats,
+}
+
+pub(crate) fn update_frame_command_stats(mut stats: ResMut<FrameCommandStats>) {
+    stats.last_frame = CommandStats::since(stats.total_at_frame_end);
+    stats.total_at_frame_end = CommandStats::total();
+}
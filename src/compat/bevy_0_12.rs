@@ -1,4 +1,10 @@
-// Same as in `bevy_winit`
+// Conversions and window diffing are the same as in `bevy_winit` 0.12
+#[cfg(feature = "gui")]
+pub(crate) use bevy::app::Update;
+pub(crate) use bevy::{
+    app::{First, Last, PreUpdate},
+    ecs::{event::ManualEventReader, query::Has},
+};
 use bevy::{
     input::{
         keyboard::{KeyCode, KeyboardInput},
@@ -6,10 +12,20 @@ use bevy::{
         touch::{ForceTouch, TouchInput, TouchPhase},
         ButtonState,
     },
+    log::{error, warn},
     math::Vec2,
-    prelude::Entity,
+    prelude::{Changed, Component, Entity, EventWriter, NonSend, NonSendMut, Query, Res, Window},
     window::{CursorIcon, WindowLevel},
 };
+use winit::dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize};
+
+use crate::{
+    get_best_videomode, get_fitting_videomode, vulkano_windows::attempt_grab, BevyVulkanoContext,
+    BevyVulkanoSettings, BevyVulkanoWindows, CursorGrabFailed, WindowResolutionClamped,
+};
+
+/// The bevy minor release this version of bevy_vulkano supports.
+pub const SUPPORTED_BEVY_VERSION: &str = "0.12";
 
 pub fn convert_keyboard_input(
     keyboard_input: &winit::event::KeyboardInput,
@@ -282,3 +298,254 @@ pub fn convert_window_level(window_level: WindowLevel) -> winit::window::WindowL
         WindowLevel::AlwaysOnTop => winit::window::WindowLevel::AlwaysOnTop,
     }
 }
+
+/// The cached state of the window so we can check which properties were changed from within the app.
+#[derive(Debug, Clone, Component)]
+pub struct CachedWindow {
+    pub window: Window,
+}
+
+// Detect changes to the window and update the winit window accordingly.
+//
+// Notes:
+// - [`Window::present_mode`] changes recreate the swapchain with the new mode, falling back to a supported mode.
+// - [`Window::composite_alpha_mode`] updating should be handled in the bevy render crate.
+// - [`Window::transparent`] currently cannot be updated after startup for winit.
+// - [`Window::canvas`] currently cannot be updated after startup, not entirely sure if it would work well with the
+//   event channel stuff.
+pub(crate) fn changed_window(
+    mut changed_windows: Query<(Entity, &mut Window, &mut CachedWindow), Changed<Window>>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    settings: NonSend<BevyVulkanoSettings>,
+    context: Res<BevyVulkanoContext>,
+    mut grab_failed_events: EventWriter<CursorGrabFailed>,
+    mut resolution_clamped_events: EventWriter<WindowResolutionClamped>,
+) {
+    let max_dimension = context
+        .context
+        .device()
+        .physical_device()
+        .properties()
+        .max_image_dimension2_d;
+    for (entity, mut window, mut cache) in &mut changed_windows {
+        if let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(entity) {
+            if window.title != cache.window.title {
+                vulkano_window.window().set_title(window.title.as_str());
+            }
+
+            if window.mode != cache.window.mode {
+                let new_mode = match window.mode {
+                    bevy::window::WindowMode::BorderlessFullscreen => {
+                        Some(winit::window::Fullscreen::Borderless(None))
+                    }
+                    bevy::window::WindowMode::Fullscreen => {
+                        Some(winit::window::Fullscreen::Exclusive(get_best_videomode(
+                            &vulkano_window.window().current_monitor().unwrap(),
+                        )))
+                    }
+                    bevy::window::WindowMode::SizedFullscreen => {
+                        Some(winit::window::Fullscreen::Exclusive(get_fitting_videomode(
+                            &vulkano_window.window().current_monitor().unwrap(),
+                            window.width() as u32,
+                            window.height() as u32,
+                        )))
+                    }
+                    bevy::window::WindowMode::Windowed => None,
+                };
+
+                if vulkano_window.window().fullscreen() != new_mode {
+                    vulkano_window.window().set_fullscreen(new_mode);
+                }
+            }
+            if window.resolution != cache.window.resolution {
+                let requested = [
+                    window.resolution.physical_width(),
+                    window.resolution.physical_height(),
+                ];
+                let current: [u32; 2] = vulkano_window.window().inner_size().into();
+                let applied = clamp_resolution(requested, current, max_dimension);
+                if applied != requested {
+                    warn!(
+                        "Invalid resolution {:?} requested for window {:?} (maximum {}), using \
+                         {:?}",
+                        requested, window.title, max_dimension, applied
+                    );
+                    window
+                        .resolution
+                        .set_physical_resolution(applied[0], applied[1]);
+                    resolution_clamped_events.send(WindowResolutionClamped {
+                        window: entity,
+                        requested,
+                        applied,
+                    });
+                }
+                vulkano_window
+                    .window()
+                    .set_inner_size(PhysicalSize::new(applied[0], applied[1]));
+            }
+
+            if window.physical_cursor_position() != cache.window.physical_cursor_position() {
+                if let Some(physical_position) = window.physical_cursor_position() {
+                    let inner_size = vulkano_window.window().inner_size();
+
+                    let position = PhysicalPosition::new(
+                        physical_position.x,
+                        // Flip the coordinate space back to winit's context.
+                        inner_size.height as f32 - physical_position.y,
+                    );
+
+                    if let Err(err) = vulkano_window.window().set_cursor_position(position) {
+                        error!("could not set cursor position: {:?}", err);
+                    }
+                }
+            }
+
+            if window.cursor.icon != cache.window.cursor.icon {
+                vulkano_window
+                    .window()
+                    .set_cursor_icon(convert_cursor_icon(window.cursor.icon));
+            }
+
+            if window.cursor.grab_mode != cache.window.cursor.grab_mode {
+                if let Some(failure) = attempt_grab(
+                    entity,
+                    vulkano_window.window(),
+                    window.cursor.grab_mode,
+                    settings.cursor_grab_fallback,
+                ) {
+                    grab_failed_events.send(failure);
+                }
+            }
+
+            if window.cursor.visible != cache.window.cursor.visible {
+                vulkano_window
+                    .window()
+                    .set_cursor_visible(window.cursor.visible);
+            }
+
+            if window.cursor.hit_test != cache.window.cursor.hit_test {
+                if let Err(err) = vulkano_window
+                    .window()
+                    .set_cursor_hittest(window.cursor.hit_test)
+                {
+                    window.cursor.hit_test = cache.window.cursor.hit_test;
+                    warn!(
+                        "Could not set cursor hit test for window {:?}: {:?}",
+                        window.title, err
+                    );
+                }
+            }
+
+            if window.decorations != cache.window.decorations
+                && window.decorations != vulkano_window.window().is_decorated()
+            {
+                vulkano_window.window().set_decorations(window.decorations);
+            }
+
+            if window.resizable != cache.window.resizable
+                && window.resizable != vulkano_window.window().is_resizable()
+            {
+                vulkano_window.window().set_resizable(window.resizable);
+            }
+
+            if window.resize_constraints != cache.window.resize_constraints {
+                let constraints = window.resize_constraints.check_constraints();
+                let min_inner_size = LogicalSize {
+                    width: constraints.min_width,
+                    height: constraints.min_height,
+                };
+                let max_inner_size = LogicalSize {
+                    width: constraints.max_width,
+                    height: constraints.max_height,
+                };
+
+                vulkano_window
+                    .window()
+                    .set_min_inner_size(Some(min_inner_size));
+                if constraints.max_width.is_finite() && constraints.max_height.is_finite() {
+                    vulkano_window
+                        .window()
+                        .set_max_inner_size(Some(max_inner_size));
+                }
+            }
+
+            if window.position != cache.window.position {
+                if let Some(position) = crate::winit_window_position(
+                    &window.position,
+                    &window.resolution,
+                    vulkano_window.window().available_monitors(),
+                    vulkano_window.window().primary_monitor(),
+                    vulkano_window.window().current_monitor(),
+                ) {
+                    let should_set = match vulkano_window.window().outer_position() {
+                        Ok(current_position) => current_position != position,
+                        _ => true,
+                    };
+
+                    if should_set {
+                        vulkano_window.window().set_outer_position(position);
+                    }
+                }
+            }
+
+            if let Some(maximized) = window.internal.take_maximize_request() {
+                vulkano_window.window().set_maximized(maximized);
+            }
+
+            if let Some(minimized) = window.internal.take_minimize_request() {
+                vulkano_window.window().set_minimized(minimized);
+            }
+
+            if window.focused != cache.window.focused && window.focused {
+                vulkano_window.window().focus_window();
+            }
+
+            if window.window_level != cache.window.window_level {
+                vulkano_window
+                    .window()
+                    .set_window_level(convert_window_level(window.window_level));
+            }
+
+            if window.present_mode != cache.window.present_mode {
+                vulkano_window
+                    .renderer
+                    .request_present_mode(window.present_mode);
+            }
+
+            // Currently unsupported changes
+            if window.transparent != cache.window.transparent {
+                window.transparent = cache.window.transparent;
+                warn!(
+                    "Winit does not currently support updating transparency after window creation."
+                );
+            }
+
+            if window.ime_enabled != cache.window.ime_enabled {
+                vulkano_window.window().set_ime_allowed(window.ime_enabled);
+            }
+
+            if window.ime_position != cache.window.ime_position {
+                vulkano_window
+                    .window()
+                    .set_ime_position(LogicalPosition::new(
+                        window.ime_position.x,
+                        window.ime_position.y,
+                    ));
+            }
+
+            cache.window = window.clone();
+        }
+    }
+}
+
+/// Keeps the current size of axes which are zero (also from NaN sizes) and clamps others to the
+/// maximum image dimension, so swapchain recreation doesn't fail.
+fn clamp_resolution(requested: [u32; 2], current: [u32; 2], max_dimension: u32) -> [u32; 2] {
+    std::array::from_fn(|i| {
+        if requested[i] == 0 {
+            current[i].clamp(1, max_dimension)
+        } else {
+            requested[i].min(max_dimension)
+        }
+    })
+}
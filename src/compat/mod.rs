@@ -0,0 +1,14 @@
+//! Glue for the bevy and winit releases bevy_vulkano builds against.
+//!
+//! Everything that changes between bevy releases (winit event conversion, window component
+//! diffing, schedule labels, items moved between bevy modules) is imported through this module
+//! instead of from bevy directly. Each supported bevy minor release has a module selected by a
+//! cargo feature of the same name, so supporting a new release means adding a module and feature
+//! next to `bevy_0_12`, rather than touching the runner and systems. As long as 0.12 is the only
+//! supported release, its module is also used without the feature, so builds with
+//! `default-features = false` keep working.
+
+mod bevy_0_12;
+
+pub use bevy_0_12::SUPPORTED_BEVY_VERSION;
+pub(crate) use bevy_0_12::*;
@@ -0,0 +1,554 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bevy::utils::HashMap;
+use vulkano::{
+    buffer::{BufferContents, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, PersistentDescriptorSet,
+        WriteDescriptorSet,
+    },
+    device::{DeviceOwned, Queue},
+    format::{Format, NumericType},
+    image::{
+        sampler::Sampler,
+        view::{ImageView, ImageViewType},
+        ImageUsage,
+    },
+    pipeline::{
+        compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    shader::{DescriptorBindingRequirements, EntryPoint},
+    sync::GpuFuture,
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk, CommandStats};
+
+/// Why a resource can't be bound to a named binding of a [`ComputePass`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComputeBindingError {
+    /// No binding was named `name` with [`ComputePass::with_binding`].
+    UnknownName { name: String },
+    /// The shader declares a different kind of descriptor, e.g. a buffer bound to an image.
+    DescriptorType {
+        name: String,
+        location: (u32, u32),
+        declared: DescriptorType,
+        expected: Vec<DescriptorType>,
+    },
+    /// The image lacks the usage the descriptor needs, e.g. `STORAGE` for storage images.
+    ImageUsage {
+        name: String,
+        location: (u32, u32),
+        required: ImageUsage,
+    },
+    /// The buffer lacks the usage the descriptor needs.
+    BufferUsage {
+        name: String,
+        location: (u32, u32),
+        required: BufferUsage,
+    },
+    /// The shader declares a storage image format, e.g. `layout(rgba8)`, which the image doesn't
+    /// have.
+    Format {
+        name: String,
+        location: (u32, u32),
+        declared: Format,
+        bound: Format,
+    },
+    /// The shader reads float, signed or unsigned integer texels, e.g. `image2D` vs `uimage2D`,
+    /// which doesn't match the image's format.
+    NumericType {
+        name: String,
+        location: (u32, u32),
+        declared: NumericType,
+        bound: Format,
+    },
+    /// The shader declares another image dimensionality, e.g. `image3D` for a 2D image view.
+    ViewType {
+        name: String,
+        location: (u32, u32),
+        declared: ImageViewType,
+        bound: ImageViewType,
+    },
+}
+
+impl std::fmt::Display for ComputeBindingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComputeBindingError::UnknownName {
+                name,
+            } => write!(f, "compute pass has no binding named {name}"),
+            ComputeBindingError::DescriptorType {
+                name,
+                location,
+                declared,
+                expected,
+            } => write!(
+                f,
+                "binding {name} (set {}, binding {}) is declared as {declared:?} in the shader, \
+                 but the bound resource needs one of {expected:?}",
+                location.0, location.1
+            ),
+            ComputeBindingError::ImageUsage {
+                name,
+                location,
+                required,
+            } => write!(
+                f,
+                "image bound to {name} (set {}, binding {}) needs usage {required:?}",
+                location.0, location.1
+            ),
+            ComputeBindingError::BufferUsage {
+                name,
+                location,
+                required,
+            } => write!(
+                f,
+                "buffer bound to {name} (set {}, binding {}) needs usage {required:?}",
+                location.0, location.1
+            ),
+            ComputeBindingError::Format {
+                name,
+                location,
+                declared,
+                bound,
+            } => write!(
+                f,
+                "binding {name} (set {}, binding {}) is declared with format {declared:?} in the \
+                 shader, but the bound image is {bound:?}",
+                location.0, location.1
+            ),
+            ComputeBindingError::NumericType {
+                name,
+                location,
+                declared,
+                bound,
+            } => write!(
+                f,
+                "binding {name} (set {}, binding {}) reads {declared:?} texels in the shader, but \
+                 the bound image is {bound:?}",
+                location.0, location.1
+            ),
+            ComputeBindingError::ViewType {
+                name,
+                location,
+                declared,
+                bound,
+            } => write!(
+                f,
+                "binding {name} (set {}, binding {}) is declared as a {declared:?} image in the \
+                 shader, but the bound view is {bound:?}",
+                location.0, location.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ComputeBindingError {}
+
+enum BoundResource {
+    Image(Arc<ImageView>),
+    SampledImage(Arc<ImageView>, Arc<Sampler>),
+    Buffer(Subbuffer<[u8]>),
+}
+
+/// A compute shader with named bindings, creating the pipeline layout from the shader's reflected
+/// descriptor requirements and rebuilding descriptor sets only when a binding changes.
+///
+/// Bound resources are checked against the shader's reflected interface (descriptor type, storage
+/// image format, texel numeric type and dimensionality) and their usage, so mismatches fail with
+/// an error naming the binding rather than in vulkano's descriptor set validation. Dispatches
+/// cover a given extent with the shader's `local_size`, e.g. the extent of a bound image.
+///
+/// ```ignore
+/// let mut pass = ComputePass::new(queue, cs::load(device)?.entry_point("main").unwrap(), [8, 8, 1])
+///     .with_binding("output", 0, 0)
+///     .with_binding("cells", 0, 1);
+/// pass.set_image("output", image.clone());
+/// pass.set_buffer("cells", cells.clone());
+/// let after = pass.dispatch(before, pass.image_extent("output").unwrap(), None::<()>);
+/// ```
+pub struct ComputePass {
+    queue: Arc<Queue>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    pipeline: Arc<ComputePipeline>,
+    local_size: [u32; 3],
+    /// Set and binding of each named binding.
+    names: HashMap<String, (u32, u32)>,
+    /// Reflected requirements of the shader's bindings.
+    requirements: HashMap<(u32, u32), DescriptorBindingRequirements>,
+    resources: BTreeMap<(u32, u32), BoundResource>,
+    /// Descriptor sets built from the current resources, `None` when a binding has changed.
+    sets: Vec<Option<Arc<PersistentDescriptorSet>>>,
+}
+
+impl ComputePass {
+    /// Creates the pass for `compute_shader`, whose work group size is `local_size`.
+    pub fn new(queue: Arc<Queue>, compute_shader: EntryPoint, local_size: [u32; 3]) -> ComputePass {
+        let device = queue.device().clone();
+        let requirements = compute_shader
+            .info()
+            .descriptor_binding_requirements
+            .iter()
+            .map(|(location, requirements)| (*location, requirements.clone()))
+            .collect();
+        let stage = PipelineShaderStageCreateInfo::new(compute_shader);
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+        let set_count = layout.set_layouts().len();
+        let pipeline = ComputePipeline::new(
+            device.clone(),
+            None,
+            ComputePipelineCreateInfo::stage_layout(stage, layout),
+        )
+        .unwrap();
+        ComputePass {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
+                device,
+                Default::default(),
+            ),
+            queue,
+            pipeline,
+            local_size: local_size.map(|size| size.max(1)),
+            names: HashMap::default(),
+            requirements,
+            resources: BTreeMap::new(),
+            sets: vec![None; set_count],
+        }
+    }
+
+    /// Names the shader's binding `binding` of descriptor set `set`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the shader doesn't use the binding.
+    pub fn with_binding(mut self, name: impl Into<String>, set: u32, binding: u32) -> ComputePass {
+        let name = name.into();
+        assert!(
+            self.descriptor_type(set, binding).is_some(),
+            "compute shader has no binding {binding} in set {set} for {name}"
+        );
+        self.names.insert(name, (set, binding));
+        self
+    }
+
+    /// The compute pipeline, e.g. for binding additional descriptor sets with the same layout.
+    pub fn pipeline(&self) -> Arc<ComputePipeline> {
+        self.pipeline.clone()
+    }
+
+    pub fn local_size(&self) -> [u32; 3] {
+        self.local_size
+    }
+
+    /// Binds a storage or sampled image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image doesn't match the binding, see [`ComputePass::try_set_image`].
+    pub fn set_image(&mut self, name: &str, view: Arc<ImageView>) {
+        self.try_set_image(name, view)
+            .unwrap_or_else(|error| panic!("{error}"));
+    }
+
+    /// Binds a storage or sampled image, checking it against the shader's declaration.
+    pub fn try_set_image(
+        &mut self,
+        name: &str,
+        view: Arc<ImageView>,
+    ) -> Result<(), ComputeBindingError> {
+        let location = self.location(name, &[
+            DescriptorType::StorageImage,
+            DescriptorType::SampledImage,
+        ])?;
+        self.check_image(name, location, &view)?;
+        self.bind(location, BoundResource::Image(view));
+        Ok(())
+    }
+
+    /// Binds an image sampled with `sampler` to a combined image sampler binding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image doesn't match the binding, see [`ComputePass::try_set_sampled_image`].
+    pub fn set_sampled_image(&mut self, name: &str, view: Arc<ImageView>, sampler: Arc<Sampler>) {
+        self.try_set_sampled_image(name, view, sampler)
+            .unwrap_or_else(|error| panic!("{error}"));
+    }
+
+    /// Binds an image sampled with `sampler`, checking it against the shader's declaration.
+    pub fn try_set_sampled_image(
+        &mut self,
+        name: &str,
+        view: Arc<ImageView>,
+        sampler: Arc<Sampler>,
+    ) -> Result<(), ComputeBindingError> {
+        let location = self.location(name, &[DescriptorType::CombinedImageSampler])?;
+        self.check_image(name, location, &view)?;
+        self.bind(location, BoundResource::SampledImage(view, sampler));
+        Ok(())
+    }
+
+    /// Binds a storage or uniform buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer doesn't match the binding, see [`ComputePass::try_set_buffer`].
+    pub fn set_buffer<T: ?Sized>(&mut self, name: &str, buffer: Subbuffer<T>) {
+        self.try_set_buffer(name, buffer)
+            .unwrap_or_else(|error| panic!("{error}"));
+    }
+
+    /// Binds a storage or uniform buffer, checking its usage against the shader's declaration.
+    pub fn try_set_buffer<T: ?Sized>(
+        &mut self,
+        name: &str,
+        buffer: Subbuffer<T>,
+    ) -> Result<(), ComputeBindingError> {
+        let location = self.location(name, &[
+            DescriptorType::StorageBuffer,
+            DescriptorType::UniformBuffer,
+        ])?;
+        let required = match self.descriptor_type(location.0, location.1) {
+            Some(DescriptorType::UniformBuffer) => BufferUsage::UNIFORM_BUFFER,
+            _ => BufferUsage::STORAGE_BUFFER,
+        };
+        if !buffer.buffer().usage().contains(required) {
+            return Err(ComputeBindingError::BufferUsage {
+                name: name.to_string(),
+                location,
+                required,
+            });
+        }
+        self.bind(location, BoundResource::Buffer(buffer.into_bytes()));
+        Ok(())
+    }
+
+    /// Extent of the image bound to `name`.
+    pub fn image_extent(&self, name: &str) -> Option<[u32; 3]> {
+        let location = self.names.get(name)?;
+        match self.resources.get(location)? {
+            BoundResource::Image(view) | BoundResource::SampledImage(view, _) => {
+                Some(view.image().extent())
+            }
+            BoundResource::Buffer(_) => None,
+        }
+    }
+
+    /// Number of work groups needed to cover `extent`.
+    pub fn group_count(&self, extent: [u32; 3]) -> [u32; 3] {
+        [
+            extent[0].max(1).div_ceil(self.local_size[0]),
+            extent[1].max(1).div_ceil(self.local_size[1]),
+            extent[2].max(1).div_ceil(self.local_size[2]),
+        ]
+    }
+
+    /// Records a dispatch covering `extent` into `builder`, e.g. to record several passes into one
+    /// command buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a binding used by the shader has not been set.
+    pub fn record<L, P: BufferContents>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        extent: [u32; 3],
+        push_constants: Option<P>,
+    ) {
+        let layout = self.pipeline.layout().clone();
+        builder
+            .bind_pipeline_compute(self.pipeline.clone())
+            .unwrap();
+        if !self.sets.is_empty() {
+            let sets = (0..self.sets.len() as u32)
+                .map(|set| self.descriptor_set(set))
+                .collect::<Vec<_>>();
+            builder
+                .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, sets)
+                .unwrap();
+        }
+        if let Some(push_constants) = push_constants {
+            builder.push_constants(layout, 0, push_constants).unwrap();
+        }
+        builder.dispatch(self.group_count(extent)).unwrap();
+        CommandStats::record_pipeline_bind();
+        CommandStats::record_dispatches(1);
+    }
+
+    /// Dispatches the shader over `extent` after `before_future`.
+    pub fn dispatch<P: BufferContents>(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        extent: [u32; 3],
+        push_constants: Option<P>,
+    ) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::compute_pass");
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        self.record(&mut builder, extent, push_constants);
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit compute pass dispatch over {:?} on queue family {}",
+            extent,
+            self.queue.queue_family_index()
+        );
+        before_future
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+
+    fn descriptor_type(&self, set: u32, binding: u32) -> Option<DescriptorType> {
+        self.pipeline
+            .layout()
+            .set_layouts()
+            .get(set as usize)?
+            .bindings()
+            .get(&binding)
+            .map(|binding| binding.descriptor_type)
+    }
+
+    /// Set and binding of `name`, checking that its descriptor type is one of `expected`.
+    fn location(
+        &self,
+        name: &str,
+        expected: &[DescriptorType],
+    ) -> Result<(u32, u32), ComputeBindingError> {
+        let location = *self
+            .names
+            .get(name)
+            .ok_or_else(|| ComputeBindingError::UnknownName {
+                name: name.to_string(),
+            })?;
+        let declared = self.descriptor_type(location.0, location.1).unwrap();
+        if !expected.contains(&declared) {
+            return Err(ComputeBindingError::DescriptorType {
+                name: name.to_string(),
+                location,
+                declared,
+                expected: expected.to_vec(),
+            });
+        }
+        Ok(location)
+    }
+
+    /// Checks the usage, format, numeric type and dimensionality of an image against the
+    /// shader's requirements of the binding at `location`.
+    fn check_image(
+        &self,
+        name: &str,
+        location: (u32, u32),
+        view: &ImageView,
+    ) -> Result<(), ComputeBindingError> {
+        let required = match self.descriptor_type(location.0, location.1) {
+            Some(DescriptorType::StorageImage) => ImageUsage::STORAGE,
+            _ => ImageUsage::SAMPLED,
+        };
+        if !view.usage().contains(required) {
+            return Err(ComputeBindingError::ImageUsage {
+                name: name.to_string(),
+                location,
+                required,
+            });
+        }
+        let Some(requirements) = self.requirements.get(&location) else {
+            return Ok(());
+        };
+        if let Some(declared) = requirements.image_format {
+            if declared != view.format() {
+                return Err(ComputeBindingError::Format {
+                    name: name.to_string(),
+                    location,
+                    declared,
+                    bound: view.format(),
+                });
+            }
+        }
+        if let Some(declared) = requirements.image_scalar_type {
+            let bound = view
+                .format()
+                .numeric_format_color()
+                .map(|numeric_format| numeric_format.numeric_type());
+            if bound.is_some_and(|bound| bound != declared) {
+                return Err(ComputeBindingError::NumericType {
+                    name: name.to_string(),
+                    location,
+                    declared,
+                    bound: view.format(),
+                });
+            }
+        }
+        if let Some(declared) = requirements.image_view_type {
+            if declared != view.view_type() {
+                return Err(ComputeBindingError::ViewType {
+                    name: name.to_string(),
+                    location,
+                    declared,
+                    bound: view.view_type(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn bind(&mut self, location: (u32, u32), resource: BoundResource) {
+        self.resources.insert(location, resource);
+        self.sets[location.0 as usize] = None;
+    }
+
+    fn descriptor_set(&mut self, set: u32) -> Arc<PersistentDescriptorSet> {
+        if let Some(descriptor_set) = &self.sets[set as usize] {
+            return descriptor_set.clone();
+        }
+        let set_layout = self.pipeline.layout().set_layouts()[set as usize].clone();
+        let writes = set_layout
+            .bindings()
+            .keys()
+            .map(|&binding| {
+                let resource = self.resources.get(&(set, binding)).unwrap_or_else(|| {
+                    panic!("binding {binding} of set {set} of compute pass has not been set")
+                });
+                match resource {
+                    BoundResource::Image(view) => {
+                        WriteDescriptorSet::image_view(binding, view.clone())
+                    }
+                    BoundResource::SampledImage(view, sampler) => {
+                        WriteDescriptorSet::image_view_sampler(
+                            binding,
+                            view.clone(),
+                            sampler.clone(),
+                        )
+                    }
+                    BoundResource::Buffer(buffer) => {
+                        WriteDescriptorSet::buffer(binding, buffer.clone())
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        let descriptor_set =
+            PersistentDescriptorSet::new(&self.descriptor_set_allocator, set_layout, writes, [])
+                .unwrap();
+        self.sets[set as usize] = Some(descriptor_set.clone());
+        descriptor_set
+    }
+}
@@ -1,10 +1,29 @@
 use std::{
     fmt::{Debug, Formatter},
+    sync::Arc,
     time::Duration,
 };
 
+use bevy::prelude::{Component, Event};
+use vulkano::{
+    device::Features,
+    format::Format,
+    image::ImageUsage,
+    swapchain::{ColorSpace, CompositeAlpha},
+};
 use vulkano_util::context::VulkanoConfig;
 
+#[cfg(feature = "gui")]
+use crate::GuiInputFilter;
+use crate::{
+    device_info::DriverWorkarounds,
+    gpu_driven::{buffer_device_address_features, draw_indirect_count_features},
+    multiview::multiview_features,
+    texture_filtering::sampler_anisotropy_features,
+    validation::ValidationMode,
+    HeadlessConfig, PresentGroup, PresentQueue, SwapchainFormatSelector, TextureFiltering,
+};
+
 /// A resource for configuring usage winit and Vulkano
 pub struct BevyVulkanoSettings {
     /// Configures `winit` to return control to the caller after exiting the
@@ -34,16 +53,145 @@ pub struct BevyVulkanoSettings {
     pub focused_mode: UpdateMode,
     /// Configures how the winit event loop updates while the window is *not* focused.
     pub unfocused_mode: UpdateMode,
-    /// Configuration of vulkano (device etc.)
+    /// Limits the update and present rate while no window is focused, on top of
+    /// [`unfocused_mode`](Self::unfocused_mode). Reduces background GPU usage, e.g. on laptops.
+    pub throttle_when_unfocused: Option<ThrottleWhenUnfocused>,
+    /// Configuration of vulkano (device etc.). After startup, holds the configuration the device
+    /// was created with, used again on [`ReinitializeDevice`](crate::ReinitializeDevice).
     pub vulkano_config: VulkanoConfig,
+    /// Whether present modes which may tear are acceptable when resolving bevy's
+    /// [`PresentMode::AutoVsync`](bevy::window::PresentMode::AutoVsync) and
+    /// [`PresentMode::AutoNoVsync`](bevy::window::PresentMode::AutoNoVsync).
+    ///
+    /// When `true` (default), `AutoVsync` prefers `FifoRelaxed` (adaptive sync) and `AutoNoVsync`
+    /// falls back to `Immediate` if `Mailbox` is unavailable. When `false`, both resolve to
+    /// non-tearing modes only.
+    pub allow_tearing: bool,
+    /// Enables the `buffer_device_address` device feature, allowing shaders to access buffers
+    /// through their device address. Only devices supporting the feature are considered. See
+    /// [`create_device_address_buffer`](crate::create_device_address_buffer).
+    pub buffer_device_address: bool,
+    /// Enables the `draw_indirect_count` device feature, or the `khr_draw_indirect_count`
+    /// extension on older devices, letting
+    /// [`IndirectCountDrawBuffer::record_draw_indirect_count`](crate::IndirectCountDrawBuffer::record_draw_indirect_count)
+    /// draw a GPU written number of commands. Enabled only if the selected device supports it.
+    pub draw_indirect_count: bool,
+    /// Enables the `multiview` device feature used for rendering to multiple array layers (e.g.
+    /// stereo) in a single pass. Only devices supporting the feature are considered. See
+    /// [`create_multiview_render_pass`](crate::create_multiview_render_pass).
+    pub multiview: bool,
+    /// Enables the `ext_swapchain_colorspace` instance extension if available, so surfaces report
+    /// HDR color spaces, see [`VulkanoWindowConfig::preferred_color_space`].
+    pub hdr: bool,
+    /// Enables the `sampler_anisotropy` device feature, needed for the anisotropy of
+    /// [`TextureFiltering`]. Only devices supporting the feature are considered.
+    pub sampler_anisotropy: bool,
+    /// Initial value of the [`TextureFiltering`] resource.
+    pub texture_filtering: TextureFiltering,
+    /// Enables the `ext_memory_budget` device extension, so the
+    /// [`GpuMemoryBudget`](crate::GpuMemoryBudget) reports the budget and usage of the driver.
+    /// Only devices supporting the extension are considered. Without it, only allocations tracked
+    /// by the crate count as usage.
+    pub memory_budget: bool,
+    /// Additional usages of swapchain images, e.g. `STORAGE` for writing the final image from a
+    /// compute shader. `COLOR_ATTACHMENT` is always included and `TRANSFER_SRC` is added when
+    /// supported to allow captures. Usages not supported by a window's surface or swapchain format
+    /// are dropped with a warning, see
+    /// [`VulkanoWindowRenderer::swapchain_image_usage`](crate::VulkanoWindowRenderer::swapchain_image_usage).
+    pub swapchain_image_usage: ImageUsage,
+    /// Preferred minimum number of swapchain images: 2 for lower latency, 3 or more for higher
+    /// throughput. Clamped to the surface capabilities, see
+    /// [`VulkanoWindowRenderer::swapchain_image_count`](crate::VulkanoWindowRenderer::swapchain_image_count)
+    /// for the final count. `None` (default) uses the surface minimum, but at least 2.
+    pub min_swapchain_image_count: Option<u32>,
+    /// What happens when the cursor grab mode requested by a window is not supported. Failures
+    /// are reported as [`CursorGrabFailed`](crate::CursorGrabFailed) events either way.
+    pub cursor_grab_fallback: CursorGrabFallback,
+    /// Queue swapchains are presented from. Windows whose surface can't be presented to from the
+    /// preferred queue use the other queue of the context.
+    pub present_queue: PresentQueue,
+    /// Defers the presents of all windows to the end of the frame and presents them
+    /// back-to-back. `None` (default) presents each window when its renderer's `present` is
+    /// called.
+    pub present_group: Option<PresentGroup>,
+    /// Driver workarounds applied by the crate. `None` (default) detects them from the device, see
+    /// [`DriverWorkarounds::detect`].
+    pub driver_workarounds: Option<DriverWorkarounds>,
+    /// Defers creating the renderers (surface, swapchain and gui) of new windows to the start of
+    /// the following frames, creating at most this many per frame. Avoids long stalls when many
+    /// windows are spawned at once. [`WindowRendererReady`](crate::WindowRendererReady) is sent
+    /// once a window's renderer exists. `None` (default) creates renderers with their windows.
+    pub deferred_renderers_per_frame: Option<usize>,
+    /// Enables `VK_LAYER_KHRONOS_validation` with a debug messenger handling its errors as set by
+    /// the [`ValidationMode`], e.g. [`ValidationMode::Panic`] to fail tests on synchronization
+    /// errors in CI. Errors are reported to the [`ValidationErrors`](crate::ValidationErrors)
+    /// resource. Overrides `vulkano_config.debug_create_info`. `None` (default) leaves the
+    /// instance as configured.
+    pub validation: Option<ValidationMode>,
+    /// Extra instance layers to enable, e.g. `VK_LAYER_LUNARG_api_dump`. Layers can also be listed
+    /// in the [`INSTANCE_LAYERS_ENV`](crate::INSTANCE_LAYERS_ENV) environment variable. Requested
+    /// layers which aren't installed are skipped with a warning.
+    pub extra_instance_layers: Vec<String>,
+    /// Creates a [`BevyVulkanoHeadlessRenderer`](crate::BevyVulkanoHeadlessRenderer) non-send
+    /// resource for rendering without windows, e.g. when `WindowPlugin` has no primary window.
+    /// `None` (default) creates none.
+    pub headless: Option<HeadlessConfig>,
     /// Whether the image gets cleared each frame by gui integration. This is only relevant if
     /// `gui` feature is set.
-    /// Default is true, thus you need to clear the image you intend to draw gui on
+    /// Default is true, thus you need to clear the image you intend to draw gui on. When false,
+    /// the gui background is filled with [`GuiClearColor`](crate::GuiClearColor), if inserted.
     #[cfg(feature = "gui")]
     pub is_gui_overlay: bool,
+    /// Whether windows get an egui [`Gui`](egui_winit_vulkano::Gui). Default is true. Windows with
+    /// [`NoGui`] never get one. This is only relevant if `gui` feature is set.
+    #[cfg(feature = "gui")]
+    pub create_gui: bool,
+    /// Which window events handled by egui are withheld from bevy's input events. Default skips
+    /// the events egui consumed. This is only relevant if `gui` feature is set.
+    #[cfg(feature = "gui")]
+    pub gui_input_filter: GuiInputFilter,
 }
 
 impl BevyVulkanoSettings {
+    /// Device features required by the settings, enabled in addition to
+    /// `vulkano_config.device_features`.
+    pub fn required_device_features(&self) -> Features {
+        let mut features = Features::empty();
+        if self.buffer_device_address {
+            features = features.union(&buffer_device_address_features());
+        }
+        if self.multiview {
+            features = features.union(&multiview_features());
+        }
+        if self.sampler_anisotropy {
+            features = features.union(&sampler_anisotropy_features());
+        }
+        features
+    }
+
+    /// Device features enabled if the selected device supports them, without restricting device
+    /// selection.
+    pub fn optional_device_features(&self) -> Features {
+        if self.draw_indirect_count {
+            draw_indirect_count_features()
+        } else {
+            Features::empty()
+        }
+    }
+
+    /// Adds the features required by the settings and `additional` features to the vulkano config
+    /// and restricts physical device selection to devices supporting them.
+    pub(crate) fn apply_required_device_features(&mut self, additional: Features) {
+        let required = self.required_device_features().union(&additional);
+        if required == Features::empty() {
+            return;
+        }
+        self.vulkano_config.device_features = self.vulkano_config.device_features.union(&required);
+        let device_filter_fn = self.vulkano_config.device_filter_fn.clone();
+        self.vulkano_config.device_filter_fn =
+            Arc::new(move |p| device_filter_fn(p) && p.supported_features().contains(&required));
+    }
+
     /// Configure winit with common settings for a game.
     pub fn game() -> Self {
         BevyVulkanoSettings::default()
@@ -69,6 +217,16 @@ impl BevyVulkanoSettings {
             false => &self.unfocused_mode,
         }
     }
+
+    /// Minimum time between updates depending on whether the window is focused or not
+    pub fn min_frame_interval(&self, focused: bool) -> Option<Duration> {
+        match focused {
+            true => None,
+            false => self
+                .throttle_when_unfocused
+                .map(|throttle| throttle.frame_interval()),
+        }
+    }
 }
 
 impl Default for BevyVulkanoSettings {
@@ -77,9 +235,32 @@ impl Default for BevyVulkanoSettings {
             return_from_run: false,
             focused_mode: UpdateMode::Continuous,
             unfocused_mode: UpdateMode::Continuous,
+            throttle_when_unfocused: None,
             vulkano_config: Default::default(),
+            allow_tearing: true,
+            buffer_device_address: false,
+            draw_indirect_count: false,
+            multiview: false,
+            hdr: false,
+            sampler_anisotropy: false,
+            texture_filtering: TextureFiltering::default(),
+            memory_budget: false,
+            swapchain_image_usage: ImageUsage::empty(),
+            min_swapchain_image_count: None,
+            cursor_grab_fallback: CursorGrabFallback::default(),
+            present_queue: PresentQueue::default(),
+            present_group: None,
+            driver_workarounds: None,
+            deferred_renderers_per_frame: None,
+            validation: None,
+            extra_instance_layers: vec![],
+            headless: None,
             #[cfg(feature = "gui")]
             is_gui_overlay: false,
+            #[cfg(feature = "gui")]
+            create_gui: true,
+            #[cfg(feature = "gui")]
+            gui_input_filter: GuiInputFilter::default(),
         }
     }
 }
@@ -90,10 +271,131 @@ impl Debug for BevyVulkanoSettings {
             .field("return_from_run", &self.return_from_run)
             .field("focused_mode", &self.focused_mode)
             .field("unfocused_mode", &self.unfocused_mode)
+            .field("throttle_when_unfocused", &self.throttle_when_unfocused)
+            .field("allow_tearing", &self.allow_tearing)
+            .field("buffer_device_address", &self.buffer_device_address)
+            .field("draw_indirect_count", &self.draw_indirect_count)
+            .field("multiview", &self.multiview)
+            .field("hdr", &self.hdr)
+            .field("sampler_anisotropy", &self.sampler_anisotropy)
+            .field("texture_filtering", &self.texture_filtering)
+            .field("memory_budget", &self.memory_budget)
+            .field("swapchain_image_usage", &self.swapchain_image_usage)
+            .field("min_swapchain_image_count", &self.min_swapchain_image_count)
+            .field("cursor_grab_fallback", &self.cursor_grab_fallback)
+            .field("present_queue", &self.present_queue)
+            .field("present_group", &self.present_group)
+            .field("driver_workarounds", &self.driver_workarounds)
+            .field(
+                "deferred_renderers_per_frame",
+                &self.deferred_renderers_per_frame,
+            )
+            .field("validation", &self.validation)
+            .field("extra_instance_layers", &self.extra_instance_layers)
+            .field("headless", &self.headless)
             .finish()
     }
 }
 
+/// Behavior when a window's cursor grab mode is not supported, e.g. `Confined` on macOS or
+/// `Locked` on Windows and X11.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CursorGrabFallback {
+    /// Use the other grab mode instead: `Locked` for `Confined` and vice versa.
+    #[default]
+    Substitute,
+    /// Leave the cursor ungrabbed, e.g. to implement a custom fallback when handling
+    /// [`CursorGrabFailed`](crate::CursorGrabFailed).
+    Strict,
+}
+
+/// Per-window renderer configuration. Insert this on a window entity alongside the `Window`
+/// component before the window gets created. Windows without it use the defaults.
+#[derive(Component, Debug, Clone)]
+pub struct VulkanoWindowConfig {
+    /// Number of array layers of the swapchain images, e.g. 2 for stereo rendering with
+    /// multiview. Clamped to what the surface supports, see
+    /// [`VulkanoWindowRenderer::swapchain_array_layers`](crate::VulkanoWindowRenderer::swapchain_array_layers).
+    pub image_array_layers: u32,
+    /// Called with the `(format, color space)` pairs supported by the window's surface when the
+    /// swapchain is created, returning the pair to use. `None` (default) uses the crate's format
+    /// selection.
+    pub format_selector: Option<SwapchainFormatSelector>,
+    /// Preferred swapchain format, e.g. `B8G8R8A8_UNORM` for linear output. `None` (default) uses
+    /// the first of [`hdr_formats`](crate::hdr_formats) for the preferred color space, which is
+    /// [`DEFAULT_IMAGE_FORMAT`](crate::DEFAULT_IMAGE_FORMAT) for SDR. When the surface doesn't
+    /// support the format and color space, a supported format with the same color space is used
+    /// instead, then the SDR default.
+    pub image_format: Option<Format>,
+    /// Preferred swapchain color space, e.g. `ExtendedSrgbLinear` with an `R16G16B16A16_SFLOAT`
+    /// format for tone-mapped renderers targeting HDR monitors. HDR color spaces require
+    /// [`BevyVulkanoSettings::hdr`]. Check the result with
+    /// [`VulkanoWindowRenderer::is_hdr`](crate::VulkanoWindowRenderer::is_hdr), and the options of
+    /// the surface with
+    /// [`VulkanoWindow::supported_surface_formats`](crate::VulkanoWindow::supported_surface_formats).
+    /// `None` (default) uses `SrgbNonLinear`.
+    pub preferred_color_space: Option<ColorSpace>,
+    /// Preferred minimum number of swapchain images, overriding
+    /// [`BevyVulkanoSettings::min_swapchain_image_count`] for this window. Clamped to the surface
+    /// capabilities.
+    pub min_image_count: Option<u32>,
+    /// How the window is composited with other windows, e.g. `PreMultiplied` for transparent
+    /// windows. `None` (default) or an unsupported mode uses the first mode supported by the
+    /// surface.
+    pub composite_alpha: Option<CompositeAlpha>,
+}
+
+impl Default for VulkanoWindowConfig {
+    fn default() -> Self {
+        VulkanoWindowConfig {
+            image_array_layers: 1,
+            format_selector: None,
+            image_format: None,
+            preferred_color_space: None,
+            min_image_count: None,
+            composite_alpha: None,
+        }
+    }
+}
+
+/// Opts a window out of egui. Insert it on a window entity alongside the `Window` component before
+/// the window gets created. The window gets no [`Gui`](egui_winit_vulkano::Gui) and
+/// [`VulkanoWindow::gui`](crate::VulkanoWindow::gui) is `None`, so it pays no egui costs, e.g. for
+/// a fullscreen game view next to a debug window. Has no effect without feature `gui`.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct NoGui;
+
+/// Limits the frame rate while no window of the app is focused, see
+/// [`BevyVulkanoSettings::throttle_when_unfocused`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThrottleWhenUnfocused {
+    /// Maximum updates (and thus presents) per second.
+    pub max_frame_rate: f64,
+}
+
+impl Default for ThrottleWhenUnfocused {
+    fn default() -> Self {
+        ThrottleWhenUnfocused {
+            max_frame_rate: 10.0,
+        }
+    }
+}
+
+impl ThrottleWhenUnfocused {
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.max_frame_rate.max(0.001))
+    }
+}
+
+/// Sent when the app gains focus (any of its windows becomes focused) or loses it (none of its
+/// windows are focused). The runner switches between
+/// [`BevyVulkanoSettings::focused_mode`] and [`BevyVulkanoSettings::unfocused_mode`] at the same
+/// time.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppFocusChanged {
+    pub focused: bool,
+}
+
 /// Configure how the winit event loop should update.
 #[derive(Debug)]
 pub enum UpdateMode {
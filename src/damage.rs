@@ -0,0 +1,90 @@
+use vulkano::{device::Device, swapchain::RectangleLayer};
+
+/// A region of a swapchain image, in physical pixels, that changed since the previous present.
+///
+/// Damage is a hint for the presentation engine through `khr_incremental_present`, which may then
+/// only update the damaged regions on screen, saving power e.g. for gui-only windows that change
+/// little. Every presented image must still be fully rendered: content outside of the damage must
+/// match the previously presented image.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DamageRect {
+    pub offset: [u32; 2],
+    pub extent: [u32; 2],
+}
+
+impl DamageRect {
+    pub fn new(offset: [u32; 2], extent: [u32; 2]) -> DamageRect {
+        DamageRect {
+            offset,
+            extent,
+        }
+    }
+
+    /// Converts an egui rect in points, e.g. `Response::rect` of a widget that changed, into the
+    /// physical pixels it covers. egui_winit_vulkano doesn't report the areas it repainted, so
+    /// gui windows add the damage of their widgets themselves.
+    #[cfg(feature = "gui")]
+    pub fn from_egui_rect(
+        rect: egui_winit_vulkano::egui::Rect,
+        pixels_per_point: f32,
+    ) -> DamageRect {
+        let to_pixels =
+            |points: f32, round: fn(f32) -> f32| round(points * pixels_per_point).max(0.0) as u32;
+        let offset = [
+            to_pixels(rect.min.x, f32::floor),
+            to_pixels(rect.min.y, f32::floor),
+        ];
+        let end = [
+            to_pixels(rect.max.x, f32::ceil),
+            to_pixels(rect.max.y, f32::ceil),
+        ];
+        DamageRect {
+            offset,
+            extent: [
+                end[0].saturating_sub(offset[0]),
+                end[1].saturating_sub(offset[1]),
+            ],
+        }
+    }
+
+    /// Clips the rect to an image extent, `None` if nothing remains.
+    pub fn clipped(&self, extent: [u32; 2]) -> Option<DamageRect> {
+        let offset = [self.offset[0].min(extent[0]), self.offset[1].min(extent[1])];
+        let end = [
+            self.offset[0].saturating_add(self.extent[0]).min(extent[0]),
+            self.offset[1].saturating_add(self.extent[1]).min(extent[1]),
+        ];
+        let clipped = DamageRect {
+            offset,
+            extent: [end[0] - offset[0], end[1] - offset[1]],
+        };
+        (clipped.extent[0] > 0 && clipped.extent[1] > 0).then_some(clipped)
+    }
+}
+
+/// Whether the device has incremental present (`khr_incremental_present`) enabled, without which
+/// damage is ignored and whole images are presented. Enable the extension through
+/// `vulkano_config.device_extensions` of [`BevyVulkanoSettings`](crate::BevyVulkanoSettings).
+pub fn supports_incremental_present(device: &Device) -> bool {
+    device.enabled_extensions().khr_incremental_present
+}
+
+/// Present regions for the damage, on each of `layers` array layers of the swapchain images.
+/// Empty if there's no damage, which presents the whole image.
+pub(crate) fn present_regions(
+    damage: &[DamageRect],
+    extent: [u32; 2],
+    layers: u32,
+) -> Vec<RectangleLayer> {
+    damage
+        .iter()
+        .filter_map(|rect| rect.clipped(extent))
+        .flat_map(|rect| {
+            (0..layers).map(move |layer| RectangleLayer {
+                offset: rect.offset,
+                extent: rect.extent,
+                layer,
+            })
+        })
+        .collect()
+}
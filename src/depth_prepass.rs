@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use bevy::prelude::{Component, Entity, NonSendMut, Query, RemovedComponents};
+use vulkano::{
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents,
+    },
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::view::ImageView,
+    pipeline::{
+        graphics::{
+            depth_stencil::{DepthState, DepthStencilState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::VertexInputState,
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        DynamicState, GraphicsPipeline, PipelineLayout, PipelineShaderStageCreateInfo,
+    },
+    render_pass::{RenderPass, Subpass},
+    shader::EntryPoint,
+    sync::GpuFuture,
+};
+
+use crate::{
+    profiling::profile_scope, trace_frame::trace_vk, BevyVulkanoWindows, FramebufferCache,
+};
+
+/// Gives a window a shared depth image of `format`, following the swapchain size. Get it with
+/// [`VulkanoWindowRenderer::shared_depth`](crate::VulkanoWindowRenderer::shared_depth) so all
+/// passes of the window use the same depth buffer instead of allocating their own. Removing the
+/// component drops the image. Without the component, the image is created on first use of
+/// [`VulkanoWindowRenderer::depth_image_view`](crate::VulkanoWindowRenderer::depth_image_view).
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedDepth {
+    pub format: Format,
+}
+
+impl Default for SharedDepth {
+    fn default() -> Self {
+        SharedDepth {
+            format: Format::D32_SFLOAT,
+        }
+    }
+}
+
+/// Creates, recreates and drops the shared depth images of windows to match their
+/// [`SharedDepth`]. Depth images of windows which never had the component are left to
+/// [`VulkanoWindowRenderer::depth_image_view`](crate::VulkanoWindowRenderer::depth_image_view).
+pub(crate) fn update_shared_depth(
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    shared_depths: Query<(Entity, &SharedDepth)>,
+    mut removed: RemovedComponents<SharedDepth>,
+) {
+    for window in removed.read() {
+        if shared_depths.contains(window) {
+            continue;
+        }
+        if let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(window) {
+            vulkano_window.renderer.disable_shared_depth();
+        }
+    }
+    for (window, shared_depth) in &shared_depths {
+        if let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(window) {
+            vulkano_window
+                .renderer
+                .depth_image_view(shared_depth.format);
+        }
+    }
+}
+
+/// A depth-only pass clearing a depth image and recording user draws into it, e.g. to fill the
+/// [`SharedDepth`] image of a window before shading so later passes only shade visible fragments.
+///
+/// Create the depth-only pipelines with [`DepthPrepass::pipeline`], and load the depth in later
+/// passes (`load_op: Load`) with an `Equal` or `LessOrEqual` depth test.
+pub struct DepthPrepass {
+    queue: Arc<Queue>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    render_pass: Arc<RenderPass>,
+    framebuffers: FramebufferCache,
+}
+
+impl DepthPrepass {
+    pub fn new(queue: Arc<Queue>, depth_format: Format) -> DepthPrepass {
+        let device = queue.device().clone();
+        let render_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                depth: {
+                    format: depth_format,
+                    samples: 1,
+                    load_op: Clear,
+                    store_op: Store,
+                }
+            },
+            pass: {
+                    color: [],
+                    depth_stencil: {depth}
+            }
+        )
+        .unwrap();
+        DepthPrepass {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                device,
+                Default::default(),
+            ),
+            queue,
+            render_pass,
+            framebuffers: FramebufferCache::new(),
+        }
+    }
+
+    pub fn render_pass(&self) -> Arc<RenderPass> {
+        self.render_pass.clone()
+    }
+
+    pub fn subpass(&self) -> Subpass {
+        Subpass::from(self.render_pass.clone(), 0).unwrap()
+    }
+
+    /// Creates a depth-only pipeline for the pass from a vertex shader, writing depth with a
+    /// `Less` depth test. The viewport is dynamic and set by [`DepthPrepass::render`].
+    pub fn pipeline(
+        &self,
+        vertex_shader: EntryPoint,
+        vertex_input_state: VertexInputState,
+    ) -> Arc<GraphicsPipeline> {
+        let device = self.queue.device().clone();
+        let stages = [PipelineShaderStageCreateInfo::new(vertex_shader)];
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+                .into_pipeline_layout_create_info(device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+        GraphicsPipeline::new(device, None, GraphicsPipelineCreateInfo {
+            stages: stages.into_iter().collect(),
+            vertex_input_state: Some(vertex_input_state),
+            input_assembly_state: Some(InputAssemblyState::default()),
+            viewport_state: Some(ViewportState::default()),
+            rasterization_state: Some(RasterizationState::default()),
+            multisample_state: Some(MultisampleState::default()),
+            depth_stencil_state: Some(DepthStencilState {
+                depth: Some(DepthState::simple()),
+                ..Default::default()
+            }),
+            dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+            subpass: Some(self.subpass().into()),
+            ..GraphicsPipelineCreateInfo::layout(layout)
+        })
+        .unwrap()
+    }
+
+    /// Clears `depth` to 1.0 and records `draw` into the pass, with the viewport covering the
+    /// whole image. `draw` binds its pipelines and resources.
+    pub fn render(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        depth: Arc<ImageView>,
+        draw: impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>),
+    ) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::depth_prepass");
+        let extent = depth.image().extent();
+        let framebuffer = self.framebuffers.get(&self.render_pass, &[depth]);
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![Some(1.0f32.into())],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassBeginInfo {
+                    contents: SubpassContents::Inline,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .set_viewport(
+                0,
+                [Viewport {
+                    offset: [0.0, 0.0],
+                    extent: [extent[0] as f32, extent[1] as f32],
+                    depth_range: 0.0..=1.0,
+                }]
+                .into_iter()
+                .collect(),
+            )
+            .unwrap();
+        draw(&mut builder);
+        builder.end_render_pass(Default::default()).unwrap();
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit depth prepass to {:?} on queue family {}",
+            extent,
+            self.queue.queue_family_index()
+        );
+        before_future
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
@@ -0,0 +1,210 @@
+use bevy::prelude::Resource;
+use vulkano::{
+    device::physical::{ConformanceVersion, DriverId, PhysicalDevice, PhysicalDeviceType},
+    image::ImageUsage,
+    memory::allocator::{AllocationCreateInfo, MemoryAllocatePreference},
+    DeviceSize, Version,
+};
+
+/// Commonly needed limits of the physical device in use, inserted as a resource by
+/// [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin) so systems can size dispatches and buffers
+/// without accessing the physical device properties.
+#[derive(Resource, Debug, Clone)]
+pub struct GpuLimits {
+    pub max_image_dimension_1d: u32,
+    pub max_image_dimension_2d: u32,
+    pub max_image_dimension_3d: u32,
+    pub max_image_dimension_cube: u32,
+    pub max_image_array_layers: u32,
+    pub max_framebuffer_width: u32,
+    pub max_framebuffer_height: u32,
+    pub max_color_attachments: u32,
+    pub max_compute_work_group_count: [u32; 3],
+    pub max_compute_work_group_size: [u32; 3],
+    pub max_compute_work_group_invocations: u32,
+    pub max_compute_shared_memory_size: u32,
+    pub max_push_constants_size: u32,
+    pub max_bound_descriptor_sets: u32,
+    pub max_uniform_buffer_range: u32,
+    pub max_storage_buffer_range: u32,
+    pub min_uniform_buffer_offset_alignment: DeviceSize,
+    pub min_storage_buffer_offset_alignment: DeviceSize,
+    pub max_sampler_anisotropy: f32,
+    /// Nanoseconds per timestamp query tick.
+    pub timestamp_period: f32,
+}
+
+impl GpuLimits {
+    pub fn from_physical_device(physical_device: &PhysicalDevice) -> GpuLimits {
+        let properties = physical_device.properties();
+        GpuLimits {
+            max_image_dimension_1d: properties.max_image_dimension1_d,
+            max_image_dimension_2d: properties.max_image_dimension2_d,
+            max_image_dimension_3d: properties.max_image_dimension3_d,
+            max_image_dimension_cube: properties.max_image_dimension_cube,
+            max_image_array_layers: properties.max_image_array_layers,
+            max_framebuffer_width: properties.max_framebuffer_width,
+            max_framebuffer_height: properties.max_framebuffer_height,
+            max_color_attachments: properties.max_color_attachments,
+            max_compute_work_group_count: properties.max_compute_work_group_count,
+            max_compute_work_group_size: properties.max_compute_work_group_size,
+            max_compute_work_group_invocations: properties.max_compute_work_group_invocations,
+            max_compute_shared_memory_size: properties.max_compute_shared_memory_size,
+            max_push_constants_size: properties.max_push_constants_size,
+            max_bound_descriptor_sets: properties.max_bound_descriptor_sets,
+            max_uniform_buffer_range: properties.max_uniform_buffer_range,
+            max_storage_buffer_range: properties.max_storage_buffer_range,
+            min_uniform_buffer_offset_alignment: properties
+                .min_uniform_buffer_offset_alignment
+                .as_devicesize(),
+            min_storage_buffer_offset_alignment: properties
+                .min_storage_buffer_offset_alignment
+                .as_devicesize(),
+            max_sampler_anisotropy: properties.max_sampler_anisotropy,
+            timestamp_period: properties.timestamp_period,
+        }
+    }
+
+    /// Number of work groups needed to cover `extent` with work groups of `local_size`, clamped to
+    /// the device's maximum work group count.
+    pub fn work_group_count(&self, extent: [u32; 3], local_size: [u32; 3]) -> [u32; 3] {
+        std::array::from_fn(|i| {
+            extent[i]
+                .div_ceil(local_size[i].max(1))
+                .clamp(1, self.max_compute_work_group_count[i])
+        })
+    }
+
+    /// Rounds `offset` up to the minimum uniform buffer offset alignment.
+    pub fn align_uniform_offset(&self, offset: DeviceSize) -> DeviceSize {
+        offset.next_multiple_of(self.min_uniform_buffer_offset_alignment)
+    }
+
+    /// Rounds `offset` up to the minimum storage buffer offset alignment.
+    pub fn align_storage_offset(&self, offset: DeviceSize) -> DeviceSize {
+        offset.next_multiple_of(self.min_storage_buffer_offset_alignment)
+    }
+}
+
+/// GPU vendor decoded from the PCI vendor id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuVendor {
+    Amd,
+    Apple,
+    Arm,
+    ImgTec,
+    Intel,
+    Nvidia,
+    Qualcomm,
+    Other(u32),
+}
+
+impl GpuVendor {
+    pub fn from_vendor_id(vendor_id: u32) -> GpuVendor {
+        match vendor_id {
+            0x1002 => GpuVendor::Amd,
+            0x106b => GpuVendor::Apple,
+            0x13b5 => GpuVendor::Arm,
+            0x1010 => GpuVendor::ImgTec,
+            0x8086 => GpuVendor::Intel,
+            0x10de => GpuVendor::Nvidia,
+            0x5143 => GpuVendor::Qualcomm,
+            other => GpuVendor::Other(other),
+        }
+    }
+}
+
+/// Vendor and driver information of the physical device in use, inserted as a resource by
+/// [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin). Useful for bug reports and for deciding on
+/// [`DriverWorkarounds`].
+#[derive(Resource, Debug, Clone)]
+pub struct GpuInfo {
+    pub device_name: String,
+    pub device_type: PhysicalDeviceType,
+    pub vendor: GpuVendor,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub api_version: Version,
+    /// Driver version in the vendor specific encoding, see [`GpuInfo::driver_version_string`].
+    pub driver_version: u32,
+    /// Requires Vulkan 1.2 or `khr_driver_properties`.
+    pub driver_id: Option<DriverId>,
+    pub driver_name: Option<String>,
+    pub driver_info: Option<String>,
+    /// Version of the Vulkan conformance test suite the driver passed.
+    pub conformance_version: Option<ConformanceVersion>,
+}
+
+impl GpuInfo {
+    pub fn from_physical_device(physical_device: &PhysicalDevice) -> GpuInfo {
+        let properties = physical_device.properties();
+        GpuInfo {
+            device_name: properties.device_name.clone(),
+            device_type: properties.device_type,
+            vendor: GpuVendor::from_vendor_id(properties.vendor_id),
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            api_version: properties.api_version,
+            driver_version: properties.driver_version,
+            driver_id: properties.driver_id,
+            driver_name: properties.driver_name.clone(),
+            driver_info: properties.driver_info.clone(),
+            conformance_version: properties.conformance_version,
+        }
+    }
+
+    /// Driver version decoded with the vendor's scheme, e.g. `"537.42.0.0"` on NVIDIA.
+    pub fn driver_version_string(&self) -> String {
+        let v = self.driver_version;
+        match self.vendor {
+            GpuVendor::Nvidia => format!(
+                "{}.{}.{}.{}",
+                (v >> 22) & 0x3ff,
+                (v >> 14) & 0xff,
+                (v >> 6) & 0xff,
+                v & 0x3f
+            ),
+            GpuVendor::Intel if cfg!(target_os = "windows") => {
+                format!("{}.{}", v >> 14, v & 0x3fff)
+            }
+            _ => format!("{}.{}.{}", v >> 22, (v >> 12) & 0x3ff, v & 0xfff),
+        }
+    }
+}
+
+/// Workarounds for driver bugs applied inside the crate's image helpers. Detected from
+/// [`GpuInfo`] unless set through
+/// [`BevyVulkanoSettings::driver_workarounds`](crate::BevyVulkanoSettings::driver_workarounds),
+/// and inserted as a resource by [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin).
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DriverWorkarounds {
+    /// Images used both as storage images and color attachments may render black on some NVIDIA
+    /// GTX 10 series and mobile parts when suballocated. Such images get dedicated allocations.
+    pub dedicated_storage_attachment_images: bool,
+}
+
+impl DriverWorkarounds {
+    /// Workarounds needed by the device described by `gpu_info`.
+    pub fn detect(gpu_info: &GpuInfo) -> DriverWorkarounds {
+        let nvidia_affected = gpu_info.vendor == GpuVendor::Nvidia
+            && ["GTX 10", "MX1", "MX2", "MX3"]
+                .iter()
+                .any(|name| gpu_info.device_name.contains(name));
+        DriverWorkarounds {
+            dedicated_storage_attachment_images: nvidia_affected,
+        }
+    }
+
+    /// Allocation info for device local images with `usage`, applying the workarounds.
+    pub fn image_allocation_info(&self, usage: ImageUsage) -> AllocationCreateInfo {
+        let storage_attachment = usage.contains(ImageUsage::STORAGE | ImageUsage::COLOR_ATTACHMENT);
+        if self.dedicated_storage_attachment_images && storage_attachment {
+            AllocationCreateInfo {
+                allocate_preference: MemoryAllocatePreference::AlwaysAllocate,
+                ..Default::default()
+            }
+        } else {
+            AllocationCreateInfo::default()
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::sync::Arc;
+
+use bevy::{
+    ecs::event::Events,
+    log::{error, info},
+    prelude::{Entity, Event, Resource, World},
+    utils::HashMap,
+    window::Window,
+};
+use vulkano::device::physical::{PhysicalDevice, PhysicalDeviceType};
+use vulkano_util::context::{VulkanoConfig, VulkanoContext};
+use winit::event_loop::EventLoopWindowTarget;
+
+use crate::{
+    config::{BevyVulkanoSettings, VulkanoWindowConfig},
+    device_selection::{check_device_selection, resolve_device_features, DeviceSelectionError},
+    vulkano_windows::swapchain_preferences,
+    BevyVulkanoContext, BevyVulkanoHeadlessRenderer, BevyVulkanoWindows, DriverWorkarounds,
+    GpuInfo, GpuLimits, GpuMemoryBudget, PipelineCompiler, RenderTargetRegistry, StartupReport,
+    TextureRegistry, WindowRendererReady,
+};
+
+type DeviceRebuildCallback = Box<dyn Fn(&mut World) + Send + Sync>;
+
+/// Which physical device [`ReinitializeDevice`] switches to. The device must also pass the
+/// `device_filter_fn` of the settings' vulkano config, and support the required extensions and
+/// features.
+#[derive(Clone)]
+pub enum DeviceSelection {
+    /// The device preferred by the vulkano config, as at startup.
+    Default,
+    /// The device with this name, see [`GpuInfo::device_name`](crate::GpuInfo).
+    Named(String),
+    /// The preferred device of a type, e.g. `IntegratedGpu` to save power.
+    Type(PhysicalDeviceType),
+    /// The preferred device passing the filter.
+    Filter(Arc<dyn Fn(&PhysicalDevice) -> bool + Send + Sync>),
+}
+
+impl std::fmt::Debug for DeviceSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceSelection::Default => f.write_str("Default"),
+            DeviceSelection::Named(name) => f.debug_tuple("Named").field(name).finish(),
+            DeviceSelection::Type(device_type) => f.debug_tuple("Type").field(device_type).finish(),
+            DeviceSelection::Filter(_) => f.write_str("Filter"),
+        }
+    }
+}
+
+impl DeviceSelection {
+    fn matches(&self, physical_device: &PhysicalDevice) -> bool {
+        match self {
+            DeviceSelection::Default => true,
+            DeviceSelection::Named(name) => &physical_device.properties().device_name == name,
+            DeviceSelection::Type(device_type) => {
+                physical_device.properties().device_type == *device_type
+            }
+            DeviceSelection::Filter(filter) => filter(physical_device),
+        }
+    }
+}
+
+/// Send to tear down the Vulkan device and recreate it with a new [`DeviceSelection`] without
+/// restarting the app, e.g. when the user switches GPUs in the settings.
+///
+/// The device is recreated between frames. The [`BevyVulkanoContext`], [`GpuInfo`],
+/// [`GpuLimits`], [`DriverWorkarounds`] and [`StartupReport`] resources are replaced, the
+/// renderers and guis of all windows are recreated, as well as the
+/// [`BevyVulkanoHeadlessRenderer`], and the [`PipelineCompiler`], [`RenderTargetRegistry`],
+/// [`TextureRegistry`] and [`TextureUploads`](crate::TextureUploads) are cleared, as well as the
+/// peaks of the [`GpuMemoryBudget`]. Window renderers start without additional image views, frame
+/// hooks or user data, and [`WindowRendererReady`] is sent for each window again.
+///
+/// Resources of the app created on the previous device must be recreated: register callbacks
+/// with [`DeviceRebuildCallbacks`], or react to [`DeviceRestored`]. If no device matches, the
+/// current device is kept and [`DeviceReinitializeFailed`] is sent.
+#[derive(Event, Debug, Clone)]
+pub struct ReinitializeDevice {
+    pub selection: DeviceSelection,
+}
+
+/// Sent after the device was recreated for [`ReinitializeDevice`] and the
+/// [`DeviceRebuildCallbacks`] have run.
+#[derive(Event, Debug, Clone)]
+pub struct DeviceRestored {
+    /// Name of the new physical device.
+    pub device_name: String,
+}
+
+/// Sent when [`ReinitializeDevice`] found no suitable device. The previous device is kept.
+#[derive(Event, Debug, Clone)]
+pub struct DeviceReinitializeFailed {
+    pub error: DeviceSelectionError,
+}
+
+/// Callbacks run with exclusive world access right after the device was recreated for
+/// [`ReinitializeDevice`], to rebuild pipelines, buffers and images of the app on the new device.
+/// Callbacks run in the order they were added, before [`DeviceRestored`] is sent. Callbacks added
+/// by a callback run from the next rebuild on.
+///
+/// ```ignore
+/// fn setup(mut callbacks: ResMut<DeviceRebuildCallbacks>) {
+///     callbacks.add(|world| {
+///         let pipeline = MyPipeline::new(&world.resource::<BevyVulkanoContext>().context);
+///         world.insert_resource(pipeline);
+///     });
+/// }
+/// ```
+#[derive(Resource, Default)]
+pub struct DeviceRebuildCallbacks {
+    callbacks: Vec<DeviceRebuildCallback>,
+}
+
+impl DeviceRebuildCallbacks {
+    pub fn add(&mut self, callback: impl Fn(&mut World) + Send + Sync + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Removes all callbacks.
+    pub fn clear(&mut self) {
+        self.callbacks.clear();
+    }
+}
+
+/// Driver workarounds given in the settings at startup, rather than detected for the device.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub(crate) struct DriverWorkaroundsOverride(pub Option<DriverWorkarounds>);
+
+/// Copies the parts of `config` needed to create another context. Vulkano's config isn't `Clone`.
+pub(crate) fn copy_vulkano_config(config: &VulkanoConfig) -> VulkanoConfig {
+    VulkanoConfig {
+        instance_create_info: config.instance_create_info.clone(),
+        debug_create_info: config.debug_create_info.clone(),
+        device_filter_fn: config.device_filter_fn.clone(),
+        device_priority_fn: config.device_priority_fn.clone(),
+        device_extensions: config.device_extensions,
+        device_features: config.device_features,
+        print_device_name: config.print_device_name,
+    }
+}
+
+/// Recreates the device and everything created on it for the last [`ReinitializeDevice`] event.
+/// Runs in the winit event loop between updates, as guis need the event loop.
+pub(crate) fn reinitialize_device(world: &mut World, event_loop: &EventLoopWindowTarget<()>) {
+    let Some(request) = world
+        .resource_mut::<Events<ReinitializeDevice>>()
+        .drain()
+        .last()
+    else {
+        return;
+    };
+
+    let settings = world.non_send_resource::<BevyVulkanoSettings>();
+    let optional_features = settings.optional_device_features();
+    let mut vulkano_config = copy_vulkano_config(&settings.vulkano_config);
+    let device_filter_fn = vulkano_config.device_filter_fn.clone();
+    let selection = request.selection.clone();
+    vulkano_config.device_filter_fn =
+        Arc::new(move |p| device_filter_fn(p) && selection.matches(p));
+    if let Err(error) = check_device_selection(&vulkano_config) {
+        error!(
+            "Failed to reinitialize the device with {:?}: {error}",
+            request.selection
+        );
+        world.send_event(DeviceReinitializeFailed {
+            error,
+        });
+        return;
+    }
+
+    resolve_device_features(&mut vulkano_config, optional_features);
+    info!("Reinitializing the device with {:?}", request.selection);
+    {
+        let previous = world.resource::<BevyVulkanoContext>();
+        // Work of the previous device must finish before its resources are dropped
+        let idle = unsafe { previous.context.device().wait_idle() };
+        if let Err(e) = idle {
+            error!("Failed to wait for the previous device: {e}");
+        }
+    }
+
+    let context = BevyVulkanoContext {
+        context: VulkanoContext::new(vulkano_config),
+    };
+    let physical_device = context.context.device().physical_device();
+    let gpu_limits = GpuLimits::from_physical_device(physical_device);
+    let gpu_info = GpuInfo::from_physical_device(physical_device);
+    let driver_workarounds = world
+        .resource::<DriverWorkaroundsOverride>()
+        .0
+        .unwrap_or_else(|| DriverWorkarounds::detect(&gpu_info));
+    let startup_report = StartupReport::new(&context);
+    info!("{startup_report}");
+    let device_name = gpu_info.device_name.clone();
+
+    // Drop resources of the previous device before creating renderers on the new one
+    world.insert_resource(PipelineCompiler::default());
+    world.insert_resource(RenderTargetRegistry::default());
+    world.insert_resource(TextureRegistry::default());
+    #[cfg(feature = "texture_utils")]
+    world.insert_resource(crate::TextureUploads::default());
+    world.resource_mut::<GpuMemoryBudget>().reset();
+    if let Some(headless) = world.remove_non_send_resource::<BevyVulkanoHeadlessRenderer>() {
+        let config = *headless.config();
+        drop(headless);
+        world.insert_non_send_resource(BevyVulkanoHeadlessRenderer::new(
+            &context.context,
+            config,
+            driver_workarounds,
+        ));
+    }
+
+    world
+        .non_send_resource_mut::<BevyVulkanoSettings>()
+        .driver_workarounds = Some(driver_workarounds);
+    let mut windows = world.query::<(Entity, &Window, Option<&VulkanoWindowConfig>)>();
+    let settings = world.non_send_resource::<BevyVulkanoSettings>();
+    let preferences = windows
+        .iter(world)
+        .map(|(entity, window, window_config)| {
+            let window_config = window_config.cloned().unwrap_or_default();
+            (
+                entity,
+                swapchain_preferences(window, &window_config, settings),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    let mut vulkano_windows = world
+        .remove_non_send_resource::<BevyVulkanoWindows>()
+        .unwrap();
+    let recreated = vulkano_windows.recreate_renderers(
+        event_loop,
+        &context.context,
+        world.non_send_resource::<BevyVulkanoSettings>(),
+        &preferences,
+    );
+    world.insert_non_send_resource(vulkano_windows);
+
+    world.insert_resource(context);
+    world.insert_resource(gpu_limits);
+    world.insert_resource(gpu_info);
+    world.insert_resource(driver_workarounds);
+    world.insert_resource(startup_report);
+
+    for entity in recreated {
+        let vulkano_window = world
+            .non_send_resource::<BevyVulkanoWindows>()
+            .get_vulkano_window(entity)
+            .unwrap();
+        let components = (
+            vulkano_window.renderer.render_extent(),
+            vulkano_window.renderer.swapchain_info(),
+        );
+        world.entity_mut(entity).insert(components);
+        world.send_event(WindowRendererReady {
+            window: entity,
+        });
+    }
+
+    // Taken out while running, so callbacks can access the resource, e.g. to add callbacks
+    let callbacks = std::mem::take(&mut world.resource_mut::<DeviceRebuildCallbacks>().callbacks);
+    for callback in callbacks.iter() {
+        callback(world);
+    }
+    let mut rebuild_callbacks = world.resource_mut::<DeviceRebuildCallbacks>();
+    let added = std::mem::replace(&mut rebuild_callbacks.callbacks, callbacks);
+    rebuild_callbacks.callbacks.extend(added);
+    world.send_event(DeviceRestored {
+        device_name,
+    });
+}
@@ -0,0 +1,194 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+use bevy::{
+    ecs::schedule::ScheduleLabel,
+    prelude::{App, Event},
+};
+use vulkano::{
+    device::{
+        physical::{PhysicalDevice, PhysicalDeviceType},
+        DeviceExtensions, Features,
+    },
+    instance::{Instance, InstanceCreateInfo},
+    Version, VulkanLibrary,
+};
+use vulkano_util::context::VulkanoConfig;
+
+/// A physical device considered during device selection, with the reasons it was rejected.
+#[derive(Debug, Clone)]
+pub struct PhysicalDeviceReport {
+    pub name: String,
+    pub device_type: PhysicalDeviceType,
+    pub api_version: Version,
+    /// Whether the device passed `VulkanoConfig::device_filter_fn`.
+    pub passes_filter: bool,
+    /// Required device extensions the device doesn't support.
+    pub missing_extensions: DeviceExtensions,
+    /// Required device features the device doesn't support.
+    pub missing_features: Features,
+}
+
+impl PhysicalDeviceReport {
+    pub fn is_suitable(&self) -> bool {
+        self.passes_filter
+            && self.missing_extensions == DeviceExtensions::empty()
+            && self.missing_features == Features::empty()
+    }
+}
+
+/// Why no Vulkan device could be created.
+#[derive(Debug, Clone)]
+pub enum DeviceSelectionError {
+    /// The Vulkan library or instance could not be loaded.
+    VulkanUnavailable(String),
+    /// No physical device meets the requirements of the settings.
+    NoSuitableDevice(Vec<PhysicalDeviceReport>),
+}
+
+impl Display for DeviceSelectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceSelectionError::VulkanUnavailable(reason) => {
+                writeln!(f, "Vulkan is not available: {reason}")?;
+            }
+            DeviceSelectionError::NoSuitableDevice(devices) => {
+                writeln!(f, "No suitable Vulkan device found.")?;
+                if devices.is_empty() {
+                    writeln!(f, "No Vulkan devices were detected.")?;
+                }
+                for device in devices {
+                    writeln!(
+                        f,
+                        "- {} ({:?}, Vulkan {})",
+                        device.name, device.device_type, device.api_version
+                    )?;
+                    if !device.passes_filter {
+                        writeln!(f, "    rejected by the device filter")?;
+                    }
+                    if device.missing_extensions != DeviceExtensions::empty() {
+                        writeln!(f, "    missing extensions: {:?}", device.missing_extensions)?;
+                    }
+                    if device.missing_features != Features::empty() {
+                        writeln!(f, "    missing features: {:?}", device.missing_features)?;
+                    }
+                }
+            }
+        }
+        write!(f, "Hint: {}", platform_hint())
+    }
+}
+
+impl Error for DeviceSelectionError {}
+
+fn platform_hint() -> &'static str {
+    if cfg!(target_os = "macos") || cfg!(target_os = "ios") {
+        "install the Vulkan SDK (MoltenVK) from https://vulkan.lunarg.com"
+    } else if cfg!(target_os = "linux") {
+        "install the Vulkan drivers of your GPU, e.g. mesa-vulkan-drivers, and the Vulkan loader"
+    } else {
+        "update your GPU drivers to a version supporting Vulkan"
+    }
+}
+
+/// Sent when the plugin could not create a Vulkan device. The app doesn't update after this;
+/// instead the [`VulkanoStartupFailure`] schedule runs once, where systems reading this event can
+/// report the error, e.g. in a native message box.
+#[derive(Event, Debug, Clone)]
+pub struct VulkanoStartupFailed {
+    pub error: DeviceSelectionError,
+}
+
+/// Schedule run once instead of the app's update loop when Vulkan startup fails, see
+/// [`VulkanoStartupFailed`].
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VulkanoStartupFailure;
+
+/// Creates an instance like the context would, for inspecting the physical devices before the
+/// context is created.
+fn probe_instance(config: &VulkanoConfig) -> Result<Arc<Instance>, DeviceSelectionError> {
+    let library =
+        VulkanLibrary::new().map_err(|e| DeviceSelectionError::VulkanUnavailable(e.to_string()))?;
+    Instance::new(library, InstanceCreateInfo {
+        flags: config.instance_create_info.flags,
+        enabled_extensions: config.instance_create_info.enabled_extensions,
+        ..Default::default()
+    })
+    .map_err(|e| DeviceSelectionError::VulkanUnavailable(e.to_string()))
+}
+
+/// The physical device the context will pick for `config`: the one passing the device filter with
+/// the lowest priority value.
+fn selected_physical_device(config: &VulkanoConfig) -> Option<Arc<PhysicalDevice>> {
+    probe_instance(config)
+        .ok()?
+        .enumerate_physical_devices()
+        .ok()?
+        .filter(|p| (config.device_filter_fn)(p))
+        .min_by_key(|p| (config.device_priority_fn)(p))
+}
+
+/// Adapts the features of `config` to the device it selects: enables those of `optional_features`
+/// the device supports, which unlike required features don't restrict device selection, and the
+/// extensions providing features of newer Vulkan versions on devices with an older api version.
+pub(crate) fn resolve_device_features(config: &mut VulkanoConfig, optional_features: Features) {
+    let Some(physical_device) = selected_physical_device(config) else {
+        return;
+    };
+    let supported = optional_features.intersection(physical_device.supported_features());
+    config.device_features = config.device_features.union(&supported);
+    let extensions = physical_device.supported_extensions();
+    if optional_features.draw_indirect_count
+        && !supported.draw_indirect_count
+        && extensions.khr_draw_indirect_count
+    {
+        config.device_extensions.khr_draw_indirect_count = true;
+    }
+    let api_version = physical_device.api_version();
+    if config.device_features.multiview && api_version < Version::V1_1 && extensions.khr_multiview {
+        config.device_extensions.khr_multiview = true;
+    }
+    if config.device_features.buffer_device_address
+        && api_version < Version::V1_2
+        && extensions.khr_buffer_device_address
+    {
+        config.device_extensions.khr_buffer_device_address = true;
+    }
+}
+
+/// Checks that a device satisfying `config` exists before creating the context, which would panic
+/// otherwise.
+pub(crate) fn check_device_selection(config: &VulkanoConfig) -> Result<(), DeviceSelectionError> {
+    let devices = probe_instance(config)?
+        .enumerate_physical_devices()
+        .map_err(|e| DeviceSelectionError::VulkanUnavailable(e.to_string()))?
+        .map(|physical_device| {
+            let properties = physical_device.properties();
+            PhysicalDeviceReport {
+                name: properties.device_name.clone(),
+                device_type: properties.device_type,
+                api_version: physical_device.api_version(),
+                passes_filter: (config.device_filter_fn)(&physical_device),
+                missing_extensions: config
+                    .device_extensions
+                    .difference(physical_device.supported_extensions()),
+                missing_features: config
+                    .device_features
+                    .difference(physical_device.supported_features()),
+            }
+        })
+        .collect::<Vec<_>>();
+    if devices.iter().any(PhysicalDeviceReport::is_suitable) {
+        Ok(())
+    } else {
+        Err(DeviceSelectionError::NoSuitableDevice(devices))
+    }
+}
+
+/// Runner used when startup failed, running [`VulkanoStartupFailure`] once and exiting.
+pub(crate) fn startup_failure_runner(mut app: App) {
+    app.world.run_schedule(VulkanoStartupFailure);
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::AutoCommandBufferBuilder,
+    pipeline::{
+        graphics::{
+            viewport::{Scissor, Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        DynamicState, GraphicsPipeline,
+    },
+};
+
+use crate::CommandStats;
+
+/// Makes the viewport and scissor of `create_info` dynamic, so the pipeline doesn't bake in a
+/// swapchain extent and keeps covering the window after resizes. Bind the pipeline with
+/// [`bind_pipeline_for_extent`] to set them at draw time.
+///
+/// ```ignore
+/// let pipeline = GraphicsPipeline::new(device, None, with_dynamic_viewport(create_info))?;
+/// // When drawing, e.g. with the window's `RenderExtent`
+/// bind_pipeline_for_extent(&mut builder, pipeline.clone(), render_extent.physical);
+/// ```
+pub fn with_dynamic_viewport(
+    mut create_info: GraphicsPipelineCreateInfo,
+) -> GraphicsPipelineCreateInfo {
+    create_info.viewport_state = Some(ViewportState::default());
+    create_info.dynamic_state.insert(DynamicState::Viewport);
+    create_info.dynamic_state.insert(DynamicState::Scissor);
+    create_info
+}
+
+/// Binds `pipeline` and sets its dynamic viewport and scissor, if any, to cover `extent`, e.g. the
+/// physical [`RenderExtent`](crate::RenderExtent) of the window being drawn to. Must be recorded
+/// inside a render pass.
+pub fn bind_pipeline_for_extent<L>(
+    builder: &mut AutoCommandBufferBuilder<L>,
+    pipeline: Arc<GraphicsPipeline>,
+    extent: [u32; 2],
+) {
+    let dynamic_state = pipeline.dynamic_state();
+    if dynamic_state.contains(&DynamicState::Viewport) {
+        builder
+            .set_viewport(
+                0,
+                [Viewport {
+                    offset: [0.0, 0.0],
+                    extent: [extent[0] as f32, extent[1] as f32],
+                    depth_range: 0.0..=1.0,
+                }]
+                .into_iter()
+                .collect(),
+            )
+            .unwrap();
+    }
+    if dynamic_state.contains(&DynamicState::Scissor) {
+        builder
+            .set_scissor(
+                0,
+                [Scissor {
+                    offset: [0, 0],
+                    extent,
+                }]
+                .into_iter()
+                .collect(),
+            )
+            .unwrap();
+    }
+    builder.bind_pipeline_graphics(pipeline).unwrap();
+    CommandStats::record_pipeline_bind();
+}
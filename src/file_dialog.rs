@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use bevy::{
+    app::{App, Plugin, PreUpdate},
+    prelude::{Entity, Event, EventReader, EventWriter, NonSend, ResMut, Resource},
+    tasks::{futures_lite::future, AsyncComputeTaskPool, Task},
+};
+use rfd::AsyncFileDialog;
+
+use crate::BevyVulkanoWindows;
+
+/// What a [`FileDialog`] asks the user for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileDialogKind {
+    OpenFile,
+    OpenFiles,
+    SaveFile,
+    PickFolder,
+}
+
+/// Send to show a native file dialog. [`FileDialogClosed`] is sent with the same `id` once the
+/// user closes it.
+#[derive(Event, Debug, Clone)]
+pub struct FileDialog {
+    /// Identifies the dialog in [`FileDialogClosed`].
+    pub id: u64,
+    pub kind: FileDialogKind,
+    pub title: Option<String>,
+    /// Filters as a name and file extensions, e.g. `("Images", vec!["png", "jpg"])`.
+    pub filters: Vec<(String, Vec<String>)>,
+    pub directory: Option<PathBuf>,
+    /// Default file name of [`FileDialogKind::SaveFile`] dialogs.
+    pub file_name: Option<String>,
+    /// Window the dialog is modal to, if any.
+    pub window: Option<Entity>,
+}
+
+impl FileDialog {
+    pub fn new(id: u64, kind: FileDialogKind) -> FileDialog {
+        FileDialog {
+            id,
+            kind,
+            title: None,
+            filters: vec![],
+            directory: None,
+            file_name: None,
+            window: None,
+        }
+    }
+}
+
+/// Sent when a [`FileDialog`] is closed, with the selected paths. `paths` is empty if the dialog
+/// was cancelled.
+#[derive(Event, Debug, Clone)]
+pub struct FileDialogClosed {
+    pub id: u64,
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Resource, Default)]
+struct FileDialogTasks {
+    tasks: Vec<(u64, Task<Vec<PathBuf>>)>,
+}
+
+/// Shows native file dialogs (with `rfd`) requested with [`FileDialog`] events, sending
+/// [`FileDialogClosed`] when they close. Added by [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin)
+/// with feature `file_dialogs`.
+///
+/// Dialogs are created on the main thread, which macOS requires, and driven by the winit event
+/// loop while their results are awaited on the [`AsyncComputeTaskPool`], so the app keeps running
+/// while a dialog is open.
+pub struct FileDialogPlugin;
+
+impl Plugin for FileDialogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FileDialogTasks>()
+            .add_event::<FileDialog>()
+            .add_event::<FileDialogClosed>()
+            .add_systems(PreUpdate, (open_file_dialogs, poll_file_dialogs));
+    }
+}
+
+/// Creates the requested dialogs. Accessing the non-send [`BevyVulkanoWindows`] keeps this system
+/// on the main thread.
+fn open_file_dialogs(
+    mut dialog_events: EventReader<FileDialog>,
+    mut tasks: ResMut<FileDialogTasks>,
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+) {
+    for request in dialog_events.read() {
+        let mut dialog = AsyncFileDialog::new();
+        if let Some(title) = &request.title {
+            dialog = dialog.set_title(title);
+        }
+        for (name, extensions) in &request.filters {
+            dialog = dialog.add_filter(name, extensions);
+        }
+        if let Some(directory) = &request.directory {
+            dialog = dialog.set_directory(directory);
+        }
+        if let Some(file_name) = &request.file_name {
+            dialog = dialog.set_file_name(file_name);
+        }
+        if let Some(vulkano_window) = request
+            .window
+            .and_then(|window| vulkano_windows.get_vulkano_window(window))
+        {
+            dialog = dialog.set_parent(vulkano_window.window());
+        }
+        let task = match request.kind {
+            FileDialogKind::OpenFile => {
+                let picked = dialog.pick_file();
+                AsyncComputeTaskPool::get().spawn(async move {
+                    picked
+                        .await
+                        .map(|file| vec![file.path().to_path_buf()])
+                        .unwrap_or_default()
+                })
+            }
+            FileDialogKind::OpenFiles => {
+                let picked = dialog.pick_files();
+                AsyncComputeTaskPool::get().spawn(async move {
+                    picked
+                        .await
+                        .map(|files| files.iter().map(|file| file.path().to_path_buf()).collect())
+                        .unwrap_or_default()
+                })
+            }
+            FileDialogKind::SaveFile => {
+                let picked = dialog.save_file();
+                AsyncComputeTaskPool::get().spawn(async move {
+                    picked
+                        .await
+                        .map(|file| vec![file.path().to_path_buf()])
+                        .unwrap_or_default()
+                })
+            }
+            FileDialogKind::PickFolder => {
+                let picked = dialog.pick_folder();
+                AsyncComputeTaskPool::get().spawn(async move {
+                    picked
+                        .await
+                        .map(|folder| vec![folder.path().to_path_buf()])
+                        .unwrap_or_default()
+                })
+            }
+        };
+        tasks.tasks.push((request.id, task));
+    }
+}
+
+fn poll_file_dialogs(
+    mut tasks: ResMut<FileDialogTasks>,
+    mut closed_events: EventWriter<FileDialogClosed>,
+) {
+    tasks.tasks.retain_mut(
+        |(id, task)| match future::block_on(future::poll_once(task)) {
+            Some(paths) => {
+                closed_events.send(FileDialogClosed {
+                    id: *id,
+                    paths,
+                });
+                false
+            }
+            None => true,
+        },
+    );
+}
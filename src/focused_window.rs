@@ -0,0 +1,74 @@
+use bevy::{
+    ecs::system::SystemParam,
+    input::keyboard::KeyboardInput,
+    prelude::{Entity, EventReader, Query, Res, ResMut, Resource},
+    window::{ReceivedCharacter, Window},
+};
+
+/// The window with keyboard focus, `None` while no window of the app is focused. Updated from
+/// winit focus events at the start of each frame.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FocusedWindow(pub Option<Entity>);
+
+impl FocusedWindow {
+    pub fn is_focused(&self, window: Entity) -> bool {
+        self.0 == Some(window)
+    }
+}
+
+/// Keyboard and character input of the [`FocusedWindow`], for systems of multi-window tools that
+/// should only react to typing in a specific window. Input of other windows is skipped.
+///
+/// ```ignore
+/// fn editor_shortcuts(mut input: FocusedWindowInput, editors: Query<&Editor>) {
+///     let Some(editor) = input.window().and_then(|window| editors.get(window).ok()) else {
+///         return;
+///     };
+///     for key in input.keyboard_input() {
+///         editor.handle_key(key);
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct FocusedWindowInput<'w, 's> {
+    focused: Res<'w, FocusedWindow>,
+    keyboard_input: EventReader<'w, 's, KeyboardInput>,
+    characters: EventReader<'w, 's, ReceivedCharacter>,
+}
+
+impl FocusedWindowInput<'_, '_> {
+    /// The focused window the input is routed to.
+    pub fn window(&self) -> Option<Entity> {
+        self.focused.0
+    }
+
+    /// Keyboard input of the focused window since the system last ran.
+    pub fn keyboard_input(&mut self) -> impl Iterator<Item = &KeyboardInput> {
+        let focused = self.focused.0;
+        self.keyboard_input
+            .read()
+            .filter(move |input| Some(input.window) == focused)
+    }
+
+    /// Characters typed in the focused window since the system last ran.
+    pub fn characters(&mut self) -> impl Iterator<Item = &ReceivedCharacter> {
+        let focused = self.focused.0;
+        self.characters
+            .read()
+            .filter(move |character| Some(character.window) == focused)
+    }
+}
+
+/// Updates [`FocusedWindow`] from the focus state of the windows, which follows winit focus
+/// events.
+pub(crate) fn update_focused_window(
+    windows: Query<(Entity, &Window)>,
+    mut focused_window: ResMut<FocusedWindow>,
+) {
+    let focused = windows
+        .iter()
+        .find_map(|(entity, window)| window.focused.then_some(entity));
+    if focused_window.0 != focused {
+        focused_window.0 = focused;
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::{
+    log::{error, info, warn},
+    prelude::{Event, EventReader, NonSendMut, Res, ResMut, Resource},
+};
+use image::RgbaImage;
+use vulkano::{command_buffer::allocator::StandardCommandBufferAllocator, image::view::ImageView};
+
+use crate::{
+    capture::read_image_blocking, BevyVulkanoContext, BevyVulkanoWindows, RenderTargetRegistry,
+};
+
+/// Send to write every target of the [`RenderTargetRegistry`], every window's additional image
+/// views and swapchain image of the current frame as PNGs into `directory`. Useful for debugging
+/// passes which render black or garbage.
+///
+/// Swapchain images are copied as they are presented, so render and present within `Update` or
+/// `PostUpdate`. Files are named `frame{n}_{name}.png`, where `n` counts dumps.
+#[derive(Event, Debug, Clone)]
+pub struct DumpFrame {
+    pub directory: PathBuf,
+}
+
+impl Default for DumpFrame {
+    fn default() -> Self {
+        DumpFrame {
+            directory: PathBuf::from("frame_dumps"),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct FrameDumpState {
+    pending: Option<PathBuf>,
+    dump_count: u32,
+}
+
+/// Requests swapchain captures from each window when a [`DumpFrame`] is received.
+pub(crate) fn request_frame_dump(
+    mut dump_events: EventReader<DumpFrame>,
+    mut state: ResMut<FrameDumpState>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+) {
+    if let Some(dump) = dump_events.read().last() {
+        state.pending = Some(dump.directory.clone());
+        for (_, vulkano_window) in vulkano_windows.iter_mut() {
+            vulkano_window.renderer.request_capture();
+        }
+    }
+}
+
+/// Writes the images of a requested frame dump after the frame has been rendered.
+pub(crate) fn write_frame_dump(
+    mut state: ResMut<FrameDumpState>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    registry: Res<RenderTargetRegistry>,
+    context: Res<BevyVulkanoContext>,
+) {
+    let Some(directory) = state.pending.take() else {
+        return;
+    };
+    let frame = state.dump_count;
+    state.dump_count += 1;
+    if let Err(e) = std::fs::create_dir_all(&directory) {
+        error!("Failed to create frame dump directory {:?}: {e}", directory);
+        return;
+    }
+
+    let context = &context.context;
+    let command_buffer_allocator =
+        StandardCommandBufferAllocator::new(context.device().clone(), Default::default());
+    let read = |view: &Arc<ImageView>| {
+        read_image_blocking(
+            context.graphics_queue().clone(),
+            context.memory_allocator().clone(),
+            &command_buffer_allocator,
+            view.image().clone(),
+        )
+    };
+
+    let mut written = 0;
+    let mut write = |name: &str, image: Option<RgbaImage>| match image {
+        Some(image) => {
+            if save_dump_image(&directory, frame, name, &image) {
+                written += 1;
+            }
+        }
+        None => warn!("Skipping {name} in frame dump, its format or usage can't be read back"),
+    };
+
+    for (name, view) in registry.iter() {
+        write(name, read(view));
+    }
+
+    for (window, vulkano_window) in vulkano_windows.iter_mut() {
+        let window_name = format!("window{}", window.index());
+        for (key, view) in vulkano_window.renderer.additional_image_views() {
+            write(&format!("{window_name}_additional{key}"), read(&view));
+        }
+        match vulkano_window.renderer.take_capture() {
+            Some(capture) => write(&format!("{window_name}_swapchain"), capture.to_rgba_image()),
+            None => warn!("No swapchain image of {window_name} was presented for the frame dump"),
+        }
+    }
+
+    info!(
+        "Wrote {written} images of frame dump {frame} to {:?}",
+        directory
+    );
+}
+
+fn save_dump_image(directory: &Path, frame: u32, name: &str, image: &RgbaImage) -> bool {
+    let file_name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    let path = directory.join(format!("frame{frame}_{file_name}.png"));
+    match image.save(&path) {
+        Ok(()) => true,
+        Err(e) => {
+            error!("Failed to write {:?}: {e}", path);
+            false
+        }
+    }
+}
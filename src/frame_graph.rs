@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        PrimaryAutoCommandBuffer,
+    },
+    device::{DeviceOwned, Queue},
+    sync::{self, GpuFuture},
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk};
+
+/// Queue a [`FrameGraph`] node prefers to execute on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueHint {
+    Graphics,
+    /// Execute on the compute queue, overlapping with graphics work until a graphics node depends
+    /// on it.
+    AsyncCompute,
+}
+
+/// Identifies a node added to a [`FrameGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+type RecordFn<'a> = Box<dyn FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) + 'a>;
+
+struct FrameGraphNode<'a> {
+    name: String,
+    queue: QueueHint,
+    dependencies: Vec<NodeId>,
+    record: RecordFn<'a>,
+}
+
+/// A frame's work split into nodes which record command buffers, allowing independent compute
+/// work (e.g. simulation or post processing of the previous frame) to overlap raster work on a
+/// separate compute queue.
+///
+/// Nodes execute in the order they are added, so dependencies must have been added before. Nodes
+/// hinted with [`QueueHint::AsyncCompute`] run on the compute queue until the first graphics node
+/// depending on one of them, where the compute work is joined into the graphics queue with a
+/// semaphore. After that point, and for compute nodes depending on graphics nodes, the hint is
+/// ignored and the node runs on the graphics queue.
+///
+/// If the queues belong to different queue families, resources shared between nodes on different
+/// queues must be created with concurrent sharing.
+///
+/// ```ignore
+/// let mut graph = FrameGraph::default();
+/// let simulate = graph.add_node("simulate", QueueHint::AsyncCompute, &[], |builder| { ... });
+/// let shadows = graph.add_node("shadows", QueueHint::Graphics, &[], |builder| { ... });
+/// graph.add_node("composite", QueueHint::Graphics, &[simulate, shadows], |builder| { ... });
+/// let after = graph.execute(before, graphics_queue, compute_queue, &command_buffer_allocator);
+/// ```
+#[derive(Default)]
+pub struct FrameGraph<'a> {
+    nodes: Vec<FrameGraphNode<'a>>,
+}
+
+impl<'a> FrameGraph<'a> {
+    /// Adds a node recording its commands with `record`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a dependency was not added to this graph.
+    pub fn add_node(
+        &mut self,
+        name: impl Into<String>,
+        queue: QueueHint,
+        dependencies: &[NodeId],
+        record: impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) + 'a,
+    ) -> NodeId {
+        let name = name.into();
+        assert!(
+            dependencies.iter().all(|dep| dep.0 < self.nodes.len()),
+            "dependencies of frame graph node {name} must be added before it"
+        );
+        self.nodes.push(FrameGraphNode {
+            name,
+            queue,
+            dependencies: dependencies.to_vec(),
+            record: Box::new(record),
+        });
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// Name of a node.
+    pub fn node_name(&self, node: NodeId) -> &str {
+        &self.nodes[node.0].name
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Records and submits all nodes after `before_future`, returning a future which completes
+    /// when all nodes on both queues have executed.
+    pub fn execute(
+        self,
+        before_future: Box<dyn GpuFuture>,
+        graphics_queue: Arc<Queue>,
+        compute_queue: Arc<Queue>,
+        command_buffer_allocator: &StandardCommandBufferAllocator,
+    ) -> Box<dyn GpuFuture> {
+        let async_compute_available = graphics_queue != compute_queue;
+        let mut graphics_future = before_future;
+        let mut compute_future: Option<Box<dyn GpuFuture>> = None;
+        let mut compute_joined = false;
+        // Queue each node was executed on
+        let mut node_queues = Vec::with_capacity(self.nodes.len());
+
+        for node in self.nodes {
+            profile_scope!("bevy_vulkano::frame_graph_node");
+            let depends_on = |queue: QueueHint| {
+                node.dependencies
+                    .iter()
+                    .any(|dep| node_queues[dep.0] == queue)
+            };
+            let queue = if node.queue == QueueHint::AsyncCompute
+                && async_compute_available
+                && !compute_joined
+                && !depends_on(QueueHint::Graphics)
+            {
+                QueueHint::AsyncCompute
+            } else {
+                QueueHint::Graphics
+            };
+            if queue == QueueHint::Graphics && depends_on(QueueHint::AsyncCompute) {
+                if let Some(compute) = compute_future.take() {
+                    trace_vk!("join async compute into graphics queue with a semaphore");
+                    graphics_future = graphics_future
+                        .join(compute.then_signal_semaphore())
+                        .boxed();
+                }
+                compute_joined = true;
+            }
+
+            let target_queue = match queue {
+                QueueHint::Graphics => graphics_queue.clone(),
+                QueueHint::AsyncCompute => compute_queue.clone(),
+            };
+            let mut builder = AutoCommandBufferBuilder::primary(
+                command_buffer_allocator,
+                target_queue.queue_family_index(),
+                CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap();
+            (node.record)(&mut builder);
+            let command_buffer = builder.build().unwrap();
+            trace_vk!(
+                "submit frame graph node {} ({:?}, hinted {:?}) on queue family {}",
+                node.name,
+                queue,
+                node.queue,
+                target_queue.queue_family_index()
+            );
+
+            match queue {
+                QueueHint::Graphics => {
+                    graphics_future = graphics_future
+                        .then_execute(target_queue, command_buffer)
+                        .unwrap()
+                        .boxed();
+                }
+                QueueHint::AsyncCompute => {
+                    let previous = compute_future
+                        .take()
+                        .unwrap_or_else(|| sync::now(compute_queue.device().clone()).boxed());
+                    compute_future = Some(
+                        previous
+                            .then_execute(target_queue, command_buffer)
+                            .unwrap()
+                            .boxed(),
+                    );
+                }
+            }
+            node_queues.push(queue);
+        }
+
+        match compute_future {
+            Some(compute) => graphics_future
+                .join(compute.then_signal_semaphore())
+                .boxed(),
+            None => graphics_future,
+        }
+    }
+}
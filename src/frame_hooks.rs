@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use vulkano::{
+    device::Queue, image::view::ImageView, memory::allocator::StandardMemoryAllocator,
+    sync::GpuFuture,
+};
+use winit::window::Window;
+
+/// Identifies a hook added with
+/// [`VulkanoWindowRenderer::add_frame_hook`](crate::VulkanoWindowRenderer::add_frame_hook), for
+/// removing it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameHookId(pub(crate) u64);
+
+/// State of the frame passed to a [`FrameHook`].
+pub struct FrameHookContext<'a> {
+    pub window: &'a Window,
+    /// Index of the acquired swapchain image.
+    pub image_index: u32,
+    /// The acquired swapchain image.
+    pub swapchain_image_view: Arc<ImageView>,
+    pub graphics_queue: Arc<Queue>,
+    pub memory_allocator: Arc<StandardMemoryAllocator>,
+}
+
+/// Callbacks invoked by [`VulkanoWindowRenderer`](crate::VulkanoWindowRenderer) at the start and
+/// end of each frame, allowing overlays, capture tools or streaming SDKs to integrate without
+/// replacing the present logic.
+///
+/// Both callbacks receive the frame's future and return the future to continue from, so hooks can
+/// execute their own command buffers, e.g. drawing an overlay onto the swapchain image right before
+/// present. Hooks run in the order they were added. Add hooks with
+/// [`VulkanoWindowRenderer::add_frame_hook`](crate::VulkanoWindowRenderer::add_frame_hook).
+pub trait FrameHook {
+    /// Called right after the swapchain image has been acquired.
+    fn after_acquire(
+        &mut self,
+        _context: &FrameHookContext,
+        future: Box<dyn GpuFuture>,
+    ) -> Box<dyn GpuFuture> {
+        future
+    }
+
+    /// Called right before the swapchain image is presented, after all of the frame's rendering.
+    fn before_present(
+        &mut self,
+        _context: &FrameHookContext,
+        future: Box<dyn GpuFuture>,
+    ) -> Box<dyn GpuFuture> {
+        future
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::{Resource, World},
+    utils::Instant,
+};
+
+use crate::{BevyVulkanoWindows, PresentTiming};
+
+/// State a [`FramePacer`] decides the next update from.
+#[derive(Debug, Clone, Copy)]
+pub struct FramePacingContext {
+    /// Start of the previous update.
+    pub last_update: Instant,
+    /// Display timing of the main render window, if known yet.
+    pub present_timing: Option<PresentTiming>,
+    /// Whether any window of the app is focused.
+    pub focused: bool,
+}
+
+/// A frame pacing strategy, deciding when the runner starts the next update. Install one with
+/// [`FramePacing`]. The runner waits until the returned instant instead of polling, on top of
+/// [`BevyVulkanoSettings::throttle_when_unfocused`](crate::BevyVulkanoSettings::throttle_when_unfocused).
+pub trait FramePacer: Send + Sync + 'static {
+    /// Earliest time of the next update, or `None` to update as soon as possible.
+    fn next_update(&self, context: &FramePacingContext) -> Option<Instant>;
+}
+
+/// Limits updates to `max_frame_rate` per second by sleeping between them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameRateLimit {
+    pub max_frame_rate: f64,
+}
+
+impl FramePacer for FrameRateLimit {
+    fn next_update(&self, context: &FramePacingContext) -> Option<Instant> {
+        context.last_update.checked_add(Duration::from_secs_f64(
+            1.0 / self.max_frame_rate.max(0.001),
+        ))
+    }
+}
+
+/// Updates once every `divisor` display refreshes of the main render window, e.g. 2 for
+/// half-refresh or 4 to run 60 Hz content on a 240 Hz panel. With `divisor` 1 and the
+/// `Immediate` or `Mailbox` present mode, this emulates a vsync swap interval without blocking
+/// in acquire. Updates are unpaced until the display timing is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefreshDivisor {
+    pub divisor: u32,
+}
+
+impl FramePacer for RefreshDivisor {
+    fn next_update(&self, context: &FramePacingContext) -> Option<Instant> {
+        let timing = context.present_timing?;
+        context
+            .last_update
+            .checked_add(timing.refresh_duration * self.divisor.max(1))
+    }
+}
+
+/// Presents only the latest frame each display refresh with the `Mailbox` present mode. Mailbox
+/// replaces queued frames with newer ones, so updating faster than the display wastes work on
+/// frames never shown. Instead each update starts `frame_time` before the predicted display of
+/// the next refresh, rendering once per refresh as late as possible for the lowest latency. Set
+/// `frame_time` a bit above the time from update start to present, or frames miss their refresh.
+/// Updates are unpaced until the display timing is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MailboxLatest {
+    pub frame_time: Duration,
+}
+
+impl FramePacer for MailboxLatest {
+    fn next_update(&self, context: &FramePacingContext) -> Option<Instant> {
+        let timing = context.present_timing?;
+        let refresh = timing.refresh_duration.as_nanos();
+        let start = timing.predicted_present.checked_sub(self.frame_time)?;
+        if start > context.last_update || refresh == 0 {
+            return Some(start);
+        }
+        // Start before the first refresh the update can still make
+        let missed = (context.last_update - start).as_nanos() / refresh + 1;
+        start.checked_add(Duration::from_nanos((missed * refresh) as u64))
+    }
+}
+
+/// The frame pacing strategy used by the runner. Insert it as a resource to pace updates, and
+/// replace the strategy at runtime with [`FramePacing::set`], e.g. from a settings menu. Without
+/// it, updates are only paced by the present mode and update mode.
+///
+/// ```ignore
+/// app.insert_resource(FramePacing::new(RefreshDivisor { divisor: 2 }));
+/// ```
+#[derive(Resource)]
+pub struct FramePacing {
+    pacer: Box<dyn FramePacer>,
+}
+
+impl FramePacing {
+    pub fn new(pacer: impl FramePacer) -> FramePacing {
+        FramePacing {
+            pacer: Box::new(pacer),
+        }
+    }
+
+    /// Replaces the strategy, taking effect from the next update.
+    pub fn set(&mut self, pacer: impl FramePacer) {
+        self.pacer = Box::new(pacer);
+    }
+
+    pub fn pacer(&self) -> &dyn FramePacer {
+        self.pacer.as_ref()
+    }
+}
+
+/// Earliest time of the next update according to the [`FramePacing`] resource, if any.
+pub(crate) fn next_paced_update(
+    world: &World,
+    last_update: Instant,
+    focused: bool,
+) -> Option<Instant> {
+    let pacing = world.get_resource::<FramePacing>()?;
+    let present_timing = world
+        .get_non_send_resource::<BevyVulkanoWindows>()
+        .and_then(|windows| {
+            let window = windows.main_window_entity()?;
+            windows
+                .get_vulkano_window(window)?
+                .renderer
+                .present_timing()
+        });
+    pacing.pacer.next_update(&FramePacingContext {
+        last_update,
+        present_timing,
+        focused,
+    })
+}
@@ -0,0 +1,441 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    log::{error, warn},
+    prelude::{Entity, NonSendMut, ResMut, Resource},
+};
+use image::RgbaImage;
+use vulkano::{
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder,
+        CommandBufferExecFuture, CommandBufferUsage,
+    },
+    device::DeviceOwned,
+    image::ImageUsage,
+    sync::future::{FenceSignalFuture, GpuFuture},
+};
+
+use crate::{
+    capture::supports_readback, profiling::profile_scope, trace_frame::trace_vk,
+    BevyVulkanoWindows, FrameHook, FrameHookContext, FrameHookId, ImageReadback,
+};
+
+type CopyFence = Arc<FenceSignalFuture<CommandBufferExecFuture<Box<dyn GpuFuture>>>>;
+
+/// A frame copied by the [`FrameRecorder`].
+pub struct RecordedFrame {
+    /// Index of the frame since recording started. Frames dropped because every staging buffer was
+    /// in use leave gaps.
+    pub frame_index: u64,
+    pub image: RgbaImage,
+}
+
+/// Where the [`FrameRecorder`] streams recorded frames to.
+pub enum RecordingOutput {
+    /// Calls the function with each frame, in order.
+    Callback(Box<dyn FnMut(RecordedFrame) + Send + Sync>),
+    /// Appends the RGBA8 pixels of each frame to a file, without any header.
+    RawRgba(PathBuf),
+    /// Writes a YUV4MPEG2 video with 4:4:4 chroma, playable with e.g. ffplay or encodable with
+    /// ffmpeg. Frames whose size differs from the first one are skipped.
+    Y4m { path: PathBuf, frame_rate: u32 },
+}
+
+impl std::fmt::Debug for RecordingOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingOutput::Callback(_) => f.write_str("Callback"),
+            RecordingOutput::RawRgba(path) => f.debug_tuple("RawRgba").field(path).finish(),
+            RecordingOutput::Y4m {
+                path,
+                frame_rate,
+            } => f
+                .debug_struct("Y4m")
+                .field("path", path)
+                .field("frame_rate", frame_rate)
+                .finish(),
+        }
+    }
+}
+
+/// Destination of a recording in progress.
+enum RecordingSink {
+    Callback(Box<dyn FnMut(RecordedFrame) + Send + Sync>),
+    RawRgba(BufWriter<File>),
+    Y4m {
+        writer: BufWriter<File>,
+        frame_rate: u32,
+        extent: Option<[u32; 2]>,
+    },
+}
+
+impl RecordingSink {
+    fn new(output: RecordingOutput) -> std::io::Result<RecordingSink> {
+        Ok(match output {
+            RecordingOutput::Callback(callback) => RecordingSink::Callback(callback),
+            RecordingOutput::RawRgba(path) => {
+                RecordingSink::RawRgba(BufWriter::new(File::create(path)?))
+            }
+            RecordingOutput::Y4m {
+                path,
+                frame_rate,
+            } => RecordingSink::Y4m {
+                writer: BufWriter::new(File::create(path)?),
+                frame_rate: frame_rate.max(1),
+                extent: None,
+            },
+        })
+    }
+
+    fn write(&mut self, frame: RecordedFrame) -> std::io::Result<()> {
+        match self {
+            RecordingSink::Callback(callback) => callback(frame),
+            RecordingSink::RawRgba(writer) => writer.write_all(frame.image.as_raw())?,
+            RecordingSink::Y4m {
+                writer,
+                frame_rate,
+                extent,
+            } => {
+                let frame_extent = [frame.image.width(), frame.image.height()];
+                match *extent {
+                    None => {
+                        writeln!(
+                            writer,
+                            "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                            frame_extent[0], frame_extent[1], frame_rate
+                        )?;
+                        *extent = Some(frame_extent);
+                    }
+                    Some(extent) if extent != frame_extent => {
+                        warn!(
+                            "Skipping recorded frame {} of size {:?}, the video is {:?}",
+                            frame.frame_index, frame_extent, extent
+                        );
+                        return Ok(());
+                    }
+                    Some(_) => {}
+                }
+                writer.write_all(b"FRAME\n")?;
+                writer.write_all(&rgba_to_yuv444(&frame.image))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            RecordingSink::Callback(_) => Ok(()),
+            RecordingSink::RawRgba(writer)
+            | RecordingSink::Y4m {
+                writer, ..
+            } => writer.flush(),
+        }
+    }
+}
+
+/// Converts RGBA8 to planar BT.601 limited range YUV.
+fn rgba_to_yuv444(image: &RgbaImage) -> Vec<u8> {
+    let pixels = (image.width() * image.height()) as usize;
+    let mut yuv = vec![0; pixels * 3];
+    for (i, pixel) in image.pixels().enumerate() {
+        let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| c as f32 / 255.0);
+        let y = 16.0 + 65.481 * r + 128.553 * g + 24.966 * b;
+        let u = 128.0 - 37.797 * r - 74.203 * g + 112.0 * b;
+        let v = 128.0 + 112.0 * r - 93.786 * g - 18.214 * b;
+        yuv[i] = y.round() as u8;
+        yuv[pixels + i] = u.round() as u8;
+        yuv[pixels * 2 + i] = v.round() as u8;
+    }
+    yuv
+}
+
+/// State shared between the [`FrameRecorder`] and the frame hook copying frames of its window.
+#[derive(Default)]
+struct RecorderShared {
+    recording: bool,
+    /// Incremented when a recording starts, so hooks drop copies of earlier recordings.
+    generation: u64,
+    slot_count: usize,
+    /// Staging buffers not in use.
+    free: Vec<ImageReadback>,
+    /// Copies that have finished, waiting to be written.
+    completed: Vec<(u64, ImageReadback)>,
+    /// Number of staging buffers allocated.
+    allocated: usize,
+    copies: u64,
+    dropped: u64,
+}
+
+/// Records the presented frames of a window into a ring of staging buffers without stalling, and
+/// streams them to a [`RecordingOutput`], e.g. for video export from tools.
+///
+/// Each frame is copied right before present by a [`FrameHook`] installed on the window. Frames are
+/// written at the end of the update after their copy has finished, usually a frame or two later.
+/// When every staging buffer is in use, frames are dropped rather than waited for, see
+/// [`FrameRecorder::frames_dropped`]. Requires the swapchain to support `TRANSFER_SRC` usage.
+///
+/// ```ignore
+/// fn toggle_recording(
+///     keys: Res<Input<KeyCode>>,
+///     windows: Query<Entity, With<PrimaryWindow>>,
+///     mut recorder: ResMut<FrameRecorder>,
+/// ) {
+///     if keys.just_pressed(KeyCode::F9) {
+///         if recorder.is_recording() {
+///             recorder.stop();
+///         } else {
+///             let output = RecordingOutput::Y4m {
+///                 path: "recording.y4m".into(),
+///                 frame_rate: 60,
+///             };
+///             recorder.start(windows.single(), output).unwrap();
+///         }
+///     }
+/// }
+/// ```
+#[derive(Resource)]
+pub struct FrameRecorder {
+    slot_count: usize,
+    window: Option<Entity>,
+    /// Window whose frame hook copies into `shared`, reused by later recordings of the window.
+    hooked_window: Option<(Entity, FrameHookId)>,
+    shared: Arc<Mutex<RecorderShared>>,
+    sink: Option<RecordingSink>,
+    written: u64,
+}
+
+impl Default for FrameRecorder {
+    fn default() -> Self {
+        FrameRecorder::new(3)
+    }
+}
+
+impl FrameRecorder {
+    /// Creates a recorder using up to `slot_count` staging buffers.
+    pub fn new(slot_count: usize) -> FrameRecorder {
+        FrameRecorder {
+            slot_count: slot_count.max(1),
+            window: None,
+            hooked_window: None,
+            shared: Arc::default(),
+            sink: None,
+            written: 0,
+        }
+    }
+
+    /// Starts recording the frames of `window` to `output`, stopping a recording in progress.
+    /// Returns an error if the output file can't be created.
+    pub fn start(&mut self, window: Entity, output: RecordingOutput) -> std::io::Result<()> {
+        self.stop();
+        self.sink = Some(RecordingSink::new(output)?);
+        self.window = Some(window);
+        self.written = 0;
+        let mut shared = self.shared.lock().unwrap();
+        *shared = RecorderShared {
+            recording: true,
+            generation: shared.generation + 1,
+            slot_count: self.slot_count,
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    /// Stops recording. Frames whose copies are still in flight are discarded. The frame hook stays
+    /// on the window but no longer copies anything, until a recording of another window replaces
+    /// it.
+    pub fn stop(&mut self) {
+        self.shared.lock().unwrap().recording = false;
+        if let Some(mut sink) = self.sink.take() {
+            if let Err(e) = sink.flush() {
+                error!("Failed to flush frame recording: {e}");
+            }
+        }
+        self.window = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    /// Window being recorded.
+    pub fn window(&self) -> Option<Entity> {
+        self.window
+    }
+
+    /// Number of frames written to the output since recording started.
+    pub fn frames_written(&self) -> u64 {
+        self.written
+    }
+
+    /// Number of frames dropped since recording started because every staging buffer was in use.
+    pub fn frames_dropped(&self) -> u64 {
+        self.shared.lock().unwrap().dropped
+    }
+}
+
+/// Frame hook copying swapchain images into the staging buffers of a [`FrameRecorder`].
+struct RecorderHook {
+    shared: Arc<Mutex<RecorderShared>>,
+    /// Recording the copies in `in_flight` belong to.
+    generation: u64,
+    command_buffer_allocator: Option<StandardCommandBufferAllocator>,
+    in_flight: Vec<(u64, ImageReadback, CopyFence)>,
+}
+
+impl RecorderHook {
+    /// Moves finished copies to the completed frames.
+    fn poll(&mut self, shared: &mut RecorderShared) {
+        let mut index = 0;
+        while index < self.in_flight.len() {
+            if self.in_flight[index].2.is_signaled().unwrap_or(false) {
+                let (frame_index, readback, _) = self.in_flight.swap_remove(index);
+                shared.completed.push((frame_index, readback));
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
+
+impl FrameHook for RecorderHook {
+    fn before_present(
+        &mut self,
+        context: &FrameHookContext,
+        future: Box<dyn GpuFuture>,
+    ) -> Box<dyn GpuFuture> {
+        let shared = self.shared.clone();
+        let mut shared = shared.lock().unwrap();
+        if shared.generation != self.generation {
+            self.in_flight.clear();
+            self.generation = shared.generation;
+        }
+        if !shared.recording {
+            self.in_flight.clear();
+            return future;
+        }
+        profile_scope!("bevy_vulkano::frame_recorder");
+        self.poll(&mut shared);
+        let image = context.swapchain_image_view.image().clone();
+        if !image.usage().intersects(ImageUsage::TRANSFER_SRC) || !supports_readback(image.format())
+        {
+            warn!(
+                "Can't record window {:?} (format {:?}, usage {:?})",
+                context.window.id(),
+                image.format(),
+                image.usage()
+            );
+            shared.recording = false;
+            return future;
+        }
+        let extent = [image.extent()[0], image.extent()[1]];
+        let frame_index = shared.copies;
+        shared.copies += 1;
+        // Reuse a free staging buffer, replacing buffers of a previous size or format
+        let readback = match shared.free.pop() {
+            Some(readback)
+                if readback.extent() == extent && readback.format() == image.format() =>
+            {
+                readback
+            }
+            Some(_) => ImageReadback::new(context.memory_allocator.clone(), extent, image.format()),
+            None if shared.allocated < shared.slot_count => {
+                shared.allocated += 1;
+                ImageReadback::new(context.memory_allocator.clone(), extent, image.format())
+            }
+            None => {
+                shared.dropped += 1;
+                return future;
+            }
+        };
+        let command_buffer_allocator = self.command_buffer_allocator.get_or_insert_with(|| {
+            StandardCommandBufferAllocator::new(
+                context.graphics_queue.device().clone(),
+                Default::default(),
+            )
+        });
+        let mut builder = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            context.graphics_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        readback.record(&mut builder, image).unwrap();
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit frame recorder copy {} of window {:?} on queue family {}",
+            frame_index,
+            context.window.id(),
+            context.graphics_queue.queue_family_index()
+        );
+        // The fence is signaled by the present's submission rather than flushed here, so a failed
+        // submission can't drop the wait on the acquire from the frame's future
+        let fence = Arc::new(
+            future
+                .then_execute(context.graphics_queue.clone(), command_buffer)
+                .unwrap()
+                .then_signal_fence(),
+        );
+        self.in_flight.push((frame_index, readback, fence.clone()));
+        fence.boxed()
+    }
+}
+
+/// Installs the recorder's frame hook on the recorded window and writes finished frames.
+pub(crate) fn record_frames(
+    mut recorder: ResMut<FrameRecorder>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+) {
+    let Some(window) = recorder.window else {
+        return;
+    };
+    if recorder.hooked_window.map(|(hooked, _)| hooked) != Some(window) {
+        if let Some((hooked, id)) = recorder.hooked_window.take() {
+            if let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(hooked) {
+                vulkano_window.renderer.remove_frame_hook(id);
+            }
+        }
+        let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(window) else {
+            warn!("Can't record {:?}, it has no renderer", window);
+            recorder.stop();
+            return;
+        };
+        let id = vulkano_window.renderer.add_frame_hook(RecorderHook {
+            shared: recorder.shared.clone(),
+            generation: 0,
+            command_buffer_allocator: None,
+            in_flight: vec![],
+        });
+        recorder.hooked_window = Some((window, id));
+    }
+
+    let recorder = &mut *recorder;
+    let mut completed = {
+        let mut shared = recorder.shared.lock().unwrap();
+        if !shared.recording {
+            drop(shared);
+            recorder.stop();
+            return;
+        }
+        std::mem::take(&mut shared.completed)
+    };
+    completed.sort_by_key(|(frame_index, _)| *frame_index);
+    let mut free = vec![];
+    for (frame_index, readback) in completed {
+        if let (Some(sink), Some(image)) = (recorder.sink.as_mut(), readback.to_rgba_image()) {
+            match sink.write(RecordedFrame {
+                frame_index,
+                image,
+            }) {
+                Ok(()) => recorder.written += 1,
+                Err(e) => error!("Failed to write recorded frame {frame_index}: {e}"),
+            }
+        }
+        free.push(readback);
+    }
+    recorder.shared.lock().unwrap().free.extend(free);
+}
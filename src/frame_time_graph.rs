@@ -0,0 +1,193 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{
+    prelude::{Component, EventReader, Query},
+    utils::Instant,
+};
+use egui_winit_vulkano::egui;
+
+use crate::FramePresented;
+
+/// A frame of a [`FrameTimeGraph`].
+#[derive(Debug, Clone, Copy)]
+pub struct FrameTimeSample {
+    /// Time since the previous present.
+    pub frame_time: Duration,
+    pub cpu_time: Duration,
+    pub acquire_wait: Duration,
+    /// GPU time of the frame, if recorded with [`FrameTimeGraph::record_gpu_time`].
+    pub gpu_time: Option<Duration>,
+}
+
+/// Frame time history of a window with an egui widget showing it as a graph with percentiles.
+/// Add it to a window entity and it is fed from the window's [`FramePresented`] events.
+///
+/// ```ignore
+/// fn debug_ui(graphs: Query<&FrameTimeGraph>, ...) {
+///     egui::Window::new("Frame times").show(&ctx, |ui| graphs.get(window).unwrap().ui(ui));
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct FrameTimeGraph {
+    samples: VecDeque<FrameTimeSample>,
+    capacity: usize,
+    last_present: Option<Instant>,
+    /// Frame time marked with a line in the graph, e.g. the refresh interval.
+    pub target_frame_time: Duration,
+}
+
+impl Default for FrameTimeGraph {
+    fn default() -> Self {
+        FrameTimeGraph::new(240)
+    }
+}
+
+impl FrameTimeGraph {
+    /// Creates a graph keeping the last `capacity` frames.
+    pub fn new(capacity: usize) -> FrameTimeGraph {
+        FrameTimeGraph {
+            samples: VecDeque::with_capacity(capacity.max(1)),
+            capacity: capacity.max(1),
+            last_present: None,
+            target_frame_time: Duration::from_micros(16_667),
+        }
+    }
+
+    /// Records a presented frame.
+    pub fn record(&mut self, presented: &FramePresented) {
+        let now = Instant::now();
+        let frame_time = self
+            .last_present
+            .map(|last| now - last)
+            .unwrap_or(presented.cpu_time + presented.acquire_wait);
+        self.last_present = Some(now);
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(FrameTimeSample {
+            frame_time,
+            cpu_time: presented.cpu_time,
+            acquire_wait: presented.acquire_wait,
+            gpu_time: None,
+        });
+    }
+
+    /// Sets the GPU time of the latest frame, e.g. from the [`GpuProfiler`](crate::GpuProfiler)
+    /// zones of the frame.
+    pub fn record_gpu_time(&mut self, gpu_time: Duration) {
+        if let Some(sample) = self.samples.back_mut() {
+            sample.gpu_time = Some(gpu_time);
+        }
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &FrameTimeSample> {
+        self.samples.iter()
+    }
+
+    /// Frame time at `percentile` in `0.0..=100.0` of the recorded frames.
+    pub fn percentile(&self, percentile: f32) -> Option<Duration> {
+        let mut frame_times = self
+            .samples
+            .iter()
+            .map(|sample| sample.frame_time)
+            .collect::<Vec<_>>();
+        if frame_times.is_empty() {
+            return None;
+        }
+        frame_times.sort_unstable();
+        let index = ((percentile.clamp(0.0, 100.0) / 100.0) * (frame_times.len() - 1) as f32)
+            .round() as usize;
+        Some(frame_times[index])
+    }
+
+    pub fn average(&self) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let total = self
+            .samples
+            .iter()
+            .map(|sample| sample.frame_time)
+            .sum::<Duration>();
+        Some(total / self.samples.len() as u32)
+    }
+
+    /// Draws the graph. Each frame is a bar of its frame time, with the CPU time (blue), acquire
+    /// wait (gray) and GPU time (orange, when recorded) drawn from the bottom.
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        let ms = |duration: Option<Duration>| {
+            duration.map_or(0.0, |duration| duration.as_secs_f32() * 1000.0)
+        };
+        ui.label(format!(
+            "avg {:.2} ms | p50 {:.2} | p95 {:.2} | p99 {:.2} | max {:.2}",
+            ms(self.average()),
+            ms(self.percentile(50.0)),
+            ms(self.percentile(95.0)),
+            ms(self.percentile(99.0)),
+            ms(self.percentile(100.0)),
+        ));
+        let cpu = ms(self.samples.back().map(|sample| sample.cpu_time));
+        let gpu = self
+            .samples
+            .back()
+            .and_then(|sample| sample.gpu_time)
+            .map_or("-".to_string(), |gpu| {
+                format!("{:.2}", gpu.as_secs_f32() * 1000.0)
+            });
+        ui.label(format!("cpu {cpu:.2} ms | gpu {gpu} ms"));
+
+        let (rect, _) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 80.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, egui::Color32::from_black_alpha(96));
+        let target = self.target_frame_time.as_secs_f32();
+        let max = ms(self.percentile(99.0)).max(target * 1000.0) * 1.25;
+        let height = |value: f32| rect.height() * (value / max).min(1.0);
+        let bar_width = rect.width() / self.capacity as f32;
+        for (i, sample) in self.samples.iter().enumerate() {
+            let x = rect.left() + i as f32 * bar_width;
+            let bar = |value: Duration, bottom: f32| {
+                let top = bottom - height(value.as_secs_f32() * 1000.0);
+                egui::Rect::from_min_max(egui::pos2(x, top), egui::pos2(x + bar_width, bottom))
+            };
+            let frame_color = if sample.frame_time.as_secs_f32() > target * 1.5 {
+                egui::Color32::from_rgb(200, 60, 60)
+            } else {
+                egui::Color32::from_rgb(80, 160, 80)
+            };
+            painter.rect_filled(bar(sample.frame_time, rect.bottom()), 0.0, frame_color);
+            let cpu_bar = bar(sample.cpu_time, rect.bottom());
+            painter.rect_filled(cpu_bar, 0.0, egui::Color32::from_rgb(70, 120, 220));
+            painter.rect_filled(
+                bar(sample.acquire_wait, cpu_bar.top()),
+                0.0,
+                egui::Color32::GRAY,
+            );
+            if let Some(gpu_time) = sample.gpu_time {
+                let y = rect.bottom() - height(gpu_time.as_secs_f32() * 1000.0);
+                painter.hline(
+                    x..=x + bar_width,
+                    y,
+                    egui::Stroke::new(1.5, egui::Color32::from_rgb(240, 150, 40)),
+                );
+            }
+        }
+        painter.hline(
+            rect.x_range(),
+            rect.bottom() - height(target * 1000.0),
+            egui::Stroke::new(1.0, egui::Color32::WHITE),
+        );
+    }
+}
+
+/// Feeds [`FramePresented`] events into the [`FrameTimeGraph`] of their window.
+pub(crate) fn update_frame_time_graphs(
+    mut presented_events: EventReader<FramePresented>,
+    mut graphs: Query<&mut FrameTimeGraph>,
+) {
+    for presented in presented_events.read() {
+        if let Ok(mut graph) = graphs.get_mut(presented.window) {
+            graph.record(presented);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use vulkano::{
+    image::view::ImageView,
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass},
+};
+
+/// Views replaced by swapchain recreations which are still alive, e.g. in cached framebuffers.
+static RETIRED_VIEWS: Mutex<Vec<Weak<ImageView>>> = Mutex::new(Vec::new());
+
+/// Marks views replaced by a swapchain recreation, so caches drop the framebuffers using them.
+pub(crate) fn retire_views<'a>(views: impl IntoIterator<Item = &'a Arc<ImageView>>) {
+    let mut retired = RETIRED_VIEWS.lock().unwrap();
+    retired.retain(|view| view.strong_count() > 0);
+    retired.extend(views.into_iter().map(Arc::downgrade));
+}
+
+/// Reuses framebuffers of a render pass across frames instead of creating one per frame.
+///
+/// Framebuffers are looked up by render pass and attachment views. Framebuffers using swapchain
+/// images or resizable additional image views of a window are dropped when its swapchain was
+/// recreated, so the old images are released without invalidating the cache by hand, even when
+/// other caches reference them too. A framebuffer is also dropped once it holds the last reference
+/// to one of its attachments, e.g. a render target of your own which was replaced.
+///
+/// ```ignore
+/// let framebuffer = self.framebuffers.get(&self.render_pass, &[target]);
+/// ```
+#[derive(Default)]
+pub struct FramebufferCache {
+    framebuffers: Vec<Arc<Framebuffer>>,
+}
+
+impl FramebufferCache {
+    pub fn new() -> FramebufferCache {
+        FramebufferCache::default()
+    }
+
+    /// The framebuffer of `render_pass` with `attachments`, created if not cached. Attachments
+    /// must be in the order of the render pass description.
+    pub fn get(
+        &mut self,
+        render_pass: &Arc<RenderPass>,
+        attachments: &[Arc<ImageView>],
+    ) -> Arc<Framebuffer> {
+        self.remove_stale();
+        if let Some(framebuffer) = self.framebuffers.iter().find(|framebuffer| {
+            Arc::ptr_eq(framebuffer.render_pass(), render_pass)
+                && framebuffer.attachments().len() == attachments.len()
+                && framebuffer
+                    .attachments()
+                    .iter()
+                    .zip(attachments)
+                    .all(|(cached, attachment)| Arc::ptr_eq(cached, attachment))
+        }) {
+            return framebuffer.clone();
+        }
+        let framebuffer = Framebuffer::new(render_pass.clone(), FramebufferCreateInfo {
+            attachments: attachments.to_vec(),
+            ..Default::default()
+        })
+        .unwrap();
+        self.framebuffers.push(framebuffer.clone());
+        framebuffer
+    }
+
+    /// Drops framebuffers using views replaced by a swapchain recreation, and framebuffers holding
+    /// the last reference to one of their attachments.
+    pub fn remove_stale(&mut self) {
+        let retired = RETIRED_VIEWS.lock().unwrap();
+        self.framebuffers.retain(|framebuffer| {
+            framebuffer.attachments().iter().all(|attachment| {
+                Arc::strong_count(attachment) > 1
+                    && !retired
+                        .iter()
+                        .any(|view| view.as_ptr() == Arc::as_ptr(attachment))
+            })
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.framebuffers.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.framebuffers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.framebuffers.is_empty()
+    }
+}
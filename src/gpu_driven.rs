@@ -0,0 +1,345 @@
+use std::{mem::size_of, sync::Arc};
+
+use ash::vk;
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{AutoCommandBufferBuilder, DispatchIndirectCommand, DrawIndirectCommand},
+    device::{Device, DeviceOwned, Features},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    DeviceSize, ValidationError, VulkanObject,
+};
+
+use crate::CommandStats;
+
+/// Device features required by the buffer device address helpers.
+pub fn buffer_device_address_features() -> Features {
+    Features {
+        buffer_device_address: true,
+        ..Features::empty()
+    }
+}
+
+/// Device features required by [`IndirectCountDrawBuffer`] to let the GPU decide the number of
+/// draws.
+pub fn draw_indirect_count_features() -> Features {
+    Features {
+        draw_indirect_count: true,
+        ..Features::empty()
+    }
+}
+
+/// Whether `draw_indirect_count` can be recorded on the device, through the Vulkan 1.2 feature or
+/// the `khr_draw_indirect_count` extension. Enable it with
+/// [`BevyVulkanoSettings::draw_indirect_count`](crate::BevyVulkanoSettings::draw_indirect_count).
+pub fn supports_draw_indirect_count(device: &Device) -> bool {
+    device.enabled_features().draw_indirect_count
+        || device.enabled_extensions().khr_draw_indirect_count
+}
+
+/// Creates a buffer of `len` elements which can be referenced from shaders through its device
+/// address. Requires [`BevyVulkanoSettings::buffer_device_address`](crate::BevyVulkanoSettings).
+pub fn create_device_address_buffer<T: BufferContents>(
+    allocator: Arc<StandardMemoryAllocator>,
+    usage: BufferUsage,
+    memory_type_filter: MemoryTypeFilter,
+    len: DeviceSize,
+) -> Subbuffer<[T]> {
+    Buffer::new_slice::<T>(
+        allocator,
+        BufferCreateInfo {
+            usage: usage | BufferUsage::SHADER_DEVICE_ADDRESS,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter,
+            ..Default::default()
+        },
+        len,
+    )
+    .unwrap()
+}
+
+/// Device address of a buffer for use in shaders (`GL_EXT_buffer_reference`). Returns `None` if
+/// the buffer was created without [`BufferUsage::SHADER_DEVICE_ADDRESS`] or the feature is not
+/// enabled.
+pub fn buffer_device_address<T: ?Sized>(buffer: &Subbuffer<T>) -> Option<DeviceSize> {
+    buffer.device_address().ok().map(|address| address.get())
+}
+
+/// A storage buffer of draw commands meant to be written by a compute shader and executed with
+/// `draw_indirect`.
+///
+/// The commands follow `VkDrawIndirectCommand` layout, so a compute shader can declare them as:
+///
+/// ```glsl
+/// struct DrawCommand {
+///     uint vertex_count;
+///     uint instance_count;
+///     uint first_vertex;
+///     uint first_instance;
+/// };
+/// layout(set = 0, binding = 0) buffer DrawCommands { DrawCommand commands[]; };
+/// ```
+///
+/// Commands the compute shader does not want executed should have `instance_count = 0`.
+pub struct IndirectDrawBuffer {
+    commands: Subbuffer<[DrawIndirectCommand]>,
+}
+
+impl IndirectDrawBuffer {
+    pub fn new(
+        allocator: Arc<StandardMemoryAllocator>,
+        max_draws: DeviceSize,
+    ) -> IndirectDrawBuffer {
+        let commands = Buffer::new_slice::<DrawIndirectCommand>(
+            allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::INDIRECT_BUFFER
+                    | BufferUsage::STORAGE_BUFFER
+                    | BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+            max_draws.max(1),
+        )
+        .unwrap();
+        IndirectDrawBuffer {
+            commands,
+        }
+    }
+
+    /// The command buffer, bind this as a storage buffer in the compute shader generating draws.
+    pub fn commands(&self) -> Subbuffer<[DrawIndirectCommand]> {
+        self.commands.clone()
+    }
+
+    /// Maximum number of draws the buffer can hold.
+    pub fn max_draws(&self) -> DeviceSize {
+        self.commands.len()
+    }
+
+    /// Records a `draw_indirect` of all commands in the buffer. Bind your graphics pipeline and
+    /// resources before calling this.
+    pub fn draw<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+    ) -> Result<(), Box<ValidationError>> {
+        builder.draw_indirect(self.commands.clone())?;
+        CommandStats::record_draws(1);
+        Ok(())
+    }
+
+    /// Records a command zeroing all draw commands, e.g. before a compute shader appends new
+    /// draws.
+    pub fn clear<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+    ) -> Result<(), Box<ValidationError>> {
+        builder.fill_buffer(self.commands.clone().reinterpret::<[u32]>(), 0)?;
+        Ok(())
+    }
+}
+
+/// Draw commands together with a draw count, both written by a compute shader, e.g. after GPU
+/// culling appends visible draws with `atomicAdd` on the count.
+///
+/// Vulkano doesn't expose `vkCmdDrawIndirectCount`, so [`IndirectCountDrawBuffer::draw`] draws
+/// all commands, and the buffer must be [cleared](IndirectCountDrawBuffer::clear) before the draws
+/// are generated, leaving unused commands with `instance_count = 0`. When the count is known on
+/// the CPU, e.g. from culling on the CPU, [`IndirectCountDrawBuffer::draw_first`] skips the unused
+/// commands. With [`supports_draw_indirect_count`], the GPU written count can be recorded into a
+/// raw command buffer with the unsafe [`IndirectCountDrawBuffer::record_draw_indirect_count`].
+///
+/// ```glsl
+/// layout(set = 0, binding = 0) buffer DrawCommands { DrawCommand commands[]; };
+/// layout(set = 0, binding = 1) buffer DrawCount { uint count; };
+/// ```
+pub struct IndirectCountDrawBuffer {
+    draws: IndirectDrawBuffer,
+    count: Subbuffer<u32>,
+    use_count: bool,
+}
+
+impl IndirectCountDrawBuffer {
+    pub fn new(
+        allocator: Arc<StandardMemoryAllocator>,
+        max_draws: DeviceSize,
+    ) -> IndirectCountDrawBuffer {
+        let use_count = supports_draw_indirect_count(allocator.device());
+        let count = Buffer::new_sized::<u32>(
+            allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::INDIRECT_BUFFER
+                    | BufferUsage::STORAGE_BUFFER
+                    | BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        IndirectCountDrawBuffer {
+            draws: IndirectDrawBuffer::new(allocator, max_draws),
+            count,
+            use_count,
+        }
+    }
+
+    /// The command buffer, bind this as a storage buffer in the compute shader generating draws.
+    pub fn commands(&self) -> Subbuffer<[DrawIndirectCommand]> {
+        self.draws.commands()
+    }
+
+    /// The draw count, bind this as a storage buffer in the compute shader generating draws.
+    pub fn count(&self) -> Subbuffer<u32> {
+        self.count.clone()
+    }
+
+    /// Maximum number of draws the buffer can hold.
+    pub fn max_draws(&self) -> DeviceSize {
+        self.draws.max_draws()
+    }
+
+    /// Whether the device can limit draws by the GPU written count, see
+    /// [`IndirectCountDrawBuffer::record_draw_indirect_count`].
+    pub fn uses_draw_count(&self) -> bool {
+        self.use_count
+    }
+
+    /// Records a draw of all generated commands, unused commands must have been left with
+    /// `instance_count = 0` by [`IndirectCountDrawBuffer::clear`]. Bind your graphics pipeline and
+    /// resources before calling this.
+    pub fn draw<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+    ) -> Result<(), Box<ValidationError>> {
+        self.draws.draw(builder)
+    }
+
+    /// Records a `vkCmdDrawIndirectCount` executing the first `count` commands into a raw command
+    /// buffer. Returns `false` without recording anything if the device doesn't
+    /// [support](supports_draw_indirect_count) it.
+    ///
+    /// # Safety
+    ///
+    /// - `command_buffer` must be a command buffer of this buffer's device in the recording
+    ///   state, inside a render pass with a compatible graphics pipeline and its resources bound.
+    /// - The commands and the count must not be written while the command buffer executes, and
+    ///   the writes producing them must be made visible to indirect command reads, which vulkano
+    ///   doesn't track for raw commands.
+    /// - The buffers must be kept alive until the command buffer finished executing.
+    pub unsafe fn record_draw_indirect_count(&self, command_buffer: vk::CommandBuffer) -> bool {
+        if !self.use_count {
+            return false;
+        }
+        let commands = self.draws.commands();
+        let device = commands.device().clone();
+        let fns = device.fns();
+        let cmd_draw_indirect_count = if device.enabled_features().draw_indirect_count {
+            fns.v1_2.cmd_draw_indirect_count
+        } else {
+            fns.khr_draw_indirect_count.cmd_draw_indirect_count_khr
+        };
+        cmd_draw_indirect_count(
+            command_buffer,
+            commands.buffer().handle(),
+            commands.offset(),
+            self.count.buffer().handle(),
+            self.count.offset(),
+            self.max_draws() as u32,
+            size_of::<DrawIndirectCommand>() as u32,
+        );
+        CommandStats::record_draws(1);
+        true
+    }
+
+    /// Records a draw of the first `count` commands, with the count known on the CPU.
+    pub fn draw_first<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        count: DeviceSize,
+    ) -> Result<(), Box<ValidationError>> {
+        let count = count.min(self.max_draws());
+        if count > 0 {
+            builder.draw_indirect(self.draws.commands().slice(..count))?;
+            CommandStats::record_draws(1);
+        }
+        Ok(())
+    }
+
+    /// Records commands zeroing the count and all draw commands, before a compute shader appends
+    /// new draws.
+    pub fn clear<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+    ) -> Result<(), Box<ValidationError>> {
+        self.draws.clear(builder)?;
+        builder.fill_buffer(self.count.clone(), 0)?;
+        Ok(())
+    }
+}
+
+/// A dispatch command written by a compute shader and executed with `dispatch_indirect`, e.g. to
+/// size a follow-up pass by the amount of work an earlier pass produced.
+///
+/// ```glsl
+/// layout(set = 0, binding = 0) buffer Dispatch { uvec3 group_count; };
+/// ```
+pub struct IndirectDispatchBuffer {
+    command: Subbuffer<[DispatchIndirectCommand]>,
+}
+
+impl IndirectDispatchBuffer {
+    pub fn new(allocator: Arc<StandardMemoryAllocator>) -> IndirectDispatchBuffer {
+        let command = Buffer::new_slice::<DispatchIndirectCommand>(
+            allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::INDIRECT_BUFFER
+                    | BufferUsage::STORAGE_BUFFER
+                    | BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+            1,
+        )
+        .unwrap();
+        IndirectDispatchBuffer {
+            command,
+        }
+    }
+
+    /// The dispatch command, bind this as a storage buffer in the compute shader sizing the
+    /// dispatch.
+    pub fn command(&self) -> Subbuffer<[DispatchIndirectCommand]> {
+        self.command.clone()
+    }
+
+    /// Records a `dispatch_indirect`. Bind your compute pipeline and resources before calling
+    /// this.
+    pub fn dispatch<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+    ) -> Result<(), Box<ValidationError>> {
+        builder.dispatch_indirect(self.command.clone())?;
+        CommandStats::record_dispatches(1);
+        Ok(())
+    }
+
+    /// Records a command zeroing the group counts, so the dispatch does nothing unless the
+    /// shader writes it.
+    pub fn clear<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+    ) -> Result<(), Box<ValidationError>> {
+        builder.fill_buffer(self.command.clone().reinterpret::<[u32]>(), 0)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use bevy::prelude::{NonSend, Res, Resource};
+use egui_winit_vulkano::egui;
+
+use crate::{BevyVulkanoSettings, BevyVulkanoWindows};
+
+/// Background color of windows whose gui clears the image each frame, i.e. when
+/// [`BevyVulkanoSettings::is_gui_overlay`] is false. Mirrors bevy's `ClearColor`, which isn't
+/// available without `bevy_render`. The color is in sRGB space and can be changed at any time.
+/// Without the resource, the gui clears to transparent black.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct GuiClearColor(pub [f32; 4]);
+
+impl Default for GuiClearColor {
+    fn default() -> Self {
+        GuiClearColor([0.0, 0.0, 0.0, 1.0])
+    }
+}
+
+impl GuiClearColor {
+    fn to_color32(self) -> egui::Color32 {
+        let [r, g, b, a] = self.0.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+    }
+}
+
+/// Paints the [`GuiClearColor`] behind each window's gui. Runs after the egui frames have begun.
+pub(crate) fn paint_gui_clear_color(
+    clear_color: Option<Res<GuiClearColor>>,
+    settings: NonSend<BevyVulkanoSettings>,
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+) {
+    let Some(clear_color) = clear_color else {
+        return;
+    };
+    if settings.is_gui_overlay {
+        return;
+    }
+    let color = clear_color.to_color32();
+    for (_, vulkano_window) in vulkano_windows.iter() {
+        let Some(gui) = &vulkano_window.gui else {
+            continue;
+        };
+        let context = gui.context();
+        context
+            .layer_painter(egui::LayerId::background())
+            .rect_filled(context.screen_rect(), 0.0, color);
+    }
+}
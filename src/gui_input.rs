@@ -0,0 +1,93 @@
+use bevy::prelude::{Commands, Component, NonSend, Query};
+use egui_winit_vulkano::egui;
+use winit::event::{ElementState, WindowEvent};
+
+use crate::BevyVulkanoWindows;
+
+/// Whether egui wants the pointer or keyboard input of a window, updated each frame on window
+/// entities with a gui. Check it to keep game input from triggering underneath gui panels.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GuiInputCapture {
+    /// The pointer is over a gui area or dragging something in the gui.
+    pub wants_pointer: bool,
+    /// A gui widget, e.g. a text field, has keyboard focus.
+    pub wants_keyboard: bool,
+}
+
+/// Which window events handled by egui are withheld from bevy's input events, see
+/// [`BevyVulkanoSettings::gui_input_filter`](crate::BevyVulkanoSettings::gui_input_filter).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GuiInputFilter {
+    /// Send all events to bevy, use [`GuiInputCapture`] to ignore input under the gui.
+    None,
+    /// Skip events egui reports as consumed.
+    #[default]
+    Consumed,
+    /// Additionally skip pointer button, wheel and touch events while egui wants pointer input,
+    /// and keyboard events while it wants keyboard input. Releases are always sent so that bevy
+    /// doesn't see buttons stuck pressed.
+    Captured,
+}
+
+impl GuiInputFilter {
+    /// Whether `event`, which egui reported as `consumed`, should be skipped in bevy.
+    pub(crate) fn skips(
+        self,
+        event: &WindowEvent,
+        consumed: bool,
+        context: &egui::Context,
+    ) -> bool {
+        match self {
+            GuiInputFilter::None => false,
+            GuiInputFilter::Consumed => consumed,
+            GuiInputFilter::Captured => {
+                consumed
+                    || match event {
+                        WindowEvent::MouseInput {
+                            state: ElementState::Pressed,
+                            ..
+                        }
+                        | WindowEvent::MouseWheel {
+                            ..
+                        }
+                        | WindowEvent::Touch(_) => context.wants_pointer_input(),
+                        WindowEvent::KeyboardInput {
+                            input, ..
+                        } => input.state == ElementState::Pressed && context.wants_keyboard_input(),
+                        WindowEvent::ReceivedCharacter(_) | WindowEvent::Ime(_) => {
+                            context.wants_keyboard_input()
+                        }
+                        _ => false,
+                    }
+            }
+        }
+    }
+}
+
+/// Updates the [`GuiInputCapture`] of each window from its gui.
+pub(crate) fn update_gui_input_capture(
+    mut commands: Commands,
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+    mut captures: Query<&mut GuiInputCapture>,
+) {
+    for (window, vulkano_window) in vulkano_windows.iter() {
+        let Some(gui) = &vulkano_window.gui else {
+            continue;
+        };
+        let context = gui.context();
+        let capture = GuiInputCapture {
+            wants_pointer: context.wants_pointer_input(),
+            wants_keyboard: context.wants_keyboard_input(),
+        };
+        match captures.get_mut(window) {
+            Ok(mut current) => {
+                if *current != capture {
+                    *current = capture;
+                }
+            }
+            Err(_) => {
+                commands.entity(window).insert(capture);
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use bevy::prelude::{Component, NonSendMut, Query};
+
+use crate::BevyVulkanoWindows;
+
+/// Scale of a window's gui on top of the window's scale factor, e.g. for a UI scale slider. Add it
+/// to a window entity; it can be changed at any time. Windows without it use a scale of 1.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct UiScale(pub f32);
+
+impl Default for UiScale {
+    fn default() -> Self {
+        UiScale(1.0)
+    }
+}
+
+/// Applies the [`UiScale`] of each window to its gui before the egui frame begins. Applied every
+/// frame, because egui resets the pixels per point when the window's scale factor changes.
+pub(crate) fn apply_ui_scale(
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    ui_scales: Query<&UiScale>,
+) {
+    for (window, vulkano_window) in vulkano_windows.iter_mut() {
+        let scale_factor = vulkano_window.window().scale_factor() as f32;
+        let Some(gui) = &mut vulkano_window.gui else {
+            continue;
+        };
+        let scale = ui_scales
+            .get(window)
+            .map_or(1.0, |scale| scale.0)
+            .clamp(0.1, 10.0);
+        let pixels_per_point = scale_factor * scale;
+        if gui.egui_winit.pixels_per_point() != pixels_per_point {
+            gui.egui_winit.set_pixels_per_point(pixels_per_point);
+        }
+    }
+}
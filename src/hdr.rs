@@ -0,0 +1,149 @@
+use ash::vk;
+use bevy::log::{info, warn};
+use vulkano::{
+    device::DeviceOwned,
+    format::Format,
+    swapchain::{ColorSpace, Swapchain},
+    VulkanLibrary, VulkanObject,
+};
+use vulkano_util::context::VulkanoConfig;
+
+/// SDR reference white in nits used when the display's SDR white level is unknown. This is the
+/// luminance of `1.0` in the `ExtendedSrgbLinear` (scRGB) color space.
+pub const DEFAULT_SDR_WHITE_LEVEL: f32 = 80.0;
+
+/// HDR metadata describing the mastering display and content light levels of the rendered
+/// content, see `VkHdrMetadataEXT`. Chromaticities are CIE 1931 xy coordinates and luminances are
+/// in nits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrMetadata {
+    pub display_primary_red: [f32; 2],
+    pub display_primary_green: [f32; 2],
+    pub display_primary_blue: [f32; 2],
+    pub white_point: [f32; 2],
+    pub max_luminance: f32,
+    pub min_luminance: f32,
+    pub max_content_light_level: f32,
+    pub max_frame_average_light_level: f32,
+}
+
+impl Default for HdrMetadata {
+    /// Rec. 2020 primaries with a D65 white point mastered at 1000 nits.
+    fn default() -> Self {
+        HdrMetadata {
+            display_primary_red: [0.708, 0.292],
+            display_primary_green: [0.170, 0.797],
+            display_primary_blue: [0.131, 0.046],
+            white_point: [0.3127, 0.3290],
+            max_luminance: 1000.0,
+            min_luminance: 0.001,
+            max_content_light_level: 1000.0,
+            max_frame_average_light_level: 400.0,
+        }
+    }
+}
+
+/// Errors from applying [`HdrMetadata`] to a swapchain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HdrMetadataError {
+    /// The `ext_hdr_metadata` device extension is not enabled. Enable it through
+    /// `vulkano_config.device_extensions` of [`BevyVulkanoSettings`](crate::BevyVulkanoSettings).
+    ExtensionNotEnabled,
+    /// The swapchain uses the SDR `SrgbNonLinear` color space.
+    NotHdr,
+}
+
+impl std::fmt::Display for HdrMetadataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HdrMetadataError::ExtensionNotEnabled => {
+                write!(f, "the ext_hdr_metadata device extension is not enabled")
+            }
+            HdrMetadataError::NotHdr => write!(f, "the swapchain does not use an HDR color space"),
+        }
+    }
+}
+
+impl std::error::Error for HdrMetadataError {}
+
+/// Whether the color space is an HDR / extended range color space.
+pub fn is_hdr_color_space(color_space: ColorSpace) -> bool {
+    color_space != ColorSpace::SrgbNonLinear
+}
+
+/// Swapchain formats suited to a color space, in order of preference. HDR color spaces need more
+/// than 8 bits per channel: `ExtendedSrgbLinear` (scRGB) uses a float format and the PQ and HLG
+/// color spaces a 10 bit format.
+pub fn hdr_formats(color_space: ColorSpace) -> &'static [Format] {
+    match color_space {
+        ColorSpace::ExtendedSrgbLinear | ColorSpace::ExtendedSrgbNonLinear => {
+            &[Format::R16G16B16A16_SFLOAT]
+        }
+        ColorSpace::Hdr10St2084 | ColorSpace::Hdr10Hlg | ColorSpace::DolbyVision => &[
+            Format::A2B10G10R10_UNORM_PACK32,
+            Format::A2R10G10B10_UNORM_PACK32,
+            Format::R16G16B16A16_SFLOAT,
+        ],
+        ColorSpace::SrgbNonLinear => &[Format::B8G8R8A8_SRGB, Format::R8G8B8A8_SRGB],
+        _ => &[
+            Format::R16G16B16A16_SFLOAT,
+            Format::A2B10G10R10_UNORM_PACK32,
+        ],
+    }
+}
+
+/// Enables the `ext_swapchain_colorspace` instance extension if the Vulkan library supports it.
+/// Without it, surfaces only report the `SrgbNonLinear` color space.
+pub(crate) fn enable_hdr_color_spaces(config: &mut VulkanoConfig) {
+    let Ok(library) = VulkanLibrary::new() else {
+        return;
+    };
+    if library.supported_extensions().ext_swapchain_colorspace {
+        config
+            .instance_create_info
+            .enabled_extensions
+            .ext_swapchain_colorspace = true;
+        info!("Enabled ext_swapchain_colorspace for HDR swapchains");
+    } else {
+        warn!("HDR swapchains are unavailable, ext_swapchain_colorspace is not supported");
+    }
+}
+
+/// Applies HDR metadata to the swapchain with `vkSetHdrMetadataEXT`.
+pub(crate) fn set_swapchain_hdr_metadata(
+    swapchain: &Swapchain,
+    metadata: &HdrMetadata,
+) -> Result<(), HdrMetadataError> {
+    let device = swapchain.device();
+    if !device.enabled_extensions().ext_hdr_metadata {
+        return Err(HdrMetadataError::ExtensionNotEnabled);
+    }
+    if !is_hdr_color_space(swapchain.image_color_space()) {
+        return Err(HdrMetadataError::NotHdr);
+    }
+    let xy = |c: [f32; 2]| vk::XYColorEXT {
+        x: c[0],
+        y: c[1],
+    };
+    let vk_metadata = vk::HdrMetadataEXT {
+        display_primary_red: xy(metadata.display_primary_red),
+        display_primary_green: xy(metadata.display_primary_green),
+        display_primary_blue: xy(metadata.display_primary_blue),
+        white_point: xy(metadata.white_point),
+        max_luminance: metadata.max_luminance,
+        min_luminance: metadata.min_luminance,
+        max_content_light_level: metadata.max_content_light_level,
+        max_frame_average_light_level: metadata.max_frame_average_light_level,
+        ..Default::default()
+    };
+    let swapchain_handle = swapchain.handle();
+    unsafe {
+        (device.fns().ext_hdr_metadata.set_hdr_metadata_ext)(
+            device.handle(),
+            1,
+            &swapchain_handle,
+            &vk_metadata,
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,307 @@
+use std::sync::Arc;
+
+#[cfg(feature = "capture")]
+use bevy::log::warn;
+#[cfg(feature = "capture")]
+use vulkano::command_buffer::{
+    allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+};
+use vulkano::{
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::StandardMemoryAllocator,
+    sync::{self, GpuFuture},
+};
+use vulkano_util::context::VulkanoContext;
+
+#[cfg(feature = "capture")]
+use crate::capture::{supports_readback, ImageReadback};
+use crate::{device_info::DriverWorkarounds, profiling::profile_scope, trace_frame::trace_vk};
+
+/// Configuration of the [`BevyVulkanoHeadlessRenderer`], see
+/// [`BevyVulkanoSettings::headless`](crate::BevyVulkanoSettings::headless).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeadlessConfig {
+    /// Size of the images, e.g. the resolution of screenshots.
+    pub extent: [u32; 2],
+    /// Format of the images. The default `R8G8B8A8_UNORM` can be captured, see
+    /// [`supports_readback`](crate::supports_readback).
+    pub format: Format,
+    /// Number of images rendered to in turn, like the images of a swapchain.
+    pub image_count: u32,
+    /// Usages of the images in addition to `COLOR_ATTACHMENT`, `SAMPLED` and `TRANSFER_SRC`, e.g.
+    /// `STORAGE` for writing the image from a compute shader.
+    pub image_usage: ImageUsage,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        HeadlessConfig {
+            extent: [1280, 720],
+            format: Format::R8G8B8A8_UNORM,
+            image_count: 2,
+            image_usage: ImageUsage::empty(),
+        }
+    }
+}
+
+/// An offscreen chain of images standing in for a window's swapchain, for rendering without any
+/// window, e.g. server-side rendering or screenshot tests in CI. Created by
+/// [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin) as a non-send resource when
+/// [`BevyVulkanoSettings::headless`](crate::BevyVulkanoSettings::headless) is set, typically
+/// together with `WindowPlugin { primary_window: None, .. }`.
+///
+/// Render systems use it like a [`VulkanoWindowRenderer`](crate::VulkanoWindowRenderer):
+///
+/// ```ignore
+/// fn render(mut headless: NonSendMut<BevyVulkanoHeadlessRenderer>) {
+///     let before = headless.acquire();
+///     let after = my_pass.draw(before, headless.image_view());
+///     headless.request_capture();
+///     headless.present(after, true);
+///     let frame = headless.take_capture().and_then(|capture| capture.to_rgba_image());
+/// }
+/// ```
+pub struct BevyVulkanoHeadlessRenderer {
+    queue: Arc<Queue>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    #[cfg(feature = "capture")]
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    driver_workarounds: DriverWorkarounds,
+    config: HeadlessConfig,
+    images: Vec<Arc<ImageView>>,
+    /// Whether the image should be copied to the host on next present.
+    #[cfg(feature = "capture")]
+    capture_requested: bool,
+    #[cfg(feature = "capture")]
+    capture: Option<ImageReadback>,
+    previous_frame_end: Option<Box<dyn GpuFuture>>,
+    image_index: u32,
+    /// Number of frames presented.
+    frame_index: u64,
+}
+
+impl BevyVulkanoHeadlessRenderer {
+    pub fn new(
+        vulkano_context: &VulkanoContext,
+        config: HeadlessConfig,
+        driver_workarounds: DriverWorkarounds,
+    ) -> BevyVulkanoHeadlessRenderer {
+        let config = HeadlessConfig {
+            extent: [config.extent[0].max(1), config.extent[1].max(1)],
+            image_count: config.image_count.max(1),
+            ..config
+        };
+        let mut renderer = BevyVulkanoHeadlessRenderer {
+            queue: vulkano_context.graphics_queue().clone(),
+            memory_allocator: vulkano_context.memory_allocator().clone(),
+            #[cfg(feature = "capture")]
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                vulkano_context.device().clone(),
+                Default::default(),
+            ),
+            driver_workarounds,
+            config,
+            images: vec![],
+            #[cfg(feature = "capture")]
+            capture_requested: false,
+            #[cfg(feature = "capture")]
+            capture: None,
+            previous_frame_end: Some(sync::now(vulkano_context.device().clone()).boxed()),
+            image_index: 0,
+            frame_index: 0,
+        };
+        renderer.create_images();
+        renderer
+    }
+
+    /// Configuration the images were created with.
+    #[inline]
+    pub fn config(&self) -> &HeadlessConfig {
+        &self.config
+    }
+
+    /// Queue rendering and captures are submitted to, the graphics queue of the context.
+    #[inline]
+    pub fn queue(&self) -> Arc<Queue> {
+        self.queue.clone()
+    }
+
+    /// Recreates the images with a new size. Frames in flight keep the previous images alive.
+    pub fn resize(&mut self, extent: [u32; 2]) {
+        let extent = [extent[0].max(1), extent[1].max(1)];
+        if extent == self.config.extent {
+            return;
+        }
+        self.config.extent = extent;
+        self.create_images();
+    }
+
+    /// Size of the images.
+    #[inline]
+    pub fn image_size(&self) -> [u32; 2] {
+        self.config.extent
+    }
+
+    #[inline]
+    pub fn image_format(&self) -> Format {
+        self.config.format
+    }
+
+    /// Index of the image that is the current render target.
+    #[inline]
+    pub fn image_index(&self) -> u32 {
+        self.image_index
+    }
+
+    /// Number of frames presented.
+    #[inline]
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// The current image to render to.
+    #[inline]
+    pub fn image_view(&self) -> Arc<ImageView> {
+        self.images[self.image_index as usize].clone()
+    }
+
+    /// All images of the chain, e.g. for creating framebuffers up front.
+    #[inline]
+    pub fn image_views(&self) -> &[Arc<ImageView>] {
+        &self.images
+    }
+
+    /// Begins a frame on the next image of the chain. Returns a [`GpuFuture`] representing the end
+    /// of the previous frame, to execute your command buffers after. Finish the frame with
+    /// [`BevyVulkanoHeadlessRenderer::present`].
+    pub fn acquire(&mut self) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::headless_acquire");
+        self.image_index = (self.frame_index % self.images.len() as u64) as u32;
+        trace_vk!("acquire headless image {}", self.image_index);
+        let mut future = self.previous_frame_end.take().unwrap();
+        future.cleanup_finished();
+        future
+    }
+
+    /// Finishes the frame by submitting `after_future`. Nothing is shown, but a requested capture
+    /// is copied. With `wait_future`, waits for the GPU to finish the frame.
+    pub fn present(&mut self, after_future: Box<dyn GpuFuture>, wait_future: bool) {
+        profile_scope!("bevy_vulkano::headless_present");
+        #[cfg(feature = "capture")]
+        let (after_future, wait_future) = if self.capture_requested {
+            // The copy must finish before the captured image can be read
+            (self.record_capture(after_future), true)
+        } else {
+            (after_future, wait_future)
+        };
+        trace_vk!(
+            "present headless image {}, wait {}",
+            self.image_index,
+            wait_future
+        );
+        match after_future.then_signal_fence_and_flush() {
+            Ok(mut future) => {
+                if wait_future {
+                    if let Err(e) = future.wait(None) {
+                        bevy::log::error!("Failed to wait on headless frame: {e}");
+                    }
+                } else {
+                    future.cleanup_finished();
+                }
+                self.previous_frame_end = Some(future.boxed());
+                self.frame_index += 1;
+            }
+            Err(e) => {
+                bevy::log::error!("Failed to flush headless frame: {e}");
+                #[cfg(feature = "capture")]
+                {
+                    self.capture = None;
+                }
+                self.previous_frame_end = Some(sync::now(self.queue.device().clone()).boxed());
+            }
+        }
+    }
+
+    /// Copy the image to the host when it is next presented. The frame is waited on and the copy
+    /// can be taken with [`BevyVulkanoHeadlessRenderer::take_capture`] after
+    /// [`BevyVulkanoHeadlessRenderer::present`].
+    #[cfg(feature = "capture")]
+    #[inline]
+    pub fn request_capture(&mut self) {
+        self.capture_requested = true;
+    }
+
+    /// Returns the image copied during the last present after
+    /// [`BevyVulkanoHeadlessRenderer::request_capture`], clearing it.
+    #[cfg(feature = "capture")]
+    #[inline]
+    pub fn take_capture(&mut self) -> Option<ImageReadback> {
+        self.capture.take()
+    }
+
+    /// Copies the current image into a new readback after `after_future`.
+    #[cfg(feature = "capture")]
+    fn record_capture(&mut self, after_future: Box<dyn GpuFuture>) -> Box<dyn GpuFuture> {
+        self.capture_requested = false;
+        let image = self.image_view().image().clone();
+        if !supports_readback(image.format()) {
+            warn!(
+                "Headless image can't be captured (format {:?})",
+                image.format()
+            );
+            return after_future;
+        }
+        let readback = ImageReadback::new(
+            self.memory_allocator.clone(),
+            self.config.extent,
+            image.format(),
+        );
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        readback.record(&mut builder, image).unwrap();
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit headless capture copy of image {} on queue family {}",
+            self.image_index,
+            self.queue.queue_family_index()
+        );
+        self.capture = Some(readback);
+        after_future
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+
+    fn create_images(&mut self) {
+        let usage = self.config.image_usage
+            | ImageUsage::COLOR_ATTACHMENT
+            | ImageUsage::SAMPLED
+            | ImageUsage::TRANSFER_SRC;
+        self.images = (0..self.config.image_count)
+            .map(|_| {
+                ImageView::new_default(
+                    Image::new(
+                        self.memory_allocator.clone(),
+                        ImageCreateInfo {
+                            image_type: ImageType::Dim2d,
+                            format: self.config.format,
+                            extent: [self.config.extent[0], self.config.extent[1], 1],
+                            usage,
+                            ..Default::default()
+                        },
+                        self.driver_workarounds.image_allocation_info(usage),
+                    )
+                    .unwrap(),
+                )
+                .unwrap()
+            })
+            .collect();
+        self.image_index = 0;
+    }
+}
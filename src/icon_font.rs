@@ -0,0 +1,616 @@
+use std::sync::Arc;
+
+use bevy::utils::HashMap;
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        RenderPassBeginInfo, SubpassBeginInfo, SubpassContents,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{
+        sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
+        view::ImageView,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    pipeline::{
+        graphics::{
+            color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::VertexInputState,
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    render_pass::{RenderPass, Subpass},
+    sync::GpuFuture,
+};
+
+use crate::{
+    profiling::profile_scope, trace_frame::trace_vk, CommandStats, FramebufferCache,
+    ImageTextureId, TextureRegistry,
+};
+
+/// Width of icon atlases, unless an icon is wider.
+const ATLAS_WIDTH: u32 = 256;
+/// Transparent pixels between icons, so that filtering doesn't bleed neighbours in.
+const ATLAS_PADDING: u32 = 1;
+/// Subsamples per axis when rasterizing the built-in icons.
+const SUBSAMPLES: u32 = 4;
+
+/// Built-in icons for debug overlays and tools, added to an atlas with
+/// [`IconAtlasBuilder::with_builtin_icons`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Icon {
+    /// A triangle with an exclamation mark.
+    Warning,
+    /// A circle with a cross.
+    Error,
+    /// A circle with an "i".
+    Info,
+    /// A check mark.
+    Check,
+    /// A cross.
+    Cross,
+}
+
+impl Icon {
+    pub const ALL: [Icon; 5] = [
+        Icon::Warning,
+        Icon::Error,
+        Icon::Info,
+        Icon::Check,
+        Icon::Cross,
+    ];
+
+    /// Name of the icon in an [`IconAtlas`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Icon::Warning => "bevy_vulkano::warning",
+            Icon::Error => "bevy_vulkano::error",
+            Icon::Info => "bevy_vulkano::info",
+            Icon::Check => "bevy_vulkano::check",
+            Icon::Cross => "bevy_vulkano::cross",
+        }
+    }
+
+    /// Whether the point `[x, y]` of a 16 by 16 icon is covered.
+    fn covers(self, [x, y]: [f32; 2]) -> bool {
+        let in_circle = (x - 8.0).powi(2) + (y - 8.0).powi(2) <= 7.5 * 7.5;
+        match self {
+            Icon::Warning => {
+                let in_triangle = (1.0..=15.0).contains(&y) && (x - 8.0).abs() <= (y - 1.0) * 0.5;
+                let exclamation = (x - 8.0).abs() <= 1.0
+                    && ((5.0..=10.0).contains(&y) || (11.5..=13.5).contains(&y));
+                in_triangle && !exclamation
+            }
+            Icon::Error => {
+                let cross = segment_distance([x, y], [5.0, 5.0], [11.0, 11.0]) <= 1.0
+                    || segment_distance([x, y], [11.0, 5.0], [5.0, 11.0]) <= 1.0;
+                in_circle && !cross
+            }
+            Icon::Info => {
+                let letter = (x - 8.0).abs() <= 1.0
+                    && ((3.5..=5.5).contains(&y) || (7.0..=12.5).contains(&y));
+                in_circle && !letter
+            }
+            Icon::Check => {
+                segment_distance([x, y], [2.5, 8.5], [6.0, 12.0]) <= 1.5
+                    || segment_distance([x, y], [6.0, 12.0], [13.5, 4.0]) <= 1.5
+            }
+            Icon::Cross => {
+                segment_distance([x, y], [3.0, 3.0], [13.0, 13.0]) <= 1.5
+                    || segment_distance([x, y], [13.0, 3.0], [3.0, 13.0]) <= 1.5
+            }
+        }
+    }
+
+    /// Rasterizes the icon into `size` by `size` coverage values.
+    fn rasterize(self, size: u32) -> Vec<u8> {
+        let scale = 16.0 / size as f32;
+        let samples = (SUBSAMPLES * SUBSAMPLES) as f32;
+        (0..size * size)
+            .map(|pixel| {
+                let [px, py] = [pixel % size, pixel / size];
+                let covered = (0..SUBSAMPLES * SUBSAMPLES)
+                    .filter(|sample| {
+                        let sx = (px as f32
+                            + (sample % SUBSAMPLES) as f32 / SUBSAMPLES as f32
+                            + 0.5 / SUBSAMPLES as f32)
+                            * scale;
+                        let sy = (py as f32
+                            + (sample / SUBSAMPLES) as f32 / SUBSAMPLES as f32
+                            + 0.5 / SUBSAMPLES as f32)
+                            * scale;
+                        self.covers([sx, sy])
+                    })
+                    .count();
+                (covered as f32 / samples * 255.0).round() as u8
+            })
+            .collect()
+    }
+}
+
+fn segment_distance(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let ap = [p[0] - a[0], p[1] - a[1]];
+    let t = ((ap[0] * ab[0] + ap[1] * ab[1]) / (ab[0] * ab[0] + ab[1] * ab[1])).clamp(0.0, 1.0);
+    let d = [ap[0] - ab[0] * t, ap[1] - ab[1] * t];
+    (d[0] * d[0] + d[1] * d[1]).sqrt()
+}
+
+/// Where an icon is in an [`IconAtlas`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IconRegion {
+    /// Offset of the icon in normalized texture coordinates.
+    pub uv_offset: [f32; 2],
+    /// Size of the icon in normalized texture coordinates.
+    pub uv_size: [f32; 2],
+    /// Size of the icon in pixels.
+    pub extent: [u32; 2],
+}
+
+/// Collects icons into an atlas texture uploaded to the [`TextureRegistry`].
+///
+/// Icons are given as coverage, one byte per pixel, and are stored as white with the coverage as
+/// alpha in an `R8G8B8A8_UNORM` texture, so they can be tinted when drawn. Rasterize the glyphs of
+/// an icon font with a font rasterizer of your choice and add them with [`IconAtlasBuilder::add`].
+///
+/// ```ignore
+/// let (atlas, upload) = IconAtlasBuilder::new()
+///     .with_builtin_icons(32)
+///     .add("save", [24, 24], save_icon_coverage)
+///     .upload("my_tool::icons", &mut registry, queue, allocator, &command_buffer_allocator);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct IconAtlasBuilder {
+    icons: Vec<(String, [u32; 2], Vec<u8>)>,
+}
+
+impl IconAtlasBuilder {
+    pub fn new() -> IconAtlasBuilder {
+        IconAtlasBuilder::default()
+    }
+
+    /// Adds the built-in [`Icon`]s, rasterized at `size` by `size` pixels.
+    pub fn with_builtin_icons(mut self, size: u32) -> IconAtlasBuilder {
+        let size = size.max(1);
+        for icon in Icon::ALL {
+            self.icons
+                .push((icon.name().to_string(), [size, size], icon.rasterize(size)));
+        }
+        self
+    }
+
+    /// Adds an icon of `extent` with tightly packed `coverage`, one byte per pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `coverage` doesn't match the extent.
+    pub fn add(mut self, name: &str, extent: [u32; 2], coverage: Vec<u8>) -> IconAtlasBuilder {
+        assert_eq!(
+            coverage.len() as u64,
+            extent[0] as u64 * extent[1] as u64,
+            "coverage of icon {name:?} doesn't match its extent {extent:?}"
+        );
+        self.icons.push((name.to_string(), extent, coverage));
+        self
+    }
+
+    /// Packs the icons row by row and returns the atlas extent and the pixel offset of each icon.
+    fn pack(&self) -> ([u32; 2], Vec<[u32; 2]>) {
+        let width = self
+            .icons
+            .iter()
+            .map(|(_, extent, _)| extent[0] + ATLAS_PADDING * 2)
+            .max()
+            .unwrap_or(1)
+            .max(ATLAS_WIDTH);
+        let mut offsets = vec![];
+        let (mut x, mut y, mut row_height) = (ATLAS_PADDING, ATLAS_PADDING, 0);
+        for (_, extent, _) in &self.icons {
+            if x + extent[0] + ATLAS_PADDING > width {
+                x = ATLAS_PADDING;
+                y += row_height + ATLAS_PADDING;
+                row_height = 0;
+            }
+            offsets.push([x, y]);
+            x += extent[0] + ATLAS_PADDING;
+            row_height = row_height.max(extent[1]);
+        }
+        ([width, (y + row_height + ATLAS_PADDING).max(1)], offsets)
+    }
+
+    /// Uploads the atlas to the registry under `key`. Returns the atlas and the upload future,
+    /// which must complete before icons are drawn. If `key` is already registered, e.g. by another
+    /// window's tool, the existing texture is used without uploading, so the same key must always
+    /// be built with the same icons.
+    pub fn upload(
+        self,
+        key: &str,
+        registry: &mut TextureRegistry,
+        queue: Arc<Queue>,
+        allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: &StandardCommandBufferAllocator,
+    ) -> (IconAtlas, Option<Box<dyn GpuFuture>>) {
+        let (extent, offsets) = self.pack();
+        let mut pixels = vec![0u8; extent[0] as usize * extent[1] as usize * 4];
+        let mut regions = HashMap::default();
+        for ((name, icon_extent, coverage), offset) in self.icons.into_iter().zip(offsets) {
+            for (i, alpha) in coverage.into_iter().enumerate() {
+                let x = offset[0] + i as u32 % icon_extent[0];
+                let y = offset[1] + i as u32 / icon_extent[0];
+                let pixel = (y as usize * extent[0] as usize + x as usize) * 4;
+                pixels[pixel..pixel + 4].copy_from_slice(&[255, 255, 255, alpha]);
+            }
+            regions.insert(name, IconRegion {
+                uv_offset: [
+                    offset[0] as f32 / extent[0] as f32,
+                    offset[1] as f32 / extent[1] as f32,
+                ],
+                uv_size: [
+                    icon_extent[0] as f32 / extent[0] as f32,
+                    icon_extent[1] as f32 / extent[1] as f32,
+                ],
+                extent: icon_extent,
+            });
+        }
+        let (texture, upload) = registry.upload(
+            key,
+            queue,
+            allocator,
+            command_buffer_allocator,
+            extent,
+            Format::R8G8B8A8_UNORM,
+            &pixels,
+        );
+        let atlas = IconAtlas {
+            texture,
+            view: registry.get(texture).unwrap(),
+            regions,
+        };
+        (atlas, upload)
+    }
+}
+
+/// Icons packed into a texture of the [`TextureRegistry`], built with [`IconAtlasBuilder`] and
+/// drawn with an [`IconPass`].
+#[derive(Debug, Clone)]
+pub struct IconAtlas {
+    texture: ImageTextureId,
+    view: Arc<ImageView>,
+    regions: HashMap<String, IconRegion>,
+}
+
+impl IconAtlas {
+    /// Id of the atlas texture in the [`TextureRegistry`], e.g. to draw icons with egui.
+    pub fn texture_id(&self) -> ImageTextureId {
+        self.texture
+    }
+
+    pub fn view(&self) -> Arc<ImageView> {
+        self.view.clone()
+    }
+
+    /// Region of the icon added with `name`.
+    pub fn region(&self, name: &str) -> Option<IconRegion> {
+        self.regions.get(name).copied()
+    }
+
+    /// Region of a built-in icon, if the atlas was built with them.
+    pub fn icon(&self, icon: Icon) -> Option<IconRegion> {
+        self.region(icon.name())
+    }
+}
+
+#[derive(BufferContents, Debug, Clone, Copy)]
+#[repr(C)]
+struct IconInstance {
+    /// Position and size in pixels.
+    rect: [f32; 4],
+    /// Offset and size in the atlas.
+    uv: [f32; 4],
+    color: [f32; 4],
+}
+
+/// Draws tinted icons of an [`IconAtlas`] over a target, e.g. warning and error glyphs of debug
+/// overlays and tools that don't use egui. Queue icons with [`IconPass::draw`] and draw all of
+/// them in one draw call with [`IconPass::render`].
+///
+/// ```ignore
+/// icons.draw(atlas.icon(Icon::Warning).unwrap(), [8.0, 8.0], [16.0, 16.0], [1.0, 0.8, 0.0, 1.0]);
+/// let after = icons.render(before, &atlas, renderer.swapchain_image_view());
+/// ```
+pub struct IconPass {
+    queue: Arc<Queue>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    render_pass: Arc<RenderPass>,
+    framebuffers: FramebufferCache,
+    pipeline: Arc<GraphicsPipeline>,
+    sampler: Arc<Sampler>,
+    instances: Vec<IconInstance>,
+}
+
+impl IconPass {
+    /// Creates a pass drawing over targets of `output_format`, keeping their contents.
+    pub fn new(
+        queue: Arc<Queue>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        output_format: Format,
+    ) -> IconPass {
+        let device = queue.device().clone();
+        let render_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    format: output_format,
+                    samples: 1,
+                    load_op: Load,
+                    store_op: Store,
+                }
+            },
+            pass: {
+                    color: [color],
+                    depth_stencil: {}
+            }
+        )
+        .unwrap();
+        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+
+        let pipeline = {
+            let vs = icon_vs::load(device.clone())
+                .expect("failed to create shader module")
+                .entry_point("main")
+                .expect("shader entry point not found");
+            let fs = icon_fs::load(device.clone())
+                .expect("failed to create shader module")
+                .entry_point("main")
+                .expect("shader entry point not found");
+            let stages = [
+                PipelineShaderStageCreateInfo::new(vs),
+                PipelineShaderStageCreateInfo::new(fs),
+            ];
+            let layout = PipelineLayout::new(
+                device.clone(),
+                PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+                    .into_pipeline_layout_create_info(device.clone())
+                    .unwrap(),
+            )
+            .unwrap();
+
+            GraphicsPipeline::new(device.clone(), None, GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(VertexInputState::default()),
+                input_assembly_state: Some(InputAssemblyState::default()),
+                viewport_state: Some(ViewportState::default()),
+                rasterization_state: Some(RasterizationState::default()),
+                multisample_state: Some(MultisampleState::default()),
+                color_blend_state: Some(ColorBlendState::with_attachment_states(
+                    subpass.num_color_attachments(),
+                    ColorBlendAttachmentState {
+                        blend: Some(AttachmentBlend::alpha()),
+                        ..Default::default()
+                    },
+                )),
+                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                subpass: Some(subpass.into()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            })
+            .unwrap()
+        };
+
+        let sampler = Sampler::new(device.clone(), SamplerCreateInfo {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..Default::default()
+        })
+        .unwrap();
+
+        IconPass {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
+                device,
+                Default::default(),
+            ),
+            queue,
+            memory_allocator,
+            render_pass,
+            framebuffers: FramebufferCache::new(),
+            pipeline,
+            sampler,
+            instances: vec![],
+        }
+    }
+
+    /// Queues the icon of `region` at `position` with `size` in pixels of the target, multiplied
+    /// by `color`.
+    pub fn draw(
+        &mut self,
+        region: IconRegion,
+        position: [f32; 2],
+        size: [f32; 2],
+        color: [f32; 4],
+    ) {
+        self.instances.push(IconInstance {
+            rect: [position[0], position[1], size[0], size[1]],
+            uv: [
+                region.uv_offset[0],
+                region.uv_offset[1],
+                region.uv_size[0],
+                region.uv_size[1],
+            ],
+            color,
+        });
+    }
+
+    /// Number of icons queued for the next render.
+    pub fn queued(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Draws the queued icons of `atlas` over `target` and clears the queue.
+    pub fn render(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        atlas: &IconAtlas,
+        target: Arc<ImageView>,
+    ) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::icon_pass");
+        if self.instances.is_empty() {
+            return before_future;
+        }
+        let instance_count = self.instances.len() as u32;
+        let instances = Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            self.instances.drain(..),
+        )
+        .unwrap();
+        let extent = target.image().extent();
+        let framebuffer = self.framebuffers.get(&self.render_pass, &[target]);
+        let layout = self.pipeline.layout().clone();
+        let set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, atlas.view(), self.sampler.clone()),
+                WriteDescriptorSet::buffer(1, instances),
+            ],
+            [],
+        )
+        .unwrap();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![None],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassBeginInfo {
+                    contents: SubpassContents::Inline,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .set_viewport(
+                0,
+                [Viewport {
+                    offset: [0.0, 0.0],
+                    extent: [extent[0] as f32, extent[1] as f32],
+                    depth_range: 0.0..=1.0,
+                }]
+                .into_iter()
+                .collect(),
+            )
+            .unwrap()
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(PipelineBindPoint::Graphics, layout.clone(), 0, set)
+            .unwrap()
+            .push_constants(layout, 0, icon_vs::PushConstants {
+                target_extent: [extent[0] as f32, extent[1] as f32],
+            })
+            .unwrap()
+            .draw(6, instance_count, 0, 0)
+            .unwrap()
+            .end_render_pass(Default::default())
+            .unwrap();
+        CommandStats::record_pipeline_bind();
+        CommandStats::record_draws(1);
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit icon pass of {} icons to {:?} on queue family {}",
+            instance_count,
+            extent,
+            self.queue.queue_family_index()
+        );
+        before_future
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
+
+mod icon_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+struct IconInstance {
+    vec4 rect;
+    vec4 uv;
+    vec4 color;
+};
+
+layout(set = 0, binding = 1) readonly buffer Instances {
+    IconInstance instances[];
+};
+
+layout(push_constant) uniform PushConstants {
+    vec2 target_extent;
+} push_constants;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec4 v_color;
+
+const vec2 CORNERS[6] = vec2[](
+    vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.0),
+    vec2(0.0, 1.0), vec2(1.0, 0.0), vec2(1.0, 1.0)
+);
+
+void main() {
+    IconInstance instance = instances[gl_InstanceIndex];
+    vec2 corner = CORNERS[gl_VertexIndex];
+    vec2 position = instance.rect.xy + corner * instance.rect.zw;
+    v_uv = instance.uv.xy + corner * instance.uv.zw;
+    v_color = instance.color;
+    gl_Position = vec4(position / push_constants.target_extent * 2.0 - 1.0, 0.0, 1.0);
+}
+"
+    }
+}
+
+mod icon_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec4 v_color;
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D atlas;
+
+void main() {
+    f_color = texture(atlas, v_uv) * v_color;
+}
+"
+    }
+}
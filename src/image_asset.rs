@@ -0,0 +1,334 @@
+use std::sync::Arc;
+
+use bevy::{
+    asset::{io::Reader, Asset, AssetApp, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::{App, Plugin},
+    reflect::TypePath,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, BufferImageCopy,
+        CommandBufferUsage, CopyBufferToImageInfo,
+    },
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{
+        view::ImageView, Image, ImageAspects, ImageCreateInfo, ImageSubresourceLayers, ImageType,
+        ImageUsage,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    sync::{self, GpuFuture},
+};
+
+use crate::{
+    profiling::profile_scope, trace_frame::trace_vk, utils::supports_format, BevyVulkanoContext,
+    GpuMemoryBudget, QueueSharing,
+};
+
+/// A sampled device local texture loaded by the [`VulkanoImageAssetPlugin`]. The upload has
+/// finished when the asset is available.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct VulkanoTexture {
+    pub image_view: Arc<ImageView>,
+}
+
+impl VulkanoTexture {
+    /// Width and height of the texture.
+    #[inline]
+    pub fn extent(&self) -> [u32; 2] {
+        let extent = self.image_view.image().extent();
+        [extent[0], extent[1]]
+    }
+}
+
+/// Settings of loading a [`VulkanoTexture`], e.g. in a `.meta` file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VulkanoTextureSettings {
+    /// Load png and jpeg images as `R8G8B8A8_UNORM` instead of `R8G8B8A8_SRGB`, e.g. for normal
+    /// maps. ktx2 textures keep their format.
+    pub linear: bool,
+}
+
+/// Errors from loading a [`VulkanoTexture`].
+#[derive(Debug)]
+pub enum VulkanoTextureLoaderError {
+    Io(std::io::Error),
+    Image(image::ImageError),
+    /// The ktx2 file is invalid.
+    Ktx2(ktx2::ParseError),
+    /// The ktx2 texture is supercompressed, or isn't a single 2D image.
+    UnsupportedKtx2(String),
+    /// The device can't sample or upload to optimally tiled images of the format.
+    UnsupportedFormat(Format),
+    /// Creating or uploading the image failed.
+    Upload(String),
+}
+
+impl std::fmt::Display for VulkanoTextureLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VulkanoTextureLoaderError::Io(e) => write!(f, "failed to read texture: {e}"),
+            VulkanoTextureLoaderError::Image(e) => write!(f, "failed to decode texture: {e}"),
+            VulkanoTextureLoaderError::Ktx2(e) => write!(f, "failed to parse ktx2 texture: {e}"),
+            VulkanoTextureLoaderError::UnsupportedKtx2(reason) => {
+                write!(f, "unsupported ktx2 texture: {reason}")
+            }
+            VulkanoTextureLoaderError::UnsupportedFormat(format) => {
+                write!(f, "format {format:?} is not supported by the device")
+            }
+            VulkanoTextureLoaderError::Upload(reason) => {
+                write!(f, "failed to upload texture: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for VulkanoTextureLoaderError {}
+
+impl From<std::io::Error> for VulkanoTextureLoaderError {
+    fn from(e: std::io::Error) -> Self {
+        VulkanoTextureLoaderError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for VulkanoTextureLoaderError {
+    fn from(e: image::ImageError) -> Self {
+        VulkanoTextureLoaderError::Image(e)
+    }
+}
+
+impl From<ktx2::ParseError> for VulkanoTextureLoaderError {
+    fn from(e: ktx2::ParseError) -> Self {
+        VulkanoTextureLoaderError::Ktx2(e)
+    }
+}
+
+/// Decoded texture data: extent, format and the data of each mip level.
+struct DecodedTexture {
+    extent: [u32; 2],
+    format: Format,
+    levels: Vec<Vec<u8>>,
+}
+
+fn decode_image(
+    bytes: &[u8],
+    settings: &VulkanoTextureSettings,
+) -> Result<DecodedTexture, VulkanoTextureLoaderError> {
+    let rgba = image::load_from_memory(bytes)?.into_rgba8();
+    Ok(DecodedTexture {
+        extent: [rgba.width(), rgba.height()],
+        format: if settings.linear {
+            Format::R8G8B8A8_UNORM
+        } else {
+            Format::R8G8B8A8_SRGB
+        },
+        levels: vec![rgba.into_raw()],
+    })
+}
+
+fn decode_ktx2(bytes: &[u8]) -> Result<DecodedTexture, VulkanoTextureLoaderError> {
+    let unsupported = |reason: &str| VulkanoTextureLoaderError::UnsupportedKtx2(reason.to_string());
+    let reader = ktx2::Reader::new(bytes)?;
+    let header = reader.header();
+    if header.supercompression_scheme.is_some() {
+        return Err(unsupported("supercompression is not supported"));
+    }
+    if header.pixel_depth > 1 || header.layer_count > 1 || header.face_count > 1 {
+        return Err(unsupported("only single 2D images are supported"));
+    }
+    let format = header
+        .format
+        .map(|format| ash::vk::Format::from_raw(format.0.get() as i32))
+        .and_then(|format| Format::try_from(format).ok())
+        .ok_or_else(|| unsupported("the format is undefined or unknown"))?;
+    Ok(DecodedTexture {
+        extent: [header.pixel_width, header.pixel_height.max(1)],
+        format,
+        levels: reader.levels().map(|level| level.to_vec()).collect(),
+    })
+}
+
+/// Loads png, jpeg and ktx2 files as [`VulkanoTexture`]s, uploading them on the compute queue of
+/// the context like [`TextureUploads`](crate::TextureUploads).
+struct VulkanoTextureLoader {
+    graphics_queue: Arc<Queue>,
+    upload_queue: Arc<Queue>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+}
+
+impl VulkanoTextureLoader {
+    /// Uploads `texture` and waits for the upload to finish.
+    fn upload(&self, texture: DecodedTexture) -> Result<Arc<ImageView>, VulkanoTextureLoaderError> {
+        profile_scope!("bevy_vulkano::texture_asset_upload");
+        let DecodedTexture {
+            extent,
+            format,
+            levels,
+        } = texture;
+        let upload_error =
+            |e: &dyn std::fmt::Display| VulkanoTextureLoaderError::Upload(e.to_string());
+        if !supports_format(
+            self.memory_allocator.device().physical_device(),
+            format,
+            ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+        ) {
+            return Err(VulkanoTextureLoaderError::UnsupportedFormat(format));
+        }
+        let queues = [self.graphics_queue.clone(), self.upload_queue.clone()];
+        let image = Image::new(
+            self.memory_allocator.clone(),
+            QueueSharing::Concurrent.image_create_info(&queues, ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent: [extent[0], extent[1], 1],
+                mip_levels: levels.len().max(1) as u32,
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                ..Default::default()
+            }),
+            AllocationCreateInfo::default(),
+        )
+        .map_err(|e| upload_error(&e))?;
+        GpuMemoryBudget::track_image(&image);
+
+        let mut regions = vec![];
+        let mut buffer_offset = 0;
+        for (mip_level, level) in levels.iter().enumerate() {
+            regions.push(BufferImageCopy {
+                buffer_offset,
+                image_subresource: ImageSubresourceLayers {
+                    aspects: ImageAspects::COLOR,
+                    mip_level: mip_level as u32,
+                    array_layers: 0..1,
+                },
+                image_extent: [
+                    (extent[0] >> mip_level).max(1),
+                    (extent[1] >> mip_level).max(1),
+                    1,
+                ],
+                ..Default::default()
+            });
+            buffer_offset += level.len() as u64;
+        }
+        let staging = Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            levels.into_iter().flatten(),
+        )
+        .map_err(|e| upload_error(&e))?;
+        GpuMemoryBudget::track_buffer(staging.buffer());
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.upload_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .map_err(|e| upload_error(&e))?;
+        builder
+            .copy_buffer_to_image(CopyBufferToImageInfo {
+                regions: regions.into(),
+                ..CopyBufferToImageInfo::buffer_image(staging, image.clone())
+            })
+            .map_err(|e| upload_error(&e))?;
+        let command_buffer = builder.build().map_err(|e| upload_error(&e))?;
+        trace_vk!(
+            "submit texture asset upload of {:?} {:?} on queue family {}",
+            extent,
+            format,
+            self.upload_queue.queue_family_index()
+        );
+        sync::now(self.upload_queue.device().clone())
+            .then_execute(self.upload_queue.clone(), command_buffer)
+            .map_err(|e| upload_error(&e))?
+            .then_signal_fence_and_flush()
+            .map_err(|e| upload_error(&e))?
+            .wait(None)
+            .map_err(|e| upload_error(&e))?;
+        ImageView::new_default(image).map_err(|e| upload_error(&e))
+    }
+}
+
+impl AssetLoader for VulkanoTextureLoader {
+    type Asset = VulkanoTexture;
+    type Error = VulkanoTextureLoaderError;
+    type Settings = VulkanoTextureSettings;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        settings: &'a VulkanoTextureSettings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<VulkanoTexture, VulkanoTextureLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes).await?;
+            let is_ktx2 = load_context
+                .path()
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("ktx2"));
+            let texture = if is_ktx2 {
+                decode_ktx2(&bytes)?
+            } else {
+                decode_image(&bytes, settings)?
+            };
+            Ok(VulkanoTexture {
+                image_view: self.upload(texture)?,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["png", "jpg", "jpeg", "ktx2"]
+    }
+}
+
+/// Registers the [`VulkanoTexture`] asset with a loader for png, jpeg and ktx2 files, so textures
+/// can be loaded with `asset_server.load("foo.png")`. Requires feature `assets`. Add it after
+/// bevy's `AssetPlugin` and the [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin).
+///
+/// Textures are decoded and uploaded on bevy's IO task pool, blocking a task until the upload
+/// finished. ktx2 files keep their format (e.g. BC7) and mip levels, but must not be
+/// supercompressed. Loading settings are given with [`VulkanoTextureSettings`].
+///
+/// ```ignore
+/// let texture: Handle<VulkanoTexture> = asset_server.load("textures/grass.png");
+/// // Later, once loaded
+/// if let Some(texture) = textures.get(&texture) {
+///     let set = PersistentDescriptorSet::new(&allocator, layout, [
+///         WriteDescriptorSet::image_view_sampler(0, texture.image_view.clone(), sampler),
+///     ], [])?;
+/// }
+/// ```
+pub struct VulkanoImageAssetPlugin;
+
+impl Plugin for VulkanoImageAssetPlugin {
+    fn build(&self, app: &mut App) {
+        let context = &app
+            .world
+            .get_resource::<BevyVulkanoContext>()
+            .expect("VulkanoImageAssetPlugin requires VulkanoWinitPlugin to be added first")
+            .context;
+        let loader = VulkanoTextureLoader {
+            graphics_queue: context.graphics_queue().clone(),
+            upload_queue: context.compute_queue().clone(),
+            memory_allocator: context.memory_allocator().clone(),
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                context.device().clone(),
+                Default::default(),
+            ),
+        };
+        app.init_asset::<VulkanoTexture>()
+            .register_asset_loader(loader);
+    }
+}
@@ -0,0 +1,53 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use vulkano::image::view::ImageView;
+
+/// Previous frames of an additional image view, see
+/// [`VulkanoWindowRenderer::add_additional_image_view_with_history`](crate::VulkanoWindowRenderer::add_additional_image_view_with_history).
+pub(crate) struct ImageHistory {
+    /// Images of previous frames, most recent first.
+    previous: VecDeque<Arc<ImageView>>,
+    /// Number of previous frames kept.
+    frames: usize,
+}
+
+impl ImageHistory {
+    pub(crate) fn new(frames: usize) -> ImageHistory {
+        ImageHistory {
+            previous: VecDeque::with_capacity(frames + 1),
+            frames: frames.max(1),
+        }
+    }
+
+    /// Moves the image of the frame which was just presented to the history. Returns the image to
+    /// render the next frame into: the oldest history image once the history is full, otherwise
+    /// `None` and a new image must be created.
+    pub(crate) fn push(&mut self, presented: Arc<ImageView>) -> Option<Arc<ImageView>> {
+        self.previous.push_front(presented);
+        if self.previous.len() > self.frames {
+            self.previous.pop_back()
+        } else {
+            None
+        }
+    }
+
+    /// The image of `frames_ago` frames ago, starting from 1 for the previous frame.
+    pub(crate) fn get(&self, frames_ago: usize) -> Option<Arc<ImageView>> {
+        self.previous.get(frames_ago.checked_sub(1)?).cloned()
+    }
+
+    /// Images of previous frames, most recent first.
+    pub(crate) fn views(&self) -> impl Iterator<Item = &Arc<ImageView>> {
+        self.previous.iter()
+    }
+
+    /// Number of previous frames available.
+    pub(crate) fn len(&self) -> usize {
+        self.previous.len()
+    }
+
+    /// Drops the history, e.g. when the images are resized.
+    pub(crate) fn clear(&mut self) {
+        self.previous.clear();
+    }
+}
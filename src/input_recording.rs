@@ -0,0 +1,368 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    app::AppExit,
+    ecs::{event::ManualEventReader, system::SystemParam},
+    input::{
+        keyboard::KeyboardInput,
+        mouse::{MouseButtonInput, MouseMotion, MouseWheel},
+        touch::TouchInput,
+    },
+    log::{error, info},
+    prelude::*,
+    window::{CursorMoved, ReceivedCharacter},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{main_window::update_main_render_window, BevyVulkanoWindows};
+
+/// An input event sent by the runner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RecordedInput {
+    Keyboard(KeyboardInput),
+    Character(ReceivedCharacter),
+    MouseButton(MouseButtonInput),
+    MouseWheel(MouseWheel),
+    MouseMotion(MouseMotion),
+    CursorMoved(CursorMoved),
+    Touch(TouchInput),
+}
+
+/// Input events of consecutive frames, saved as RON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputRecording {
+    /// Frames with input, as the frame index of the main render window's renderer relative to the
+    /// start of the recording, and the frame's events in the order they were sent.
+    pub frames: Vec<(u64, Vec<RecordedInput>)>,
+    /// Number of recorded frames, including frames without input.
+    pub frame_count: u64,
+}
+
+impl InputRecording {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, ron)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<InputRecording> {
+        let ron = fs::read_to_string(path)?;
+        ron::from_str(&ron).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+enum RecorderState {
+    Idle,
+    Recording {
+        recording: InputRecording,
+        /// File the recording is saved to when stopped.
+        output: Option<PathBuf>,
+        /// Renderer frame index of the first recorded frame.
+        start_frame: Option<u64>,
+    },
+    Playing {
+        recording: InputRecording,
+        /// Renderer frame index of the first replayed frame.
+        start_frame: Option<u64>,
+        next: usize,
+    },
+}
+
+/// Records the input events sent by the runner and replays them into the same event streams, for
+/// deterministic demo playback and reproducing bugs.
+///
+/// Events are recorded per frame, by the [frame index](crate::VulkanoWindowRenderer::frame_index)
+/// of the main render window's renderer, and replayed at the same frame relative to the start of
+/// the playback. Input of updates which don't present a frame is merged into the next presented
+/// frame, and no input is recorded or replayed while there is no main render window. While
+/// playing, live input is discarded. Window entities are replayed as recorded, so
+/// replays expect the app to spawn its windows in the same order.
+///
+/// ```ignore
+/// recorder.start_recording(Some("demo.ron".into()));
+/// // Later, possibly in another run
+/// recorder.play(InputRecording::load("demo.ron")?);
+/// ```
+#[derive(Resource)]
+pub struct InputRecorder {
+    state: RecorderState,
+}
+
+impl Default for InputRecorder {
+    fn default() -> Self {
+        InputRecorder {
+            state: RecorderState::Idle,
+        }
+    }
+}
+
+impl InputRecorder {
+    /// Starts recording from the next frame, discarding an unfinished recording or playback. The
+    /// recording is saved to `output` when stopped.
+    pub fn start_recording(&mut self, output: Option<PathBuf>) {
+        self.state = RecorderState::Recording {
+            recording: InputRecording::default(),
+            output,
+            start_frame: None,
+        };
+    }
+
+    /// Stops recording, saving the recording if an output was given, and returns it.
+    pub fn stop_recording(&mut self) -> Option<InputRecording> {
+        let RecorderState::Recording {
+            recording,
+            output,
+            ..
+        } = std::mem::replace(&mut self.state, RecorderState::Idle)
+        else {
+            return None;
+        };
+        if let Some(output) = output {
+            match recording.save(&output) {
+                Ok(()) => info!(
+                    "Saved input recording of {} frames to {:?}",
+                    recording.frame_count, output
+                ),
+                Err(e) => error!("Failed to save input recording to {:?}: {e}", output),
+            }
+        }
+        Some(recording)
+    }
+
+    /// Replays `recording` from the next frame.
+    pub fn play(&mut self, recording: InputRecording) {
+        self.state = RecorderState::Playing {
+            recording,
+            start_frame: None,
+            next: 0,
+        };
+    }
+
+    /// Stops an ongoing playback.
+    pub fn stop_playing(&mut self) {
+        if self.is_playing() {
+            self.state = RecorderState::Idle;
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        matches!(self.state, RecorderState::Recording { .. })
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, RecorderState::Playing { .. })
+    }
+}
+
+/// Adds the [`InputRecorder`]. With `record` or `play` set, recording or playback starts with the
+/// app.
+#[derive(Default)]
+pub struct InputRecordingPlugin {
+    /// File to record input to, saved on exit.
+    pub record: Option<PathBuf>,
+    /// Recording to replay.
+    pub play: Option<PathBuf>,
+}
+
+impl Plugin for InputRecordingPlugin {
+    fn build(&self, app: &mut App) {
+        let mut recorder = InputRecorder::default();
+        if let Some(play) = &self.play {
+            match InputRecording::load(play) {
+                Ok(recording) => recorder.play(recording),
+                Err(e) => error!("Failed to load input recording {:?}: {e}", play),
+            }
+        } else if let Some(record) = &self.record {
+            recorder.start_recording(Some(record.clone()));
+        }
+        app.insert_resource(recorder)
+            .add_systems(First, record_or_play_input.after(update_main_render_window))
+            .add_systems(Last, save_recording_on_exit);
+    }
+}
+
+#[derive(SystemParam)]
+struct InputStreams<'w> {
+    keyboard: ResMut<'w, Events<KeyboardInput>>,
+    character: ResMut<'w, Events<ReceivedCharacter>>,
+    mouse_button: ResMut<'w, Events<MouseButtonInput>>,
+    mouse_wheel: ResMut<'w, Events<MouseWheel>>,
+    mouse_motion: ResMut<'w, Events<MouseMotion>>,
+    cursor_moved: ResMut<'w, Events<CursorMoved>>,
+    touch: ResMut<'w, Events<TouchInput>>,
+}
+
+impl InputStreams<'_> {
+    /// Input sent by the runner since the last frame.
+    fn live_input(&self, readers: &mut InputReaders) -> Vec<RecordedInput> {
+        let mut input = vec![];
+        input.extend(
+            readers
+                .keyboard
+                .read(&self.keyboard)
+                .cloned()
+                .map(RecordedInput::Keyboard),
+        );
+        input.extend(
+            readers
+                .character
+                .read(&self.character)
+                .cloned()
+                .map(RecordedInput::Character),
+        );
+        input.extend(
+            readers
+                .mouse_button
+                .read(&self.mouse_button)
+                .cloned()
+                .map(RecordedInput::MouseButton),
+        );
+        input.extend(
+            readers
+                .mouse_wheel
+                .read(&self.mouse_wheel)
+                .cloned()
+                .map(RecordedInput::MouseWheel),
+        );
+        input.extend(
+            readers
+                .mouse_motion
+                .read(&self.mouse_motion)
+                .cloned()
+                .map(RecordedInput::MouseMotion),
+        );
+        input.extend(
+            readers
+                .cursor_moved
+                .read(&self.cursor_moved)
+                .cloned()
+                .map(RecordedInput::CursorMoved),
+        );
+        input.extend(
+            readers
+                .touch
+                .read(&self.touch)
+                .cloned()
+                .map(RecordedInput::Touch),
+        );
+        input
+    }
+
+    /// Discards live input and sends `input` instead.
+    fn replace_input(&mut self, readers: &mut InputReaders, input: &[RecordedInput]) {
+        // Mark live input as read so that it isn't recorded after the playback
+        self.live_input(readers);
+        self.keyboard.clear();
+        self.character.clear();
+        self.mouse_button.clear();
+        self.mouse_wheel.clear();
+        self.mouse_motion.clear();
+        self.cursor_moved.clear();
+        self.touch.clear();
+        for event in input.iter().cloned() {
+            match event {
+                RecordedInput::Keyboard(event) => self.keyboard.send(event),
+                RecordedInput::Character(event) => self.character.send(event),
+                RecordedInput::MouseButton(event) => self.mouse_button.send(event),
+                RecordedInput::MouseWheel(event) => self.mouse_wheel.send(event),
+                RecordedInput::MouseMotion(event) => self.mouse_motion.send(event),
+                RecordedInput::CursorMoved(event) => self.cursor_moved.send(event),
+                RecordedInput::Touch(event) => self.touch.send(event),
+            }
+        }
+        // The replayed events have been handled by the time they'd be read
+        self.live_input(readers);
+    }
+}
+
+#[derive(Default)]
+struct InputReaders {
+    keyboard: ManualEventReader<KeyboardInput>,
+    character: ManualEventReader<ReceivedCharacter>,
+    mouse_button: ManualEventReader<MouseButtonInput>,
+    mouse_wheel: ManualEventReader<MouseWheel>,
+    mouse_motion: ManualEventReader<MouseMotion>,
+    cursor_moved: ManualEventReader<CursorMoved>,
+    touch: ManualEventReader<TouchInput>,
+}
+
+/// Records this frame's input or replaces it with recorded input. Runs in `First`, after the
+/// runner has sent the frame's events and before bevy's input systems read them.
+fn record_or_play_input(
+    mut recorder: ResMut<InputRecorder>,
+    mut streams: InputStreams,
+    mut readers: Local<InputReaders>,
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+) {
+    let frame_index = vulkano_windows
+        .main_window()
+        .map(|window| window.renderer.frame_index());
+    let mut finished = false;
+    match &mut recorder.state {
+        RecorderState::Idle => {
+            streams.live_input(&mut readers);
+        }
+        RecorderState::Recording {
+            recording,
+            start_frame,
+            ..
+        } => {
+            let input = streams.live_input(&mut readers);
+            let Some(frame_index) = frame_index else {
+                return;
+            };
+            // Saturating, as the frame index restarts when the window's renderer is recreated
+            let frame = frame_index.saturating_sub(*start_frame.get_or_insert(frame_index));
+            if !input.is_empty() {
+                match recording.frames.last_mut() {
+                    Some((last_frame, last_input)) if *last_frame == frame => {
+                        last_input.extend(input)
+                    }
+                    _ => recording.frames.push((frame, input)),
+                }
+            }
+            recording.frame_count = recording.frame_count.max(frame + 1);
+        }
+        RecorderState::Playing {
+            recording,
+            start_frame,
+            next,
+        } => {
+            let Some(frame_index) = frame_index else {
+                streams.replace_input(&mut readers, &[]);
+                return;
+            };
+            let frame = frame_index.saturating_sub(*start_frame.get_or_insert(frame_index));
+            let input = match recording.frames.get(*next) {
+                Some((input_frame, input)) if *input_frame <= frame => {
+                    *next += 1;
+                    input.as_slice()
+                }
+                _ => &[],
+            };
+            streams.replace_input(&mut readers, input);
+            if *next >= recording.frames.len() && frame + 1 >= recording.frame_count {
+                info!(
+                    "Finished input playback of {} frames",
+                    recording.frame_count
+                );
+                finished = true;
+            }
+        }
+    }
+    if finished {
+        recorder.state = RecorderState::Idle;
+    }
+}
+
+fn save_recording_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    mut recorder: ResMut<InputRecorder>,
+) {
+    if exit_events.read().next().is_some() {
+        recorder.stop_recording();
+    }
+}
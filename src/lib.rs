@@ -7,15 +7,116 @@
     clippy::match_like_matches_macro
 )]
 
+#[cfg(feature = "bench")]
+mod bench;
+#[cfg(feature = "capture")]
+mod capture;
+#[cfg(feature = "capture")]
+mod capture_encoder;
+mod command_stats;
+mod compat;
+mod compute_pass;
 mod config;
-mod converters;
+mod damage;
+mod depth_prepass;
+mod device_info;
+mod device_reinit;
+mod device_selection;
+mod dynamic_viewport;
+#[cfg(feature = "file_dialogs")]
+mod file_dialog;
+#[cfg(feature = "windowing")]
+mod focused_window;
+#[cfg(feature = "capture")]
+mod frame_dump;
+mod frame_graph;
+mod frame_hooks;
+mod frame_pacing;
+#[cfg(feature = "capture")]
+mod frame_recorder;
+#[cfg(feature = "gui")]
+mod frame_time_graph;
+mod framebuffer_cache;
+mod gpu_driven;
+#[cfg(feature = "gui")]
+mod gui_clear;
+#[cfg(feature = "gui")]
+mod gui_input;
+#[cfg(feature = "gui")]
+mod gui_scale;
+mod hdr;
+mod headless;
+#[cfg(feature = "texture_utils")]
+mod icon_font;
+#[cfg(feature = "assets")]
+mod image_asset;
+mod image_history;
+#[cfg(feature = "input_recording")]
+mod input_recording;
+mod main_thread;
+mod main_window;
+mod memory_budget;
+mod multiview;
+mod ping_pong;
+mod pipeline_compiler;
+mod plugin_builder;
+#[cfg(feature = "post_process")]
+mod post_process;
+pub mod prelude;
+#[cfg(feature = "windowing")]
+mod present_mode_bench;
+mod profiling;
+mod push_constants;
+mod queue_ownership;
+mod readback_ring;
+#[cfg(feature = "windowing")]
+mod render_on_demand;
+#[cfg(feature = "gui")]
+mod render_target_inspector;
+mod render_targets;
+mod renderer;
+#[cfg(feature = "runtime_shaders")]
+mod runtime_shader;
+#[cfg(feature = "capture")]
+mod screenshot;
+mod shader_preprocessor;
+#[cfg(feature = "windowing")]
+mod shared_image;
+#[cfg(feature = "texture_utils")]
+mod sprite_animation;
+mod startup_report;
+mod state_render;
+#[cfg(feature = "texture_utils")]
+mod streaming_image;
+mod swapchain_compute;
 mod system;
+mod texture_filtering;
+mod texture_registry;
+#[cfg(feature = "texture_utils")]
+mod texture_upload;
+#[cfg(feature = "texture_utils")]
+mod texture_utils;
+#[cfg(feature = "capture")]
+mod thumbnail;
+mod trace_frame;
+mod tracked_image;
+pub mod utils;
+mod validation;
 mod vulkano_windows;
-
+mod watchdog;
+#[cfg(feature = "texture_utils")]
+mod waveform_texture;
+#[cfg(feature = "wgsl")]
+mod wgsl;
+#[cfg(feature = "windowing")]
+mod windowing;
+
+#[cfg(feature = "bench")]
+pub use bench::*;
 use bevy::{
     app::{App, AppExit, Plugin},
     ecs::{
-        event::{Events, ManualEventReader},
+        event::Events,
         system::{SystemParam, SystemState},
     },
     input::{
@@ -27,16 +128,131 @@ use bevy::{
     prelude::*,
     utils::Instant,
     window::{
-        exit_on_all_closed, CursorEntered, CursorLeft, CursorMoved, FileDragAndDrop,
+        exit_on_all_closed, CursorEntered, CursorLeft, CursorMoved, FileDragAndDrop, PrimaryWindow,
         ReceivedCharacter, RequestRedraw, WindowBackendScaleFactorChanged, WindowCloseRequested,
         WindowCreated, WindowFocused, WindowMoved, WindowResized, WindowScaleFactorChanged,
     },
 };
+#[cfg(feature = "capture")]
+pub use capture::*;
+#[cfg(feature = "capture")]
+pub use capture_encoder::{
+    CaptureEncoded, CaptureEncoder, CaptureFormat, CaptureId, CaptureQueueFull, CaptureWindow,
+    VulkanoCapturePlugin,
+};
+pub use command_stats::{CommandStats, FrameCommandStats};
+#[cfg(feature = "gui")]
+use compat::Update;
+pub use compat::SUPPORTED_BEVY_VERSION;
+use compat::{changed_window, CachedWindow, First, Has, Last, ManualEventReader, PreUpdate};
+pub use compute_pass::*;
 pub use config::*;
+pub use damage::{supports_incremental_present, DamageRect};
+pub use depth_prepass::{DepthPrepass, SharedDepth};
+pub use device_info::*;
+pub use device_reinit::{
+    DeviceRebuildCallbacks, DeviceReinitializeFailed, DeviceRestored, DeviceSelection,
+    ReinitializeDevice,
+};
+pub use device_selection::{
+    DeviceSelectionError, PhysicalDeviceReport, VulkanoStartupFailed, VulkanoStartupFailure,
+};
+pub use dynamic_viewport::*;
 #[cfg(feature = "gui")]
 pub use egui_winit_vulkano;
-use vulkano_util::context::{VulkanoConfig, VulkanoContext};
+#[cfg(feature = "file_dialogs")]
+pub use file_dialog::*;
+#[cfg(feature = "windowing")]
+pub use focused_window::{FocusedWindow, FocusedWindowInput};
+#[cfg(feature = "capture")]
+pub use frame_dump::DumpFrame;
+pub use frame_graph::*;
+pub use frame_hooks::*;
+pub use frame_pacing::{
+    FramePacer, FramePacing, FramePacingContext, FrameRateLimit, MailboxLatest, RefreshDivisor,
+};
+#[cfg(feature = "capture")]
+pub use frame_recorder::{FrameRecorder, RecordedFrame, RecordingOutput};
+#[cfg(feature = "gui")]
+pub use frame_time_graph::{FrameTimeGraph, FrameTimeSample};
+pub use framebuffer_cache::FramebufferCache;
+pub use gpu_driven::*;
+#[cfg(feature = "gui")]
+pub use gui_clear::GuiClearColor;
+#[cfg(feature = "gui")]
+pub use gui_input::{GuiInputCapture, GuiInputFilter};
+#[cfg(feature = "gui")]
+pub use gui_scale::UiScale;
+pub use hdr::*;
+pub use headless::{BevyVulkanoHeadlessRenderer, HeadlessConfig};
+#[cfg(feature = "texture_utils")]
+pub use icon_font::{Icon, IconAtlas, IconAtlasBuilder, IconPass, IconRegion};
+#[cfg(feature = "assets")]
+pub use image_asset::{
+    VulkanoImageAssetPlugin, VulkanoTexture, VulkanoTextureLoaderError, VulkanoTextureSettings,
+};
+#[cfg(feature = "input_recording")]
+pub use input_recording::*;
+pub use main_thread::{MainThreadTask, MainThreadTasks};
+pub use main_window::{exit_on_main_render_window_closed, MainRenderWindow};
+pub use memory_budget::{
+    memory_budget_extensions, GpuMemoryBudget, HeapBudget, MemoryBudgetWarning,
+};
+pub use multiview::*;
+pub use ping_pong::*;
+pub use pipeline_compiler::*;
+use plugin_builder::PluginConfig;
+pub use plugin_builder::VulkanoWinitPluginBuilder;
+#[cfg(feature = "post_process")]
+pub use post_process::*;
+#[cfg(feature = "windowing")]
+pub use present_mode_bench::{
+    PresentModeBenchFinished, PresentModeBenchResults, PresentModeBenchmark, PresentModeStats,
+};
+pub use profiling::{GpuProfiler, GpuZone};
+pub use push_constants::*;
+pub use queue_ownership::*;
+pub use readback_ring::*;
+#[cfg(feature = "windowing")]
+pub use render_on_demand::{RenderDirty, RenderOnDemand};
+#[cfg(feature = "gui")]
+pub use render_target_inspector::RenderTargetInspector;
+pub use render_targets::*;
+pub use renderer::*;
+#[cfg(feature = "runtime_shaders")]
+pub use runtime_shader::*;
+#[cfg(feature = "capture")]
+pub use screenshot::{ScreenshotPlugin, ScreenshotRequested, ScreenshotSaved};
+pub use shader_preprocessor::*;
+#[cfg(feature = "windowing")]
+pub use shared_image::*;
+#[cfg(feature = "texture_utils")]
+pub use sprite_animation::SpriteAnimation;
+pub use startup_report::{QueueFamilyReport, StartupReport, WindowSurfaceReport};
+pub use state_render::*;
+#[cfg(feature = "texture_utils")]
+pub use streaming_image::*;
+pub use swapchain_compute::*;
+pub use texture_filtering::*;
+pub use texture_registry::*;
+#[cfg(feature = "texture_utils")]
+pub use texture_upload::{TextureUploadProgress, TextureUploads};
+#[cfg(feature = "texture_utils")]
+pub use texture_utils::VulkanoTextureUtilsPlugin;
+#[cfg(feature = "capture")]
+pub use thumbnail::*;
+pub use trace_frame::TraceFrame;
+pub use tracked_image::*;
+pub use validation::{ValidationErrors, ValidationMessage, ValidationMode, INSTANCE_LAYERS_ENV};
+use vulkano_util::context::VulkanoContext;
 pub use vulkano_windows::*;
+pub use watchdog::*;
+#[cfg(feature = "texture_utils")]
+pub use waveform_texture::WaveformTexture;
+#[cfg(feature = "wgsl")]
+pub use wgsl::*;
+#[cfg(feature = "windowing")]
+pub use windowing::VulkanoWindowingPlugin;
 
 /// Wrapper around [`VulkanoContext`] to allow using them as resources
 #[derive(Resource)]
@@ -51,7 +267,28 @@ use winit::{
     event_loop::{ControlFlow, EventLoop, EventLoopBuilder, EventLoopWindowTarget},
 };
 
-use crate::system::{changed_window, create_window, despawn_window, CachedWindow};
+use crate::{
+    command_stats::update_frame_command_stats,
+    depth_prepass::update_shared_depth,
+    device_reinit::{copy_vulkano_config, reinitialize_device, DriverWorkaroundsOverride},
+    device_selection::{check_device_selection, resolve_device_features, startup_failure_runner},
+    frame_pacing::next_paced_update,
+    hdr::enable_hdr_color_spaces,
+    main_thread::run_main_thread_tasks,
+    main_window::update_main_render_window,
+    memory_budget::{enable_memory_budget, update_memory_budget},
+    pipeline_compiler::pipeline_compiled_events,
+    profiling::{frame_mark, start_profiling},
+    startup_report::report_window_surfaces,
+    system::{
+        create_pending_renderers, create_window, cursor_grab_failed_events, despawn_window,
+        frame_presented_events, present_mode_fallback_events, present_window_group,
+        update_render_extents,
+    },
+    trace_frame::begin_trace_frame,
+    validation::{check_validation_errors, enable_instance_layers, enable_validation},
+    watchdog::frame_watchdog,
+};
 
 #[cfg(target_os = "android")]
 pub static ANDROID_APP: once_cell::sync::OnceCell<AndroidApp> = once_cell::sync::OnceCell::new();
@@ -59,113 +296,282 @@ pub static ANDROID_APP: once_cell::sync::OnceCell<AndroidApp> = once_cell::sync:
 /// A [`Plugin`] that utilizes [`winit`] for window creation and event loop management.
 /// In addition, windows include custom render functionality with Vulkano.
 /// This is intended to replace `bevy_winit`.
+///
+/// Add it with the settings of an inserted [`BevyVulkanoSettings`] non-send resource, inserted
+/// before adding the plugin, or add the plugin returned by [`VulkanoWinitPlugin::builder`] instead.
 #[derive(Default)]
 pub struct VulkanoWinitPlugin;
 
+impl VulkanoWinitPlugin {
+    /// A configurable plugin to add instead of [`VulkanoWinitPlugin`].
+    pub fn builder() -> VulkanoWinitPluginBuilder {
+        VulkanoWinitPluginBuilder::default()
+    }
+}
+
 impl Plugin for VulkanoWinitPlugin {
     fn build(&self, app: &mut App) {
-        let mut event_loop_builder = EventLoopBuilder::<()>::with_user_event();
-
-        #[cfg(target_os = "android")]
-        {
-            use winit::platform::android::EventLoopBuilderExtAndroid;
-            event_loop_builder.with_android_app(
-                ANDROID_APP
-                    .get()
-                    .expect("Bevy must be setup with the #[bevy_main] macro on Android")
-                    .clone(),
-            );
-        }
-
-        let event_loop = event_loop_builder.build();
-        app.insert_non_send_resource(event_loop);
+        build_vulkano_winit(app, &PluginConfig::default());
+    }
+}
 
-        // Retrieve config, or use default.
-        let config = if app
-            .world
-            .get_non_send_resource::<BevyVulkanoSettings>()
-            .is_none()
-        {
-            BevyVulkanoSettings::default()
-        } else {
-            app.world
-                .remove_non_send_resource::<BevyVulkanoSettings>()
-                .unwrap()
-        };
-
-        // Create vulkano context using the vulkano config from settings
-        let BevyVulkanoSettings {
-            vulkano_config, ..
-        } = config;
-        let vulkano_context = BevyVulkanoContext {
-            context: VulkanoContext::new(vulkano_config),
-        };
-        // Place config back as resource..
-        let new_config = BevyVulkanoSettings {
-            vulkano_config: VulkanoConfig::default(),
-            ..config
-        };
-
-        app.init_non_send_resource::<BevyVulkanoWindows>()
-            .insert_resource(vulkano_context)
-            .insert_non_send_resource(new_config)
-            .set_runner(winit_runner)
-            // exit_on_all_closed only uses the query to determine if the query is empty,
-            // and so doesn't care about ordering relative to changed_window
-            .add_systems(
-                Last,
-                (
-                    changed_window.ambiguous_with(exit_on_all_closed),
-                    // Update the state of the window before attempting to despawn to ensure consistent event ordering
-                    despawn_window.after(changed_window),
-                ),
-            );
+/// Builds [`VulkanoWinitPlugin`] with the configuration of a [`VulkanoWinitPluginBuilder`].
+pub(crate) fn build_vulkano_winit(app: &mut App, plugin_config: &PluginConfig) {
+    let mut event_loop_builder = EventLoopBuilder::<()>::with_user_event();
+
+    #[cfg(target_os = "android")]
+    {
+        use winit::platform::android::EventLoopBuilderExtAndroid;
+        event_loop_builder.with_android_app(
+            ANDROID_APP
+                .get()
+                .expect("Bevy must be setup with the #[bevy_main] macro on Android")
+                .clone(),
+        );
+    }
 
-        #[cfg(feature = "gui")]
-        {
-            app.add_systems(PreUpdate, begin_egui_frame_system);
+    let event_loop = event_loop_builder.build();
+    app.insert_non_send_resource(event_loop);
+
+    // Retrieve config, or use default.
+    let inserted_config = app.world.remove_non_send_resource::<BevyVulkanoSettings>();
+    let mut config = match (&plugin_config.settings, inserted_config) {
+        (Some(settings), inserted) => {
+            if inserted.is_some() {
+                warn!(
+                    "Ignoring the inserted BevyVulkanoSettings in favor of the settings given to \
+                     VulkanoWinitPlugin::builder"
+                );
+            }
+            settings()
         }
+        (None, Some(inserted)) => inserted,
+        (None, None) => BevyVulkanoSettings::default(),
+    };
+    plugin_config.apply(&mut config);
 
-        let mut create_window_system_state: SystemState<(
-            Commands,
-            NonSendMut<EventLoop<()>>,
-            Query<(Entity, &mut Window)>,
-            EventWriter<WindowCreated>,
-            NonSendMut<BevyVulkanoWindows>,
-            Res<BevyVulkanoContext>,
-            NonSend<BevyVulkanoSettings>,
-        )> = SystemState::from_world(&mut app.world);
-
-        // And for ios and macos, we should not create window early, all ui related code should be executed inside
-        // UIApplicationMain/NSApplicationMain.
-        #[cfg(not(any(target_os = "android", target_os = "ios", target_os = "macos")))]
-        {
-            let (
-                commands,
-                event_loop,
-                mut new_windows,
-                event_writer,
-                vulkano_windows,
-                context,
-                settings,
-            ) = create_window_system_state.get_mut(&mut app.world);
+    config.apply_required_device_features(plugin_config.device_features);
+    if config.hdr {
+        enable_hdr_color_spaces(&mut config.vulkano_config);
+    }
+    if config.memory_budget {
+        enable_memory_budget(&mut config.vulkano_config);
+    }
+    start_profiling();
+
+    enable_instance_layers(&mut config.vulkano_config, &config.extra_instance_layers);
+    let validation_errors = config
+        .validation
+        .and_then(|mode| enable_validation(&mut config.vulkano_config, mode));
+
+    // Create vulkano context using the vulkano config from settings
+    let optional_features = config.optional_device_features();
+    let BevyVulkanoSettings {
+        mut vulkano_config,
+        ..
+    } = config;
+    if let Err(error) = check_device_selection(&vulkano_config) {
+        error!("Failed to start bevy_vulkano: {error}");
+        app.add_event::<VulkanoStartupFailed>()
+            .init_schedule(VulkanoStartupFailure)
+            .set_runner(startup_failure_runner);
+        app.world.send_event(VulkanoStartupFailed {
+            error,
+        });
+        return;
+    }
+    // Kept in the settings to create the device again on ReinitializeDevice, where optional
+    // features are enabled for the new device
+    let reinitialize_config = copy_vulkano_config(&vulkano_config);
+    resolve_device_features(&mut vulkano_config, optional_features);
+    let vulkano_context = BevyVulkanoContext {
+        context: VulkanoContext::new(vulkano_config),
+    };
+    let physical_device = vulkano_context.context.device().physical_device();
+    let gpu_limits = GpuLimits::from_physical_device(physical_device);
+    let gpu_info = GpuInfo::from_physical_device(physical_device);
+    let driver_workarounds_override = config.driver_workarounds;
+    let driver_workarounds =
+        driver_workarounds_override.unwrap_or_else(|| DriverWorkarounds::detect(&gpu_info));
+    if driver_workarounds != DriverWorkarounds::default() {
+        info!(
+            "Using driver workarounds for {} (driver {}): {:?}",
+            gpu_info.device_name,
+            gpu_info.driver_version_string(),
+            driver_workarounds
+        );
+    }
+    let startup_report = StartupReport::new(&vulkano_context);
+    info!("{startup_report}");
+    if let Some(headless) = config.headless {
+        app.insert_non_send_resource(BevyVulkanoHeadlessRenderer::new(
+            &vulkano_context.context,
+            headless,
+            driver_workarounds,
+        ));
+    }
+    // Place config back as resource..
+    let new_config = BevyVulkanoSettings {
+        vulkano_config: reinitialize_config,
+        driver_workarounds: Some(driver_workarounds),
+        ..config
+    };
 
-            // Here we need to create a winit-window and give it a WindowHandle which the renderer can use.
-            // It needs to be spawned before the start of the startup schedule, so we cannot use a regular system.
-            // Instead we need to create the window and spawn it using direct world access
-            create_window(
-                commands,
-                &event_loop,
-                new_windows.iter_mut(),
-                event_writer,
-                vulkano_windows,
-                context,
-                settings,
-            );
+    app.init_non_send_resource::<BevyVulkanoWindows>()
+        .insert_resource(vulkano_context)
+        .insert_resource(gpu_limits)
+        .insert_resource(gpu_info)
+        .insert_resource(driver_workarounds)
+        .insert_resource(startup_report)
+        .insert_resource(config.texture_filtering)
+        .insert_resource(DriverWorkaroundsOverride(driver_workarounds_override))
+        .init_resource::<DeviceRebuildCallbacks>()
+        .init_resource::<PipelineCompiler>()
+        .init_resource::<RenderTargetRegistry>()
+        .init_resource::<TextureRegistry>()
+        .init_resource::<TraceFrame>()
+        .init_resource::<MainThreadTasks>()
+        .init_resource::<GpuMemoryBudget>()
+        .init_resource::<FrameCommandStats>()
+        .insert_non_send_resource(new_config)
+        .add_event::<PresentModeFallback>()
+        .add_event::<PipelineCompiled>()
+        .add_event::<AppFocusChanged>()
+        .add_event::<FrameHang>()
+        .add_event::<WindowRendererReady>()
+        .add_event::<FramePresented>()
+        .add_event::<CursorGrabFailed>()
+        .add_event::<WindowResolutionClamped>()
+        .add_event::<ReinitializeDevice>()
+        .add_event::<DeviceRestored>()
+        .add_event::<DeviceReinitializeFailed>()
+        .add_event::<MemoryBudgetWarning>()
+        .set_runner(winit_runner)
+        // exit_on_all_closed only uses the query to determine if the query is empty,
+        // and so doesn't care about ordering relative to changed_window
+        .add_systems(
+            Last,
+            (
+                changed_window.ambiguous_with(exit_on_all_closed),
+                // Update the state of the window before attempting to despawn to ensure consistent event ordering
+                despawn_window.after(changed_window),
+                present_mode_fallback_events.after(changed_window),
+                present_window_group,
+                frame_presented_events.after(present_window_group),
+                update_memory_budget.after(present_window_group),
+                update_frame_command_stats.after(present_window_group),
+                cursor_grab_failed_events,
+                frame_watchdog.run_if(resource_exists::<FrameWatchdog>()),
+            ),
+        );
+
+    #[cfg(feature = "capture")]
+    app.add_plugins(VulkanoCapturePlugin);
+    #[cfg(feature = "post_process")]
+    app.add_plugins(VulkanoPostProcessPlugin);
+    #[cfg(feature = "texture_utils")]
+    app.add_plugins(VulkanoTextureUtilsPlugin);
+    #[cfg(feature = "windowing")]
+    app.add_plugins(VulkanoWindowingPlugin);
+    #[cfg(feature = "file_dialogs")]
+    app.add_plugins(FileDialogPlugin);
+
+    if let Some(validation_errors) = validation_errors {
+        app.insert_resource(validation_errors)
+            .add_systems(Last, check_validation_errors);
+    }
+
+    app.add_systems(First, (begin_trace_frame, update_main_render_window));
+
+    app.add_systems(
+        PreUpdate,
+        (
+            pipeline_compiled_events,
+            update_render_extents,
+            report_window_surfaces,
+            update_shared_depth.after(update_render_extents),
+        ),
+    );
+
+    #[cfg(feature = "gui")]
+    {
+        app.init_resource::<RenderTargetInspector>()
+            .add_systems(Update, render_target_inspector::render_target_inspector);
+        app.add_systems(
+            Last,
+            frame_time_graph::update_frame_time_graphs.after(frame_presented_events),
+        );
+        app.add_systems(
+            PreUpdate,
+            (
+                begin_egui_frame_system.after(gui_scale::apply_ui_scale),
+                gui_scale::apply_ui_scale,
+                gui_input::update_gui_input_capture,
+                gui_clear::paint_gui_clear_color.after(begin_egui_frame_system),
+            ),
+        );
+    }
+
+    if let Some(present_mode) = plugin_config.present_mode {
+        let mut primary_windows = app
+            .world
+            .query_filtered::<&mut Window, With<PrimaryWindow>>();
+        match primary_windows.get_single_mut(&mut app.world) {
+            Ok(mut window) => window.present_mode = present_mode,
+            Err(_) => warn!(
+                "No primary window to apply the present mode to, add WindowPlugin before \
+                 VulkanoWinitPlugin"
+            ),
         }
+    }
+
+    let mut create_window_system_state: SystemState<(
+        Commands,
+        NonSendMut<EventLoop<()>>,
+        Query<(
+            Entity,
+            &mut Window,
+            Option<&VulkanoWindowConfig>,
+            Has<NoGui>,
+        )>,
+        EventWriter<WindowCreated>,
+        EventWriter<WindowRendererReady>,
+        NonSendMut<BevyVulkanoWindows>,
+        Res<BevyVulkanoContext>,
+        NonSend<BevyVulkanoSettings>,
+    )> = SystemState::from_world(&mut app.world);
 
-        create_window_system_state.apply(&mut app.world);
+    // And for ios and macos, we should not create window early, all ui related code should be executed inside
+    // UIApplicationMain/NSApplicationMain.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_os = "macos")))]
+    {
+        let (
+            commands,
+            event_loop,
+            mut new_windows,
+            event_writer,
+            ready_writer,
+            vulkano_windows,
+            context,
+            settings,
+        ) = create_window_system_state.get_mut(&mut app.world);
+
+        // Here we need to create a winit-window and give it a WindowHandle which the renderer can use.
+        // It needs to be spawned before the start of the startup schedule, so we cannot use a regular system.
+        // Instead we need to create the window and spawn it using direct world access
+        create_window(
+            commands,
+            &event_loop,
+            new_windows.iter_mut(),
+            event_writer,
+            ready_writer,
+            vulkano_windows,
+            context,
+            settings,
+        );
     }
+
+    create_window_system_state.apply(&mut app.world);
 }
 
 fn run<F>(event_loop: EventLoop<()>, event_handler: F) -> !
@@ -247,6 +653,8 @@ struct WinitPersistentState {
     /// Tracks if the event loop was started this frame because of a `WaitUntil` timeout.
     timeout_reached: bool,
     last_update: Instant,
+    /// Whether any window was focused during the last update, `None` before the first update.
+    app_focused: Option<bool>,
 }
 
 impl Default for WinitPersistentState {
@@ -257,6 +665,7 @@ impl Default for WinitPersistentState {
             redraw_request_sent: false,
             timeout_reached: false,
             last_update: Instant::now(),
+            app_focused: None,
         }
     }
 }
@@ -278,6 +687,12 @@ pub fn winit_runner(mut app: App) {
         .non_send_resource::<BevyVulkanoSettings>()
         .return_from_run;
 
+    #[cfg(feature = "gui")]
+    let gui_input_filter = app
+        .world
+        .non_send_resource::<BevyVulkanoSettings>()
+        .gui_input_filter;
+
     trace!("Entering winit event loop");
 
     let mut focused_window_state: SystemState<(NonSend<BevyVulkanoSettings>, Query<&Window>)> =
@@ -285,8 +700,26 @@ pub fn winit_runner(mut app: App) {
 
     let mut create_window_system_state: SystemState<(
         Commands,
-        Query<(Entity, &mut Window), Added<Window>>,
+        Query<
+            (
+                Entity,
+                &mut Window,
+                Option<&VulkanoWindowConfig>,
+                Has<NoGui>,
+            ),
+            Added<Window>,
+        >,
         EventWriter<WindowCreated>,
+        EventWriter<WindowRendererReady>,
+        NonSendMut<BevyVulkanoWindows>,
+        Res<BevyVulkanoContext>,
+        NonSend<BevyVulkanoSettings>,
+    )> = SystemState::from_world(&mut app.world);
+
+    let mut pending_renderers_system_state: SystemState<(
+        Commands,
+        Query<&mut Window>,
+        EventWriter<WindowRendererReady>,
         NonSendMut<BevyVulkanoWindows>,
         Res<BevyVulkanoContext>,
         NonSend<BevyVulkanoSettings>,
@@ -377,15 +810,25 @@ pub fn winit_runner(mut app: App) {
                         return;
                     };
 
+                // On demand windows redraw after events, including those consumed by egui
+                if let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(window_entity)
+                {
+                    vulkano_window.renderer.request_redraw();
+                }
+
                 // Skip event if egui wants it
                 #[cfg(feature = "gui")]
                 {
                     if let Some(vulkano_window) =
                         vulkano_windows.get_vulkano_window_mut(window_entity)
                     {
-                        // Update egui with the window event. If false, we should skip the event in bevy
-                        if vulkano_window.gui.update(&event) {
-                            return;
+                        // Update egui with the window event. If it was consumed, the filter decides
+                        // whether to skip the event in bevy
+                        if let Some(gui) = &mut vulkano_window.gui {
+                            let consumed = gui.update(&event);
+                            if gui_input_filter.skips(&event, consumed, &gui.context()) {
+                                return;
+                            }
                         }
                     }
                 }
@@ -416,7 +859,7 @@ pub fn winit_runner(mut app: App) {
                     } => {
                         input_events
                             .keyboard_input
-                            .send(converters::convert_keyboard_input(input, window_entity));
+                            .send(compat::convert_keyboard_input(input, window_entity));
                     }
                     WindowEvent::CursorMoved {
                         position, ..
@@ -453,8 +896,8 @@ pub fn winit_runner(mut app: App) {
                         ..
                     } => {
                         input_events.mouse_button_input.send(MouseButtonInput {
-                            button: converters::convert_mouse_button(button),
-                            state: converters::convert_element_state(state),
+                            button: compat::convert_mouse_button(button),
+                            state: compat::convert_element_state(state),
                             window: window_entity,
                         });
                     }
@@ -484,7 +927,7 @@ pub fn winit_runner(mut app: App) {
                         // Event
                         input_events
                             .touch_input
-                            .send(converters::convert_touch_input(touch, location));
+                            .send(compat::convert_touch_input(touch, location));
                     }
                     WindowEvent::ReceivedCharacter(c) => {
                         input_events.character_input.send(ReceivedCharacter {
@@ -634,9 +1077,14 @@ pub fn winit_runner(mut app: App) {
             event::Event::MainEventsCleared => {
                 let (winit_config, window_focused_query) = focused_window_state.get(&app.world);
 
-                let update = if winit_state.active {
-                    // True if _any_ windows are currently being focused
-                    let app_focused = window_focused_query.iter().any(|window| window.focused);
+                // True if _any_ windows are currently being focused
+                let app_focused = window_focused_query.iter().any(|window| window.focused);
+                // Skip updates until the throttled frame interval has passed
+                let throttled = winit_config
+                    .min_frame_interval(app_focused)
+                    .is_some_and(|interval| winit_state.last_update.elapsed() < interval);
+
+                let update = if winit_state.active && !throttled {
                     match winit_config.update_mode(app_focused) {
                         UpdateMode::Continuous
                         | UpdateMode::Reactive {
@@ -653,10 +1101,40 @@ pub fn winit_runner(mut app: App) {
                 } else {
                     false
                 };
+                // Wait for the frame pacing strategy, if any
+                let update = update
+                    && !next_paced_update(&app.world, winit_state.last_update, app_focused)
+                        .is_some_and(|next_update| Instant::now() < next_update);
 
                 if update {
+                    if winit_state
+                        .app_focused
+                        .is_some_and(|focused| focused != app_focused)
+                    {
+                        app.world.send_event(AppFocusChanged {
+                            focused: app_focused,
+                        });
+                    }
+                    winit_state.app_focused = Some(app_focused);
                     winit_state.last_update = Instant::now();
+
+                    reinitialize_device(&mut app.world, event_loop);
+                    let (commands, windows, ready_writer, vulkano_windows, context, settings) =
+                        pending_renderers_system_state.get_mut(&mut app.world);
+                    create_pending_renderers(
+                        commands,
+                        event_loop,
+                        windows,
+                        ready_writer,
+                        vulkano_windows,
+                        context,
+                        settings,
+                    );
+                    pending_renderers_system_state.apply(&mut app.world);
+
                     app.update();
+                    run_main_thread_tasks(&mut app.world);
+                    frame_mark();
                 }
             }
             Event::RedrawEventsCleared => {
@@ -697,6 +1175,21 @@ pub fn winit_runner(mut app: App) {
                     }
                 }
 
+                // Wait for the throttled frame interval instead of polling
+                let (winit_config, window_focused_query) = focused_window_state.get(&app.world);
+                let app_focused = window_focused_query.iter().any(|window| window.focused);
+                if let Some(next_update) = winit_config
+                    .min_frame_interval(app_focused)
+                    .and_then(|interval| winit_state.last_update.checked_add(interval))
+                {
+                    wait_until(control_flow, next_update);
+                }
+                if let Some(next_update) =
+                    next_paced_update(&app.world, winit_state.last_update, app_focused)
+                {
+                    wait_until(control_flow, next_update);
+                }
+
                 winit_state.redraw_request_sent = redraw;
             }
 
@@ -708,6 +1201,7 @@ pub fn winit_runner(mut app: App) {
                 commands,
                 mut new_windows,
                 created_window_writer,
+                ready_writer,
                 vulkano_windows,
                 context,
                 settings,
@@ -719,6 +1213,7 @@ pub fn winit_runner(mut app: App) {
                 event_loop,
                 new_windows.iter_mut(),
                 created_window_writer,
+                ready_writer,
                 vulkano_windows,
                 context,
                 settings,
@@ -736,9 +1231,20 @@ pub fn winit_runner(mut app: App) {
     }
 }
 
+/// Waits until `next_update` unless the event loop already waits longer or indefinitely.
+fn wait_until(control_flow: &mut ControlFlow, next_update: Instant) {
+    *control_flow = match *control_flow {
+        ControlFlow::Poll => ControlFlow::WaitUntil(next_update),
+        ControlFlow::WaitUntil(instant) => ControlFlow::WaitUntil(instant.max(next_update)),
+        control_flow => control_flow,
+    };
+}
+
 #[cfg(feature = "gui")]
 pub fn begin_egui_frame_system(mut vulkano_windows: NonSendMut<BevyVulkanoWindows>) {
     for (_, w) in vulkano_windows.windows.iter_mut() {
-        w.gui.begin_frame();
+        if let Some(gui) = &mut w.gui {
+            gui.begin_frame();
+        }
     }
 }
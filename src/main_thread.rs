@@ -0,0 +1,106 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use bevy::prelude::{Resource, World};
+
+type MainThreadJob = Box<dyn FnOnce(&mut World) + Send>;
+
+struct Completion<T> {
+    result: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// Completes with the result of a closure queued with [`MainThreadTasks::run`]. Await it in a
+/// task, or poll it with [`MainThreadTask::try_take`] from systems.
+pub struct MainThreadTask<T> {
+    completion: Arc<Mutex<Completion<T>>>,
+}
+
+impl<T> MainThreadTask<T> {
+    /// Takes the result if the closure has run.
+    pub fn try_take(&self) -> Option<T> {
+        self.completion.lock().unwrap().result.take()
+    }
+}
+
+impl<T> Future for MainThreadTask<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut completion = self.completion.lock().unwrap();
+        match completion.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                completion.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Runs closures on the winit main thread between frames, for APIs which must be called from
+/// the main thread, e.g. setting window icons or macOS-only calls. Closures get exclusive access
+/// to the world, including non-send resources like [`BevyVulkanoWindows`](crate::BevyVulkanoWindows).
+///
+/// Closures queued during a frame run after the frame's update, in the order they were queued.
+///
+/// ```ignore
+/// fn set_icon(main_thread: Res<MainThreadTasks>) {
+///     main_thread.run(move |world| {
+///         let windows = world.non_send_resource::<BevyVulkanoWindows>();
+///         let vulkano_window = windows.get_vulkano_window(entity).unwrap();
+///         vulkano_window.window().set_window_icon(Some(icon));
+///     });
+/// }
+/// ```
+#[derive(Resource, Clone, Default)]
+pub struct MainThreadTasks {
+    jobs: Arc<Mutex<Vec<MainThreadJob>>>,
+}
+
+impl MainThreadTasks {
+    /// Queues `job` to run on the main thread after the current frame. The returned task
+    /// completes with its result.
+    pub fn run<T: Send + 'static>(
+        &self,
+        job: impl FnOnce(&mut World) -> T + Send + 'static,
+    ) -> MainThreadTask<T> {
+        let completion = Arc::new(Mutex::new(Completion {
+            result: None,
+            waker: None,
+        }));
+        let job_completion = completion.clone();
+        self.jobs.lock().unwrap().push(Box::new(move |world| {
+            let result = job(world);
+            let mut completion = job_completion.lock().unwrap();
+            completion.result = Some(result);
+            if let Some(waker) = completion.waker.take() {
+                waker.wake();
+            }
+        }));
+        MainThreadTask {
+            completion,
+        }
+    }
+
+    /// Number of closures waiting to run.
+    pub fn pending(&self) -> usize {
+        self.jobs.lock().unwrap().len()
+    }
+}
+
+/// Runs the queued closures. Called by the runner on the main thread after each update. Closures
+/// queued by closures run after the next update.
+pub(crate) fn run_main_thread_tasks(world: &mut World) {
+    let Some(tasks) = world.get_resource::<MainThreadTasks>() else {
+        return;
+    };
+    let jobs = std::mem::take(&mut *tasks.jobs.lock().unwrap());
+    for job in jobs {
+        job(world);
+    }
+}
@@ -0,0 +1,59 @@
+use bevy::{
+    app::AppExit,
+    log::{info, warn},
+    prelude::{
+        Component, Entity, EventWriter, NonSendMut, Query, RemovedComponents, With, Without,
+    },
+    window::{PrimaryWindow, Window},
+};
+
+use crate::BevyVulkanoWindows;
+
+/// Marks the window used as the main render window, returned by
+/// [`BevyVulkanoWindows::main_window`] and closing which exits the app with
+/// [`exit_on_main_render_window_closed`].
+///
+/// Without a marked window, the [`PrimaryWindow`] is the main render window. Use this when the
+/// conceptual main view is not the first created window, e.g. when a launcher window appears
+/// first. Only one window should be marked at a time.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct MainRenderWindow;
+
+/// Updates the main render window of [`BevyVulkanoWindows`] from the [`MainRenderWindow`] marker,
+/// falling back to the [`PrimaryWindow`].
+pub(crate) fn update_main_render_window(
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    marked: Query<Entity, (With<Window>, With<MainRenderWindow>)>,
+    primary: Query<Entity, (With<Window>, With<PrimaryWindow>, Without<MainRenderWindow>)>,
+) {
+    let mut marked_iter = marked.iter();
+    let main_window = match marked_iter.next() {
+        Some(entity) => {
+            if marked_iter.next().is_some() && vulkano_windows.main_window_entity() != Some(entity)
+            {
+                warn!(
+                    "Multiple windows are marked as MainRenderWindow, using {:?}",
+                    entity
+                );
+            }
+            Some(entity)
+        }
+        None => primary.get_single().ok(),
+    };
+    vulkano_windows.main_window = main_window;
+}
+
+/// Exits the app when the window marked as [`MainRenderWindow`] is closed, similar to bevy's
+/// `exit_on_primary_closed` for the primary window.
+///
+/// Moving the marker to another window doesn't exit the app.
+pub fn exit_on_main_render_window_closed(
+    mut app_exit_events: EventWriter<AppExit>,
+    mut removed: RemovedComponents<MainRenderWindow>,
+    windows: Query<(), (With<Window>, With<MainRenderWindow>)>,
+) {
+    if removed.read().count() > 0 && windows.is_empty() {
+        info!("Main render window was closed, exiting");
+        app_exit_events.send(AppExit);
+    }
+}
@@ -0,0 +1,295 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use ash::vk;
+use bevy::{
+    log::{info, warn},
+    prelude::{Event, EventWriter, Res, ResMut, Resource},
+};
+use vulkano::{
+    buffer::{Buffer, BufferMemory},
+    device::{physical::PhysicalDevice, DeviceExtensions, DeviceOwned},
+    image::{Image, ImageMemory},
+    instance::InstanceExtensions,
+    memory::{MemoryHeapFlags, ResourceMemory},
+    DeviceSize, Version, VulkanLibrary, VulkanObject,
+};
+use vulkano_util::context::VulkanoConfig;
+
+use crate::BevyVulkanoContext;
+
+/// `VK_MAX_MEMORY_HEAPS`
+const MAX_HEAPS: usize = 16;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+/// Bytes allocated per heap by tracked allocations since the last budget update.
+static FRAME_ALLOCATED: [AtomicU64; MAX_HEAPS] = [ZERO; MAX_HEAPS];
+
+/// Device extensions required for querying the memory budget.
+pub fn memory_budget_extensions() -> DeviceExtensions {
+    DeviceExtensions {
+        ext_memory_budget: true,
+        ..DeviceExtensions::empty()
+    }
+}
+
+/// Requires `ext_memory_budget` of the device, and enables `khr_get_physical_device_properties2`
+/// for instances older than Vulkan 1.1, which the budget query needs.
+pub(crate) fn enable_memory_budget(config: &mut VulkanoConfig) {
+    config.device_extensions = config.device_extensions.union(&memory_budget_extensions());
+    let Ok(library) = VulkanLibrary::new() else {
+        return;
+    };
+    if library.api_version() < Version::V1_1
+        && library
+            .supported_extensions()
+            .khr_get_physical_device_properties2
+    {
+        config.instance_create_info.enabled_extensions = config
+            .instance_create_info
+            .enabled_extensions
+            .union(&InstanceExtensions {
+                khr_get_physical_device_properties2: true,
+                ..InstanceExtensions::empty()
+            });
+    }
+    info!("Requiring ext_memory_budget for memory budget tracking");
+}
+
+/// Budget and usage of a memory heap.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HeapBudget {
+    /// Size of the heap.
+    pub size: DeviceSize,
+    /// Whether the heap is device local (VRAM on discrete GPUs).
+    pub device_local: bool,
+    /// How much the app can allocate from the heap before risking allocation failures or
+    /// degraded performance, as reported by the driver. The heap size without the
+    /// `ext_memory_budget` extension.
+    pub budget: DeviceSize,
+    /// Memory of the heap used by the app at the end of the last frame. Without the
+    /// `ext_memory_budget` extension, only the allocations tracked during the last frame.
+    pub usage: DeviceSize,
+    /// Estimated highest usage during the last frame: the usage at the start of the frame plus
+    /// the tracked allocations made during it, or the usage at the end if higher.
+    pub frame_peak: DeviceSize,
+    /// Highest `frame_peak` since startup.
+    pub peak: DeviceSize,
+}
+
+impl HeapBudget {
+    /// `frame_peak` relative to the budget.
+    #[inline]
+    pub fn frame_peak_fraction(&self) -> f32 {
+        if self.budget == 0 {
+            0.0
+        } else {
+            self.frame_peak as f32 / self.budget as f32
+        }
+    }
+}
+
+/// Sent when the frame peak of a heap reaches the
+/// [`warning_threshold`](GpuMemoryBudget::warning_threshold) of its budget, e.g. to lower texture
+/// quality or render scale before allocations start failing with device lost errors. Sent again
+/// only after the peak dropped below the threshold.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MemoryBudgetWarning {
+    pub heap_index: usize,
+    pub heap: HeapBudget,
+}
+
+/// GPU memory budget and high-water marks of the memory heaps, updated at the end of every frame.
+///
+/// Usage is queried from the driver with the `ext_memory_budget` extension, enabled by
+/// [`BevyVulkanoSettings::memory_budget`](crate::BevyVulkanoSettings::memory_budget). Polling only
+/// catches memory still allocated at the end of a frame, so allocation spikes within a frame are
+/// estimated from tracked allocations: images and buffers created by the crate's helpers (render
+/// targets, blur pyramids, upload staging buffers etc.) are tracked. Track your own transient
+/// allocations with [`GpuMemoryBudget::track_image`] and [`GpuMemoryBudget::track_buffer`].
+///
+/// ```ignore
+/// fn lower_quality(mut warnings: EventReader<MemoryBudgetWarning>, mut quality: ResMut<Quality>) {
+///     for warning in warnings.read() {
+///         if warning.heap.device_local {
+///             quality.texture_resolution /= 2;
+///         }
+///     }
+/// }
+/// ```
+#[derive(Resource, Debug, Clone)]
+pub struct GpuMemoryBudget {
+    /// Fraction of the budget at which [`MemoryBudgetWarning`] is sent. Default is `0.9`.
+    pub warning_threshold: f32,
+    heaps: Vec<HeapBudget>,
+    warned: Vec<bool>,
+    budget_supported: bool,
+}
+
+impl Default for GpuMemoryBudget {
+    fn default() -> Self {
+        GpuMemoryBudget {
+            warning_threshold: 0.9,
+            heaps: vec![],
+            warned: vec![],
+            budget_supported: false,
+        }
+    }
+}
+
+impl GpuMemoryBudget {
+    /// Budgets of all memory heaps of the device, indexed like the heaps of its memory properties.
+    #[inline]
+    pub fn heaps(&self) -> &[HeapBudget] {
+        &self.heaps
+    }
+
+    /// Whether budgets and usage come from the driver (`ext_memory_budget` is enabled).
+    #[inline]
+    pub fn is_budget_supported(&self) -> bool {
+        self.budget_supported
+    }
+
+    /// Highest [`HeapBudget::frame_peak_fraction`] of the device local heaps.
+    pub fn device_local_pressure(&self) -> f32 {
+        self.heaps
+            .iter()
+            .filter(|heap| heap.device_local)
+            .map(HeapBudget::frame_peak_fraction)
+            .fold(0.0, f32::max)
+    }
+
+    /// Forgets the heaps and peaks of the previous device.
+    pub(crate) fn reset(&mut self) {
+        self.heaps.clear();
+        self.warned.clear();
+        self.budget_supported = false;
+    }
+
+    /// Counts the memory bound to `image` towards the frame peak of its heap.
+    pub fn track_image(image: &Image) {
+        if let ImageMemory::Normal(allocations) = image.memory() {
+            for allocation in allocations {
+                track_allocation(image.device().physical_device(), allocation);
+            }
+        }
+    }
+
+    /// Counts the memory bound to `buffer` towards the frame peak of its heap.
+    pub fn track_buffer(buffer: &Buffer) {
+        if let BufferMemory::Normal(allocation) = buffer.memory() {
+            track_allocation(buffer.device().physical_device(), allocation);
+        }
+    }
+}
+
+fn track_allocation(physical_device: &PhysicalDevice, allocation: &ResourceMemory) {
+    let memory_type = allocation.device_memory().memory_type_index() as usize;
+    let heap_index =
+        physical_device.memory_properties().memory_types[memory_type].heap_index as usize;
+    if let Some(allocated) = FRAME_ALLOCATED.get(heap_index) {
+        allocated.fetch_add(allocation.size(), Ordering::Relaxed);
+    }
+}
+
+/// Queries `(budget, usage)` of each heap with `vkGetPhysicalDeviceMemoryProperties2`.
+fn query_heap_budgets(physical_device: &PhysicalDevice) -> Option<Vec<(DeviceSize, DeviceSize)>> {
+    let instance = physical_device.instance();
+    let mut budget_properties = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+    let mut properties = vk::PhysicalDeviceMemoryProperties2 {
+        p_next: &mut budget_properties as *mut _ as *mut _,
+        ..Default::default()
+    };
+    let fns = instance.fns();
+    // The core function is only valid for devices supporting Vulkan 1.1, even on a 1.1 instance
+    unsafe {
+        if physical_device.api_version() >= Version::V1_1 {
+            (fns.v1_1.get_physical_device_memory_properties2)(
+                physical_device.handle(),
+                &mut properties,
+            );
+        } else if instance
+            .enabled_extensions()
+            .khr_get_physical_device_properties2
+        {
+            (fns.khr_get_physical_device_properties2
+                .get_physical_device_memory_properties2_khr)(
+                physical_device.handle(),
+                &mut properties,
+            );
+        } else {
+            return None;
+        }
+    }
+    let heap_count = properties.memory_properties.memory_heap_count as usize;
+    Some(
+        budget_properties.heap_budget[..heap_count]
+            .iter()
+            .copied()
+            .zip(budget_properties.heap_usage[..heap_count].iter().copied())
+            .collect(),
+    )
+}
+
+/// Updates the [`GpuMemoryBudget`] after all windows presented and sends [`MemoryBudgetWarning`]s.
+pub(crate) fn update_memory_budget(
+    context: Res<BevyVulkanoContext>,
+    mut memory_budget: ResMut<GpuMemoryBudget>,
+    mut warnings: EventWriter<MemoryBudgetWarning>,
+) {
+    let device = context.context.device();
+    let physical_device = device.physical_device();
+    let memory_heaps = &physical_device.memory_properties().memory_heaps;
+    let queried = if device.enabled_extensions().ext_memory_budget {
+        query_heap_budgets(physical_device)
+    } else {
+        None
+    };
+    let memory_budget = &mut *memory_budget;
+    memory_budget.budget_supported = queried.is_some();
+    // The device may have been reinitialized with different heaps
+    if memory_budget.heaps.len() != memory_heaps.len() {
+        memory_budget.heaps = vec![HeapBudget::default(); memory_heaps.len()];
+        memory_budget.warned = vec![false; memory_heaps.len()];
+    }
+
+    for (heap_index, memory_heap) in memory_heaps.iter().enumerate() {
+        let allocated = FRAME_ALLOCATED
+            .get(heap_index)
+            .map_or(0, |allocated| allocated.swap(0, Ordering::Relaxed));
+        let (budget, usage) = queried
+            .as_ref()
+            .map_or((memory_heap.size, allocated), |queried| queried[heap_index]);
+        let heap = &mut memory_budget.heaps[heap_index];
+        let frame_start_usage = if memory_budget.budget_supported {
+            heap.usage
+        } else {
+            0
+        };
+        heap.size = memory_heap.size;
+        heap.device_local = memory_heap.flags.intersects(MemoryHeapFlags::DEVICE_LOCAL);
+        heap.budget = budget;
+        heap.usage = usage;
+        heap.frame_peak = (frame_start_usage + allocated).max(usage);
+        heap.peak = heap.peak.max(heap.frame_peak);
+
+        let heap = *heap;
+        let warned = &mut memory_budget.warned[heap_index];
+        if heap.frame_peak_fraction() >= memory_budget.warning_threshold {
+            if !*warned {
+                warn!(
+                    "Memory heap {heap_index} peaked at {} MiB of its {} MiB budget",
+                    heap.frame_peak / (1024 * 1024),
+                    heap.budget / (1024 * 1024)
+                );
+                warnings.send(MemoryBudgetWarning {
+                    heap_index,
+                    heap,
+                });
+                *warned = true;
+            }
+        } else {
+            *warned = false;
+        }
+    }
+}
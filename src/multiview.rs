@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use vulkano::{
+    device::{Device, Features},
+    format::Format,
+    image::{
+        view::ImageView, Image, ImageCreateInfo, ImageLayout, ImageType, ImageUsage, SampleCount,
+    },
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+    render_pass::{
+        AttachmentDescription, AttachmentLoadOp, AttachmentReference, AttachmentStoreOp,
+        RenderPass, RenderPassCreateInfo, SubpassDescription,
+    },
+};
+
+/// Device features required for multiview rendering.
+pub fn multiview_features() -> Features {
+    Features {
+        multiview: true,
+        ..Features::empty()
+    }
+}
+
+/// View mask rendering to the first `view_count` array layers.
+pub fn multiview_view_mask(view_count: u32) -> u32 {
+    u32::MAX >> (32 - view_count.clamp(1, 32))
+}
+
+/// Creates a single subpass render pass with one color attachment which renders to `view_count`
+/// array layers at once. Vertex shaders select their view with `gl_ViewIndex`
+/// (`GL_EXT_multiview`).
+///
+/// Use with a stereo swapchain ([`VulkanoWindowConfig::image_array_layers`](crate::VulkanoWindowConfig)
+/// set to 2) or a target created with [`create_multiview_image`]. Requires
+/// [`BevyVulkanoSettings::multiview`](crate::BevyVulkanoSettings).
+pub fn create_multiview_render_pass(
+    device: Arc<Device>,
+    format: Format,
+    view_count: u32,
+    load_op: AttachmentLoadOp,
+) -> Arc<RenderPass> {
+    let view_mask = multiview_view_mask(view_count);
+    RenderPass::new(device, RenderPassCreateInfo {
+        attachments: vec![AttachmentDescription {
+            format,
+            samples: SampleCount::Sample1,
+            load_op,
+            store_op: AttachmentStoreOp::Store,
+            initial_layout: match load_op {
+                AttachmentLoadOp::Load => ImageLayout::ColorAttachmentOptimal,
+                _ => ImageLayout::Undefined,
+            },
+            final_layout: ImageLayout::ColorAttachmentOptimal,
+            ..Default::default()
+        }],
+        subpasses: vec![SubpassDescription {
+            view_mask,
+            color_attachments: vec![Some(AttachmentReference {
+                attachment: 0,
+                layout: ImageLayout::ColorAttachmentOptimal,
+                ..Default::default()
+            })],
+            ..Default::default()
+        }],
+        correlated_view_masks: vec![view_mask],
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// Creates an intermediate 2D array image with `view_count` layers, e.g. for rendering stereo
+/// views side by side before composing them to a regular swapchain.
+pub fn create_multiview_image(
+    allocator: Arc<StandardMemoryAllocator>,
+    format: Format,
+    extent: [u32; 2],
+    view_count: u32,
+    usage: ImageUsage,
+) -> Arc<ImageView> {
+    ImageView::new_default(
+        Image::new(
+            allocator,
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent: [extent[0], extent[1], 1],
+                array_layers: view_count.max(1),
+                usage: usage | ImageUsage::COLOR_ATTACHMENT,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap(),
+    )
+    .unwrap()
+}
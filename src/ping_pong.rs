@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::BufferContents,
+    command_buffer::AutoCommandBufferBuilder,
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+};
+
+use crate::{ComputePass, GpuMemoryBudget};
+
+/// Two storage images used alternately as input and output of iterative compute passes, e.g.
+/// cellular automata or fluid simulations, where each step reads the previous state and writes
+/// the next.
+///
+/// Record steps with [`PingPongImage::record_step`], which binds the images to a [`ComputePass`]
+/// and swaps them after the dispatch. Steps recorded into the same command buffer are separated by
+/// the barriers the command buffer builder inserts for the read after write, and steps submitted
+/// separately are ordered by chaining their futures.
+///
+/// ```ignore
+/// let mut state = PingPongImage::new(allocator, [512, 512], Format::R32_SFLOAT, ImageUsage::SAMPLED);
+/// for _ in 0..steps {
+///     state.record_step(&mut builder, &mut pass, "state_in", "state_out", None::<()>);
+/// }
+/// // Sample the latest state from `state.current()`
+/// ```
+pub struct PingPongImage {
+    images: [Arc<ImageView>; 2],
+    current: usize,
+}
+
+impl PingPongImage {
+    /// Creates two images of `extent` and `format` with `usage` in addition to `STORAGE`.
+    pub fn new(
+        allocator: Arc<StandardMemoryAllocator>,
+        extent: [u32; 2],
+        format: Format,
+        usage: ImageUsage,
+    ) -> PingPongImage {
+        let create_image = || {
+            let image = Image::new(
+                allocator.clone(),
+                ImageCreateInfo {
+                    image_type: ImageType::Dim2d,
+                    format,
+                    extent: [extent[0], extent[1], 1],
+                    usage: usage | ImageUsage::STORAGE,
+                    ..Default::default()
+                },
+                AllocationCreateInfo::default(),
+            )
+            .unwrap();
+            GpuMemoryBudget::track_image(&image);
+            ImageView::new_default(image).unwrap()
+        };
+        PingPongImage {
+            images: [create_image(), create_image()],
+            current: 0,
+        }
+    }
+
+    /// Uses existing images, which must have the same extent and format.
+    pub fn from_views(first: Arc<ImageView>, second: Arc<ImageView>) -> PingPongImage {
+        assert_eq!(
+            first.image().extent(),
+            second.image().extent(),
+            "ping pong images must have the same extent"
+        );
+        assert_eq!(
+            first.format(),
+            second.format(),
+            "ping pong images must have the same format"
+        );
+        PingPongImage {
+            images: [first, second],
+            current: 0,
+        }
+    }
+
+    /// The image holding the latest state.
+    pub fn current(&self) -> Arc<ImageView> {
+        self.images[self.current].clone()
+    }
+
+    /// The image the next state is written to.
+    pub fn next(&self) -> Arc<ImageView> {
+        self.images[1 - self.current].clone()
+    }
+
+    /// Makes the next image current, after the next state has been written.
+    pub fn swap(&mut self) {
+        self.current = 1 - self.current;
+    }
+
+    pub fn extent(&self) -> [u32; 3] {
+        self.images[0].image().extent()
+    }
+
+    pub fn format(&self) -> Format {
+        self.images[0].format()
+    }
+
+    /// Records a dispatch of `pass` over the images, with the current image bound to `input` and
+    /// the next image bound to `output`, and swaps the images.
+    pub fn record_step<L, P: BufferContents>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        pass: &mut ComputePass,
+        input: &str,
+        output: &str,
+        push_constants: Option<P>,
+    ) {
+        pass.set_image(input, self.current());
+        pass.set_image(output, self.next());
+        pass.record(builder, self.extent(), push_constants);
+        self.swap();
+    }
+}
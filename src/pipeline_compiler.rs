@@ -0,0 +1,226 @@
+use std::{
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use bevy::{
+    log::error,
+    prelude::{Event, EventWriter, ResMut, Resource},
+    utils::HashMap,
+};
+use vulkano::{
+    device::Device,
+    pipeline::{
+        cache::PipelineCache, compute::ComputePipelineCreateInfo, ComputePipeline,
+        GraphicsPipeline, GraphicsPipelineCreateInfo,
+    },
+};
+
+/// Identifies a pipeline submitted to the [`PipelineCompiler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineId(u64);
+
+/// A pipeline compiled by the [`PipelineCompiler`].
+#[derive(Clone)]
+pub enum CompiledPipeline {
+    Compute(Arc<ComputePipeline>),
+    Graphics(Arc<GraphicsPipeline>),
+}
+
+/// Sent when a pipeline submitted to the [`PipelineCompiler`] has finished compiling. On failure,
+/// `error` holds the reason and the pipeline will not become available.
+#[derive(Event, Debug, Clone)]
+pub struct PipelineCompiled {
+    pub id: PipelineId,
+    pub error: Option<String>,
+}
+
+type CompileJob = Box<dyn FnOnce() -> Result<CompiledPipeline, String> + Send>;
+
+/// Compiles pipelines on background threads to avoid hitches when pipelines are created during
+/// gameplay.
+///
+/// Submit create infos with [`PipelineCompiler::compile_compute`] or
+/// [`PipelineCompiler::compile_graphics`] and keep rendering with a fallback pipeline (e.g.
+/// through [`PipelineCompiler::graphics_or`]) until [`PipelineCompiled`] is received. Derivative
+/// pipelines can be requested by setting `base_pipeline` and the derivative flags in the create
+/// info, and a shared [`PipelineCache`] speeds up compiles of similar pipelines.
+#[derive(Resource)]
+pub struct PipelineCompiler {
+    threads: usize,
+    job_sender: Option<Sender<(PipelineId, CompileJob)>>,
+    result_receiver: Mutex<Receiver<(PipelineId, Result<CompiledPipeline, String>)>>,
+    result_sender: Sender<(PipelineId, Result<CompiledPipeline, String>)>,
+    pipelines: HashMap<PipelineId, CompiledPipeline>,
+    next_id: u64,
+}
+
+impl Default for PipelineCompiler {
+    fn default() -> Self {
+        let threads = thread::available_parallelism()
+            .map(|n| n.get() / 2)
+            .unwrap_or(1)
+            .clamp(1, 4);
+        PipelineCompiler::new(threads)
+    }
+}
+
+impl PipelineCompiler {
+    /// Creates a compiler using `threads` worker threads. Threads are spawned on first use.
+    pub fn new(threads: usize) -> PipelineCompiler {
+        let (result_sender, result_receiver) = channel();
+        PipelineCompiler {
+            threads: threads.max(1),
+            job_sender: None,
+            result_receiver: Mutex::new(result_receiver),
+            result_sender,
+            pipelines: HashMap::default(),
+            next_id: 0,
+        }
+    }
+
+    fn submit(&mut self, job: CompileJob) -> PipelineId {
+        let id = PipelineId(self.next_id);
+        self.next_id += 1;
+        let sender = self.job_sender.get_or_insert_with(|| {
+            let (job_sender, job_receiver) = channel::<(PipelineId, CompileJob)>();
+            let job_receiver = Arc::new(Mutex::new(job_receiver));
+            for i in 0..self.threads {
+                let job_receiver = job_receiver.clone();
+                let result_sender = self.result_sender.clone();
+                thread::Builder::new()
+                    .name(format!("bevy_vulkano pipeline compiler {i}"))
+                    .spawn(move || loop {
+                        let job = job_receiver.lock().unwrap().recv();
+                        match job {
+                            Ok((id, job)) => {
+                                if result_sender.send((id, job())).is_err() {
+                                    break;
+                                }
+                            }
+                            Err(_) => break,
+                        }
+                    })
+                    .unwrap();
+            }
+            job_sender
+        });
+        sender.send((id, job)).unwrap();
+        id
+    }
+
+    /// Compiles a compute pipeline in the background.
+    pub fn compile_compute(
+        &mut self,
+        device: Arc<Device>,
+        cache: Option<Arc<PipelineCache>>,
+        create_info: ComputePipelineCreateInfo,
+    ) -> PipelineId {
+        self.submit(Box::new(move || {
+            ComputePipeline::new(device, cache, create_info)
+                .map(CompiledPipeline::Compute)
+                .map_err(|e| e.to_string())
+        }))
+    }
+
+    /// Compiles a graphics pipeline in the background. Wrap `create_info` with
+    /// [`with_dynamic_viewport`](crate::with_dynamic_viewport) to keep the pipeline usable after
+    /// resizes.
+    pub fn compile_graphics(
+        &mut self,
+        device: Arc<Device>,
+        cache: Option<Arc<PipelineCache>>,
+        create_info: GraphicsPipelineCreateInfo,
+    ) -> PipelineId {
+        self.submit(Box::new(move || {
+            GraphicsPipeline::new(device, cache, create_info)
+                .map(CompiledPipeline::Graphics)
+                .map_err(|e| e.to_string())
+        }))
+    }
+
+    /// Whether the pipeline has been compiled successfully.
+    pub fn is_ready(&self, id: PipelineId) -> bool {
+        self.pipelines.contains_key(&id)
+    }
+
+    /// The compiled compute pipeline, if ready.
+    pub fn compute_pipeline(&self, id: PipelineId) -> Option<Arc<ComputePipeline>> {
+        match self.pipelines.get(&id) {
+            Some(CompiledPipeline::Compute(pipeline)) => Some(pipeline.clone()),
+            _ => None,
+        }
+    }
+
+    /// The compiled graphics pipeline, if ready.
+    pub fn graphics_pipeline(&self, id: PipelineId) -> Option<Arc<GraphicsPipeline>> {
+        match self.pipelines.get(&id) {
+            Some(CompiledPipeline::Graphics(pipeline)) => Some(pipeline.clone()),
+            _ => None,
+        }
+    }
+
+    /// The compiled compute pipeline, or `fallback` while it is still compiling.
+    pub fn compute_or(
+        &self,
+        id: PipelineId,
+        fallback: &Arc<ComputePipeline>,
+    ) -> Arc<ComputePipeline> {
+        self.compute_pipeline(id)
+            .unwrap_or_else(|| fallback.clone())
+    }
+
+    /// The compiled graphics pipeline, or `fallback` while it is still compiling.
+    pub fn graphics_or(
+        &self,
+        id: PipelineId,
+        fallback: &Arc<GraphicsPipeline>,
+    ) -> Arc<GraphicsPipeline> {
+        self.graphics_pipeline(id)
+            .unwrap_or_else(|| fallback.clone())
+    }
+
+    /// Removes a compiled pipeline from the compiler, returning it.
+    pub fn take(&mut self, id: PipelineId) -> Option<CompiledPipeline> {
+        self.pipelines.remove(&id)
+    }
+
+    /// Collects finished compiles.
+    fn poll(&mut self) -> Vec<(PipelineId, Option<String>)> {
+        let results = self
+            .result_receiver
+            .lock()
+            .unwrap()
+            .try_iter()
+            .collect::<Vec<_>>();
+        results
+            .into_iter()
+            .map(|(id, result)| match result {
+                Ok(pipeline) => {
+                    self.pipelines.insert(id, pipeline);
+                    (id, None)
+                }
+                Err(e) => {
+                    error!("Failed to compile pipeline {:?}: {}", id, e);
+                    (id, Some(e))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Sends [`PipelineCompiled`] events for pipelines which finished compiling.
+pub(crate) fn pipeline_compiled_events(
+    mut compiler: ResMut<PipelineCompiler>,
+    mut compiled_events: EventWriter<PipelineCompiled>,
+) {
+    for (id, error) in compiler.poll() {
+        compiled_events.send(PipelineCompiled {
+            id,
+            error,
+        });
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    fmt::{Debug, Formatter},
+    sync::Arc,
+};
+
+use bevy::{
+    prelude::{App, Plugin},
+    window::PresentMode,
+};
+use vulkano::device::Features;
+
+use crate::{build_vulkano_winit, BevyVulkanoSettings, HeadlessConfig, ValidationMode};
+
+type SettingsFn = Arc<dyn Fn() -> BevyVulkanoSettings + Send + Sync>;
+
+/// Configuration collected by [`VulkanoWinitPluginBuilder`], applied over the base settings when
+/// the plugin is built.
+#[derive(Default, Clone)]
+pub(crate) struct PluginConfig {
+    /// Constructor of the base settings, replacing an inserted [`BevyVulkanoSettings`] resource.
+    pub(crate) settings: Option<SettingsFn>,
+    #[cfg(feature = "gui")]
+    pub(crate) gui: Option<bool>,
+    #[cfg(feature = "gui")]
+    pub(crate) gui_overlay: Option<bool>,
+    pub(crate) present_mode: Option<PresentMode>,
+    pub(crate) device_features: Features,
+    pub(crate) validation: Option<ValidationMode>,
+    pub(crate) return_from_run: Option<bool>,
+    pub(crate) headless: Option<HeadlessConfig>,
+}
+
+impl Debug for PluginConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("PluginConfig");
+        debug.field("settings", &self.settings.as_ref().map(|_| "fn"));
+        #[cfg(feature = "gui")]
+        debug
+            .field("gui", &self.gui)
+            .field("gui_overlay", &self.gui_overlay);
+        debug
+            .field("present_mode", &self.present_mode)
+            .field("device_features", &self.device_features)
+            .field("validation", &self.validation)
+            .field("return_from_run", &self.return_from_run)
+            .field("headless", &self.headless)
+            .finish()
+    }
+}
+
+impl PluginConfig {
+    /// Applies the options other than the base settings and device features, which are required
+    /// with [`BevyVulkanoSettings::apply_required_device_features`].
+    pub(crate) fn apply(&self, settings: &mut BevyVulkanoSettings) {
+        #[cfg(feature = "gui")]
+        {
+            if let Some(gui) = self.gui {
+                settings.create_gui = gui;
+            }
+            if let Some(gui_overlay) = self.gui_overlay {
+                settings.is_gui_overlay = gui_overlay;
+            }
+        }
+        if self.validation.is_some() {
+            settings.validation = self.validation;
+        }
+        if let Some(return_from_run) = self.return_from_run {
+            settings.return_from_run = return_from_run;
+        }
+        if self.headless.is_some() {
+            settings.headless = self.headless;
+        }
+    }
+}
+
+/// A configured [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin), returned by
+/// [`VulkanoWinitPlugin::builder`](crate::VulkanoWinitPlugin::builder). Add it instead of the
+/// unconfigured plugin.
+///
+/// Unlike inserting [`BevyVulkanoSettings`] as a non-send resource, which is silently ignored when
+/// inserted after the plugin is added, the configuration travels with the plugin.
+///
+/// ```ignore
+/// App::new().add_plugins((
+///     WindowPlugin::default(),
+///     VulkanoWinitPlugin::builder()
+///         .settings(BevyVulkanoSettings::desktop_app)
+///         .gui(true)
+///         .present_mode(PresentMode::Mailbox)
+///         .device_features(Features { fill_mode_non_solid: true, ..Features::empty() }),
+/// ));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct VulkanoWinitPluginBuilder {
+    config: PluginConfig,
+}
+
+impl VulkanoWinitPluginBuilder {
+    /// Base settings, e.g. [`BevyVulkanoSettings::desktop_app`] or a closure building custom
+    /// settings. Other builder options are applied on top. Defaults to an inserted
+    /// [`BevyVulkanoSettings`] resource, or [`BevyVulkanoSettings::default`].
+    pub fn settings(
+        mut self,
+        settings: impl Fn() -> BevyVulkanoSettings + Send + Sync + 'static,
+    ) -> Self {
+        self.config.settings = Some(Arc::new(settings));
+        self
+    }
+
+    /// Whether windows get an egui [`Gui`](egui_winit_vulkano::Gui). Windows with
+    /// [`NoGui`](crate::NoGui) never get one.
+    #[cfg(feature = "gui")]
+    pub fn gui(mut self, gui: bool) -> Self {
+        self.config.gui = Some(gui);
+        self
+    }
+
+    /// See [`BevyVulkanoSettings::is_gui_overlay`].
+    #[cfg(feature = "gui")]
+    pub fn gui_overlay(mut self, gui_overlay: bool) -> Self {
+        self.config.gui_overlay = Some(gui_overlay);
+        self
+    }
+
+    /// Present mode of the primary window. `WindowPlugin` must be added before the plugin, as in
+    /// bevy's plugin groups, for the primary window to exist.
+    pub fn present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.config.present_mode = Some(present_mode);
+        self
+    }
+
+    /// Device features to enable in addition to those of the base settings. Only devices
+    /// supporting them are considered, like with
+    /// [`BevyVulkanoSettings::required_device_features`].
+    pub fn device_features(mut self, features: Features) -> Self {
+        self.config.device_features = self.config.device_features.union(&features);
+        self
+    }
+
+    /// See [`BevyVulkanoSettings::validation`].
+    pub fn validation(mut self, validation: ValidationMode) -> Self {
+        self.config.validation = Some(validation);
+        self
+    }
+
+    /// See [`BevyVulkanoSettings::return_from_run`].
+    pub fn return_from_run(mut self, return_from_run: bool) -> Self {
+        self.config.return_from_run = Some(return_from_run);
+        self
+    }
+
+    /// See [`BevyVulkanoSettings::headless`].
+    pub fn headless(mut self, headless: HeadlessConfig) -> Self {
+        self.config.headless = Some(headless);
+        self
+    }
+}
+
+impl Plugin for VulkanoWinitPluginBuilder {
+    fn build(&self, app: &mut App) {
+        build_vulkano_winit(app, &self.config);
+    }
+}
@@ -0,0 +1,407 @@
+use std::sync::Arc;
+
+use bevy::prelude::{Component, Resource};
+use vulkano::{
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{
+        sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
+        view::ImageView,
+        Image, ImageCreateInfo, ImageType, ImageUsage,
+    },
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+    pipeline::{
+        compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    sync::GpuFuture,
+};
+
+use crate::{
+    post_process::{
+        is_srgb_format, BlurPyramid, ColorConversion, FullscreenPass, WorkingColorSpace,
+        BLUR_PYRAMID_FORMAT,
+    },
+    profiling::profile_scope,
+    trace_frame::trace_vk,
+    CommandStats, GpuMemoryBudget,
+};
+
+/// Number of pyramid levels blurred and composited by [`BloomPass`].
+pub const BLOOM_LEVELS: u32 = 6;
+
+/// Bloom configuration for [`BloomPass`]. The resource applies to all windows; add it as a
+/// component to a window entity to override it for that window.
+///
+/// ```ignore
+/// let settings = window_settings.get(window).unwrap_or(&bloom_settings);
+/// let after = bloom.render(before, scene_view, renderer.swapchain_image_view(), settings);
+/// ```
+#[derive(Resource, Component, Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    /// Strength of the bloom added to the image. `0.0` disables bloom.
+    pub intensity: f32,
+    /// Linear brightness above which colors bloom. Values below `1.0` make non-HDR content bloom.
+    pub threshold: f32,
+    /// Width of the soft transition around the threshold, relative to it. `0.0` is a hard cutoff.
+    pub knee: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        BloomSettings {
+            intensity: 0.15,
+            threshold: 1.0,
+            knee: 0.5,
+        }
+    }
+}
+
+impl BloomSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.intensity > 0.0
+    }
+}
+
+/// Post process pass adding bloom to an image while copying it onto a target: bright parts of the
+/// input are extracted with a soft threshold, blurred with a [`BlurPyramid`] and added back.
+///
+/// Bloom is computed on linear values. Inputs in the sRGB encoded [`WorkingColorSpace`] are decoded
+/// first, and the result is encoded for the target format like other built-in passes.
+pub struct BloomPass {
+    queue: Arc<Queue>,
+    allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    threshold_pipeline: Arc<ComputePipeline>,
+    sampler: Arc<Sampler>,
+    /// Bright parts of the input, recreated when the input extent changes.
+    bright: Option<Arc<ImageView>>,
+    /// Threshold descriptor set and the input it reads.
+    threshold_set: Option<(Arc<ImageView>, Arc<PersistentDescriptorSet>)>,
+    pyramid: BlurPyramid,
+    composite: FullscreenPass,
+    working_color_space: WorkingColorSpace,
+}
+
+impl BloomPass {
+    pub fn new(
+        queue: Arc<Queue>,
+        allocator: Arc<StandardMemoryAllocator>,
+        output_format: Format,
+    ) -> BloomPass {
+        let device = queue.device().clone();
+        let stage = PipelineShaderStageCreateInfo::new(
+            threshold_cs::load(device.clone())
+                .expect("failed to create shader module")
+                .entry_point("main")
+                .expect("shader entry point not found"),
+        );
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+        let threshold_pipeline = ComputePipeline::new(
+            device.clone(),
+            None,
+            ComputePipelineCreateInfo::stage_layout(stage, layout),
+        )
+        .unwrap();
+        let sampler = Sampler::new(device.clone(), SamplerCreateInfo {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..Default::default()
+        })
+        .unwrap();
+        let composite_fs = composite_fs::load(device.clone())
+            .expect("failed to create shader module")
+            .entry_point("main")
+            .expect("shader entry point not found");
+        BloomPass {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
+                device,
+                Default::default(),
+            ),
+            pyramid: BlurPyramid::new(queue.clone(), allocator.clone(), BLOOM_LEVELS),
+            composite: FullscreenPass::new(
+                queue.clone(),
+                composite_fs,
+                output_format,
+                Filter::Linear,
+            ),
+            queue,
+            allocator,
+            threshold_pipeline,
+            sampler,
+            bright: None,
+            threshold_set: None,
+            working_color_space: WorkingColorSpace::default(),
+        }
+    }
+
+    pub fn set_working_color_space(&mut self, working_color_space: WorkingColorSpace) {
+        self.working_color_space = working_color_space;
+    }
+
+    pub fn working_color_space(&self) -> WorkingColorSpace {
+        self.working_color_space
+    }
+
+    /// The blurred bright parts of the last render, e.g. to reuse for lens dirt or flares.
+    pub fn pyramid(&self) -> &BlurPyramid {
+        &self.pyramid
+    }
+
+    /// Draws `input` with bloom over `target`. With bloom disabled in `settings`, `input` is only
+    /// copied.
+    pub fn render(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        input: Arc<ImageView>,
+        target: Arc<ImageView>,
+        settings: &BloomSettings,
+    ) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::bloom");
+        // Conversion of input values to linear
+        let decode = match self.working_color_space {
+            WorkingColorSpace::SrgbEncoded if !is_srgb_format(input.format()) => {
+                ColorConversion::SrgbToLinear
+            }
+            _ => ColorConversion::None,
+        };
+        let after_blur = if settings.is_enabled() {
+            self.blur_bright(before_future, input.clone(), settings, decode)
+        } else {
+            before_future
+        };
+
+        // The composite shader has a binding per level, so missing levels repeat the smallest
+        let levels = self.pyramid.levels();
+        let level_count = if settings.is_enabled() {
+            levels.len() as u32
+        } else {
+            0
+        };
+        let mut inputs = vec![input.clone()];
+        inputs.extend((0..BLOOM_LEVELS as usize).map(|level| {
+            levels
+                .get(level)
+                .or(levels.last())
+                .unwrap_or(&input)
+                .clone()
+        }));
+        let output_conversion = WorkingColorSpace::Linear.output_conversion(target.format());
+        self.composite.render(
+            after_blur,
+            &inputs,
+            target,
+            Some(composite_fs::PushConstants {
+                intensity: settings.intensity,
+                level_count,
+                input_conversion: decode as u32,
+                output_conversion: output_conversion as u32,
+            }),
+        )
+    }
+
+    /// Extracts the bright parts of `input` and blurs them into the pyramid.
+    fn blur_bright(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        input: Arc<ImageView>,
+        settings: &BloomSettings,
+        decode: ColorConversion,
+    ) -> Box<dyn GpuFuture> {
+        let extent = input.image().extent();
+        if !self
+            .bright
+            .as_ref()
+            .is_some_and(|bright| bright.image().extent() == extent)
+        {
+            let image = Image::new(
+                self.allocator.clone(),
+                ImageCreateInfo {
+                    image_type: ImageType::Dim2d,
+                    format: BLUR_PYRAMID_FORMAT,
+                    extent: [extent[0], extent[1], 1],
+                    usage: ImageUsage::STORAGE | ImageUsage::SAMPLED,
+                    ..Default::default()
+                },
+                AllocationCreateInfo::default(),
+            )
+            .unwrap();
+            GpuMemoryBudget::track_image(&image);
+            self.bright = Some(ImageView::new_default(image).unwrap());
+            self.threshold_set = None;
+        }
+        let bright = self.bright.clone().unwrap();
+        if !self
+            .threshold_set
+            .as_ref()
+            .is_some_and(|(set_input, _)| Arc::ptr_eq(set_input, &input))
+        {
+            let set = PersistentDescriptorSet::new(
+                &self.descriptor_set_allocator,
+                self.threshold_pipeline.layout().set_layouts()[0].clone(),
+                [
+                    WriteDescriptorSet::image_view_sampler(0, input.clone(), self.sampler.clone()),
+                    WriteDescriptorSet::image_view(1, bright.clone()),
+                ],
+                [],
+            )
+            .unwrap();
+            self.threshold_set = Some((input, set));
+        }
+
+        let layout = self.threshold_pipeline.layout().clone();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .bind_pipeline_compute(self.threshold_pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                layout.clone(),
+                0,
+                self.threshold_set.as_ref().unwrap().1.clone(),
+            )
+            .unwrap()
+            .push_constants(layout, 0, threshold_cs::PushConstants {
+                threshold: settings.threshold.max(0.0),
+                knee: settings.knee.max(0.0) * settings.threshold.max(0.0),
+                input_conversion: decode as u32,
+            })
+            .unwrap()
+            .dispatch([extent[0].div_ceil(8), extent[1].div_ceil(8), 1])
+            .unwrap();
+        CommandStats::record_pipeline_bind();
+        CommandStats::record_dispatches(1);
+        self.pyramid.record(&mut builder, bright);
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit bloom threshold and blur of {:?} on queue family {}",
+            extent,
+            self.queue.queue_family_index()
+        );
+        before_future
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
+
+mod threshold_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: "
+#version 450
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler2D src;
+layout(set = 0, binding = 1, rgba16f) uniform writeonly image2D dst;
+
+layout(push_constant) uniform PushConstants {
+    float threshold;
+    float knee;
+    uint input_conversion;
+} push_constants;
+
+vec3 srgb_to_linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), greaterThan(c, vec3(0.04045)));
+}
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(dst);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+    vec3 color = max(texture(src, (vec2(pixel) + 0.5) / vec2(size)).rgb, vec3(0.0));
+    if (push_constants.input_conversion == 1) {
+        color = srgb_to_linear(color);
+    }
+    // Soft knee: a quadratic ramp from threshold - knee to threshold + knee
+    float brightness = max(color.r, max(color.g, color.b));
+    float knee = push_constants.knee;
+    float soft = clamp(brightness - push_constants.threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.00001);
+    float contribution = max(soft, brightness - push_constants.threshold) / max(brightness, 0.00001);
+    imageStore(dst, pixel, vec4(color * contribution, 1.0));
+}
+"
+    }
+}
+
+mod composite_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec2 v_uv;
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D scene;
+layout(set = 0, binding = 1) uniform sampler2D level0;
+layout(set = 0, binding = 2) uniform sampler2D level1;
+layout(set = 0, binding = 3) uniform sampler2D level2;
+layout(set = 0, binding = 4) uniform sampler2D level3;
+layout(set = 0, binding = 5) uniform sampler2D level4;
+layout(set = 0, binding = 6) uniform sampler2D level5;
+
+layout(push_constant) uniform PushConstants {
+    float intensity;
+    uint level_count;
+    uint input_conversion;
+    uint output_conversion;
+} push_constants;
+
+vec3 srgb_to_linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), greaterThan(c, vec3(0.04045)));
+}
+
+vec3 linear_to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, greaterThan(c, vec3(0.0031308)));
+}
+
+void main() {
+    vec4 color = texture(scene, v_uv);
+    vec3 rgb = max(color.rgb, vec3(0.0));
+    if (push_constants.input_conversion == 1) {
+        rgb = srgb_to_linear(rgb);
+    }
+    if (push_constants.level_count > 0) {
+        vec3 bloom = texture(level0, v_uv).rgb;
+        if (push_constants.level_count > 1) bloom += texture(level1, v_uv).rgb;
+        if (push_constants.level_count > 2) bloom += texture(level2, v_uv).rgb;
+        if (push_constants.level_count > 3) bloom += texture(level3, v_uv).rgb;
+        if (push_constants.level_count > 4) bloom += texture(level4, v_uv).rgb;
+        if (push_constants.level_count > 5) bloom += texture(level5, v_uv).rgb;
+        rgb += bloom / float(push_constants.level_count) * push_constants.intensity;
+    }
+    if (push_constants.output_conversion == 2) {
+        rgb = linear_to_srgb(rgb);
+    }
+    f_color = vec4(rgb, color.a);
+}
+"
+    }
+}
@@ -0,0 +1,302 @@
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{
+        sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
+        view::ImageView,
+        Image, ImageCreateInfo, ImageType, ImageUsage,
+    },
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+    pipeline::{
+        compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    sync::GpuFuture,
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk, CommandStats, GpuMemoryBudget};
+
+/// Work group size of the downsample shader.
+const LOCAL_SIZE: u32 = 8;
+
+/// Format of the pyramid levels, as declared by the downsample shader.
+pub const BLUR_PYRAMID_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+/// A chain of successively blurred and halved copies of an image, built with compute passes, as
+/// used by bloom and depth of field.
+///
+/// Each level is downsampled from the previous one (level 0 from the input) with a 13 tap filter,
+/// which blurs with a roughly gaussian kernel while halving, so level `n` is a blur of the input
+/// at `1 / 2^(n + 1)` resolution. Levels are [`BLUR_PYRAMID_FORMAT`] images, recreated whenever
+/// the input extent changes.
+///
+/// ```ignore
+/// let mut pyramid = BlurPyramid::new(queue, allocator, 6);
+/// let after = pyramid.render(before, scene_view);
+/// // Sample `pyramid.level(2)` for a wide blur of the scene
+/// ```
+pub struct BlurPyramid {
+    queue: Arc<Queue>,
+    allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    pipeline: Arc<ComputePipeline>,
+    sampler: Arc<Sampler>,
+    max_levels: u32,
+    levels: Vec<Arc<ImageView>>,
+    /// Downsample descriptor set of each level, reading the previous level.
+    sets: Vec<Arc<PersistentDescriptorSet>>,
+    /// Input the descriptor set of level 0 reads.
+    input: Option<Arc<ImageView>>,
+}
+
+impl BlurPyramid {
+    /// Creates a pyramid of at most `max_levels` levels. Fewer levels are created for inputs too
+    /// small to halve that often.
+    pub fn new(
+        queue: Arc<Queue>,
+        allocator: Arc<StandardMemoryAllocator>,
+        max_levels: u32,
+    ) -> BlurPyramid {
+        let device = queue.device().clone();
+        let stage = PipelineShaderStageCreateInfo::new(
+            downsample_cs::load(device.clone())
+                .expect("failed to create shader module")
+                .entry_point("main")
+                .expect("shader entry point not found"),
+        );
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+        let pipeline = ComputePipeline::new(
+            device.clone(),
+            None,
+            ComputePipelineCreateInfo::stage_layout(stage, layout),
+        )
+        .unwrap();
+        let sampler = Sampler::new(device.clone(), SamplerCreateInfo {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..Default::default()
+        })
+        .unwrap();
+        BlurPyramid {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
+                device,
+                Default::default(),
+            ),
+            queue,
+            allocator,
+            pipeline,
+            sampler,
+            max_levels: max_levels.max(1),
+            levels: vec![],
+            sets: vec![],
+            input: None,
+        }
+    }
+
+    /// The levels from the last render, largest first.
+    pub fn levels(&self) -> &[Arc<ImageView>] {
+        &self.levels
+    }
+
+    /// Level `index`, `None` if the input was too small for it or nothing was rendered yet.
+    pub fn level(&self, index: usize) -> Option<Arc<ImageView>> {
+        self.levels.get(index).cloned()
+    }
+
+    /// Sampler matching the filtering the pyramid was built with, for sampling its levels.
+    pub fn sampler(&self) -> Arc<Sampler> {
+        self.sampler.clone()
+    }
+
+    /// Records the downsample passes of `input` into `builder`, e.g. ahead of passes reading the
+    /// levels in the same command buffer.
+    pub fn record<L>(&mut self, builder: &mut AutoCommandBufferBuilder<L>, input: Arc<ImageView>) {
+        self.prepare(input);
+        let layout = self.pipeline.layout().clone();
+        builder
+            .bind_pipeline_compute(self.pipeline.clone())
+            .unwrap();
+        let mut src_extent = self.input.as_ref().unwrap().image().extent();
+        for (level, set) in self.levels.iter().zip(&self.sets) {
+            let extent = level.image().extent();
+            builder
+                .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, set.clone())
+                .unwrap()
+                .push_constants(layout.clone(), 0, downsample_cs::PushConstants {
+                    src_texel_size: [1.0 / src_extent[0] as f32, 1.0 / src_extent[1] as f32],
+                })
+                .unwrap()
+                .dispatch([
+                    extent[0].div_ceil(LOCAL_SIZE),
+                    extent[1].div_ceil(LOCAL_SIZE),
+                    1,
+                ])
+                .unwrap();
+            src_extent = extent;
+        }
+        CommandStats::record_pipeline_bind();
+        CommandStats::record_dispatches(self.levels.len() as u64);
+    }
+
+    /// Builds the pyramid from `input` after `before_future`.
+    pub fn render(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        input: Arc<ImageView>,
+    ) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::blur_pyramid");
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        self.record(&mut builder, input);
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit blur pyramid of {} levels on queue family {}",
+            self.levels.len(),
+            self.queue.queue_family_index()
+        );
+        before_future
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+
+    /// Recreates the levels when the input extent changed and the descriptor sets when the input
+    /// changed.
+    fn prepare(&mut self, input: Arc<ImageView>) {
+        let extent = input.image().extent();
+        let resized = !self
+            .input
+            .as_ref()
+            .is_some_and(|previous| previous.image().extent() == extent);
+        if resized {
+            self.levels.clear();
+            let mut level_extent = [extent[0] / 2, extent[1] / 2];
+            while self.levels.len() < self.max_levels as usize
+                && level_extent[0] > 0
+                && level_extent[1] > 0
+            {
+                let image = Image::new(
+                    self.allocator.clone(),
+                    ImageCreateInfo {
+                        image_type: ImageType::Dim2d,
+                        format: BLUR_PYRAMID_FORMAT,
+                        extent: [level_extent[0], level_extent[1], 1],
+                        usage: ImageUsage::STORAGE | ImageUsage::SAMPLED,
+                        ..Default::default()
+                    },
+                    AllocationCreateInfo::default(),
+                )
+                .unwrap();
+                GpuMemoryBudget::track_image(&image);
+                self.levels.push(ImageView::new_default(image).unwrap());
+                level_extent = [level_extent[0] / 2, level_extent[1] / 2];
+            }
+        }
+        let input_changed = !self
+            .input
+            .as_ref()
+            .is_some_and(|previous| Arc::ptr_eq(previous, &input));
+        if resized || input_changed {
+            let set_layout = self.pipeline.layout().set_layouts()[0].clone();
+            self.sets = self
+                .levels
+                .iter()
+                .enumerate()
+                .map(|(index, level)| {
+                    let src = match index {
+                        0 => input.clone(),
+                        _ => self.levels[index - 1].clone(),
+                    };
+                    PersistentDescriptorSet::new(
+                        &self.descriptor_set_allocator,
+                        set_layout.clone(),
+                        [
+                            WriteDescriptorSet::image_view_sampler(0, src, self.sampler.clone()),
+                            WriteDescriptorSet::image_view(1, level.clone()),
+                        ],
+                        [],
+                    )
+                    .unwrap()
+                })
+                .collect();
+        }
+        self.input = Some(input);
+    }
+}
+
+mod downsample_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: "
+#version 450
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler2D src;
+layout(set = 0, binding = 1, rgba16f) uniform writeonly image2D dst;
+
+layout(push_constant) uniform PushConstants {
+    vec2 src_texel_size;
+} push_constants;
+
+vec4 tap(vec2 uv, float x, float y) {
+    return texture(src, uv + push_constants.src_texel_size * vec2(x, y));
+}
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(dst);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+    vec2 uv = (vec2(pixel) + 0.5) / vec2(size);
+
+    // 13 bilinear taps: an inner box weighted 0.5 and four overlapping outer boxes
+    vec4 a = tap(uv, -2.0, -2.0);
+    vec4 b = tap(uv, 0.0, -2.0);
+    vec4 c = tap(uv, 2.0, -2.0);
+    vec4 d = tap(uv, -1.0, -1.0);
+    vec4 e = tap(uv, 1.0, -1.0);
+    vec4 f = tap(uv, -2.0, 0.0);
+    vec4 g = tap(uv, 0.0, 0.0);
+    vec4 h = tap(uv, 2.0, 0.0);
+    vec4 i = tap(uv, -1.0, 1.0);
+    vec4 j = tap(uv, 1.0, 1.0);
+    vec4 k = tap(uv, -2.0, 2.0);
+    vec4 l = tap(uv, 0.0, 2.0);
+    vec4 m = tap(uv, 2.0, 2.0);
+
+    vec4 color = (d + e + i + j) * 0.125;
+    color += (a + b + f + g) * 0.03125;
+    color += (b + c + g + h) * 0.03125;
+    color += (f + g + k + l) * 0.03125;
+    color += (g + h + l + m) * 0.03125;
+    imageStore(dst, pixel, color);
+}
+"
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::prelude::Resource;
+use vulkano::format::{Format, NumericFormat};
+
+/// The color encoding built-in passes ([`ScaledBlitPass`](crate::ScaledBlitPass),
+/// [`DisplayAdjustmentsPass`](crate::DisplayAdjustmentsPass)) work in, i.e. what the values their
+/// shaders read and write mean.
+///
+/// The passes convert at their boundaries based on the actual image formats: sampling an `_SRGB`
+/// image decodes to linear and writing an `_SRGB` target encodes from linear in hardware, while
+/// `_UNORM` and float images store values as they are. With [`WorkingColorSpace::Linear`],
+/// `_UNORM` swapchain images (e.g. when `STORAGE` usage forced a non-sRGB format) get sRGB encoded
+/// values written by the shader, so colors look the same as on an `_SRGB` swapchain.
+///
+/// egui handles both kinds of targets itself, as the gui is created with
+/// `allow_srgb_render_target`.
+///
+/// Inserted as a resource by [`VulkanoPostProcessPlugin`](crate::VulkanoPostProcessPlugin) so apps
+/// have one place to configure their passes from.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WorkingColorSpace {
+    /// Linear values, which blending and filtering assume. Use this unless your render targets
+    /// hold sRGB encoded values in `_UNORM` formats.
+    #[default]
+    Linear,
+    /// sRGB encoded values, e.g. when porting content authored for non-sRGB pipelines.
+    SrgbEncoded,
+}
+
+impl WorkingColorSpace {
+    /// Conversion from texels sampled from an image of `format` into the working space.
+    pub fn input_conversion(self, format: Format) -> ColorConversion {
+        match (self, is_srgb_format(format)) {
+            // Sampling decodes sRGB images to linear
+            (WorkingColorSpace::SrgbEncoded, true) => ColorConversion::LinearToSrgb,
+            _ => ColorConversion::None,
+        }
+    }
+
+    /// Conversion from the working space into values written to a target of `format`.
+    pub fn output_conversion(self, format: Format) -> ColorConversion {
+        match (self, is_srgb_format(format)) {
+            // Writes to sRGB targets are encoded from linear
+            (WorkingColorSpace::SrgbEncoded, true) => ColorConversion::SrgbToLinear,
+            (WorkingColorSpace::Linear, false) => ColorConversion::LinearToSrgb,
+            _ => ColorConversion::None,
+        }
+    }
+}
+
+/// A transfer function conversion applied by a shader, passed to the shaders of built-in passes as
+/// a push constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ColorConversion {
+    None = 0,
+    SrgbToLinear = 1,
+    LinearToSrgb = 2,
+}
+
+impl ColorConversion {
+    /// Applies the conversion to the color channels of `color` on the CPU, e.g. for clear colors.
+    pub fn apply(self, color: [f32; 4]) -> [f32; 4] {
+        let convert = |c: f32| match self {
+            ColorConversion::None => c,
+            ColorConversion::SrgbToLinear => {
+                if c <= 0.04045 {
+                    c / 12.92
+                } else {
+                    ((c + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            ColorConversion::LinearToSrgb => {
+                if c <= 0.0031308 {
+                    c * 12.92
+                } else {
+                    1.055 * c.powf(1.0 / 2.4) - 0.055
+                }
+            }
+        };
+        [
+            convert(color[0]),
+            convert(color[1]),
+            convert(color[2]),
+            color[3],
+        ]
+    }
+}
+
+/// Whether images of `format` are sRGB encoded and converted to and from linear by the hardware.
+pub fn is_srgb_format(format: Format) -> bool {
+    format.numeric_format_color() == Some(NumericFormat::SRGB)
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use bevy::prelude::Resource;
+use vulkano::{
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{sampler::Filter, view::ImageView},
+    sync::GpuFuture,
+};
+
+use crate::post_process::{FullscreenPass, WorkingColorSpace};
+
+/// User configurable display adjustments applied by [`DisplayAdjustmentsPass`], e.g. from a
+/// settings menu.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct DisplayAdjustments {
+    /// Gamma applied as `color^(1 / gamma)`. `1.0` leaves colors unchanged.
+    pub gamma: f32,
+    /// Added to colors after gamma. `0.0` leaves colors unchanged.
+    pub brightness: f32,
+    /// Scales colors around mid gray. `1.0` leaves colors unchanged.
+    pub contrast: f32,
+}
+
+impl Default for DisplayAdjustments {
+    fn default() -> Self {
+        DisplayAdjustments {
+            gamma: 1.0,
+            brightness: 0.0,
+            contrast: 1.0,
+        }
+    }
+}
+
+impl DisplayAdjustments {
+    /// Whether the adjustments leave colors unchanged.
+    pub fn is_identity(&self) -> bool {
+        *self == DisplayAdjustments::default()
+    }
+}
+
+/// Final pass applying [`DisplayAdjustments`] while copying an image onto a target, typically the
+/// swapchain image. The adjustments apply to colors in the [`WorkingColorSpace`], converted from
+/// the input and to the target format at the boundaries.
+pub struct DisplayAdjustmentsPass {
+    pass: FullscreenPass,
+    working_color_space: WorkingColorSpace,
+}
+
+impl DisplayAdjustmentsPass {
+    pub fn new(queue: Arc<Queue>, output_format: Format) -> DisplayAdjustmentsPass {
+        let fs = display_adjustments_fs::load(queue.device().clone())
+            .expect("failed to create shader module")
+            .entry_point("main")
+            .expect("shader entry point not found");
+        DisplayAdjustmentsPass {
+            pass: FullscreenPass::new(queue, fs, output_format, Filter::Linear),
+            working_color_space: WorkingColorSpace::default(),
+        }
+    }
+
+    pub fn set_working_color_space(&mut self, working_color_space: WorkingColorSpace) {
+        self.working_color_space = working_color_space;
+    }
+
+    pub fn working_color_space(&self) -> WorkingColorSpace {
+        self.working_color_space
+    }
+
+    /// Draws `input` over `target` with the adjustments applied.
+    pub fn render(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        input: Arc<ImageView>,
+        target: Arc<ImageView>,
+        adjustments: &DisplayAdjustments,
+    ) -> Box<dyn GpuFuture> {
+        let input_conversion = self.working_color_space.input_conversion(input.format());
+        let output_conversion = self.working_color_space.output_conversion(target.format());
+        self.pass.render(
+            before_future,
+            &[input],
+            target,
+            Some(display_adjustments_fs::PushConstants {
+                gamma: adjustments.gamma.max(0.01),
+                brightness: adjustments.brightness,
+                contrast: adjustments.contrast,
+                input_conversion: input_conversion as u32,
+                output_conversion: output_conversion as u32,
+            }),
+        )
+    }
+}
+
+mod display_adjustments_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec2 v_uv;
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+layout(push_constant) uniform PushConstants {
+    float gamma;
+    float brightness;
+    float contrast;
+    uint input_conversion;
+    uint output_conversion;
+} push_constants;
+
+vec3 srgb_to_linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), greaterThan(c, vec3(0.04045)));
+}
+
+vec3 linear_to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, greaterThan(c, vec3(0.0031308)));
+}
+
+vec3 convert(vec3 c, uint conversion) {
+    c = max(c, vec3(0.0));
+    if (conversion == 1) {
+        return srgb_to_linear(c);
+    } else if (conversion == 2) {
+        return linear_to_srgb(c);
+    }
+    return c;
+}
+
+void main() {
+    vec4 color = texture(tex, v_uv);
+    vec3 rgb = convert(color.rgb, push_constants.input_conversion);
+    rgb = pow(max(rgb, vec3(0.0)), vec3(1.0 / push_constants.gamma));
+    rgb = (rgb - 0.5) * push_constants.contrast + 0.5 + push_constants.brightness;
+    f_color = vec4(convert(clamp(rgb, 0.0, 1.0), push_constants.output_conversion), color.a);
+}
+"
+    }
+}
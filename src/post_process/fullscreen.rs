@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::BufferContents,
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        RenderPassBeginInfo, SubpassBeginInfo, SubpassContents,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{
+        sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
+        view::ImageView,
+    },
+    pipeline::{
+        graphics::{
+            color_blend::{ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::VertexInputState,
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    render_pass::{RenderPass, Subpass},
+    shader::EntryPoint,
+    sync::GpuFuture,
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk, CommandStats, FramebufferCache};
+
+/// A pass drawing a fullscreen triangle with a user fragment shader over a target image. This is
+/// the building block of the crate's post process effects.
+///
+/// The fragment shader receives `layout(location = 0) in vec2 v_uv;` and the input images as
+/// combined image samplers in set 0, bindings `0..n`, in the order given to
+/// [`FullscreenPass::render`].
+pub struct FullscreenPass {
+    queue: Arc<Queue>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    render_pass: Arc<RenderPass>,
+    framebuffers: FramebufferCache,
+    pipeline: Arc<GraphicsPipeline>,
+    sampler: Arc<Sampler>,
+    clear_color: [f32; 4],
+}
+
+impl FullscreenPass {
+    /// Creates a pass rendering with `fragment_shader` into targets of `output_format`. Input
+    /// images are sampled with `filter` and clamped to edge.
+    pub fn new(
+        queue: Arc<Queue>,
+        fragment_shader: EntryPoint,
+        output_format: Format,
+        filter: Filter,
+    ) -> FullscreenPass {
+        let device = queue.device().clone();
+        let render_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    format: output_format,
+                    samples: 1,
+                    load_op: Clear,
+                    store_op: Store,
+                }
+            },
+            pass: {
+                    color: [color],
+                    depth_stencil: {}
+            }
+        )
+        .unwrap();
+        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+
+        let pipeline = {
+            let vs = fullscreen_vs::load(device.clone())
+                .expect("failed to create shader module")
+                .entry_point("main")
+                .expect("shader entry point not found");
+            let stages = [
+                PipelineShaderStageCreateInfo::new(vs),
+                PipelineShaderStageCreateInfo::new(fragment_shader),
+            ];
+            let layout = PipelineLayout::new(
+                device.clone(),
+                PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+                    .into_pipeline_layout_create_info(device.clone())
+                    .unwrap(),
+            )
+            .unwrap();
+
+            GraphicsPipeline::new(device.clone(), None, GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(VertexInputState::default()),
+                input_assembly_state: Some(InputAssemblyState::default()),
+                viewport_state: Some(ViewportState::default()),
+                rasterization_state: Some(RasterizationState::default()),
+                multisample_state: Some(MultisampleState::default()),
+                color_blend_state: Some(ColorBlendState::with_attachment_states(
+                    subpass.num_color_attachments(),
+                    ColorBlendAttachmentState::default(),
+                )),
+                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                subpass: Some(subpass.into()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            })
+            .unwrap()
+        };
+
+        let sampler = Sampler::new(device.clone(), SamplerCreateInfo {
+            mag_filter: filter,
+            min_filter: filter,
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..Default::default()
+        })
+        .unwrap();
+
+        FullscreenPass {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
+                device,
+                Default::default(),
+            ),
+            queue,
+            render_pass,
+            framebuffers: FramebufferCache::new(),
+            pipeline,
+            sampler,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    /// Queue the pass executes on.
+    pub fn queue(&self) -> Arc<Queue> {
+        self.queue.clone()
+    }
+
+    /// Format of the targets this pass renders to.
+    pub fn output_format(&self) -> Format {
+        self.render_pass.attachments()[0].format
+    }
+
+    /// Color the target is cleared with before drawing. Visible outside of the viewport when
+    /// rendering with [`FullscreenPass::render_viewport`].
+    pub fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        self.clear_color = clear_color;
+    }
+
+    /// Draws over the whole `target`.
+    pub fn render<P: BufferContents>(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        inputs: &[Arc<ImageView>],
+        target: Arc<ImageView>,
+        push_constants: Option<P>,
+    ) -> Box<dyn GpuFuture> {
+        let extent = target.image().extent();
+        let viewport = Viewport {
+            offset: [0.0, 0.0],
+            extent: [extent[0] as f32, extent[1] as f32],
+            depth_range: 0.0..=1.0,
+        };
+        self.render_viewport(before_future, inputs, target, viewport, push_constants)
+    }
+
+    /// Draws into `viewport` of `target`, clearing the rest of the target with the clear color.
+    pub fn render_viewport<P: BufferContents>(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        inputs: &[Arc<ImageView>],
+        target: Arc<ImageView>,
+        viewport: Viewport,
+        push_constants: Option<P>,
+    ) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::fullscreen_pass");
+        let extent = target.image().extent();
+        let framebuffer = self.framebuffers.get(&self.render_pass, &[target]);
+        let layout = self.pipeline.layout().clone();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![Some(self.clear_color.into())],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassBeginInfo {
+                    contents: SubpassContents::Inline,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .set_viewport(0, [viewport].into_iter().collect())
+            .unwrap()
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .unwrap();
+        if !inputs.is_empty() {
+            let set = PersistentDescriptorSet::new(
+                &self.descriptor_set_allocator,
+                layout.set_layouts()[0].clone(),
+                inputs.iter().enumerate().map(|(binding, view)| {
+                    WriteDescriptorSet::image_view_sampler(
+                        binding as u32,
+                        view.clone(),
+                        self.sampler.clone(),
+                    )
+                }),
+                [],
+            )
+            .unwrap();
+            builder
+                .bind_descriptor_sets(PipelineBindPoint::Graphics, layout.clone(), 0, set)
+                .unwrap();
+        }
+        if let Some(push_constants) = push_constants {
+            builder.push_constants(layout, 0, push_constants).unwrap();
+        }
+        builder
+            .draw(3, 1, 0, 0)
+            .unwrap()
+            .end_render_pass(Default::default())
+            .unwrap();
+        CommandStats::record_pipeline_bind();
+        CommandStats::record_draws(1);
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit fullscreen pass to {:?} {:?} with {} inputs on queue family {}",
+            extent,
+            self.output_format(),
+            inputs.len(),
+            self.queue.queue_family_index()
+        );
+        before_future
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
+
+mod fullscreen_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+layout(location = 0) out vec2 v_uv;
+
+void main() {
+    // Fullscreen triangle covering the viewport
+    v_uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(v_uv * 2.0 - 1.0, 0.0, 1.0);
+}
+"
+    }
+}
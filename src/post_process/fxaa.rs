@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use bevy::prelude::{Component, Resource};
+use vulkano::{
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{sampler::Filter, view::ImageView},
+    sync::GpuFuture,
+};
+
+use crate::post_process::{FullscreenPass, WorkingColorSpace};
+
+/// FXAA configuration for [`FxaaPass`], e.g. from a settings menu. The resource applies to all
+/// windows; add it as a component to a window entity to override it for that window.
+///
+/// Combined with rendering at a lower resolution and scaling up with
+/// [`ScaledBlitPass`](crate::ScaledBlitPass), this gives a quality and performance trade-off for
+/// pipelines without MSAA.
+#[derive(Resource, Component, Debug, Clone, Copy, PartialEq)]
+pub struct FxaaSettings {
+    /// Whether edges are smoothed. When disabled, the pass only copies its input.
+    pub enabled: bool,
+    /// Local contrast, relative to the brightest neighbor, needed for a pixel to be treated as an
+    /// edge. Lower values smooth more edges at the cost of blurring detail.
+    pub edge_threshold: f32,
+    /// Contrast below which dark pixels are never treated as an edge.
+    pub edge_threshold_min: f32,
+    /// Maximum distance in pixels searched along an edge.
+    pub span_max: f32,
+}
+
+impl Default for FxaaSettings {
+    fn default() -> Self {
+        FxaaSettings {
+            enabled: true,
+            edge_threshold: 0.125,
+            edge_threshold_min: 0.0312,
+            span_max: 8.0,
+        }
+    }
+}
+
+/// Post process pass applying FXAA while copying an image onto a target. Edge detection uses
+/// perceptual luma, with colors converted between the input, the [`WorkingColorSpace`] and the
+/// target format like other built-in passes.
+///
+/// ```ignore
+/// let settings = window_settings.get(window).unwrap_or(&fxaa_settings);
+/// let after = fxaa.render(before, scene_view, renderer.swapchain_image_view(), settings);
+/// ```
+pub struct FxaaPass {
+    pass: FullscreenPass,
+    working_color_space: WorkingColorSpace,
+}
+
+impl FxaaPass {
+    pub fn new(queue: Arc<Queue>, output_format: Format) -> FxaaPass {
+        let fs = fxaa_fs::load(queue.device().clone())
+            .expect("failed to create shader module")
+            .entry_point("main")
+            .expect("shader entry point not found");
+        FxaaPass {
+            pass: FullscreenPass::new(queue, fs, output_format, Filter::Linear),
+            working_color_space: WorkingColorSpace::default(),
+        }
+    }
+
+    pub fn set_working_color_space(&mut self, working_color_space: WorkingColorSpace) {
+        self.working_color_space = working_color_space;
+    }
+
+    pub fn working_color_space(&self) -> WorkingColorSpace {
+        self.working_color_space
+    }
+
+    /// Draws `input` over `target`, smoothing edges if enabled in `settings`.
+    pub fn render(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        input: Arc<ImageView>,
+        target: Arc<ImageView>,
+        settings: &FxaaSettings,
+    ) -> Box<dyn GpuFuture> {
+        let extent = input.image().extent();
+        let input_conversion = self.working_color_space.input_conversion(input.format());
+        let output_conversion = self.working_color_space.output_conversion(target.format());
+        self.pass.render(
+            before_future,
+            &[input],
+            target,
+            Some(fxaa_fs::PushConstants {
+                texel_size: [1.0 / extent[0] as f32, 1.0 / extent[1] as f32],
+                edge_threshold: settings.edge_threshold,
+                edge_threshold_min: settings.edge_threshold_min,
+                span_max: settings.span_max.max(1.0),
+                enabled: settings.enabled as u32,
+                linear_working_space: (self.working_color_space == WorkingColorSpace::Linear)
+                    as u32,
+                input_conversion: input_conversion as u32,
+                output_conversion: output_conversion as u32,
+            }),
+        )
+    }
+}
+
+mod fxaa_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec2 v_uv;
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+layout(push_constant) uniform PushConstants {
+    vec2 texel_size;
+    float edge_threshold;
+    float edge_threshold_min;
+    float span_max;
+    uint enabled;
+    uint linear_working_space;
+    uint input_conversion;
+    uint output_conversion;
+} push_constants;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+
+vec3 srgb_to_linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), greaterThan(c, vec3(0.04045)));
+}
+
+vec3 linear_to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, greaterThan(c, vec3(0.0031308)));
+}
+
+vec3 convert(vec3 c, uint conversion) {
+    c = max(c, vec3(0.0));
+    if (conversion == 1) {
+        return srgb_to_linear(c);
+    } else if (conversion == 2) {
+        return linear_to_srgb(c);
+    }
+    return c;
+}
+
+vec3 sample_color(vec2 uv) {
+    return convert(texture(tex, uv).rgb, push_constants.input_conversion);
+}
+
+// Perceptual luma, approximating sRGB encoding of linear colors with a square root
+float luma(vec3 c) {
+    float l = dot(c, vec3(0.299, 0.587, 0.114));
+    return push_constants.linear_working_space == 1 ? sqrt(l) : l;
+}
+
+vec3 fxaa(vec3 rgb_m) {
+    vec2 t = push_constants.texel_size;
+    float luma_nw = luma(sample_color(v_uv + vec2(-1.0, -1.0) * t));
+    float luma_ne = luma(sample_color(v_uv + vec2(1.0, -1.0) * t));
+    float luma_sw = luma(sample_color(v_uv + vec2(-1.0, 1.0) * t));
+    float luma_se = luma(sample_color(v_uv + vec2(1.0, 1.0) * t));
+    float luma_m = luma(rgb_m);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+    if (luma_max - luma_min < max(push_constants.edge_threshold_min, luma_max * push_constants.edge_threshold)) {
+        return rgb_m;
+    }
+
+    vec2 dir = vec2(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se)
+    );
+    float dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * rcp_dir_min, vec2(-push_constants.span_max), vec2(push_constants.span_max)) * t;
+
+    vec3 rgb_a = 0.5 * (
+        sample_color(v_uv + dir * (1.0 / 3.0 - 0.5)) +
+        sample_color(v_uv + dir * (2.0 / 3.0 - 0.5))
+    );
+    vec3 rgb_b = rgb_a * 0.5 + 0.25 * (
+        sample_color(v_uv + dir * -0.5) +
+        sample_color(v_uv + dir * 0.5)
+    );
+    float luma_b = luma(rgb_b);
+    return (luma_b < luma_min || luma_b > luma_max) ? rgb_a : rgb_b;
+}
+
+void main() {
+    vec4 center = texture(tex, v_uv);
+    vec3 rgb = convert(center.rgb, push_constants.input_conversion);
+    if (push_constants.enabled == 1) {
+        rgb = fxaa(rgb);
+    }
+    f_color = vec4(convert(rgb, push_constants.output_conversion), center.a);
+}
+"
+    }
+}
@@ -0,0 +1,47 @@
+mod bloom;
+mod blur_pyramid;
+mod color_space;
+mod display_adjustments;
+mod fullscreen;
+mod fxaa;
+#[cfg(feature = "retro_filters")]
+mod retro;
+mod scaling;
+
+use bevy::{
+    app::{App, Plugin, PreUpdate},
+    prelude::IntoSystemConfigs,
+};
+pub use bloom::*;
+pub use blur_pyramid::*;
+pub use color_space::*;
+pub use display_adjustments::*;
+pub use fullscreen::*;
+pub use fxaa::*;
+#[cfg(feature = "retro_filters")]
+pub use retro::*;
+pub use scaling::*;
+
+/// Registers the [`DisplayAdjustments`], [`BloomSettings`], [`FxaaSettings`],
+/// [`LetterboxSettings`] and [`WorkingColorSpace`] resources, and maintains the [`SafeArea`] of
+/// windows. Added by [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin) with feature
+/// `post_process`. With feature `retro_filters`, also registers [`CrtSettings`] and
+/// [`PaletteSettings`].
+pub struct VulkanoPostProcessPlugin;
+
+impl Plugin for VulkanoPostProcessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DisplayAdjustments>()
+            .init_resource::<BloomSettings>()
+            .init_resource::<FxaaSettings>()
+            .init_resource::<LetterboxSettings>()
+            .init_resource::<WorkingColorSpace>()
+            .add_systems(
+                PreUpdate,
+                scaling::update_safe_areas.after(crate::system::update_render_extents),
+            );
+        #[cfg(feature = "retro_filters")]
+        app.init_resource::<CrtSettings>()
+            .init_resource::<PaletteSettings>();
+    }
+}
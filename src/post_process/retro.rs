@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use bevy::prelude::Resource;
+use vulkano::{
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{sampler::Filter, view::ImageView},
+    sync::GpuFuture,
+};
+
+use crate::post_process::{is_srgb_format, ColorConversion, FullscreenPass, WorkingColorSpace};
+
+/// CRT emulation parameters of [`RetroPass`]. All effects are off at their zero value, which is
+/// the default.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct CrtSettings {
+    /// Darkening of every other output pixel row, `0.0..=1.0`.
+    pub scanlines: f32,
+    /// Barrel distortion of the screen, around `0.1` for a typical tube. Outside of the curved
+    /// screen is black.
+    pub curvature: f32,
+    /// Darkening towards the corners, `0.0..=1.0`.
+    pub vignette: f32,
+}
+
+/// Palette reduction parameters of [`RetroPass`], for a limited color depth look.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct PaletteSettings {
+    /// Number of levels per color channel, e.g. 4 for a 64 color palette. `0` disables
+    /// quantization.
+    pub levels: u32,
+    /// Strength of the 4x4 ordered dither applied before quantization, `0.0..=1.0`. Dithering is
+    /// aligned to input pixels, so it stays stable under integer scaling.
+    pub dither: f32,
+}
+
+/// Post process pass with retro filters for pixel art and emulator style presentation: CRT
+/// curvature, scanlines and vignette configured by [`CrtSettings`], and palette quantization with
+/// ordered dithering configured by [`PaletteSettings`].
+///
+/// Quantization happens on sRGB encoded values so palette steps are perceptually even. Like other
+/// built-in passes, colors are converted between the input, the [`WorkingColorSpace`] and the
+/// target format.
+///
+/// ```ignore
+/// let after = retro.render(before, pixel_art_view, renderer.swapchain_image_view(), &crt, &palette);
+/// ```
+pub struct RetroPass {
+    pass: FullscreenPass,
+    working_color_space: WorkingColorSpace,
+}
+
+impl RetroPass {
+    pub fn new(queue: Arc<Queue>, output_format: Format) -> RetroPass {
+        let fs = retro_fs::load(queue.device().clone())
+            .expect("failed to create shader module")
+            .entry_point("main")
+            .expect("shader entry point not found");
+        RetroPass {
+            pass: FullscreenPass::new(queue, fs, output_format, Filter::Nearest),
+            working_color_space: WorkingColorSpace::default(),
+        }
+    }
+
+    pub fn set_working_color_space(&mut self, working_color_space: WorkingColorSpace) {
+        self.working_color_space = working_color_space;
+    }
+
+    pub fn working_color_space(&self) -> WorkingColorSpace {
+        self.working_color_space
+    }
+
+    /// Draws `input` over `target` with the filters applied.
+    pub fn render(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        input: Arc<ImageView>,
+        target: Arc<ImageView>,
+        crt: &CrtSettings,
+        palette: &PaletteSettings,
+    ) -> Box<dyn GpuFuture> {
+        let input_extent = input.image().extent();
+        let output_extent = target.image().extent();
+        // The shader works on linear values
+        let decode = match self.working_color_space {
+            WorkingColorSpace::SrgbEncoded if !is_srgb_format(input.format()) => {
+                ColorConversion::SrgbToLinear
+            }
+            _ => ColorConversion::None,
+        };
+        let output_conversion = WorkingColorSpace::Linear.output_conversion(target.format());
+        self.pass.render(
+            before_future,
+            &[input],
+            target,
+            Some(retro_fs::PushConstants {
+                input_size: [input_extent[0] as f32, input_extent[1] as f32],
+                output_size: [output_extent[0] as f32, output_extent[1] as f32],
+                scanlines: crt.scanlines.clamp(0.0, 1.0),
+                curvature: crt.curvature.max(0.0),
+                vignette: crt.vignette.clamp(0.0, 1.0),
+                dither: palette.dither.clamp(0.0, 1.0),
+                levels: palette.levels,
+                input_conversion: decode as u32,
+                output_conversion: output_conversion as u32,
+            }),
+        )
+    }
+}
+
+mod retro_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec2 v_uv;
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+layout(push_constant) uniform PushConstants {
+    vec2 input_size;
+    vec2 output_size;
+    float scanlines;
+    float curvature;
+    float vignette;
+    float dither;
+    uint levels;
+    uint input_conversion;
+    uint output_conversion;
+} push_constants;
+
+const float BAYER[16] = float[](
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0
+);
+
+vec3 srgb_to_linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), greaterThan(c, vec3(0.04045)));
+}
+
+vec3 linear_to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, greaterThan(c, vec3(0.0031308)));
+}
+
+void main() {
+    vec2 uv = v_uv;
+    if (push_constants.curvature > 0.0) {
+        vec2 centered = uv * 2.0 - 1.0;
+        centered *= 1.0 + push_constants.curvature * dot(centered, centered) * 0.25;
+        uv = centered * 0.5 + 0.5;
+        if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+            f_color = vec4(0.0, 0.0, 0.0, 1.0);
+            return;
+        }
+    }
+
+    vec4 color = texture(tex, uv);
+    vec3 rgb = max(color.rgb, vec3(0.0));
+    if (push_constants.input_conversion == 1) {
+        rgb = srgb_to_linear(rgb);
+    }
+
+    if (push_constants.levels > 0) {
+        float steps = float(max(push_constants.levels, 2) - 1);
+        ivec2 pixel = ivec2(uv * push_constants.input_size);
+        float threshold = BAYER[(pixel.y % 4) * 4 + pixel.x % 4] / 16.0 - 0.5;
+        vec3 encoded = linear_to_srgb(clamp(rgb, 0.0, 1.0));
+        encoded += threshold * push_constants.dither / steps;
+        encoded = round(clamp(encoded, 0.0, 1.0) * steps) / steps;
+        rgb = srgb_to_linear(encoded);
+    }
+
+    if (push_constants.scanlines > 0.0) {
+        float row = uv.y * push_constants.output_size.y;
+        rgb *= 1.0 - push_constants.scanlines * step(1.0, mod(row, 2.0));
+    }
+
+    if (push_constants.vignette > 0.0) {
+        vec2 centered = uv * 2.0 - 1.0;
+        rgb *= 1.0 - push_constants.vignette * dot(centered, centered) * 0.5;
+    }
+
+    if (push_constants.output_conversion == 2) {
+        rgb = linear_to_srgb(max(rgb, vec3(0.0)));
+    }
+    f_color = vec4(rgb, color.a);
+}
+"
+    }
+}
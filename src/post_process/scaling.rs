@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use bevy::{
+    math::{Rect, Vec2},
+    prelude::{Commands, Component, Entity, Query, Res, Resource},
+};
+use vulkano::{
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{sampler::Filter, view::ImageView},
+    pipeline::graphics::viewport::Viewport,
+    sync::GpuFuture,
+};
+
+use crate::{
+    post_process::{FullscreenPass, WorkingColorSpace},
+    RenderExtent,
+};
+
+/// How a fixed resolution render target is fitted into a window by [`ScaledBlitPass`]. Add it to
+/// a window entity to configure the policy per window.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ScalingPolicy {
+    /// Fill the whole window, ignoring the aspect ratio.
+    #[default]
+    Stretch,
+    /// Scale as large as fits while keeping the aspect ratio, with bars on two sides.
+    Letterbox,
+    /// Scale by the largest whole number which fits, with bars around, so pixel art stays crisp.
+    /// Sources larger than the window are scaled down like [`ScalingPolicy::Letterbox`].
+    IntegerScale,
+}
+
+impl ScalingPolicy {
+    /// Viewport of the scaled `source` extent centered in `target`, in pixels.
+    pub fn viewport(self, source: [u32; 2], target: [u32; 2]) -> Viewport {
+        let source = [source[0].max(1) as f32, source[1].max(1) as f32];
+        let target = [target[0] as f32, target[1] as f32];
+        let fit_scale = (target[0] / source[0]).min(target[1] / source[1]);
+        let extent = match self {
+            ScalingPolicy::Stretch => target,
+            ScalingPolicy::Letterbox => [source[0] * fit_scale, source[1] * fit_scale],
+            ScalingPolicy::IntegerScale => {
+                let scale = if fit_scale >= 1.0 {
+                    fit_scale.floor()
+                } else {
+                    fit_scale
+                };
+                [source[0] * scale, source[1] * scale]
+            }
+        };
+        // Whole pixel offsets keep integer scaled pixels aligned to the window's pixels
+        Viewport {
+            offset: [
+                ((target[0] - extent[0]) / 2.0).floor(),
+                ((target[1] - extent[1]) / 2.0).floor(),
+            ],
+            extent,
+            depth_range: 0.0..=1.0,
+        }
+    }
+
+    /// Filter to sample the source with, nearest for crisp integer scaling.
+    pub fn filter(self) -> Filter {
+        match self {
+            ScalingPolicy::IntegerScale => Filter::Nearest,
+            _ => Filter::Linear,
+        }
+    }
+}
+
+/// Letterbox bars and overscan margins of windows showing scaled content. Pass `bar_color` to
+/// [`ScaledBlitPass::set_clear_color`].
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct LetterboxSettings {
+    /// Color of the bars around content scaled with [`ScalingPolicy::Letterbox`] or
+    /// [`ScalingPolicy::IntegerScale`], in the working color space.
+    pub bar_color: [f32; 4],
+    /// Fraction of the content's width and height on each side which may be cut off by TV
+    /// overscan, e.g. `[0.05, 0.05]` for the usual 90% title safe area. UI should stay within
+    /// [`SafeArea::safe`].
+    pub overscan: [f32; 2],
+}
+
+impl Default for LetterboxSettings {
+    fn default() -> Self {
+        LetterboxSettings {
+            bar_color: [0.0, 0.0, 0.0, 1.0],
+            overscan: [0.0, 0.0],
+        }
+    }
+}
+
+/// Resolution of the render target a window's content is scaled from. Add it to a window entity
+/// rendering with [`ScaledBlitPass`] so its [`SafeArea`] matches the scaled content. Without it,
+/// content fills the window.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentResolution(pub [u32; 2]);
+
+/// Where a window's content is shown, in physical pixels of the window with the origin at the top
+/// left, for laying out UI. Maintained on window entities by the plugin from the window's
+/// [`RenderExtent`], [`ScalingPolicy`], [`ContentResolution`] and the [`LetterboxSettings`].
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct SafeArea {
+    /// The scaled content, excluding letterbox bars.
+    pub content: Rect,
+    /// The content inset by the overscan margins.
+    pub safe: Rect,
+}
+
+/// Updates the [`SafeArea`] of windows after their render extents.
+pub(crate) fn update_safe_areas(
+    mut commands: Commands,
+    settings: Res<LetterboxSettings>,
+    windows: Query<(
+        Entity,
+        &RenderExtent,
+        Option<&ScalingPolicy>,
+        Option<&ContentResolution>,
+        Option<&SafeArea>,
+    )>,
+) {
+    for (window, extent, policy, resolution, safe_area) in windows.iter() {
+        let source = resolution.map_or(extent.physical, |resolution| resolution.0);
+        let viewport = policy
+            .copied()
+            .unwrap_or_default()
+            .viewport(source, extent.physical);
+        let min = Vec2::from(viewport.offset);
+        let size = Vec2::from(viewport.extent);
+        let content = Rect::from_corners(min, min + size);
+        let inset = size * Vec2::from(settings.overscan).clamp(Vec2::ZERO, Vec2::splat(0.5));
+        let new_safe_area = SafeArea {
+            content,
+            safe: Rect::from_corners(content.min + inset, content.max - inset),
+        };
+        if safe_area != Some(&new_safe_area) {
+            commands.entity(window).insert(new_safe_area);
+        }
+    }
+}
+
+/// Final pass copying a fixed resolution render target onto a window's swapchain image according
+/// to a [`ScalingPolicy`], clearing the bars with the clear color. Colors are converted between
+/// the input, the [`WorkingColorSpace`] and the target format, so an `_UNORM` swapchain shows the
+/// same colors as an `_SRGB` one.
+///
+/// ```ignore
+/// let policy = policies.get(window).copied().unwrap_or_default();
+/// let after = scaled_blit.render(before, internal_view, renderer.swapchain_image_view(), policy);
+/// ```
+pub struct ScaledBlitPass {
+    linear: FullscreenPass,
+    nearest: FullscreenPass,
+    clear_color: [f32; 4],
+    working_color_space: WorkingColorSpace,
+}
+
+impl ScaledBlitPass {
+    pub fn new(queue: Arc<Queue>, output_format: Format) -> ScaledBlitPass {
+        let fs = || {
+            scaled_blit_fs::load(queue.device().clone())
+                .expect("failed to create shader module")
+                .entry_point("main")
+                .expect("shader entry point not found")
+        };
+        ScaledBlitPass {
+            linear: FullscreenPass::new(queue.clone(), fs(), output_format, Filter::Linear),
+            nearest: FullscreenPass::new(queue.clone(), fs(), output_format, Filter::Nearest),
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            working_color_space: WorkingColorSpace::default(),
+        }
+    }
+
+    /// Color of the bars around the scaled image, in the working color space.
+    pub fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        self.clear_color = clear_color;
+    }
+
+    pub fn set_working_color_space(&mut self, working_color_space: WorkingColorSpace) {
+        self.working_color_space = working_color_space;
+    }
+
+    pub fn working_color_space(&self) -> WorkingColorSpace {
+        self.working_color_space
+    }
+
+    /// Draws `input` into `target` scaled with `policy`.
+    pub fn render(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        input: Arc<ImageView>,
+        target: Arc<ImageView>,
+        policy: ScalingPolicy,
+    ) -> Box<dyn GpuFuture> {
+        let source = input.image().extent();
+        let extent = target.image().extent();
+        let viewport = policy.viewport([source[0], source[1]], [extent[0], extent[1]]);
+        let input_conversion = self.working_color_space.input_conversion(input.format());
+        let output_conversion = self.working_color_space.output_conversion(target.format());
+        let pass = match policy.filter() {
+            Filter::Nearest => &mut self.nearest,
+            _ => &mut self.linear,
+        };
+        // Clear values of sRGB targets are encoded by the hardware like shader outputs
+        pass.set_clear_color(output_conversion.apply(self.clear_color));
+        pass.render_viewport(
+            before_future,
+            &[input],
+            target,
+            viewport,
+            Some(scaled_blit_fs::PushConstants {
+                input_conversion: input_conversion as u32,
+                output_conversion: output_conversion as u32,
+            }),
+        )
+    }
+}
+
+mod scaled_blit_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec2 v_uv;
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+layout(push_constant) uniform PushConstants {
+    uint input_conversion;
+    uint output_conversion;
+} push_constants;
+
+vec3 srgb_to_linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), greaterThan(c, vec3(0.04045)));
+}
+
+vec3 linear_to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, greaterThan(c, vec3(0.0031308)));
+}
+
+vec3 convert(vec3 c, uint conversion) {
+    c = max(c, vec3(0.0));
+    if (conversion == 1) {
+        return srgb_to_linear(c);
+    } else if (conversion == 2) {
+        return linear_to_srgb(c);
+    }
+    return c;
+}
+
+void main() {
+    vec4 color = texture(tex, v_uv);
+    vec3 rgb = convert(color.rgb, push_constants.input_conversion);
+    f_color = vec4(convert(rgb, push_constants.output_conversion), color.a);
+}
+"
+    }
+}
@@ -0,0 +1,13 @@
+//! The commonly used types, for a quick start with `use bevy_vulkano::prelude::*;`.
+
+#[cfg(feature = "gui")]
+pub use crate::egui_winit_vulkano::{egui, Gui};
+#[cfg(feature = "windowing")]
+pub use crate::FocusedWindow;
+pub use crate::{
+    BevyVulkanoContext, BevyVulkanoSettings, BevyVulkanoWindows, ComputePass, FramePresented,
+    MainRenderWindow, NoGui, RenderExtent, SwapchainInfo, VulkanoWindowConfig,
+    VulkanoWindowRenderer, VulkanoWinitPlugin, WindowRendererReady,
+};
+#[cfg(feature = "post_process")]
+pub use crate::{FullscreenPass, ScaledBlitPass, ScalingPolicy};
@@ -0,0 +1,319 @@
+use std::time::{Duration, Instant};
+
+use bevy::{
+    log::{info, warn},
+    prelude::{Entity, Event, EventReader, EventWriter, NonSendMut, ResMut, Resource},
+};
+#[cfg(feature = "gui")]
+use egui_winit_vulkano::egui;
+use vulkano::swapchain::PresentMode;
+
+use crate::{BevyVulkanoWindows, FramePresented};
+
+/// Statistics of a present mode measured by the [`PresentModeBenchmark`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PresentModeStats {
+    pub present_mode: PresentMode,
+    /// Frames presented after the warmup.
+    pub frames: u32,
+    /// Presented frames per second.
+    pub frames_per_second: f32,
+    /// Mean time between presents.
+    pub mean_frame_time: Duration,
+    /// 95th percentile of the time between presents, showing stutter.
+    pub p95_frame_time: Duration,
+    /// Mean CPU time from acquire to present.
+    pub mean_cpu_time: Duration,
+    /// Mean time blocked acquiring the next image. High waits mean frames queue up behind the
+    /// display, adding latency.
+    pub mean_acquire_wait: Duration,
+    /// Frames acquired from a suboptimal swapchain.
+    pub suboptimal_frames: u32,
+}
+
+/// Results of a [`PresentModeBenchmark`] run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresentModeBenchResults {
+    pub window: Entity,
+    /// Statistics of each supported present mode, in the order they were measured.
+    pub modes: Vec<PresentModeStats>,
+}
+
+impl PresentModeBenchResults {
+    /// The mode with the lowest mean acquire wait plus CPU time, e.g. for competitive games.
+    pub fn lowest_latency(&self) -> Option<&PresentModeStats> {
+        self.modes
+            .iter()
+            .min_by_key(|stats| stats.mean_acquire_wait + stats.mean_cpu_time)
+    }
+
+    /// The mode presenting the most frames per second.
+    pub fn highest_throughput(&self) -> Option<&PresentModeStats> {
+        self.modes
+            .iter()
+            .max_by(|a, b| a.frames_per_second.total_cmp(&b.frames_per_second))
+    }
+}
+
+/// Sent when a [`PresentModeBenchmark`] run finished.
+#[derive(Event, Debug, Clone)]
+pub struct PresentModeBenchFinished {
+    pub results: PresentModeBenchResults,
+}
+
+struct BenchRun {
+    window: Entity,
+    modes: Vec<PresentMode>,
+    current: usize,
+    original: PresentMode,
+    mode_started: Instant,
+    last_present: Option<Instant>,
+    frame_times: Vec<Duration>,
+    cpu_time: Duration,
+    acquire_wait: Duration,
+    suboptimal_frames: u32,
+    stats: Vec<PresentModeStats>,
+}
+
+impl BenchRun {
+    fn begin_mode(&mut self, now: Instant) {
+        self.mode_started = now;
+        self.last_present = None;
+        self.frame_times.clear();
+        self.cpu_time = Duration::ZERO;
+        self.acquire_wait = Duration::ZERO;
+        self.suboptimal_frames = 0;
+    }
+
+    fn finish_mode(&mut self) {
+        let frames = self.frame_times.len() as u32;
+        let total = self.frame_times.iter().sum::<Duration>();
+        let mut sorted = self.frame_times.clone();
+        sorted.sort();
+        let p95 = sorted
+            .get(((sorted.len() as f32 * 0.95) as usize).min(sorted.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or_default();
+        let divisor = frames.max(1);
+        self.stats.push(PresentModeStats {
+            present_mode: self.modes[self.current],
+            frames,
+            frames_per_second: if total.is_zero() {
+                0.0
+            } else {
+                frames as f32 / total.as_secs_f32()
+            },
+            mean_frame_time: total / divisor,
+            p95_frame_time: p95,
+            mean_cpu_time: self.cpu_time / divisor,
+            mean_acquire_wait: self.acquire_wait / divisor,
+            suboptimal_frames: self.suboptimal_frames,
+        });
+    }
+}
+
+/// Measures each present mode supported by a window for a few seconds, to help choosing the
+/// default present mode of a platform. Start a run with [`PresentModeBenchmark::start`]; the
+/// window's present mode is switched while it runs and restored afterwards. Results are available
+/// with [`PresentModeBenchmark::results`] and sent as a [`PresentModeBenchFinished`] event. Keep
+/// rendering a representative workload while the benchmark runs.
+///
+/// ```ignore
+/// fn bench_on_key(keys: Res<Input<KeyCode>>, main: Query<Entity, With<MainRenderWindow>>, mut bench: ResMut<PresentModeBenchmark>) {
+///     if keys.just_pressed(KeyCode::F9) {
+///         bench.start(main.single());
+///     }
+/// }
+/// ```
+#[derive(Resource)]
+pub struct PresentModeBenchmark {
+    /// Time each present mode is measured for.
+    pub duration_per_mode: Duration,
+    /// Time after switching the mode before measuring, excluding the swapchain recreation.
+    pub warmup: Duration,
+    requested: Option<Entity>,
+    run: Option<BenchRun>,
+    results: Option<PresentModeBenchResults>,
+}
+
+impl Default for PresentModeBenchmark {
+    fn default() -> Self {
+        PresentModeBenchmark {
+            duration_per_mode: Duration::from_secs(3),
+            warmup: Duration::from_millis(500),
+            requested: None,
+            run: None,
+            results: None,
+        }
+    }
+}
+
+impl PresentModeBenchmark {
+    /// Starts benchmarking the present modes of `window` at the end of the frame. Ignored while a
+    /// run is in progress.
+    pub fn start(&mut self, window: Entity) {
+        if !self.is_running() {
+            self.requested = Some(window);
+        }
+    }
+
+    /// Whether a run was started and hasn't finished.
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.requested.is_some() || self.run.is_some()
+    }
+
+    /// Present mode measured currently.
+    pub fn current_mode(&self) -> Option<PresentMode> {
+        self.run.as_ref().map(|run| run.modes[run.current])
+    }
+
+    /// Results of the last finished run.
+    #[inline]
+    pub fn results(&self) -> Option<&PresentModeBenchResults> {
+        self.results.as_ref()
+    }
+
+    /// Draws a button starting a run for `window` and a table of the last results.
+    #[cfg(feature = "gui")]
+    pub fn ui(&mut self, ui: &mut egui::Ui, window: Entity) {
+        match self.current_mode() {
+            Some(present_mode) => {
+                ui.label(format!("Measuring {present_mode:?}..."));
+            }
+            None => {
+                if ui.button("Benchmark present modes").clicked() {
+                    self.start(window);
+                }
+            }
+        }
+        let Some(results) = &self.results else {
+            return;
+        };
+        let ms = |duration: Duration| duration.as_secs_f32() * 1000.0;
+        egui::Grid::new("present_mode_bench")
+            .striped(true)
+            .show(ui, |ui| {
+                for header in ["mode", "fps", "frame ms", "p95 ms", "cpu ms", "acquire ms"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for stats in &results.modes {
+                    ui.label(format!("{:?}", stats.present_mode));
+                    ui.label(format!("{:.1}", stats.frames_per_second));
+                    ui.label(format!("{:.2}", ms(stats.mean_frame_time)));
+                    ui.label(format!("{:.2}", ms(stats.p95_frame_time)));
+                    ui.label(format!("{:.2}", ms(stats.mean_cpu_time)));
+                    ui.label(format!("{:.2}", ms(stats.mean_acquire_wait)));
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+/// Switches present modes and records the [`FramePresented`] statistics of a running
+/// [`PresentModeBenchmark`].
+pub(crate) fn run_present_mode_benchmark(
+    mut benchmark: ResMut<PresentModeBenchmark>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    mut presented_events: EventReader<FramePresented>,
+    mut finished_events: EventWriter<PresentModeBenchFinished>,
+) {
+    let now = Instant::now();
+    let benchmark = &mut *benchmark;
+    if let Some(window) = benchmark.requested.take() {
+        let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(window) else {
+            warn!("Can't benchmark present modes of window {window:?} without a renderer");
+            return;
+        };
+        let renderer = &mut vulkano_window.renderer;
+        let modes = renderer.supported_present_modes().to_vec();
+        if modes.is_empty() {
+            warn!("Window {window:?} reports no supported present modes to benchmark");
+            return;
+        }
+        info!("Benchmarking present modes {modes:?} of window {window:?}");
+        let mut run = BenchRun {
+            window,
+            modes,
+            current: 0,
+            original: renderer.present_mode(),
+            mode_started: now,
+            last_present: None,
+            frame_times: vec![],
+            cpu_time: Duration::ZERO,
+            acquire_wait: Duration::ZERO,
+            suboptimal_frames: 0,
+            stats: vec![],
+        };
+        run.begin_mode(now);
+        renderer.set_present_mode(run.modes[0]);
+        benchmark.run = Some(run);
+        return;
+    }
+    let Some(run) = &mut benchmark.run else {
+        return;
+    };
+
+    let measuring = now.duration_since(run.mode_started) >= benchmark.warmup;
+    for presented in presented_events.read() {
+        if presented.window != run.window {
+            continue;
+        }
+        let last_present = run.last_present.replace(presented.presented_at);
+        if !measuring {
+            continue;
+        }
+        if let Some(last_present) = last_present {
+            run.frame_times.push(
+                presented
+                    .presented_at
+                    .saturating_duration_since(last_present),
+            );
+            run.cpu_time += presented.cpu_time;
+            run.acquire_wait += presented.acquire_wait;
+            run.suboptimal_frames += presented.suboptimal as u32;
+        }
+    }
+
+    let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(run.window) else {
+        warn!(
+            "Window {:?} closed during the present mode benchmark",
+            run.window
+        );
+        benchmark.run = None;
+        return;
+    };
+    if now.duration_since(run.mode_started) < benchmark.warmup + benchmark.duration_per_mode {
+        return;
+    }
+    run.finish_mode();
+    run.current += 1;
+    if let Some(present_mode) = run.modes.get(run.current) {
+        run.begin_mode(now);
+        vulkano_window.renderer.set_present_mode(*present_mode);
+        return;
+    }
+
+    vulkano_window.renderer.set_present_mode(run.original);
+    let run = benchmark.run.take().unwrap();
+    for stats in &run.stats {
+        info!(
+            "{:?}: {:.1} fps, frame time {:?} (p95 {:?}), cpu time {:?}, acquire wait {:?}",
+            stats.present_mode,
+            stats.frames_per_second,
+            stats.mean_frame_time,
+            stats.p95_frame_time,
+            stats.mean_cpu_time,
+            stats.mean_acquire_wait
+        );
+    }
+    let results = PresentModeBenchResults {
+        window: run.window,
+        modes: run.stats,
+    };
+    finished_events.send(PresentModeBenchFinished {
+        results: results.clone(),
+    });
+    benchmark.results = Some(results);
+}
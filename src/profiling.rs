@@ -0,0 +1,232 @@
+use std::{sync::Arc, time::Duration};
+
+use bevy::log::warn;
+use vulkano::{
+    command_buffer::AutoCommandBufferBuilder,
+    device::{DeviceOwned, Queue},
+    query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType},
+    sync::PipelineStage,
+};
+#[cfg(feature = "profiling_tracy")]
+use vulkano::{
+    command_buffer::{allocator::StandardCommandBufferAllocator, CommandBufferUsage},
+    sync::{self, GpuFuture},
+};
+
+/// Opens a profiler span for the rest of the scope when the `profiling_tracy` or
+/// `profiling_puffin` feature is enabled. `$name` must be a string literal.
+macro_rules! profile_scope {
+    ($name:literal) => {
+        #[cfg(feature = "profiling_tracy")]
+        let _tracy_span = tracy_client::span!($name);
+        #[cfg(feature = "profiling_puffin")]
+        puffin::profile_scope!($name);
+    };
+}
+
+pub(crate) use profile_scope;
+
+/// Starts the profiler clients of enabled profiling features.
+pub(crate) fn start_profiling() {
+    #[cfg(feature = "profiling_tracy")]
+    tracy_client::Client::start();
+    #[cfg(feature = "profiling_puffin")]
+    puffin::set_scopes_on(true);
+}
+
+/// Marks the end of a frame in enabled profilers. Called by the runner after each app update.
+pub(crate) fn frame_mark() {
+    #[cfg(feature = "profiling_tracy")]
+    if let Some(client) = tracy_client::Client::running() {
+        client.frame_mark();
+    }
+    #[cfg(feature = "profiling_puffin")]
+    puffin::GlobalProfiler::lock().new_frame();
+}
+
+/// Records a timestamp on `queue` and waits for it, so that Tracy can align the GPU timeline of
+/// the profiler's context with the CPU timeline. Returns 0 if the timestamp couldn't be read.
+#[cfg(feature = "profiling_tracy")]
+fn initial_gpu_timestamp(queue: &Arc<Queue>) -> i64 {
+    let device = queue.device();
+    let query_pool = QueryPool::new(device.clone(), QueryPoolCreateInfo {
+        query_count: 1,
+        ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+    })
+    .unwrap();
+    let command_buffer_allocator =
+        StandardCommandBufferAllocator::new(device.clone(), Default::default());
+    let mut builder = AutoCommandBufferBuilder::primary(
+        &command_buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .unwrap();
+    unsafe {
+        builder
+            .reset_query_pool(query_pool.clone(), 0..1)
+            .unwrap()
+            .write_timestamp(query_pool.clone(), 0, PipelineStage::BottomOfPipe)
+            .unwrap();
+    }
+    let command_buffer = builder.build().unwrap();
+    let mut timestamp = [0u64];
+    if let Err(e) = sync::now(device.clone())
+        .then_execute(queue.clone(), command_buffer)
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .and_then(|future| future.wait(None))
+        .and_then(|()| query_pool.get_results(0..1, &mut timestamp, QueryResultFlags::WAIT))
+    {
+        warn!("Failed to read the initial GPU timestamp for Tracy: {e}");
+    }
+    timestamp[0] as i64
+}
+
+/// GPU time of a zone measured by a [`GpuProfiler`].
+#[derive(Debug, Clone)]
+pub struct GpuZone {
+    pub name: &'static str,
+    pub duration: Duration,
+}
+
+/// Measures GPU time of zones within command buffers with timestamp queries.
+///
+/// Record zones with [`GpuProfiler::zone`] after [`GpuProfiler::begin_frame`], and call
+/// [`GpuProfiler::resolve`] once the frame's commands have finished executing, e.g. before
+/// recording the next frame after waiting on its fence. With the `profiling_tracy` feature, zones
+/// are uploaded to Tracy as GPU zones. Otherwise, read them from [`GpuProfiler::zones`].
+pub struct GpuProfiler {
+    query_pool: Arc<QueryPool>,
+    max_zones: u32,
+    /// Nanoseconds per timestamp tick.
+    timestamp_period: f32,
+    /// Names of the zones recorded this frame. Zone `i` uses queries `2 * i` and `2 * i + 1`.
+    recorded: Vec<&'static str>,
+    zones: Vec<GpuZone>,
+    #[cfg(feature = "profiling_tracy")]
+    tracy_context: Option<tracy_client::GpuContext>,
+}
+
+impl GpuProfiler {
+    /// Creates a profiler measuring up to `max_zones` zones per frame on `queue`. Returns `None`
+    /// if the queue doesn't support timestamps.
+    pub fn new(queue: &Arc<Queue>, max_zones: u32) -> Option<GpuProfiler> {
+        let device = queue.device();
+        let family_properties = &device.physical_device().queue_family_properties()
+            [queue.queue_family_index() as usize];
+        if family_properties.timestamp_valid_bits.is_none() {
+            warn!(
+                "Queue family {} doesn't support timestamps, GPU zones are unavailable",
+                queue.queue_family_index()
+            );
+            return None;
+        }
+        let max_zones = max_zones.max(1);
+        let query_pool = QueryPool::new(device.clone(), QueryPoolCreateInfo {
+            query_count: max_zones * 2,
+            ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+        })
+        .unwrap();
+        let timestamp_period = device.physical_device().properties().timestamp_period;
+        Some(GpuProfiler {
+            query_pool,
+            max_zones,
+            timestamp_period,
+            recorded: vec![],
+            zones: vec![],
+            #[cfg(feature = "profiling_tracy")]
+            tracy_context: tracy_client::Client::running().and_then(|client| {
+                client
+                    .new_gpu_context(
+                        Some("bevy_vulkano"),
+                        tracy_client::GpuContextType::Vulkan,
+                        initial_gpu_timestamp(queue),
+                        timestamp_period,
+                    )
+                    .ok()
+            }),
+        })
+    }
+
+    /// Resets the queries for a new frame. Must be recorded outside of a render pass before any
+    /// zone of the frame.
+    pub fn begin_frame<L>(&mut self, builder: &mut AutoCommandBufferBuilder<L>) {
+        self.recorded.clear();
+        unsafe {
+            builder
+                .reset_query_pool(self.query_pool.clone(), 0..self.max_zones * 2)
+                .unwrap();
+        }
+    }
+
+    /// Records `record` into `builder` within a zone named `name`. When the frame's zones are
+    /// exhausted, `record` is recorded without measuring.
+    pub fn zone<L>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        name: &'static str,
+        record: impl FnOnce(&mut AutoCommandBufferBuilder<L>),
+    ) {
+        let zone = self.recorded.len() as u32;
+        if zone >= self.max_zones {
+            record(builder);
+            return;
+        }
+        self.recorded.push(name);
+        unsafe {
+            builder
+                .write_timestamp(self.query_pool.clone(), zone * 2, PipelineStage::TopOfPipe)
+                .unwrap();
+        }
+        record(builder);
+        unsafe {
+            builder
+                .write_timestamp(
+                    self.query_pool.clone(),
+                    zone * 2 + 1,
+                    PipelineStage::BottomOfPipe,
+                )
+                .unwrap();
+        }
+    }
+
+    /// Reads the timestamps of the zones recorded since [`GpuProfiler::begin_frame`], waiting for
+    /// them if they're not available yet.
+    pub fn resolve(&mut self) {
+        self.zones.clear();
+        if self.recorded.is_empty() {
+            return;
+        }
+        let mut timestamps = vec![0u64; self.recorded.len() * 2];
+        if let Err(e) = self.query_pool.get_results(
+            0..timestamps.len() as u32,
+            &mut timestamps,
+            QueryResultFlags::WAIT,
+        ) {
+            warn!("Failed to read GPU timestamps: {e}");
+            return;
+        }
+        for (&name, range) in self.recorded.iter().zip(timestamps.chunks_exact(2)) {
+            let ticks = range[1].saturating_sub(range[0]);
+            self.zones.push(GpuZone {
+                name,
+                duration: Duration::from_nanos(
+                    (ticks as f64 * self.timestamp_period as f64) as u64,
+                ),
+            });
+            #[cfg(feature = "profiling_tracy")]
+            if let Some(context) = &self.tracy_context {
+                if let Ok(mut span) = context.span_alloc(name, "", file!(), line!()) {
+                    span.end_zone();
+                    span.upload_timestamp(range[0] as i64, range[1] as i64);
+                }
+            }
+        }
+    }
+
+    /// Zones resolved by the last [`GpuProfiler::resolve`].
+    pub fn zones(&self) -> &[GpuZone] {
+        &self.zones
+    }
+}
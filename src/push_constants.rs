@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::BufferContents,
+    command_buffer::AutoCommandBufferBuilder,
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorSetLayoutCreateFlags,
+        PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::Device,
+    pipeline::{
+        layout::{PipelineDescriptorSetLayoutCreateInfo, PipelineLayoutCreateInfo},
+        PipelineBindPoint, PipelineLayout, PipelineShaderStageCreateInfo,
+    },
+    ValidationError,
+};
+
+/// Minimum `maxPushConstantsSize` guaranteed by the Vulkan specification.
+pub const GUARANTEED_PUSH_CONSTANTS_SIZE: usize = 128;
+
+/// Statically asserts that a push constant struct fits the size guaranteed on all devices and
+/// that its size is a multiple of 4 as required by `vkCmdPushConstants`.
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(BufferContents)]
+/// struct MyPushConstants {
+///     color: [f32; 4],
+///     step: i32,
+///     _pad: [i32; 3],
+/// }
+/// bevy_vulkano::assert_push_constants!(MyPushConstants);
+/// ```
+#[macro_export]
+macro_rules! assert_push_constants {
+    ($ty:ty) => {
+        const _: () = {
+            assert!(
+                ::std::mem::size_of::<$ty>() <= $crate::GUARANTEED_PUSH_CONSTANTS_SIZE,
+                "push constants exceed the 128 bytes guaranteed by Vulkan"
+            );
+            assert!(
+                ::std::mem::size_of::<$ty>() % 4 == 0,
+                "push constant size must be a multiple of 4"
+            );
+            assert!(
+                ::std::mem::align_of::<$ty>() <= 16,
+                "push constants must not require alignment above 16"
+            );
+        };
+    };
+}
+
+/// Checks that push constants of type `T` at `offset` are covered by the push constant ranges of
+/// `layout`, returning a readable error otherwise.
+pub fn validate_push_constants<T: BufferContents>(
+    layout: &PipelineLayout,
+    offset: u32,
+) -> Result<(), String> {
+    let size = std::mem::size_of::<T>() as u32;
+    let end = offset + size;
+    let covered = layout
+        .push_constant_ranges()
+        .iter()
+        .any(|range| range.offset <= offset && end <= range.offset + range.size);
+    if covered {
+        Ok(())
+    } else {
+        Err(format!(
+            "Push constants `{}` ({} bytes at offset {}) are not covered by the pipeline layout \
+             ranges {:?}",
+            std::any::type_name::<T>(),
+            size,
+            offset,
+            layout
+                .push_constant_ranges()
+                .iter()
+                .map(|range| (range.offset, range.size))
+                .collect::<Vec<_>>()
+        ))
+    }
+}
+
+/// Records push constants after validating them against the layout.
+pub fn push_constants<L, T: BufferContents>(
+    builder: &mut AutoCommandBufferBuilder<L>,
+    layout: Arc<PipelineLayout>,
+    offset: u32,
+    push_constants: T,
+) -> Result<(), Box<ValidationError>> {
+    if let Err(e) = validate_push_constants::<T>(&layout, offset) {
+        return Err(Box::new(ValidationError {
+            problem: e.into(),
+            ..Default::default()
+        }));
+    }
+    builder.push_constants(layout, offset, push_constants)?;
+    Ok(())
+}
+
+/// Whether the device has push descriptors (`khr_push_descriptor`) enabled. Enable the extension
+/// through `vulkano_config.device_extensions` of
+/// [`BevyVulkanoSettings`](crate::BevyVulkanoSettings).
+pub fn supports_push_descriptors(device: &Device) -> bool {
+    device.enabled_extensions().khr_push_descriptor
+}
+
+/// Creates a pipeline layout from the shader stages, turning descriptor set `push_set` into a
+/// push descriptor set if the device supports push descriptors.
+pub fn pipeline_layout_with_push_descriptors<'a>(
+    device: Arc<Device>,
+    stages: impl IntoIterator<Item = &'a PipelineShaderStageCreateInfo>,
+    push_set: usize,
+) -> Arc<PipelineLayout> {
+    let mut layout_create_info = PipelineDescriptorSetLayoutCreateInfo::from_stages(stages);
+    if supports_push_descriptors(&device) {
+        if let Some(set_layout) = layout_create_info.set_layouts.get_mut(push_set) {
+            set_layout.flags |= DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR;
+        }
+    }
+    let create_info: PipelineLayoutCreateInfo = layout_create_info
+        .into_pipeline_layout_create_info(device.clone())
+        .unwrap();
+    PipelineLayout::new(device, create_info).unwrap()
+}
+
+/// Binds descriptors to set `set_num`. Uses push descriptors if the set layout was created as a
+/// push descriptor set (see [`pipeline_layout_with_push_descriptors`]), otherwise allocates a
+/// [`PersistentDescriptorSet`] from `allocator` and binds it.
+pub fn bind_descriptors<L>(
+    builder: &mut AutoCommandBufferBuilder<L>,
+    allocator: &StandardDescriptorSetAllocator,
+    bind_point: PipelineBindPoint,
+    layout: Arc<PipelineLayout>,
+    set_num: u32,
+    writes: impl IntoIterator<Item = WriteDescriptorSet>,
+) -> Result<(), Box<ValidationError>> {
+    let set_layout = layout.set_layouts()[set_num as usize].clone();
+    if set_layout
+        .flags()
+        .intersects(DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR)
+    {
+        builder.push_descriptor_set(bind_point, layout, set_num, writes.into_iter().collect())?;
+    } else {
+        let set = PersistentDescriptorSet::new(allocator, set_layout, writes, []).unwrap();
+        builder.bind_descriptor_sets(bind_point, layout, set_num, set)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,465 @@
+use std::{ptr, sync::Arc};
+
+use ash::vk;
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo},
+    device::{Device, DeviceOwned, Queue},
+    image::{Image, ImageCreateInfo, ImageLayout},
+    sync::{GpuFuture, Sharing},
+    Validated, VulkanError, VulkanObject,
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk};
+
+/// How a resource used on several queues is shared between their queue families, chosen when
+/// creating it.
+///
+/// Concurrent sharing needs no ownership transfers but may be slower on some hardware, e.g. for
+/// compressed render targets. Exclusive sharing is owned by one queue family at a time, and
+/// content is only preserved when moved to another family with a [`QueueOwnershipTransfer`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum QueueSharing {
+    #[default]
+    Exclusive,
+    /// Shared concurrently between the queue families of the queues, or exclusive when they all
+    /// belong to one family.
+    Concurrent,
+}
+
+impl QueueSharing {
+    /// Sets the sharing of `create_info` for use on `queues`.
+    pub fn image_create_info(
+        self,
+        queues: &[Arc<Queue>],
+        create_info: ImageCreateInfo,
+    ) -> ImageCreateInfo {
+        ImageCreateInfo {
+            sharing: match self.concurrent_families(queues) {
+                Some(families) => Sharing::Concurrent(families.into_iter().collect()),
+                None => Sharing::Exclusive,
+            },
+            ..create_info
+        }
+    }
+
+    /// Sets the sharing of `create_info` for use on `queues`.
+    pub fn buffer_create_info(
+        self,
+        queues: &[Arc<Queue>],
+        create_info: BufferCreateInfo,
+    ) -> BufferCreateInfo {
+        BufferCreateInfo {
+            sharing: match self.concurrent_families(queues) {
+                Some(families) => Sharing::Concurrent(families.into_iter().collect()),
+                None => Sharing::Exclusive,
+            },
+            ..create_info
+        }
+    }
+
+    fn concurrent_families(self, queues: &[Arc<Queue>]) -> Option<Vec<u32>> {
+        if self == QueueSharing::Exclusive {
+            return None;
+        }
+        let mut families = queues
+            .iter()
+            .map(|queue| queue.queue_family_index())
+            .collect::<Vec<_>>();
+        families.sort_unstable();
+        families.dedup();
+        (families.len() > 1).then_some(families)
+    }
+}
+
+/// A resource moved between queue families by a [`QueueOwnershipTransfer`].
+#[derive(Debug, Clone)]
+pub enum OwnershipResource {
+    /// An image in `layout`, which it stays in. Images used by vulkano's command buffers are in
+    /// `General` or the optimal layout for their usage between command buffers.
+    Image {
+        image: Arc<Image>,
+        layout: ImageLayout,
+    },
+    Buffer(Arc<Buffer>),
+}
+
+struct PendingTransfer {
+    release: vk::CommandBuffer,
+    acquire: vk::CommandBuffer,
+    semaphore: vk::Semaphore,
+    fence: vk::Fence,
+    /// Kept alive until the transfer has executed.
+    resources: Vec<OwnershipResource>,
+}
+
+/// Moves exclusively shared images and buffers from the queue family of `src_queue` to the family
+/// of `dst_queue`, e.g. an image written on the compute queue of
+/// [`BevyVulkanoContext`](crate::BevyVulkanoContext) and sampled on the graphics queue.
+///
+/// A transfer is a release barrier submitted on the source queue followed by a matching acquire
+/// barrier submitted on the destination queue, which waits for the release with a semaphore.
+/// vulkano's command buffers can't record these, so they're submitted directly. Create one
+/// transfer helper per direction and reuse it, as it pools its command buffers.
+///
+/// ```ignore
+/// let mut to_graphics = QueueOwnershipTransfer::new(compute_queue.clone(), graphics_queue.clone());
+/// let after_compute = compute_pass.render(before, ...);
+/// // Safety: the image is exclusively shared, in `General` layout and only used on the graphics
+/// // queue after `before_graphics`
+/// let before_graphics = unsafe {
+///     to_graphics.transfer(after_compute, &[OwnershipResource::Image {
+///         image: simulation.image().clone(),
+///         layout: ImageLayout::General,
+///     }])
+/// }
+/// .unwrap();
+/// let after = before_graphics.then_execute(graphics_queue, command_buffer).unwrap();
+/// ```
+pub struct QueueOwnershipTransfer {
+    src_queue: Arc<Queue>,
+    dst_queue: Arc<Queue>,
+    src_pool: vk::CommandPool,
+    dst_pool: vk::CommandPool,
+    pending: Vec<PendingTransfer>,
+}
+
+impl QueueOwnershipTransfer {
+    pub fn new(src_queue: Arc<Queue>, dst_queue: Arc<Queue>) -> QueueOwnershipTransfer {
+        let device = src_queue.device().clone();
+        QueueOwnershipTransfer {
+            src_pool: create_command_pool(&device, src_queue.queue_family_index()),
+            dst_pool: create_command_pool(&device, dst_queue.queue_family_index()),
+            src_queue,
+            dst_queue,
+            pending: vec![],
+        }
+    }
+
+    /// Whether the queues belong to different queue families, so exclusively shared resources need
+    /// a transfer. Otherwise [`QueueOwnershipTransfer::transfer`] returns `before_future`
+    /// unchanged.
+    pub fn is_needed(&self) -> bool {
+        self.src_queue.queue_family_index() != self.dst_queue.queue_family_index()
+    }
+
+    /// Transfers `resources` after the work of `before_future`, which must execute on the source
+    /// queue. Returns a future to continue on the destination queue from, e.g. with
+    /// `then_execute(dst_queue, ...)`. Resources shared concurrently don't need a transfer and
+    /// should not be passed.
+    ///
+    /// # Safety
+    ///
+    /// vulkano doesn't track queue family ownership, nor the layouts of the raw barriers.
+    ///
+    /// - `resources` must be exclusively shared and owned by the source queue family, and an
+    ///   [`OwnershipResource::Image`] must be in its `layout`.
+    /// - The resources must not be used on the source queue after the transfer, nor on the
+    ///   destination queue other than after the returned future, until transferred back.
+    pub unsafe fn transfer(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        resources: &[OwnershipResource],
+    ) -> Result<Box<dyn GpuFuture>, Validated<VulkanError>> {
+        if !self.is_needed() || resources.is_empty() {
+            return Ok(before_future);
+        }
+        profile_scope!("bevy_vulkano::queue_ownership_transfer");
+        self.cleanup_finished();
+        // The release must follow the work of `before_future` in the source queue's submission
+        // order
+        before_future.flush()?;
+
+        let device = self.src_queue.device().clone();
+        let mut transfer = PendingTransfer {
+            release: vk::CommandBuffer::null(),
+            acquire: vk::CommandBuffer::null(),
+            semaphore: vk::Semaphore::null(),
+            fence: vk::Fence::null(),
+            resources: resources.to_vec(),
+        };
+        if let Err(e) = self.submit(&device, &mut transfer) {
+            self.destroy(&device, transfer);
+            return Err(e.into());
+        }
+        self.pending.push(transfer);
+        // Work submitted to the destination queue after the acquire is ordered after it by the
+        // acquire barrier. The semaphore keeps vulkano's resource tracking of `before_future`
+        // across the queue change.
+        Ok(before_future.then_signal_semaphore().boxed())
+    }
+
+    /// Records and submits the release and acquire of `transfer`, storing the created objects in
+    /// it as they're created so they can be destroyed on failure.
+    unsafe fn submit(
+        &self,
+        device: &Device,
+        transfer: &mut PendingTransfer,
+    ) -> Result<(), VulkanError> {
+        let resources = &transfer.resources;
+        let src_family = self.src_queue.queue_family_index();
+        let dst_family = self.dst_queue.queue_family_index();
+        let fns = device.fns();
+        transfer.release = record_barriers(
+            device,
+            self.src_pool,
+            resources,
+            src_family,
+            dst_family,
+            true,
+        )?;
+        transfer.acquire = record_barriers(
+            device,
+            self.dst_pool,
+            resources,
+            src_family,
+            dst_family,
+            false,
+        )?;
+        (fns.v1_0.create_semaphore)(
+            device.handle(),
+            &vk::SemaphoreCreateInfo::default(),
+            ptr::null(),
+            &mut transfer.semaphore,
+        )
+        .result()
+        .map_err(VulkanError::from)?;
+        (fns.v1_0.create_fence)(
+            device.handle(),
+            &vk::FenceCreateInfo::default(),
+            ptr::null(),
+            &mut transfer.fence,
+        )
+        .result()
+        .map_err(VulkanError::from)?;
+
+        trace_vk!(
+            "submit ownership release of {} resources on queue family {}",
+            resources.len(),
+            src_family
+        );
+        let release_submit = vk::SubmitInfo {
+            command_buffer_count: 1,
+            p_command_buffers: &transfer.release,
+            signal_semaphore_count: 1,
+            p_signal_semaphores: &transfer.semaphore,
+            ..Default::default()
+        };
+        self.src_queue
+            .with(|_guard| {
+                (fns.v1_0.queue_submit)(
+                    self.src_queue.handle(),
+                    1,
+                    &release_submit,
+                    vk::Fence::null(),
+                )
+            })
+            .result()
+            .map_err(VulkanError::from)?;
+
+        trace_vk!(
+            "submit ownership acquire of {} resources on queue family {}",
+            resources.len(),
+            dst_family
+        );
+        let wait_stage = vk::PipelineStageFlags::ALL_COMMANDS;
+        let acquire_submit = vk::SubmitInfo {
+            wait_semaphore_count: 1,
+            p_wait_semaphores: &transfer.semaphore,
+            p_wait_dst_stage_mask: &wait_stage,
+            command_buffer_count: 1,
+            p_command_buffers: &transfer.acquire,
+            ..Default::default()
+        };
+        let result = self
+            .dst_queue
+            .with(|_guard| {
+                (fns.v1_0.queue_submit)(self.dst_queue.handle(), 1, &acquire_submit, transfer.fence)
+            })
+            .result();
+        if let Err(e) = result {
+            // The release was submitted, so it must finish before its objects are destroyed
+            let _ = self
+                .src_queue
+                .with(|_guard| (fns.v1_0.queue_wait_idle)(self.src_queue.handle()));
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Frees the command buffers and sync objects of transfers which have executed.
+    fn cleanup_finished(&mut self) {
+        let device = self.src_queue.device().clone();
+        let (finished, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|transfer| unsafe {
+                (device.fns().v1_0.get_fence_status)(device.handle(), transfer.fence)
+                    == vk::Result::SUCCESS
+            });
+        self.pending = pending;
+        for transfer in finished {
+            unsafe { self.destroy(&device, transfer) };
+        }
+    }
+
+    /// Destroys the objects of `transfer`, null handles of a failed submission are ignored.
+    unsafe fn destroy(&self, device: &Device, transfer: PendingTransfer) {
+        let fns = device.fns();
+        (fns.v1_0.free_command_buffers)(device.handle(), self.src_pool, 1, &transfer.release);
+        (fns.v1_0.free_command_buffers)(device.handle(), self.dst_pool, 1, &transfer.acquire);
+        (fns.v1_0.destroy_semaphore)(device.handle(), transfer.semaphore, ptr::null());
+        (fns.v1_0.destroy_fence)(device.handle(), transfer.fence, ptr::null());
+    }
+}
+
+impl Drop for QueueOwnershipTransfer {
+    fn drop(&mut self) {
+        let device = self.src_queue.device().clone();
+        let fns = device.fns();
+        unsafe {
+            let fences = self
+                .pending
+                .iter()
+                .map(|transfer| transfer.fence)
+                .collect::<Vec<_>>();
+            if !fences.is_empty() {
+                let _ = (fns.v1_0.wait_for_fences)(
+                    device.handle(),
+                    fences.len() as u32,
+                    fences.as_ptr(),
+                    vk::TRUE,
+                    u64::MAX,
+                );
+            }
+            for transfer in std::mem::take(&mut self.pending) {
+                self.destroy(&device, transfer);
+            }
+            (fns.v1_0.destroy_command_pool)(device.handle(), self.src_pool, ptr::null());
+            (fns.v1_0.destroy_command_pool)(device.handle(), self.dst_pool, ptr::null());
+        }
+    }
+}
+
+fn create_command_pool(device: &Device, queue_family_index: u32) -> vk::CommandPool {
+    let create_info = vk::CommandPoolCreateInfo {
+        flags: vk::CommandPoolCreateFlags::TRANSIENT,
+        queue_family_index,
+        ..Default::default()
+    };
+    let mut pool = vk::CommandPool::null();
+    unsafe {
+        (device.fns().v1_0.create_command_pool)(
+            device.handle(),
+            &create_info,
+            ptr::null(),
+            &mut pool,
+        )
+        .result()
+        .unwrap();
+    }
+    pool
+}
+
+/// Records the release (on the source family) or acquire (on the destination family) half of the
+/// transfer of `resources`.
+unsafe fn record_barriers(
+    device: &Device,
+    pool: vk::CommandPool,
+    resources: &[OwnershipResource],
+    src_family: u32,
+    dst_family: u32,
+    release: bool,
+) -> Result<vk::CommandBuffer, VulkanError> {
+    let fns = device.fns();
+    // Access masks of the other half are ignored
+    let (src_access, dst_access) = if release {
+        (vk::AccessFlags::MEMORY_WRITE, vk::AccessFlags::empty())
+    } else {
+        (
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+        )
+    };
+    let (src_stage, dst_stage) = if release {
+        (
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        )
+    } else {
+        (
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+        )
+    };
+    let mut buffer_barriers = vec![];
+    let mut image_barriers = vec![];
+    for resource in resources {
+        match resource {
+            OwnershipResource::Image {
+                image,
+                layout,
+            } => image_barriers.push(vk::ImageMemoryBarrier {
+                src_access_mask: src_access,
+                dst_access_mask: dst_access,
+                old_layout: (*layout).into(),
+                new_layout: (*layout).into(),
+                src_queue_family_index: src_family,
+                dst_queue_family_index: dst_family,
+                image: image.handle(),
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: image.format().aspects().into(),
+                    base_mip_level: 0,
+                    level_count: vk::REMAINING_MIP_LEVELS,
+                    base_array_layer: 0,
+                    layer_count: vk::REMAINING_ARRAY_LAYERS,
+                },
+                ..Default::default()
+            }),
+            OwnershipResource::Buffer(buffer) => buffer_barriers.push(vk::BufferMemoryBarrier {
+                src_access_mask: src_access,
+                dst_access_mask: dst_access,
+                src_queue_family_index: src_family,
+                dst_queue_family_index: dst_family,
+                buffer: buffer.handle(),
+                offset: 0,
+                size: vk::WHOLE_SIZE,
+                ..Default::default()
+            }),
+        }
+    }
+
+    let allocate_info = vk::CommandBufferAllocateInfo {
+        command_pool: pool,
+        level: vk::CommandBufferLevel::PRIMARY,
+        command_buffer_count: 1,
+        ..Default::default()
+    };
+    let mut command_buffer = vk::CommandBuffer::null();
+    (fns.v1_0.allocate_command_buffers)(device.handle(), &allocate_info, &mut command_buffer)
+        .result()
+        .map_err(VulkanError::from)?;
+    let begin_info = vk::CommandBufferBeginInfo {
+        flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+        ..Default::default()
+    };
+    if let Err(e) = (fns.v1_0.begin_command_buffer)(command_buffer, &begin_info).result() {
+        (fns.v1_0.free_command_buffers)(device.handle(), pool, 1, &command_buffer);
+        return Err(e.into());
+    }
+    (fns.v1_0.cmd_pipeline_barrier)(
+        command_buffer,
+        src_stage,
+        dst_stage,
+        vk::DependencyFlags::empty(),
+        0,
+        ptr::null(),
+        buffer_barriers.len() as u32,
+        buffer_barriers.as_ptr(),
+        image_barriers.len() as u32,
+        image_barriers.as_ptr(),
+    );
+    if let Err(e) = (fns.v1_0.end_command_buffer)(command_buffer).result() {
+        (fns.v1_0.free_command_buffers)(device.handle(), pool, 1, &command_buffer);
+        return Err(e.into());
+    }
+    Ok(command_buffer)
+}
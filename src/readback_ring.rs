@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use bevy::log::warn;
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferReadGuard, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder,
+        CommandBufferExecFuture, CommandBufferUsage, CopyBufferInfo,
+    },
+    device::{DeviceOwned, Queue},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    sync::{
+        self,
+        future::{FenceSignalFuture, GpuFuture},
+    },
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk};
+
+type CopyFence = Arc<FenceSignalFuture<CommandBufferExecFuture<Box<dyn GpuFuture>>>>;
+
+struct ReadbackSlot<T: BufferContents> {
+    buffer: Subbuffer<[T]>,
+    /// Fence of the copy in flight into this slot.
+    fence: Option<CopyFence>,
+    /// Whether the slot holds a completed copy.
+    completed: bool,
+    /// Index of the copy held by the slot, counting all scheduled copies.
+    copy_index: u64,
+}
+
+/// A ring of host visible buffers for steadily streaming data from the GPU to the CPU without
+/// stalling, e.g. for audio visualization, analytics or machine learning in the loop.
+///
+/// Each frame, [`ReadbackRing::schedule`] copies a device buffer into the next slot of the ring,
+/// and [`ReadbackRing::latest`] returns the newest slot whose copy has completed. When every slot
+/// is still in flight, the copy is skipped instead of waiting, see [`ReadbackRing::skipped`].
+///
+/// ```ignore
+/// let mut ring = ReadbackRing::<f32>::new(queue, allocator, 1024, 3);
+/// let after = ring.schedule(after_compute, spectrum_buffer.clone());
+/// if let Some((copy_index, spectrum)) = ring.latest() {
+///     // Use `spectrum`, which is a few frames old
+/// }
+/// ```
+pub struct ReadbackRing<T: BufferContents> {
+    queue: Arc<Queue>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    slots: Vec<ReadbackSlot<T>>,
+    next_slot: usize,
+    copies: u64,
+    skipped: u64,
+}
+
+impl<T: BufferContents> ReadbackRing<T> {
+    /// Creates `slot_count` host visible slots of `len` elements each. Two or three slots suffice
+    /// to read data one or two frames old without stalling.
+    pub fn new(
+        queue: Arc<Queue>,
+        allocator: Arc<StandardMemoryAllocator>,
+        len: u64,
+        slot_count: usize,
+    ) -> ReadbackRing<T> {
+        let slots = (0..slot_count.max(1))
+            .map(|_| ReadbackSlot {
+                buffer: Buffer::new_slice::<T>(
+                    allocator.clone(),
+                    BufferCreateInfo {
+                        usage: BufferUsage::TRANSFER_DST,
+                        ..Default::default()
+                    },
+                    AllocationCreateInfo {
+                        memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                            | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                        ..Default::default()
+                    },
+                    len.max(1),
+                )
+                .unwrap(),
+                fence: None,
+                completed: false,
+                copy_index: 0,
+            })
+            .collect();
+        ReadbackRing {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                queue.device().clone(),
+                Default::default(),
+            ),
+            queue,
+            slots,
+            next_slot: 0,
+            copies: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Copies `source` into the next slot after `before_future` and returns the future of the
+    /// copy. The copy is skipped and `before_future` returned when the next slot is still in
+    /// flight. `source` must have been created with `TRANSFER_SRC` usage and at most as many
+    /// elements as the slots.
+    pub fn schedule(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        source: Subbuffer<[T]>,
+    ) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::readback_ring");
+        self.poll();
+        let slot_index = self.next_slot;
+        if self.slots[slot_index].fence.is_some() {
+            self.skipped += 1;
+            return before_future;
+        }
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let slot = &mut self.slots[slot_index];
+        builder
+            .copy_buffer(CopyBufferInfo::buffers(source, slot.buffer.clone()))
+            .unwrap();
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit readback ring copy {} into slot {} on queue family {}",
+            self.copies,
+            slot_index,
+            self.queue.queue_family_index()
+        );
+        let fence = match before_future
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .then_signal_fence_and_flush()
+        {
+            Ok(fence) => Arc::new(fence),
+            Err(e) => {
+                warn!("Failed to flush readback ring copy: {e}");
+                return sync::now(self.queue.device().clone()).boxed();
+            }
+        };
+        slot.fence = Some(fence.clone());
+        slot.completed = false;
+        slot.copy_index = self.copies;
+        self.copies += 1;
+        self.next_slot = (slot_index + 1) % self.slots.len();
+        fence.boxed()
+    }
+
+    /// The newest completed copy with its index, counting all scheduled copies. Doesn't wait for
+    /// copies in flight.
+    pub fn latest(&mut self) -> Option<(u64, BufferReadGuard<'_, [T]>)> {
+        self.poll();
+        let slot = self
+            .slots
+            .iter()
+            .filter(|slot| slot.completed)
+            .max_by_key(|slot| slot.copy_index)?;
+        let data = slot.buffer.read().ok()?;
+        Some((slot.copy_index, data))
+    }
+
+    /// Number of slots.
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Number of copies skipped because all slots were in flight.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Marks slots whose copies have finished as completed.
+    fn poll(&mut self) {
+        for slot in self.slots.iter_mut() {
+            let signaled = slot
+                .fence
+                .as_ref()
+                .is_some_and(|fence| fence.is_signaled().unwrap_or(false));
+            if signaled {
+                slot.fence = None;
+                slot.completed = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use bevy::{
+    prelude::{Commands, Component, Entity, EventWriter, NonSendMut, Query, With},
+    window::{RequestRedraw, Window},
+};
+
+use crate::{compat::Has, BevyVulkanoWindows};
+
+/// Renders a window only when something changed, for desktop apps that shouldn't spend power on
+/// redrawing identical frames. Insert it on a window entity at any time.
+///
+/// Render systems check [`VulkanoWindowRenderer::needs_redraw`](crate::VulkanoWindowRenderer::needs_redraw)
+/// and skip acquire and present when it is false. A window needs a redraw after winit events for
+/// it, when egui requested a repaint, when it is marked with [`RenderDirty`] and when its
+/// swapchain must be recreated. Combined with reactive update modes, e.g. from
+/// [`BevyVulkanoSettings::desktop_app`](crate::BevyVulkanoSettings::desktop_app), the app then
+/// idles until something happens.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct RenderOnDemand;
+
+/// Marks a [`RenderOnDemand`] window for a redraw in the next update, e.g. after a system changed
+/// what it shows. The component is removed once the redraw is requested, and wakes a reactive
+/// event loop. To render in the current update instead, call
+/// [`VulkanoWindowRenderer::request_redraw`](crate::VulkanoWindowRenderer::request_redraw) before
+/// the render system runs.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct RenderDirty;
+
+/// Requests redraws of on demand windows that are dirty or whose gui wants to repaint, waking the
+/// event loop for them.
+pub(crate) fn request_on_demand_redraws(
+    mut commands: Commands,
+    windows: Query<(Entity, Has<RenderOnDemand>, Has<RenderDirty>), With<Window>>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) {
+    let mut wake = false;
+    for (entity, on_demand, dirty) in windows.iter() {
+        if dirty {
+            commands.entity(entity).remove::<RenderDirty>();
+        }
+        let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(entity) else {
+            continue;
+        };
+        vulkano_window.renderer.set_render_on_demand(on_demand);
+        if !on_demand {
+            continue;
+        }
+        #[cfg(feature = "gui")]
+        let dirty = dirty
+            || vulkano_window
+                .gui
+                .as_ref()
+                .is_some_and(|gui| gui.context().has_requested_repaint());
+        if dirty {
+            vulkano_window.renderer.request_redraw();
+            wake = true;
+        }
+    }
+    if wake {
+        redraw_events.send(RequestRedraw);
+    }
+}
@@ -0,0 +1,169 @@
+use std::sync::{Arc, Weak};
+
+use bevy::{
+    prelude::{Entity, NonSendMut, Res, ResMut, Resource},
+    utils::HashMap,
+};
+use egui_winit_vulkano::egui;
+use vulkano::{
+    device::DeviceOwned,
+    image::{sampler::SamplerCreateInfo, view::ImageView, ImageUsage},
+};
+
+use crate::{BevyVulkanoWindows, RenderTargetRegistry, TextureFiltering, TextureRegistry};
+
+const THUMBNAIL_SIZE: f32 = 96.0;
+
+/// A debug window listing the images managed by the crate: [`RenderTargetRegistry`] targets,
+/// [`TextureRegistry`] textures and the additional image views of each window, with their format,
+/// extent, usage and a live thumbnail of sampleable images. Useful for finding passes which render
+/// black and images which are never released.
+///
+/// Set `open` to show it, e.g. from a debug key binding.
+#[derive(Resource, Default)]
+pub struct RenderTargetInspector {
+    pub open: bool,
+    /// Window the inspector is shown in. `None` uses the main render window.
+    pub window: Option<Entity>,
+    /// Thumbnails registered with the gui of `shown_in`, by image view.
+    thumbnails: HashMap<usize, (Weak<ImageView>, egui::TextureId)>,
+    shown_in: Option<Entity>,
+}
+
+struct InspectedImage {
+    name: String,
+    view: Arc<ImageView>,
+}
+
+/// Draws the [`RenderTargetInspector`] window.
+pub(crate) fn render_target_inspector(
+    mut inspector: ResMut<RenderTargetInspector>,
+    render_targets: Res<RenderTargetRegistry>,
+    textures: Res<TextureRegistry>,
+    filtering: Res<TextureFiltering>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+) {
+    if !inspector.open {
+        return;
+    }
+    let mut images = render_targets
+        .iter()
+        .map(|(name, view)| InspectedImage {
+            name: name.to_string(),
+            view: view.clone(),
+        })
+        .chain(textures.iter().map(|(id, view)| InspectedImage {
+            name: format!("{id:?}"),
+            view: view.clone(),
+        }))
+        .collect::<Vec<_>>();
+    for (window, vulkano_window) in vulkano_windows.iter() {
+        let mut views = vulkano_window
+            .renderer
+            .additional_image_views()
+            .collect::<Vec<_>>();
+        views.sort_by_key(|(key, _)| *key);
+        images.extend(views.into_iter().map(|(key, view)| InspectedImage {
+            name: format!("{window:?} additional view {key}"),
+            view,
+        }));
+    }
+
+    let Some(window) = inspector
+        .window
+        .or_else(|| vulkano_windows.main_window_entity())
+    else {
+        return;
+    };
+    let Some(gui) = vulkano_windows
+        .get_vulkano_window_mut(window)
+        .and_then(|vulkano_window| vulkano_window.gui.as_mut())
+    else {
+        return;
+    };
+    if inspector.shown_in != Some(window) {
+        // The previous window's gui owns the old thumbnails, if it still exists
+        inspector.thumbnails.clear();
+        inspector.shown_in = Some(window);
+    }
+
+    // Release thumbnails of images which are gone or no longer listed
+    inspector.thumbnails.retain(|key, (view, texture_id)| {
+        let listed = view.upgrade().is_some()
+            && images
+                .iter()
+                .any(|image| Arc::as_ptr(&image.view) as usize == *key);
+        if !listed {
+            gui.unregister_user_image(*texture_id);
+        }
+        listed
+    });
+    let mut thumbnails = vec![];
+    for image in &images {
+        let key = Arc::as_ptr(&image.view) as usize;
+        let texture_id = if image.view.usage().intersects(ImageUsage::SAMPLED) {
+            let (_, texture_id) = inspector.thumbnails.entry(key).or_insert_with(|| {
+                let texture_id = gui.register_user_image_view(
+                    image.view.clone(),
+                    filtering.apply(
+                        image.view.device(),
+                        SamplerCreateInfo::simple_repeat_linear_no_mipmap(),
+                    ),
+                );
+                (Arc::downgrade(&image.view), texture_id)
+            });
+            Some(*texture_id)
+        } else {
+            None
+        };
+        thumbnails.push(texture_id);
+    }
+
+    let context = gui.context();
+    let mut open = inspector.open;
+    egui::Window::new("Render targets")
+        .open(&mut open)
+        .default_width(360.0)
+        .show(&context, |ui| {
+            ui.label(format!("{} images", images.len()));
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (image, texture_id) in images.iter().zip(thumbnails) {
+                    let extent = image.view.image().extent();
+                    ui.horizontal(|ui| {
+                        match texture_id {
+                            Some(texture_id) => {
+                                let aspect = extent[0] as f32 / extent[1].max(1) as f32;
+                                let size = if aspect >= 1.0 {
+                                    egui::vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE / aspect)
+                                } else {
+                                    egui::vec2(THUMBNAIL_SIZE * aspect, THUMBNAIL_SIZE)
+                                };
+                                ui.image((texture_id, size));
+                            }
+                            None => {
+                                ui.allocate_space(egui::vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE));
+                            }
+                        }
+                        ui.vertical(|ui| {
+                            ui.strong(&image.name);
+                            ui.label(format!(
+                                "{}x{}x{} {:?}",
+                                extent[0],
+                                extent[1],
+                                extent[2],
+                                image.view.format()
+                            ));
+                            ui.label(format!(
+                                "{} mips, {} layers",
+                                image.view.image().mip_levels(),
+                                image.view.image().array_layers()
+                            ));
+                            ui.small(format!("{:?}", image.view.image().usage()));
+                        });
+                    });
+                    ui.separator();
+                }
+            });
+        });
+    inspector.open = open;
+}
@@ -0,0 +1,50 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bevy::prelude::Resource;
+use vulkano::image::view::ImageView;
+
+/// Named images rendered during a frame, e.g. G-buffer attachments or intermediate post process
+/// targets. Registered targets are picked up by debugging tools such as
+/// [`DumpFrame`](crate::DumpFrame).
+///
+/// Re-register a target under the same name when it is recreated, e.g. on resize.
+#[derive(Resource, Default)]
+pub struct RenderTargetRegistry {
+    targets: BTreeMap<String, Arc<ImageView>>,
+}
+
+impl RenderTargetRegistry {
+    /// Registers `view` under `name`, returning the previously registered view.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        view: Arc<ImageView>,
+    ) -> Option<Arc<ImageView>> {
+        self.targets.insert(name.into(), view)
+    }
+
+    /// Removes the target registered under `name`.
+    pub fn unregister(&mut self, name: &str) -> Option<Arc<ImageView>> {
+        self.targets.remove(name)
+    }
+
+    /// The target registered under `name`.
+    pub fn get(&self, name: &str) -> Option<Arc<ImageView>> {
+        self.targets.get(name).cloned()
+    }
+
+    /// Iterate registered targets in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Arc<ImageView>)> {
+        self.targets
+            .iter()
+            .map(|(name, view)| (name.as_str(), view))
+    }
+
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+}
@@ -0,0 +1,2015 @@
+// Mostly same as `vulkano_util::renderer`, but owned by this crate so that swapchain creation can be
+// negotiated against the capabilities of the surface.
+
+#[cfg(feature = "capture")]
+use std::sync::mpsc::{channel, Sender};
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    log::{info, warn},
+    prelude::{Component, Entity, Event},
+    utils::HashMap,
+    window::PresentMode as BevyPresentMode,
+};
+#[cfg(feature = "capture")]
+use image::RgbaImage;
+#[cfg(feature = "capture")]
+use vulkano::command_buffer::{
+    allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+};
+use vulkano::{
+    device::{physical::PhysicalDevice, Device, Queue},
+    format::{Format, NumericFormat},
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::StandardMemoryAllocator,
+    swapchain::{
+        self, ColorSpace, CompositeAlpha, CompositeAlphas, PresentFuture, PresentMode, Surface,
+        SurfaceInfo, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo,
+    },
+    sync::{self, future::FenceSignalFuture, GpuFuture, Sharing},
+    Validated, VulkanError,
+};
+use vulkano_util::context::VulkanoContext;
+use winit::window::Window;
+
+#[cfg(feature = "capture")]
+use crate::capture::{supports_readback, FrameCapture, ImageReadback};
+use crate::{
+    damage::{present_regions, supports_incremental_present, DamageRect},
+    device_info::DriverWorkarounds,
+    frame_hooks::{FrameHook, FrameHookContext, FrameHookId},
+    framebuffer_cache::retire_views,
+    hdr::{
+        hdr_formats, is_hdr_color_space, set_swapchain_hdr_metadata, HdrMetadata, HdrMetadataError,
+        DEFAULT_SDR_WHITE_LEVEL,
+    },
+    image_history::ImageHistory,
+    memory_budget::GpuMemoryBudget,
+    profiling::profile_scope,
+    trace_frame::trace_vk,
+    utils::supports_format,
+};
+
+/// Swapchain image format used when nothing else has been requested.
+pub const DEFAULT_IMAGE_FORMAT: Format = Format::B8G8R8A8_SRGB;
+
+/// Chooses the swapchain format and color space of a window from the `(format, color space)`
+/// pairs supported by its surface, see
+/// [`VulkanoWindowConfig::format_selector`](crate::VulkanoWindowConfig::format_selector).
+#[derive(Clone)]
+pub struct SwapchainFormatSelector(
+    pub Arc<dyn Fn(&[(Format, ColorSpace)]) -> (Format, ColorSpace) + Send + Sync>,
+);
+
+impl SwapchainFormatSelector {
+    pub fn new(
+        selector: impl Fn(&[(Format, ColorSpace)]) -> (Format, ColorSpace) + Send + Sync + 'static,
+    ) -> SwapchainFormatSelector {
+        SwapchainFormatSelector(Arc::new(selector))
+    }
+}
+
+impl std::fmt::Debug for SwapchainFormatSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SwapchainFormatSelector")
+    }
+}
+
+/// Preferences used when creating the swapchain of a [`VulkanoWindowRenderer`].
+#[derive(Debug, Clone)]
+pub struct SwapchainPreferences {
+    /// Present mode requested through bevy's `Window::present_mode`.
+    pub present_mode: BevyPresentMode,
+    /// Whether present modes which may tear (`Immediate`, `FifoRelaxed`) are acceptable when
+    /// resolving [`BevyPresentMode::AutoVsync`] and [`BevyPresentMode::AutoNoVsync`].
+    pub allow_tearing: bool,
+    /// Format of the swapchain images.
+    pub image_format: Format,
+    /// Color space of the swapchain images.
+    pub image_color_space: ColorSpace,
+    /// Overrides `image_format` and `image_color_space` with a choice from the formats supported
+    /// by the surface.
+    pub format_selector: Option<SwapchainFormatSelector>,
+    /// Number of array layers of the swapchain images. Clamped to the surface capabilities.
+    pub image_array_layers: u32,
+    /// Usages of the swapchain images in addition to `COLOR_ATTACHMENT`. Usages not supported by
+    /// the surface or the image format are dropped with a warning.
+    pub image_usage: ImageUsage,
+    /// Preferred minimum number of swapchain images. Clamped to the surface capabilities. `None`
+    /// uses the surface minimum, but at least 2.
+    pub min_image_count: Option<u32>,
+    /// Queue the swapchain is presented from. Falls back to the other queue when the preferred
+    /// queue can't present to the window's surface.
+    pub present_queue: PresentQueue,
+    /// How the swapchain images are composited with other windows. `None` or an unsupported mode
+    /// uses the first mode supported by the surface.
+    pub composite_alpha: Option<CompositeAlpha>,
+}
+
+/// Queue of the [`VulkanoContext`] swapchain images are presented from, see
+/// [`VulkanoWindowRenderer::present_queue`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PresentQueue {
+    /// Present from the graphics queue, avoiding a semaphore handoff between queues.
+    #[default]
+    Graphics,
+    /// Present from the compute queue, e.g. when its queue family is the one supporting
+    /// presentation to the surface. Rendering on the graphics queue is handed off with a
+    /// semaphore.
+    Compute,
+}
+
+impl Default for SwapchainPreferences {
+    fn default() -> Self {
+        SwapchainPreferences {
+            present_mode: BevyPresentMode::Fifo,
+            allow_tearing: true,
+            image_format: DEFAULT_IMAGE_FORMAT,
+            image_color_space: ColorSpace::SrgbNonLinear,
+            format_selector: None,
+            image_array_layers: 1,
+            image_usage: ImageUsage::empty(),
+            min_image_count: None,
+            present_queue: PresentQueue::default(),
+            composite_alpha: None,
+        }
+    }
+}
+
+/// Order of present modes tried for each requested bevy present mode. The first supported mode
+/// is selected.
+///
+/// - `AutoVsync`: `FifoRelaxed`, `Fifo`
+/// - `AutoNoVsync`: `Mailbox`, `Immediate`, `Fifo`
+/// - `Immediate`: `Immediate`, `Mailbox`, `FifoRelaxed`, `Fifo`
+/// - `Mailbox`: `Mailbox`, `Immediate`, `Fifo`
+/// - `FifoRelaxed`: `FifoRelaxed`, `Fifo`
+/// - `Fifo`: `Fifo`
+///
+/// `Fifo` is always supported, so it is the final fallback of each chain.
+pub fn present_mode_preference(requested: BevyPresentMode) -> &'static [PresentMode] {
+    match requested {
+        BevyPresentMode::AutoVsync => &[PresentMode::FifoRelaxed, PresentMode::Fifo],
+        BevyPresentMode::AutoNoVsync => &[
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+            PresentMode::Fifo,
+        ],
+        BevyPresentMode::Immediate => &[
+            PresentMode::Immediate,
+            PresentMode::Mailbox,
+            PresentMode::FifoRelaxed,
+            PresentMode::Fifo,
+        ],
+        BevyPresentMode::Mailbox => &[
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+            PresentMode::Fifo,
+        ],
+        BevyPresentMode::FifoRelaxed => &[PresentMode::FifoRelaxed, PresentMode::Fifo],
+        BevyPresentMode::Fifo => &[PresentMode::Fifo],
+    }
+}
+
+/// The vulkano present mode matching an explicit bevy present mode. `None` for the automatic
+/// modes.
+pub fn explicit_present_mode(requested: BevyPresentMode) -> Option<PresentMode> {
+    match requested {
+        BevyPresentMode::AutoVsync | BevyPresentMode::AutoNoVsync => None,
+        BevyPresentMode::Fifo => Some(PresentMode::Fifo),
+        BevyPresentMode::FifoRelaxed => Some(PresentMode::FifoRelaxed),
+        BevyPresentMode::Immediate => Some(PresentMode::Immediate),
+        BevyPresentMode::Mailbox => Some(PresentMode::Mailbox),
+    }
+}
+
+/// Resolves bevy's present mode into a vulkano present mode supported by the surface using the
+/// order described in [`present_mode_preference`].
+///
+/// Modes which may tear (`Immediate`, `FifoRelaxed`) are skipped unless `allow_tearing` is set or
+/// they were explicitly requested.
+pub fn resolve_present_mode(
+    requested: BevyPresentMode,
+    supported: &[PresentMode],
+    allow_tearing: bool,
+) -> PresentMode {
+    let explicit = explicit_present_mode(requested);
+    present_mode_preference(requested)
+        .iter()
+        .copied()
+        .find(|mode| {
+            supported.contains(mode)
+                && (allow_tearing
+                    || explicit == Some(*mode)
+                    || !matches!(mode, PresentMode::Immediate | PresentMode::FifoRelaxed))
+        })
+        .unwrap_or(PresentMode::Fifo)
+}
+
+/// Sent when the present mode requested for a window is not supported by its surface and another
+/// mode was selected instead. See [`present_mode_preference`] for the fallback order.
+#[derive(Event, Debug, Clone)]
+pub struct PresentModeFallback {
+    pub window: Entity,
+    pub requested: BevyPresentMode,
+    pub selected: PresentMode,
+}
+
+/// Sent after each successful present of a window, allowing frame pacing to be recorded per
+/// window.
+#[derive(Event, Debug, Clone)]
+pub struct FramePresented {
+    pub window: Entity,
+    /// Number of frames the window presented before this one.
+    pub frame_index: u64,
+    /// CPU time from the end of acquire until present, i.e. recording and submitting the frame.
+    pub cpu_time: Duration,
+    /// Time spent in acquire waiting for a swapchain image.
+    pub acquire_wait: Duration,
+    /// Whether the swapchain no longer matched the surface exactly when the image was acquired.
+    pub suboptimal: bool,
+    /// Time the present was submitted.
+    pub presented_at: Instant,
+}
+
+/// Presents all windows back-to-back at the end of the frame instead of when each window calls
+/// [`VulkanoWindowRenderer::present`], reducing the offset between windows presenting to the same
+/// vblank, e.g. for video walls. See
+/// [`BevyVulkanoSettings::present_group`](crate::BevyVulkanoSettings::present_group).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentGroup {
+    /// Submit the rendering of all windows before presenting any of them, so that no present
+    /// waits behind another window's command buffers being submitted.
+    pub submit_first: bool,
+    /// Block in each window's acquire until its swapchain image is available, so the images of
+    /// all windows are acquired before the group's rendering is submitted and no window's
+    /// rendering waits for its image on the GPU while others present. Trades CPU time for presents
+    /// lining up.
+    pub wait_for_acquires: bool,
+}
+
+impl Default for PresentGroup {
+    fn default() -> Self {
+        PresentGroup {
+            submit_first: true,
+            wait_for_acquires: false,
+        }
+    }
+}
+
+/// A present deferred until the [`PresentGroup`] is presented.
+struct DeferredPresent {
+    future: Box<dyn GpuFuture>,
+    wait_future: bool,
+    cpu_time: Duration,
+}
+
+/// Statistics of a presented frame, see [`FramePresented`].
+#[derive(Debug, Clone, Copy)]
+pub struct FrameStats {
+    pub frame_index: u64,
+    pub cpu_time: Duration,
+    pub acquire_wait: Duration,
+    pub suboptimal: bool,
+    pub presented_at: Instant,
+}
+
+/// Number of presents used to estimate [`PresentTiming`].
+const PRESENT_TIMING_SAMPLES: usize = 32;
+
+/// Estimated display timing of a window, e.g. for fixed timestep games interpolating rendering
+/// between simulation steps. See [`VulkanoWindowRenderer::present_timing`].
+///
+/// Vulkano doesn't expose `VK_GOOGLE_display_timing`, so the timing is estimated from the monitor's
+/// refresh rate and the measured intervals between presents, which converge to the display
+/// interval when presents are paced by vsync.
+#[derive(Debug, Clone, Copy)]
+pub struct PresentTiming {
+    /// Time between display refreshes.
+    pub refresh_duration: Duration,
+    /// Average time between the window's recent presents, which is a multiple of
+    /// `refresh_duration` when frames take longer than a refresh.
+    pub present_interval: Duration,
+    /// Predicted time the next presented frame is displayed.
+    pub predicted_present: Instant,
+    /// Where `refresh_duration` comes from.
+    pub source: PresentTimingSource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentTimingSource {
+    /// The refresh rate of the window's current monitor.
+    MonitorRefreshRate,
+    /// Measured present intervals, when the monitor's refresh rate is unknown.
+    MeasuredIntervals,
+}
+
+/// Size of a window's swapchain images together with the matching logical size and scale factor.
+/// Maintained on window entities by the plugin and updated together with the swapchain at the
+/// beginning of each frame, unlike bevy's `Window::resolution` which may already reflect a resize
+/// the swapchain has not caught up with.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct RenderExtent {
+    /// Extent of the swapchain images in pixels.
+    pub physical: [u32; 2],
+    /// Physical extent divided by the scale factor.
+    pub logical: [f32; 2],
+    pub scale_factor: f64,
+}
+
+impl RenderExtent {
+    pub fn new(physical: [u32; 2], scale_factor: f64) -> RenderExtent {
+        RenderExtent {
+            physical,
+            logical: [
+                (physical[0] as f64 / scale_factor) as f32,
+                (physical[1] as f64 / scale_factor) as f32,
+            ],
+            scale_factor,
+        }
+    }
+
+    #[inline]
+    pub fn aspect_ratio(&self) -> f32 {
+        self.physical[0] as f32 / self.physical[1].max(1) as f32
+    }
+}
+
+/// Swapchain state of a window, maintained on window entities by the plugin like
+/// [`RenderExtent`], so systems can read it and react to swapchain recreation with change
+/// detection instead of accessing the renderer.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct SwapchainInfo {
+    pub format: Format,
+    pub color_space: ColorSpace,
+    /// Extent of the swapchain images in pixels.
+    pub extent: [u32; 2],
+    pub image_count: u32,
+    pub present_mode: PresentMode,
+    /// [`VulkanoWindowRenderer::frame_index`] when the swapchain was last created or recreated.
+    pub recreated_at_frame: u64,
+}
+
+type FrameFence = Arc<FenceSignalFuture<PresentFuture<Box<dyn GpuFuture>>>>;
+
+/// Swapchain Image view to render to. Resized with the window.
+pub struct VulkanoWindowRenderer {
+    window: Arc<Window>,
+    graphics_queue: Arc<Queue>,
+    compute_queue: Arc<Queue>,
+    /// Queue presenting the swapchain, one of the graphics and compute queues.
+    present_queue: Arc<Queue>,
+    swapchain: Arc<Swapchain>,
+    final_views: Vec<Arc<ImageView>>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    #[cfg(feature = "capture")]
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    /// Additional image views that you can add which are resized with the window.
+    /// Use associated functions to get access to these.
+    additional_image_views: HashMap<usize, Arc<ImageView>>,
+    /// Previous frames of additional image views created with history.
+    image_histories: HashMap<usize, ImageHistory>,
+    /// Depth image following the swapchain size, see [`SharedDepth`](crate::SharedDepth).
+    shared_depth: Option<Arc<ImageView>>,
+    supported_present_modes: Vec<PresentMode>,
+    allow_tearing: bool,
+    /// Requested and selected present modes if the last requested mode was not supported.
+    present_mode_fallback: Option<(BevyPresentMode, PresentMode)>,
+    driver_workarounds: DriverWorkarounds,
+    present_group: Option<PresentGroup>,
+    deferred_present: Option<DeferredPresent>,
+    /// HDR metadata re-applied whenever the swapchain is recreated.
+    hdr_metadata: Option<HdrMetadata>,
+    sdr_white_level: f32,
+    /// Regions that changed in the image being rendered, presented as a hint if supported.
+    damage: Vec<DamageRect>,
+    /// Swapchain extent and the scale factor at the time the swapchain was created.
+    render_extent: RenderExtent,
+    /// Frame index at the last swapchain recreation.
+    swapchain_recreated_at: u64,
+    /// Number of swapchain recreations.
+    swapchain_generation: u64,
+    /// Whether the swapchain image should be copied to the host on next present.
+    #[cfg(feature = "capture")]
+    capture_requested: bool,
+    #[cfg(feature = "capture")]
+    capture: Option<ImageReadback>,
+    /// Receivers of the next captured frame, see [`VulkanoWindowRenderer::capture_next_frame`].
+    #[cfg(feature = "capture")]
+    capture_senders: Vec<Sender<RgbaImage>>,
+    recreate_swapchain: bool,
+    /// Whether the window only renders when a redraw was requested, see
+    /// [`RenderOnDemand`](crate::RenderOnDemand).
+    render_on_demand: bool,
+    redraw_requested: bool,
+    previous_frame_end: Option<Box<dyn GpuFuture>>,
+    /// Time of the last successful acquire.
+    acquired_at: Option<Instant>,
+    /// Correlation id of the last acquire when tracing a frame.
+    acquire_trace_id: Option<u64>,
+    /// Time the last acquire waited for an image and whether it was suboptimal.
+    acquire_wait: Duration,
+    acquire_suboptimal: bool,
+    /// Number of frames presented.
+    frame_index: u64,
+    /// Statistics of frames presented since they were last taken.
+    presented_frames: Vec<FrameStats>,
+    /// Times of the most recent presents, used to estimate [`PresentTiming`].
+    present_times: VecDeque<Instant>,
+    frame_hooks: Vec<(FrameHookId, Box<dyn FrameHook>)>,
+    next_frame_hook_id: u64,
+    /// Fence of the last presented frame and the time its image was acquired.
+    frame_in_flight: Option<(FrameFence, Instant)>,
+    image_index: u32,
+    present_mode: PresentMode,
+}
+
+impl VulkanoWindowRenderer {
+    /// Creates a new [`VulkanoWindowRenderer`] which is used to orchestrate your rendering with
+    /// Vulkano. Pass the winit window and preferences for the swapchain, which are resolved against
+    /// the capabilities of the window's surface.
+    pub fn new(
+        vulkano_context: &VulkanoContext,
+        window: Window,
+        preferences: &SwapchainPreferences,
+    ) -> VulkanoWindowRenderer {
+        Self::with_shared_window(vulkano_context, Arc::new(window), preferences)
+    }
+
+    /// Like [`VulkanoWindowRenderer::new`], for a window whose previous renderer was dropped, e.g.
+    /// when the device is reinitialized.
+    pub(crate) fn with_shared_window(
+        vulkano_context: &VulkanoContext,
+        window: Arc<Window>,
+        preferences: &SwapchainPreferences,
+    ) -> VulkanoWindowRenderer {
+        // Create rendering surface from window
+        let surface =
+            Surface::from_window(vulkano_context.instance().clone(), window.clone()).unwrap();
+
+        let supported_present_modes = vulkano_context
+            .device()
+            .physical_device()
+            .surface_present_modes(&surface, SurfaceInfo::default())
+            .map(|modes| modes.into_iter().collect::<Vec<_>>())
+            .unwrap_or_else(|_| vec![PresentMode::Fifo]);
+        let present_mode = resolve_present_mode(
+            preferences.present_mode,
+            &supported_present_modes,
+            preferences.allow_tearing,
+        );
+        let present_mode_fallback =
+            Self::check_present_mode_fallback(&window, preferences.present_mode, present_mode);
+
+        let (image_format, image_color_space) = match &preferences.format_selector {
+            Some(selector) => Self::negotiate_image_format(
+                vulkano_context.device().physical_device(),
+                &window,
+                &surface,
+                selector,
+                preferences,
+            ),
+            None => Self::select_image_format(
+                vulkano_context.device().physical_device(),
+                &window,
+                &surface,
+                preferences,
+            ),
+        };
+        let present_queue =
+            Self::select_present_queue(vulkano_context, &window, &surface, preferences);
+
+        // Create swap chain & frame(s) to which we'll render
+        let (swapchain, final_views) = Self::create_swapchain(
+            vulkano_context.device().clone(),
+            &window,
+            surface,
+            present_mode,
+            &[
+                vulkano_context.graphics_queue().queue_family_index(),
+                present_queue.queue_family_index(),
+            ],
+            &SwapchainPreferences {
+                image_format,
+                image_color_space,
+                ..preferences.clone()
+            },
+        );
+
+        let render_extent = RenderExtent::new(swapchain.image_extent(), window.scale_factor());
+        let previous_frame_end = Some(sync::now(vulkano_context.device().clone()).boxed());
+
+        VulkanoWindowRenderer {
+            window,
+            graphics_queue: vulkano_context.graphics_queue().clone(),
+            compute_queue: vulkano_context.compute_queue().clone(),
+            present_queue,
+            swapchain,
+            final_views,
+            memory_allocator: vulkano_context.memory_allocator().clone(),
+            #[cfg(feature = "capture")]
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                vulkano_context.device().clone(),
+                Default::default(),
+            ),
+            additional_image_views: HashMap::default(),
+            image_histories: HashMap::default(),
+            shared_depth: None,
+            supported_present_modes,
+            allow_tearing: preferences.allow_tearing,
+            present_mode_fallback,
+            driver_workarounds: DriverWorkarounds::default(),
+            present_group: None,
+            deferred_present: None,
+            hdr_metadata: None,
+            sdr_white_level: DEFAULT_SDR_WHITE_LEVEL,
+            damage: vec![],
+            render_extent,
+            #[cfg(feature = "capture")]
+            capture_requested: false,
+            #[cfg(feature = "capture")]
+            capture: None,
+            #[cfg(feature = "capture")]
+            capture_senders: vec![],
+            recreate_swapchain: false,
+            render_on_demand: false,
+            redraw_requested: true,
+            previous_frame_end,
+            acquired_at: None,
+            acquire_trace_id: None,
+            acquire_wait: Duration::ZERO,
+            acquire_suboptimal: false,
+            frame_index: 0,
+            swapchain_recreated_at: 0,
+            swapchain_generation: 0,
+            presented_frames: vec![],
+            present_times: VecDeque::with_capacity(PRESENT_TIMING_SAMPLES),
+            frame_hooks: vec![],
+            next_frame_hook_id: 0,
+            frame_in_flight: None,
+            image_index: 0,
+            present_mode,
+        }
+    }
+
+    /// Creates the swapchain and its images based on the surface capabilities and preferences.
+    fn create_swapchain(
+        device: Arc<Device>,
+        window: &Arc<Window>,
+        surface: Arc<Surface>,
+        present_mode: PresentMode,
+        queue_family_indices: &[u32],
+        preferences: &SwapchainPreferences,
+    ) -> (Arc<Swapchain>, Vec<Arc<ImageView>>) {
+        let surface_capabilities = device
+            .physical_device()
+            .surface_capabilities(&surface, Default::default())
+            .unwrap();
+        let supported_usage = Self::supported_swapchain_usage(
+            device.physical_device(),
+            surface_capabilities.supported_usage_flags,
+            preferences.image_format,
+        );
+        let unsupported_usage = preferences.image_usage.difference(supported_usage);
+        if !unsupported_usage.is_empty() {
+            warn!(
+                "Swapchain image usage {:?} is not supported by the surface of window {:?} with \
+                 format {:?}, ignoring it",
+                unsupported_usage,
+                window.id(),
+                preferences.image_format
+            );
+        }
+        // Allow copying swapchain images for captures when the surface supports it
+        let image_usage = ImageUsage::COLOR_ATTACHMENT
+            | (preferences.image_usage & supported_usage)
+            | (supported_usage & ImageUsage::TRANSFER_SRC);
+        let min_image_count = Self::select_min_image_count(
+            window,
+            surface_capabilities.min_image_count,
+            surface_capabilities.max_image_count,
+            preferences.min_image_count,
+        );
+        // Images are rendered to on the graphics queue and presented from the present queue
+        let mut queue_family_indices = queue_family_indices.to_vec();
+        queue_family_indices.dedup();
+        let image_sharing = if queue_family_indices.len() > 1 {
+            Sharing::Concurrent(queue_family_indices.into_iter().collect())
+        } else {
+            Sharing::Exclusive
+        };
+        let (swapchain, images) = Swapchain::new(device, surface, SwapchainCreateInfo {
+            min_image_count,
+            image_format: preferences.image_format,
+            image_color_space: preferences.image_color_space,
+            image_extent: window.inner_size().into(),
+            image_array_layers: preferences
+                .image_array_layers
+                .clamp(1, surface_capabilities.max_image_array_layers),
+            image_usage,
+            image_sharing,
+            composite_alpha: Self::select_composite_alpha(
+                window,
+                surface_capabilities.supported_composite_alpha,
+                preferences.composite_alpha,
+            ),
+            present_mode,
+            ..Default::default()
+        })
+        .unwrap();
+        info!(
+            "Created swapchain of window {:?} with {} images",
+            window.id(),
+            images.len()
+        );
+        let images = images
+            .into_iter()
+            .map(|image| ImageView::new_default(image).unwrap())
+            .collect::<Vec<_>>();
+        (swapchain, images)
+    }
+
+    /// Selects the preferred present queue if its queue family supports presenting to `surface`,
+    /// otherwise the other queue of the context. Falls back to the graphics queue with an error
+    /// if neither does.
+    fn select_present_queue(
+        vulkano_context: &VulkanoContext,
+        window: &Window,
+        surface: &Surface,
+        preferences: &SwapchainPreferences,
+    ) -> Arc<Queue> {
+        let graphics_queue = vulkano_context.graphics_queue();
+        let compute_queue = vulkano_context.compute_queue();
+        let candidates = match preferences.present_queue {
+            PresentQueue::Graphics => [graphics_queue, compute_queue],
+            PresentQueue::Compute => [compute_queue, graphics_queue],
+        };
+        let physical_device = vulkano_context.device().physical_device();
+        let Some(queue) = candidates.into_iter().find(|queue| {
+            physical_device
+                .surface_support(queue.queue_family_index(), surface)
+                .unwrap_or(false)
+        }) else {
+            bevy::log::error!(
+                "No queue of the context can present to the surface of window {:?}, using the \
+                 graphics queue",
+                window.id()
+            );
+            return graphics_queue.clone();
+        };
+        if !Arc::ptr_eq(queue, candidates[0]) {
+            warn!(
+                "Queue family {} can't present to the surface of window {:?}, presenting from \
+                 queue family {} instead",
+                candidates[0].queue_family_index(),
+                window.id(),
+                queue.queue_family_index()
+            );
+        }
+        queue.clone()
+    }
+
+    /// Uses the preferred composite alpha mode if the surface supports it, otherwise the first
+    /// supported mode.
+    fn select_composite_alpha(
+        window: &Window,
+        supported: CompositeAlphas,
+        preferred: Option<CompositeAlpha>,
+    ) -> CompositeAlpha {
+        let first = supported.into_iter().next().unwrap();
+        match preferred {
+            Some(preferred) if supported.contains_enum(preferred) => preferred,
+            Some(preferred) => {
+                warn!(
+                    "Composite alpha {:?} is not supported by the surface of window {:?}, using \
+                     {:?}",
+                    preferred,
+                    window.id(),
+                    first
+                );
+                first
+            }
+            None => first,
+        }
+    }
+
+    /// Clamps the preferred minimum image count to the surface's `min..=max` image count. The
+    /// default is the surface minimum, but at least 2.
+    fn select_min_image_count(
+        window: &Window,
+        surface_min: u32,
+        surface_max: Option<u32>,
+        preferred: Option<u32>,
+    ) -> u32 {
+        let requested = preferred.unwrap_or(surface_min.max(2));
+        let count = requested
+            .max(surface_min)
+            .min(surface_max.unwrap_or(u32::MAX));
+        if count != requested {
+            warn!(
+                "Requested {} swapchain images for window {:?}, but the surface supports {}..={}, \
+                 using {}",
+                requested,
+                window.id(),
+                surface_min,
+                surface_max.map_or("unlimited".to_string(), |max| max.to_string()),
+                count
+            );
+        }
+        count
+    }
+
+    /// Selects the preferred format and color space if the surface supports them. Otherwise falls
+    /// back to a supported format with the preferred color space, preferring one with the same
+    /// sRGB encoding, and finally to the first supported format.
+    ///
+    /// sRGB formats can rarely be used as storage images. When `STORAGE` usage is requested and the
+    /// preferred format does not support it, the first `SrgbNonLinear` surface format which does
+    /// (usually a UNORM format) is selected instead.
+    fn select_image_format(
+        physical_device: &Arc<PhysicalDevice>,
+        window: &Window,
+        surface: &Surface,
+        preferences: &SwapchainPreferences,
+    ) -> (Format, ColorSpace) {
+        let preferred = (preferences.image_format, preferences.image_color_space);
+        let supported = physical_device
+            .surface_formats(surface, SurfaceInfo::default())
+            .unwrap_or_default();
+        let storage_usage = ImageUsage::STORAGE | ImageUsage::COLOR_ATTACHMENT;
+        if preferences.image_usage.intersects(ImageUsage::STORAGE)
+            && !supports_format(physical_device, preferences.image_format, storage_usage)
+        {
+            if let Some(&(format, color_space)) = supported.iter().find(|(format, color_space)| {
+                *color_space == ColorSpace::SrgbNonLinear
+                    && supports_format(physical_device, *format, storage_usage)
+            }) {
+                info!(
+                    "Swapchain format {:?} does not support storage usage, using {:?} instead",
+                    preferences.image_format, format
+                );
+                return (format, color_space);
+            }
+        }
+        // Surfaces not reporting any format are trusted with the preference
+        if supported.is_empty() || supported.contains(&preferred) {
+            return preferred;
+        }
+        let is_srgb = |format: Format| format.numeric_format_color() == Some(NumericFormat::SRGB);
+        let srgb = is_srgb(preferences.image_format);
+        let same_color_space = supported
+            .iter()
+            .filter(|(_, color_space)| *color_space == preferences.image_color_space);
+        let sdr = (DEFAULT_IMAGE_FORMAT, ColorSpace::SrgbNonLinear);
+        let fallback = hdr_formats(preferences.image_color_space)
+            .iter()
+            .map(|format| (*format, preferences.image_color_space))
+            .find(|candidate| supported.contains(candidate))
+            .or_else(|| {
+                same_color_space
+                    .clone()
+                    .find(|(format, _)| is_srgb(*format) == srgb)
+                    .or_else(|| same_color_space.clone().next())
+                    .copied()
+            })
+            .or_else(|| supported.contains(&sdr).then_some(sdr))
+            .unwrap_or(supported[0]);
+        warn!(
+            "Swapchain format {:?} is not supported by the surface of window {:?}, using {:?}",
+            preferred,
+            window.id(),
+            fallback
+        );
+        fallback
+    }
+
+    /// Lets `selector` choose the swapchain format and color space from the surface's supported
+    /// formats. Choices the surface doesn't support are ignored with a warning.
+    fn negotiate_image_format(
+        physical_device: &Arc<PhysicalDevice>,
+        window: &Window,
+        surface: &Surface,
+        selector: &SwapchainFormatSelector,
+        preferences: &SwapchainPreferences,
+    ) -> (Format, ColorSpace) {
+        let supported = physical_device
+            .surface_formats(surface, SurfaceInfo::default())
+            .unwrap_or_default();
+        let selected = (selector.0)(&supported);
+        if supported.contains(&selected) {
+            info!(
+                "Swapchain format of window {:?} negotiated as {:?}",
+                window.id(),
+                selected
+            );
+            selected
+        } else {
+            warn!(
+                "Swapchain format {:?} selected for window {:?} is not supported by its surface, \
+                 using {:?}",
+                selected,
+                window.id(),
+                (preferences.image_format, preferences.image_color_space)
+            );
+            (preferences.image_format, preferences.image_color_space)
+        }
+    }
+
+    /// Swapchain image usages supported by both the surface and the image format.
+    fn supported_swapchain_usage(
+        physical_device: &PhysicalDevice,
+        surface_usage: ImageUsage,
+        format: Format,
+    ) -> ImageUsage {
+        [
+            ImageUsage::TRANSFER_SRC,
+            ImageUsage::TRANSFER_DST,
+            ImageUsage::SAMPLED,
+            ImageUsage::STORAGE,
+            ImageUsage::COLOR_ATTACHMENT,
+            ImageUsage::INPUT_ATTACHMENT,
+        ]
+        .into_iter()
+        .filter(|usage| {
+            surface_usage.contains(*usage) && supports_format(physical_device, format, *usage)
+        })
+        .fold(ImageUsage::empty(), |supported, usage| supported | usage)
+    }
+
+    /// Logs a warning and returns the fallback if an explicitly requested present mode was
+    /// substituted.
+    fn check_present_mode_fallback(
+        window: &Window,
+        requested: BevyPresentMode,
+        selected: PresentMode,
+    ) -> Option<(BevyPresentMode, PresentMode)> {
+        match explicit_present_mode(requested) {
+            Some(explicit) if explicit != selected => {
+                warn!(
+                    "Present mode {:?} is not supported by the surface of window {:?}, using {:?} \
+                     instead",
+                    requested,
+                    window.id(),
+                    selected
+                );
+                Some((requested, selected))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the requested and selected present modes if the last requested mode was not
+    /// supported by the surface, clearing it. Used to emit [`PresentModeFallback`] events.
+    #[inline]
+    pub fn take_present_mode_fallback(&mut self) -> Option<(BevyPresentMode, PresentMode)> {
+        self.present_mode_fallback.take()
+    }
+
+    /// Number of frames presented by this window.
+    #[inline]
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// Estimated display timing of the window, available after two presents. Resets when the
+    /// swapchain is recreated.
+    pub fn present_timing(&self) -> Option<PresentTiming> {
+        let (first, last) = (*self.present_times.front()?, *self.present_times.back()?);
+        if self.present_times.len() < 2 {
+            return None;
+        }
+        let present_interval = (last - first) / (self.present_times.len() as u32 - 1);
+        let monitor_refresh = self
+            .window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .filter(|&millihertz| millihertz > 0)
+            .map(|millihertz| Duration::from_secs_f64(1000.0 / millihertz as f64));
+        let (refresh_duration, source) = match monitor_refresh {
+            Some(refresh) => (refresh, PresentTimingSource::MonitorRefreshRate),
+            None => (present_interval, PresentTimingSource::MeasuredIntervals),
+        };
+        // Next multiple of the present interval after now, in phase with the last present
+        let interval = present_interval.max(Duration::from_micros(100));
+        let elapsed = last.elapsed();
+        // Saturated after long pauses, where the prediction falls back to an interval from now
+        let intervals =
+            u32::try_from(elapsed.as_nanos() / interval.as_nanos() + 1).unwrap_or(u32::MAX);
+        let predicted_present = last
+            .checked_add(interval.saturating_mul(intervals))
+            .unwrap_or_else(|| Instant::now() + interval);
+        Some(PresentTiming {
+            refresh_duration,
+            present_interval,
+            predicted_present,
+            source,
+        })
+    }
+
+    /// Returns statistics of the frames presented since the last call, clearing them. Used to
+    /// emit [`FramePresented`] events.
+    #[inline]
+    pub fn take_presented_frames(&mut self) -> Vec<FrameStats> {
+        std::mem::take(&mut self.presented_frames)
+    }
+
+    /// Set window renderer present mode. This triggers a swapchain recreation.
+    #[inline]
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        if self.present_mode != present_mode {
+            self.present_mode = present_mode;
+            self.recreate_swapchain = true;
+        }
+    }
+
+    /// Resolves bevy's present mode against the modes supported by the surface, see
+    /// [`resolve_present_mode`], and recreates the swapchain if the selected mode changed. Used when
+    /// `Window::present_mode` changes at runtime, e.g. when toggling vsync. An unsupported explicit
+    /// mode falls back like at window creation and is reported with a [`PresentModeFallback`]
+    /// event.
+    pub fn request_present_mode(&mut self, requested: BevyPresentMode) {
+        let present_mode =
+            resolve_present_mode(requested, &self.supported_present_modes, self.allow_tearing);
+        self.present_mode_fallback =
+            Self::check_present_mode_fallback(&self.window, requested, present_mode);
+        if present_mode != self.present_mode {
+            info!(
+                "Switching present mode of window {:?} from {:?} to {:?}",
+                self.window.id(),
+                self.present_mode,
+                present_mode
+            );
+        }
+        self.set_present_mode(present_mode);
+    }
+
+    /// Return the current present mode of the swapchain.
+    #[inline]
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+
+    /// Present modes supported by the surface of this window.
+    #[inline]
+    pub fn supported_present_modes(&self) -> &[PresentMode] {
+        &self.supported_present_modes
+    }
+
+    /// The `(format, color space)` pairs supported by the window's surface. HDR color spaces are
+    /// only reported with [`BevyVulkanoSettings::hdr`](crate::BevyVulkanoSettings::hdr).
+    pub fn supported_surface_formats(&self) -> Vec<(Format, ColorSpace)> {
+        self.graphics_queue
+            .device()
+            .physical_device()
+            .surface_formats(&self.surface(), SurfaceInfo::default())
+            .unwrap_or_default()
+    }
+
+    /// Whether the surface supports given present mode.
+    #[inline]
+    pub fn supports_present_mode(&self, present_mode: PresentMode) -> bool {
+        self.supported_present_modes.contains(&present_mode)
+    }
+
+    /// Whether the surface supports a present mode which allows tearing (`Immediate` or
+    /// `FifoRelaxed`), e.g. for adaptive sync.
+    #[inline]
+    pub fn supports_tearing(&self) -> bool {
+        self.supports_present_mode(PresentMode::Immediate)
+            || self.supports_present_mode(PresentMode::FifoRelaxed)
+    }
+
+    /// Whether tearing present modes are allowed when resolving automatic present modes.
+    #[inline]
+    pub fn allow_tearing(&self) -> bool {
+        self.allow_tearing
+    }
+
+    /// Number of array layers in the swapchain images, e.g. 2 for stereo rendering.
+    #[inline]
+    pub fn swapchain_array_layers(&self) -> u32 {
+        self.swapchain.image_array_layers()
+    }
+
+    /// Number of swapchain images, which may be more than requested with
+    /// [`BevyVulkanoSettings::min_swapchain_image_count`](crate::BevyVulkanoSettings::min_swapchain_image_count).
+    #[inline]
+    pub fn swapchain_image_count(&self) -> u32 {
+        self.final_views.len() as u32
+    }
+
+    /// Usages the swapchain images were created with.
+    #[inline]
+    pub fn swapchain_image_usage(&self) -> ImageUsage {
+        self.swapchain.image_usage()
+    }
+
+    /// Whether the swapchain images can be written directly by compute shaders, see
+    /// [`SwapchainComputePass`](crate::SwapchainComputePass).
+    #[inline]
+    pub fn supports_storage_writes(&self) -> bool {
+        self.swapchain_image_usage().intersects(ImageUsage::STORAGE)
+    }
+
+    /// Return swapchain image format
+    #[inline]
+    pub fn swapchain_format(&self) -> Format {
+        self.final_views[self.image_index as usize].format()
+    }
+
+    /// Return swapchain color space
+    #[inline]
+    pub fn swapchain_color_space(&self) -> ColorSpace {
+        self.swapchain.image_color_space()
+    }
+
+    /// Whether the swapchain uses an HDR / extended range color space.
+    #[inline]
+    pub fn is_hdr(&self) -> bool {
+        is_hdr_color_space(self.swapchain_color_space())
+    }
+
+    /// Sets HDR metadata of the swapchain. Requires the `ext_hdr_metadata` device extension and an
+    /// HDR swapchain. The metadata is re-applied when the swapchain is recreated.
+    pub fn set_hdr_metadata(&mut self, metadata: HdrMetadata) -> Result<(), HdrMetadataError> {
+        set_swapchain_hdr_metadata(&self.swapchain, &metadata)?;
+        self.hdr_metadata = Some(metadata);
+        Ok(())
+    }
+
+    /// HDR metadata set with [`VulkanoWindowRenderer::set_hdr_metadata`].
+    #[inline]
+    pub fn hdr_metadata(&self) -> Option<HdrMetadata> {
+        self.hdr_metadata
+    }
+
+    /// Luminance in nits at which SDR content (e.g. UI) should be composited on an HDR swapchain.
+    ///
+    /// Vulkan does not expose the display's SDR white level, so this defaults to
+    /// [`DEFAULT_SDR_WHITE_LEVEL`] until set with [`VulkanoWindowRenderer::set_sdr_white_level`],
+    /// e.g. from an in-game brightness setting or a platform specific query.
+    #[inline]
+    pub fn sdr_white_level(&self) -> f32 {
+        self.sdr_white_level
+    }
+
+    /// Sets the SDR white level in nits.
+    #[inline]
+    pub fn set_sdr_white_level(&mut self, nits: f32) {
+        self.sdr_white_level = nits.max(0.0);
+    }
+
+    /// Multiplier for linear SDR colors written to an `ExtendedSrgbLinear` swapchain so that SDR
+    /// white matches [`VulkanoWindowRenderer::sdr_white_level`].
+    #[inline]
+    pub fn sdr_white_scale(&self) -> f32 {
+        self.sdr_white_level / DEFAULT_SDR_WHITE_LEVEL
+    }
+
+    /// Returns the index of last swapchain image that is the next render target.
+    #[inline]
+    pub fn image_index(&self) -> u32 {
+        self.image_index
+    }
+
+    /// Graphics queue of this window. You also can access this through [`VulkanoContext`].
+    #[inline]
+    pub fn graphics_queue(&self) -> Arc<Queue> {
+        self.graphics_queue.clone()
+    }
+
+    /// Queue the swapchain is presented from, either the graphics or the compute queue, see
+    /// [`PresentQueue`].
+    #[inline]
+    pub fn present_queue(&self) -> Arc<Queue> {
+        self.present_queue.clone()
+    }
+
+    /// Compute queue of this window. You can also access this through [`VulkanoContext`].
+    #[inline]
+    pub fn compute_queue(&self) -> Arc<Queue> {
+        self.compute_queue.clone()
+    }
+
+    /// Render target surface.
+    #[inline]
+    pub fn surface(&self) -> Arc<Surface> {
+        self.swapchain.surface().clone()
+    }
+
+    /// Winit window (you can manipulate window through this).
+    #[inline]
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    pub(crate) fn shared_window(&self) -> Arc<Window> {
+        self.window.clone()
+    }
+
+    /// Size of the physical window.
+    #[inline]
+    pub fn window_size(&self) -> [f32; 2] {
+        let size = self.window().inner_size();
+        [size.width as f32, size.height as f32]
+    }
+
+    /// Size of the final swapchain image (surface).
+    #[inline]
+    pub fn swapchain_image_size(&self) -> [u32; 2] {
+        let extent = self.final_views[0].image().extent();
+        [extent[0], extent[1]]
+    }
+
+    /// Extent of the swapchain images along with the logical size and scale factor they were
+    /// created with.
+    #[inline]
+    pub fn render_extent(&self) -> RenderExtent {
+        self.render_extent
+    }
+
+    /// Current swapchain state, see [`SwapchainInfo`].
+    pub fn swapchain_info(&self) -> SwapchainInfo {
+        SwapchainInfo {
+            format: self.swapchain_format(),
+            color_space: self.swapchain_color_space(),
+            extent: self.swapchain.image_extent(),
+            image_count: self.swapchain_image_count(),
+            present_mode: self.present_mode,
+            recreated_at_frame: self.swapchain_recreated_at,
+        }
+    }
+
+    /// Number of swapchain recreations of this window. Changes whenever the swapchain image views
+    /// and resizable additional image views of the window are replaced, so caches of objects
+    /// referencing them can be dropped. [`FramebufferCache`](crate::FramebufferCache) does this
+    /// by itself.
+    #[inline]
+    pub fn swapchain_generation(&self) -> u64 {
+        self.swapchain_generation
+    }
+
+    /// Driver workarounds applied to images created by the renderer.
+    #[inline]
+    pub fn driver_workarounds(&self) -> DriverWorkarounds {
+        self.driver_workarounds
+    }
+
+    /// Sets the driver workarounds applied to images created by the renderer from now on.
+    #[inline]
+    pub fn set_driver_workarounds(&mut self, driver_workarounds: DriverWorkarounds) {
+        self.driver_workarounds = driver_workarounds;
+    }
+
+    /// Whether presents are deferred to the end of the frame to present with other windows.
+    #[inline]
+    pub fn present_group(&self) -> Option<PresentGroup> {
+        self.present_group
+    }
+
+    /// Sets whether presents are deferred to the end of the frame to present with other windows.
+    #[inline]
+    pub fn set_present_group(&mut self, present_group: Option<PresentGroup>) {
+        self.present_group = present_group;
+    }
+
+    /// Return the current swapchain image view.
+    #[inline]
+    pub fn swapchain_image_view(&self) -> Arc<ImageView> {
+        self.final_views[self.image_index as usize].clone()
+    }
+
+    /// Return scale factor accounted window size.
+    #[inline]
+    pub fn resolution(&self) -> [f32; 2] {
+        let size = self.window().inner_size();
+        let scale_factor = self.window().scale_factor();
+        [
+            (size.width as f64 / scale_factor) as f32,
+            (size.height as f64 / scale_factor) as f32,
+        ]
+    }
+
+    #[inline]
+    pub fn aspect_ratio(&self) -> f32 {
+        let dims = self.window_size();
+        dims[0] / dims[1]
+    }
+
+    /// Resize swapchain and camera view images at the beginning of next frame based on window
+    /// size.
+    #[inline]
+    pub fn resize(&mut self) {
+        self.recreate_swapchain = true;
+    }
+
+    /// Add interim image view that resizes with window.
+    #[inline]
+    pub fn add_additional_image_view(&mut self, key: usize, format: Format, usage: ImageUsage) {
+        let image = new_resizable_image(
+            &self.memory_allocator,
+            &self.driver_workarounds,
+            self.final_views[0].image().extent(),
+            format,
+            usage,
+        );
+        self.additional_image_views.insert(key, image);
+    }
+
+    /// Adds an additional image view like
+    /// [`VulkanoWindowRenderer::add_additional_image_view`], keeping its images of the previous
+    /// `frames` frames accessible with [`VulkanoWindowRenderer::additional_image_view_history`],
+    /// e.g. for TAA or temporal denoising. Add `SAMPLED` to `usage` to sample the history.
+    ///
+    /// Each present moves the frame's image into the history and the view of `key` becomes the
+    /// oldest history image (or a new image while the history fills up). Reading the history in
+    /// the next frame is ordered after the previous frame's writes, as frames are chained on the
+    /// graphics queue.
+    pub fn add_additional_image_view_with_history(
+        &mut self,
+        key: usize,
+        format: Format,
+        usage: ImageUsage,
+        frames: usize,
+    ) {
+        self.add_additional_image_view(key, format, usage);
+        self.image_histories.insert(key, ImageHistory::new(frames));
+    }
+
+    /// The image of additional image view `key` from `frames_ago` frames ago, 1 being the previous
+    /// frame. `None` if the view has no history or not enough frames were presented since it was
+    /// created or resized, see [`VulkanoWindowRenderer::additional_image_view_history_len`].
+    pub fn additional_image_view_history(
+        &self,
+        key: usize,
+        frames_ago: usize,
+    ) -> Option<Arc<ImageView>> {
+        self.image_histories.get(&key)?.get(frames_ago)
+    }
+
+    /// Number of previous frames available for additional image view `key`. The history is reset
+    /// when the swapchain is resized, so temporal effects should treat 0 as a camera cut.
+    pub fn additional_image_view_history_len(&self, key: usize) -> usize {
+        self.image_histories
+            .get(&key)
+            .map_or(0, |history| history.len())
+    }
+
+    /// Moves the images of additional image views with history to their history after a present.
+    fn rotate_image_histories(&mut self) {
+        for (key, history) in self.image_histories.iter_mut() {
+            let Some(presented) = self.additional_image_views.get(key).cloned() else {
+                continue;
+            };
+            let next = history.push(presented.clone()).unwrap_or_else(|| {
+                new_resizable_image(
+                    &self.memory_allocator,
+                    &self.driver_workarounds,
+                    presented.image().extent(),
+                    presented.format(),
+                    presented.usage(),
+                )
+            });
+            self.additional_image_views.insert(*key, next);
+        }
+    }
+
+    /// Get additional image view by key.
+    #[inline]
+    pub fn get_additional_image_view(&mut self, key: usize) -> Arc<ImageView> {
+        self.additional_image_views.get(&key).unwrap().clone()
+    }
+
+    /// Remove additional image by key.
+    #[inline]
+    pub fn remove_additional_image_view(&mut self, key: usize) {
+        self.additional_image_views.remove(&key);
+        self.image_histories.remove(&key);
+    }
+
+    /// Iterate additional image views with their keys.
+    #[inline]
+    pub fn additional_image_views(&self) -> impl Iterator<Item = (usize, Arc<ImageView>)> + '_ {
+        self.additional_image_views
+            .iter()
+            .map(|(key, view)| (*key, view.clone()))
+    }
+
+    /// Creates the shared depth image of the window with `format`, replacing the previous one. Like
+    /// additional image views, it follows the swapchain size. Usually managed with the
+    /// [`SharedDepth`](crate::SharedDepth) component.
+    pub fn enable_shared_depth(&mut self, format: Format) {
+        let usage = ImageUsage::DEPTH_STENCIL_ATTACHMENT | ImageUsage::SAMPLED;
+        let image = Image::new(
+            self.memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent: self.final_views[0].image().extent(),
+                usage,
+                ..Default::default()
+            },
+            self.driver_workarounds.image_allocation_info(usage),
+        )
+        .unwrap();
+        GpuMemoryBudget::track_image(&image);
+        self.shared_depth = Some(ImageView::new_default(image).unwrap());
+    }
+
+    /// Drops the shared depth image. Passes still using it keep it alive until they finish.
+    #[inline]
+    pub fn disable_shared_depth(&mut self) {
+        self.shared_depth = None;
+    }
+
+    /// The shared depth image, if enabled. All passes of the window rendering with depth should
+    /// use this image, so depth written by a [`DepthPrepass`](crate::DepthPrepass) is tested
+    /// against by later passes.
+    #[inline]
+    pub fn shared_depth(&self) -> Option<Arc<ImageView>> {
+        self.shared_depth.clone()
+    }
+
+    /// The depth/stencil image of the window with `format`, sized like the swapchain. Created on
+    /// first use and recreated when the format changes or the window resizes, so passes can call
+    /// this every frame instead of recreating depth images on `WindowResized`. This is the
+    /// [`shared_depth`](Self::shared_depth) image.
+    ///
+    /// ```ignore
+    /// let depth = renderer.depth_image_view(Format::D24_UNORM_S8_UINT);
+    /// let framebuffer = Framebuffer::new(render_pass, FramebufferCreateInfo {
+    ///     attachments: vec![renderer.swapchain_image_view(), depth],
+    ///     ..Default::default()
+    /// })?;
+    /// ```
+    pub fn depth_image_view(&mut self, format: Format) -> Arc<ImageView> {
+        if self.shared_depth.as_ref().map(|depth| depth.format()) != Some(format) {
+            self.enable_shared_depth(format);
+        }
+        self.shared_depth.clone().unwrap()
+    }
+
+    /// Copy the swapchain image to the host when it is next presented. The frame is waited on and
+    /// the copy can be taken with [`VulkanoWindowRenderer::take_capture`] after
+    /// [`VulkanoWindowRenderer::present`]. Requires the surface to support `TRANSFER_SRC` usage.
+    #[cfg(feature = "capture")]
+    #[inline]
+    pub fn request_capture(&mut self) {
+        self.capture_requested = true;
+    }
+
+    /// Returns the swapchain image copied during the last present after
+    /// [`VulkanoWindowRenderer::request_capture`], clearing it.
+    #[cfg(feature = "capture")]
+    #[inline]
+    pub fn take_capture(&mut self) -> Option<ImageReadback> {
+        self.capture.take()
+    }
+
+    /// Captures the next presented frame as an [`RgbaImage`], delivered through the returned
+    /// [`FrameCapture`] once presented. Like [`VulkanoWindowRenderer::request_capture`], the frame
+    /// is waited on, but the copy stays available to [`VulkanoWindowRenderer::take_capture`].
+    #[cfg(feature = "capture")]
+    pub fn capture_next_frame(&mut self) -> FrameCapture {
+        let (sender, receiver) = channel();
+        self.capture_senders.push(sender);
+        self.capture_requested = true;
+        FrameCapture {
+            receiver,
+        }
+    }
+
+    /// Sends the frame captured by the last present to [`FrameCapture`] receivers. Dropping the
+    /// senders without a frame tells receivers the capture failed.
+    #[cfg(feature = "capture")]
+    fn send_frame_captures(&mut self) {
+        if self.capture_senders.is_empty() {
+            return;
+        }
+        let senders = std::mem::take(&mut self.capture_senders);
+        let Some(image) = self
+            .capture
+            .as_ref()
+            .and_then(|capture| capture.to_rgba_image())
+        else {
+            return;
+        };
+        for sender in senders {
+            // The receiver may have been dropped
+            let _ = sender.send(image.clone());
+        }
+    }
+
+    /// Time since the image of the last presented frame was acquired, if the GPU has not finished
+    /// that frame yet. Used by the [`FrameWatchdog`](crate::FrameWatchdog) to detect hangs.
+    pub fn pending_frame_duration(&self) -> Option<Duration> {
+        let (fence, acquired_at) = self.frame_in_flight.as_ref()?;
+        match fence.is_signaled() {
+            Ok(false) => Some(acquired_at.elapsed()),
+            _ => None,
+        }
+    }
+
+    /// Recreates the swapchain now if it has been invalidated, e.g. by a resize, instead of on
+    /// the next [`VulkanoWindowRenderer::acquire`].
+    #[inline]
+    pub fn recreate_swapchain_if_needed(&mut self) {
+        if self.recreate_swapchain {
+            self.recreate_swapchain_and_views();
+        }
+    }
+
+    /// Whether the window should be rendered this update. Always true unless the window has
+    /// [`RenderOnDemand`](crate::RenderOnDemand), in which case render systems can skip acquire
+    /// and present until a redraw is requested.
+    #[inline]
+    pub fn needs_redraw(&self) -> bool {
+        !self.render_on_demand || self.redraw_requested || self.recreate_swapchain
+    }
+
+    /// Requests a redraw of a [`RenderOnDemand`](crate::RenderOnDemand) window, cleared by the
+    /// next present.
+    #[inline]
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    #[cfg(feature = "windowing")]
+    pub(crate) fn set_render_on_demand(&mut self, render_on_demand: bool) {
+        if render_on_demand && !self.render_on_demand {
+            self.redraw_requested = true;
+        }
+        self.render_on_demand = render_on_demand;
+    }
+
+    /// Begin your rendering by calling `acquire`.
+    /// Returns a [`GpuFuture`] representing the time after which the swapchain image has been
+    /// acquired and previous frame ended.
+    /// Execute your command buffers after calling this function and finish rendering by calling
+    /// [`VulkanoWindowRenderer::present`].
+    #[inline]
+    pub fn acquire(&mut self) -> Result<Box<dyn GpuFuture>, VulkanError> {
+        profile_scope!("bevy_vulkano::acquire");
+        // Recreate swap chain if needed (when resizing of window occurs or swapchain is outdated)
+        // Also resize render views if needed
+        self.recreate_swapchain_if_needed();
+
+        // Acquire next image in the swapchain
+        let acquire_start = Instant::now();
+        let (image_index, suboptimal, acquire_future) =
+            match swapchain::acquire_next_image(self.swapchain.clone(), None)
+                .map_err(Validated::unwrap)
+            {
+                Ok(r) => r,
+                Err(VulkanError::OutOfDate) => {
+                    self.recreate_swapchain = true;
+                    return Err(VulkanError::OutOfDate);
+                }
+                Err(e) => panic!("failed to acquire next image: {e}"),
+            };
+        if suboptimal {
+            self.recreate_swapchain = true;
+        }
+        // Update our image index
+        self.image_index = image_index;
+        self.acquired_at = Some(Instant::now());
+        self.acquire_wait = acquire_start.elapsed();
+        self.acquire_suboptimal = suboptimal;
+        self.acquire_trace_id = trace_vk!(
+            "acquire window {:?}: image {}, suboptimal {}",
+            self.window.id(),
+            image_index,
+            suboptimal
+        );
+
+        if self
+            .present_group
+            .is_some_and(|group| group.wait_for_acquires)
+        {
+            if let Err(e) = acquire_future.wait(None) {
+                warn!("Failed to wait for the swapchain image: {e}");
+            }
+        }
+        let future = self.previous_frame_end.take().unwrap().join(acquire_future);
+
+        Ok(
+            self.run_frame_hooks(future.boxed(), |hook, context, future| {
+                hook.after_acquire(context, future)
+            }),
+        )
+    }
+
+    /// Marks a region of the current swapchain image as changed since the previous present.
+    ///
+    /// With incremental present enabled, presenting only updates the damaged regions on screen.
+    /// Without any damage, or without the extension, the whole image is presented. Damage is
+    /// cleared after each present. The whole image must still be rendered, e.g. by redrawing the
+    /// damage of the previous frames that were presented from other swapchain images.
+    pub fn add_damage(&mut self, rect: DamageRect) {
+        self.damage.push(rect);
+    }
+
+    /// Damage added since the last present.
+    #[inline]
+    pub fn damage(&self) -> &[DamageRect] {
+        &self.damage
+    }
+
+    /// Whether damage is presented, see [`supports_incremental_present`].
+    #[inline]
+    pub fn supports_incremental_present(&self) -> bool {
+        supports_incremental_present(self.graphics_queue.device())
+    }
+
+    /// Adds a hook called after each acquire and before each present of this window.
+    pub fn add_frame_hook(&mut self, hook: impl FrameHook + 'static) -> FrameHookId {
+        let id = FrameHookId(self.next_frame_hook_id);
+        self.next_frame_hook_id += 1;
+        self.frame_hooks.push((id, Box::new(hook)));
+        id
+    }
+
+    /// Removes a hook added with [`VulkanoWindowRenderer::add_frame_hook`]. Returns `false` if it
+    /// was already removed.
+    pub fn remove_frame_hook(&mut self, id: FrameHookId) -> bool {
+        let len = self.frame_hooks.len();
+        self.frame_hooks.retain(|(hook_id, _)| *hook_id != id);
+        self.frame_hooks.len() != len
+    }
+
+    /// Removes all frame hooks of this window.
+    pub fn clear_frame_hooks(&mut self) {
+        self.frame_hooks.clear();
+    }
+
+    fn run_frame_hooks(
+        &mut self,
+        mut future: Box<dyn GpuFuture>,
+        run: impl Fn(&mut dyn FrameHook, &FrameHookContext, Box<dyn GpuFuture>) -> Box<dyn GpuFuture>,
+    ) -> Box<dyn GpuFuture> {
+        if self.frame_hooks.is_empty() {
+            return future;
+        }
+        let context = FrameHookContext {
+            window: &self.window,
+            image_index: self.image_index,
+            swapchain_image_view: self.swapchain_image_view(),
+            graphics_queue: self.graphics_queue.clone(),
+            memory_allocator: self.memory_allocator.clone(),
+        };
+        for (_, hook) in self.frame_hooks.iter_mut() {
+            future = run(hook.as_mut(), &context, future);
+        }
+        future
+    }
+
+    /// Finishes rendering by presenting the swapchain. Pass your last future as an input to this
+    /// function.
+    ///
+    /// Depending on your implementation, you may want to wait on your future. For example, a
+    /// compute shader dispatch using an image that's being later drawn should probably be waited
+    /// on.
+    #[inline]
+    pub fn present(&mut self, after_future: Box<dyn GpuFuture>, wait_future: bool) {
+        profile_scope!("bevy_vulkano::present");
+        let cpu_time = self
+            .acquired_at
+            .map(|acquired_at| acquired_at.elapsed())
+            .unwrap_or_default();
+        let after_future = self.run_frame_hooks(after_future, |hook, context, future| {
+            hook.before_present(context, future)
+        });
+        #[cfg(feature = "capture")]
+        let (after_future, wait_future) = if self.capture_requested {
+            // The copy must finish before the captured image can be read
+            (self.record_capture(after_future), true)
+        } else {
+            (after_future, wait_future)
+        };
+        if self.present_group.is_some() {
+            self.deferred_present = Some(DeferredPresent {
+                future: after_future,
+                wait_future,
+                cpu_time,
+            });
+            return;
+        }
+        self.submit_present(after_future, wait_future, cpu_time);
+    }
+
+    /// Submits the frame's rendering of a deferred present, so that presenting the group doesn't
+    /// wait for it.
+    pub(crate) fn submit_deferred_rendering(&mut self) {
+        let Some(deferred) = self.deferred_present.as_mut() else {
+            return;
+        };
+        let future = std::mem::replace(
+            &mut deferred.future,
+            sync::now(self.graphics_queue.device().clone()).boxed(),
+        );
+        trace_vk!(
+            "submit grouped rendering of window {:?}: image {}",
+            self.window.id(),
+            self.image_index
+        );
+        match future.then_signal_semaphore_and_flush() {
+            Ok(future) => deferred.future = future.boxed(),
+            Err(e) => {
+                // The acquire wait was lost with the failed submission, so presenting now could
+                // race the presentation engine. Drop the frame, the recreated swapchain retires
+                // the acquired image
+                bevy::log::error!("Failed to flush future: {e}");
+                self.deferred_present = None;
+                self.recreate_swapchain = true;
+                #[cfg(feature = "capture")]
+                {
+                    self.capture = None;
+                    self.capture_senders.clear();
+                }
+                self.previous_frame_end =
+                    Some(sync::now(self.graphics_queue.device().clone()).boxed());
+            }
+        }
+    }
+
+    /// Presents a deferred present, if any.
+    pub(crate) fn present_deferred(&mut self) {
+        if let Some(deferred) = self.deferred_present.take() {
+            self.submit_present(deferred.future, deferred.wait_future, deferred.cpu_time);
+        }
+    }
+
+    fn submit_present(
+        &mut self,
+        after_future: Box<dyn GpuFuture>,
+        wait_future: bool,
+        cpu_time: Duration,
+    ) {
+        trace_vk!(
+            "present window {:?}: image {} (acquire #{:?}), {:?}, wait {}",
+            self.window.id(),
+            self.image_index,
+            self.acquire_trace_id,
+            self.present_mode,
+            wait_future
+        );
+        // Rendering on the graphics queue is handed off to a separate present queue with a
+        // semaphore
+        let after_future = if Arc::ptr_eq(&self.present_queue, &self.graphics_queue) {
+            after_future
+        } else {
+            after_future.then_signal_semaphore().boxed()
+        };
+        let mut present_info =
+            SwapchainPresentInfo::swapchain_image_index(self.swapchain.clone(), self.image_index);
+        let damage = std::mem::take(&mut self.damage);
+        if self.supports_incremental_present() {
+            let extent = self.swapchain.image_extent();
+            present_info.present_regions =
+                present_regions(&damage, extent, self.swapchain.image_array_layers());
+        }
+        let future = after_future
+            .then_swapchain_present(self.present_queue.clone(), present_info)
+            .then_signal_fence_and_flush();
+        match future.map_err(Validated::unwrap) {
+            Ok(future) => {
+                let presented_at = Instant::now();
+                let mut future = Arc::new(future);
+                // Prevent OutOfMemory error on Nvidia :(
+                // https://github.com/vulkano-rs/vulkano/issues/627
+                if wait_future {
+                    if let Err(e) = future.wait(None) {
+                        bevy::log::error!("Failed to wait on present future: {e}");
+                    }
+                    // wait allows you to organize resource waiting yourself.
+                } else {
+                    future.cleanup_finished();
+                }
+                #[cfg(feature = "capture")]
+                self.send_frame_captures();
+
+                let acquired_at = self.acquired_at.take().unwrap_or_else(Instant::now);
+                self.presented_frames.push(FrameStats {
+                    frame_index: self.frame_index,
+                    cpu_time,
+                    acquire_wait: self.acquire_wait,
+                    suboptimal: self.acquire_suboptimal,
+                    presented_at,
+                });
+                self.frame_index += 1;
+                self.redraw_requested = false;
+                self.rotate_image_histories();
+                if self.present_times.len() == PRESENT_TIMING_SAMPLES {
+                    self.present_times.pop_front();
+                }
+                self.present_times.push_back(Instant::now());
+                self.frame_in_flight = Some((future.clone(), acquired_at));
+                self.previous_frame_end = Some(future.boxed());
+            }
+            Err(VulkanError::OutOfDate) => {
+                trace_vk!("present window {:?}: out of date", self.window.id());
+                self.recreate_swapchain = true;
+                #[cfg(feature = "capture")]
+                {
+                    self.capture = None;
+                    self.capture_senders.clear();
+                }
+                self.previous_frame_end =
+                    Some(sync::now(self.graphics_queue.device().clone()).boxed());
+            }
+            Err(e) => {
+                bevy::log::error!("Failed to flush future: {e}");
+                #[cfg(feature = "capture")]
+                {
+                    self.capture = None;
+                    self.capture_senders.clear();
+                }
+                self.previous_frame_end =
+                    Some(sync::now(self.graphics_queue.device().clone()).boxed());
+            }
+        }
+    }
+
+    /// Copies the current swapchain image into a new readback after `after_future`.
+    #[cfg(feature = "capture")]
+    fn record_capture(&mut self, after_future: Box<dyn GpuFuture>) -> Box<dyn GpuFuture> {
+        self.capture_requested = false;
+        let image = self.final_views[self.image_index as usize].image().clone();
+        if !image.usage().intersects(ImageUsage::TRANSFER_SRC) || !supports_readback(image.format())
+        {
+            warn!(
+                "Swapchain image of window {:?} can't be captured (format {:?}, usage {:?})",
+                self.window.id(),
+                image.format(),
+                image.usage()
+            );
+            return after_future;
+        }
+        let extent = image.extent();
+        let readback = ImageReadback::new(
+            self.memory_allocator.clone(),
+            [extent[0], extent[1]],
+            image.format(),
+        );
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.graphics_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        readback.record(&mut builder, image).unwrap();
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit swapchain capture copy of window {:?}, image {} on queue family {}",
+            self.window.id(),
+            self.image_index,
+            self.graphics_queue.queue_family_index()
+        );
+        self.capture = Some(readback);
+        after_future
+            .then_execute(self.graphics_queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+
+    /// Recreates swapchain images and image views which follow the window size.
+    fn recreate_swapchain_and_views(&mut self) {
+        profile_scope!("bevy_vulkano::recreate_swapchain");
+        let image_extent: [u32; 2] = self.window().inner_size().into();
+
+        if image_extent.contains(&0) {
+            return;
+        }
+
+        trace_vk!(
+            "recreate swapchain of window {:?}: {:?}, {:?}",
+            self.window.id(),
+            image_extent,
+            self.present_mode
+        );
+        let (new_swapchain, new_images) = self
+            .swapchain
+            .recreate(SwapchainCreateInfo {
+                image_extent,
+                // Use present mode from current state
+                present_mode: self.present_mode,
+                ..self.swapchain.create_info()
+            })
+            .expect("failed to recreate swapchain");
+
+        self.swapchain = new_swapchain;
+        retire_views(
+            self.final_views
+                .iter()
+                .chain(self.additional_image_views.values())
+                .chain(
+                    self.image_histories
+                        .values()
+                        .flat_map(|history| history.views()),
+                )
+                .chain(self.shared_depth.as_ref()),
+        );
+        self.present_times.clear();
+        self.render_extent = RenderExtent::new(image_extent, self.window.scale_factor());
+        self.swapchain_recreated_at = self.frame_index;
+        if let Some(metadata) = self.hdr_metadata {
+            if let Err(e) = set_swapchain_hdr_metadata(&self.swapchain, &metadata) {
+                warn!("Failed to re-apply HDR metadata after swapchain recreation: {e}");
+            }
+        }
+        let new_images = new_images
+            .into_iter()
+            .map(|image| ImageView::new_default(image).unwrap())
+            .collect::<Vec<_>>();
+        self.final_views = new_images;
+        // Resize images that follow swapchain size
+        let resizable_views = self
+            .additional_image_views
+            .iter()
+            .map(|c| *c.0)
+            .collect::<Vec<usize>>();
+        for i in resizable_views {
+            let format = self.get_additional_image_view(i).format();
+            let usage = self.get_additional_image_view(i).usage();
+            let history = self.image_histories.remove(&i);
+            self.remove_additional_image_view(i);
+            self.add_additional_image_view(i, format, usage);
+            if let Some(mut history) = history {
+                history.clear();
+                self.image_histories.insert(i, history);
+            }
+        }
+        if let Some(format) = self.shared_depth.as_ref().map(|depth| depth.format()) {
+            self.enable_shared_depth(format);
+        }
+        self.swapchain_generation += 1;
+        self.recreate_swapchain = false;
+    }
+}
+
+/// Creates an image following the swapchain size, for additional image views.
+fn new_resizable_image(
+    allocator: &Arc<StandardMemoryAllocator>,
+    driver_workarounds: &DriverWorkarounds,
+    extent: [u32; 3],
+    format: Format,
+    usage: ImageUsage,
+) -> Arc<ImageView> {
+    let image = Image::new(
+        allocator.clone(),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent,
+            usage,
+            ..Default::default()
+        },
+        driver_workarounds.image_allocation_info(usage),
+    )
+    .unwrap();
+    GpuMemoryBudget::track_image(&image);
+    ImageView::new_default(image).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_MODES: [PresentMode; 4] = [
+        PresentMode::Immediate,
+        PresentMode::Mailbox,
+        PresentMode::Fifo,
+        PresentMode::FifoRelaxed,
+    ];
+
+    const BEVY_MODES: [BevyPresentMode; 6] = [
+        BevyPresentMode::AutoVsync,
+        BevyPresentMode::AutoNoVsync,
+        BevyPresentMode::Immediate,
+        BevyPresentMode::Mailbox,
+        BevyPresentMode::FifoRelaxed,
+        BevyPresentMode::Fifo,
+    ];
+
+    #[test]
+    fn preference_chains_end_with_fifo() {
+        for requested in BEVY_MODES {
+            assert_eq!(
+                present_mode_preference(requested).last(),
+                Some(&PresentMode::Fifo),
+                "{requested:?}"
+            );
+        }
+        assert_eq!(present_mode_preference(BevyPresentMode::Mailbox), &[
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+            PresentMode::Fifo
+        ]);
+        assert_eq!(present_mode_preference(BevyPresentMode::AutoNoVsync), &[
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+            PresentMode::Fifo
+        ]);
+    }
+
+    #[test]
+    fn resolves_requested_mode_when_supported() {
+        for allow_tearing in [true, false] {
+            assert_eq!(
+                resolve_present_mode(BevyPresentMode::Mailbox, &ALL_MODES, allow_tearing),
+                PresentMode::Mailbox
+            );
+            assert_eq!(
+                resolve_present_mode(BevyPresentMode::AutoNoVsync, &ALL_MODES, allow_tearing),
+                PresentMode::Mailbox
+            );
+            assert_eq!(
+                resolve_present_mode(BevyPresentMode::Fifo, &ALL_MODES, allow_tearing),
+                PresentMode::Fifo
+            );
+            // Explicitly requested tearing modes are used even if tearing isn't allowed
+            assert_eq!(
+                resolve_present_mode(BevyPresentMode::Immediate, &ALL_MODES, allow_tearing),
+                PresentMode::Immediate
+            );
+            assert_eq!(
+                resolve_present_mode(BevyPresentMode::FifoRelaxed, &ALL_MODES, allow_tearing),
+                PresentMode::FifoRelaxed
+            );
+        }
+    }
+
+    #[test]
+    fn mailbox_falls_back_to_immediate_with_tearing() {
+        let supported = [PresentMode::Immediate, PresentMode::Fifo];
+        for requested in [BevyPresentMode::Mailbox, BevyPresentMode::AutoNoVsync] {
+            assert_eq!(
+                resolve_present_mode(requested, &supported, true),
+                PresentMode::Immediate
+            );
+        }
+    }
+
+    #[test]
+    fn mailbox_falls_back_to_fifo_without_tearing() {
+        let supported = [PresentMode::Immediate, PresentMode::Fifo];
+        for requested in [BevyPresentMode::Mailbox, BevyPresentMode::AutoNoVsync] {
+            assert_eq!(
+                resolve_present_mode(requested, &supported, false),
+                PresentMode::Fifo
+            );
+        }
+    }
+
+    #[test]
+    fn immediate_falls_back_to_mailbox_then_fifo() {
+        let supported = [PresentMode::Mailbox, PresentMode::Fifo];
+        for allow_tearing in [true, false] {
+            assert_eq!(
+                resolve_present_mode(BevyPresentMode::Immediate, &supported, allow_tearing),
+                PresentMode::Mailbox
+            );
+        }
+        let supported = [PresentMode::FifoRelaxed, PresentMode::Fifo];
+        assert_eq!(
+            resolve_present_mode(BevyPresentMode::Immediate, &supported, true),
+            PresentMode::FifoRelaxed
+        );
+        assert_eq!(
+            resolve_present_mode(BevyPresentMode::Immediate, &supported, false),
+            PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn auto_vsync_uses_fifo_relaxed_only_with_tearing() {
+        assert_eq!(
+            resolve_present_mode(BevyPresentMode::AutoVsync, &ALL_MODES, true),
+            PresentMode::FifoRelaxed
+        );
+        assert_eq!(
+            resolve_present_mode(BevyPresentMode::AutoVsync, &ALL_MODES, false),
+            PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn falls_back_to_fifo_when_nothing_matches() {
+        for requested in BEVY_MODES {
+            for allow_tearing in [true, false] {
+                assert_eq!(
+                    resolve_present_mode(requested, &[PresentMode::Fifo], allow_tearing),
+                    PresentMode::Fifo
+                );
+                assert_eq!(
+                    resolve_present_mode(requested, &[], allow_tearing),
+                    PresentMode::Fifo
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::{fmt, path::Path, sync::Arc};
+
+pub use shaderc::ShaderKind;
+use vulkano::{
+    device::Device,
+    shader::{ShaderModule, ShaderModuleCreateInfo},
+    Validated, VulkanError,
+};
+
+use crate::shader_preprocessor::{ShaderPreprocessError, ShaderPreprocessor};
+
+/// Errors from compiling shaders at runtime.
+#[derive(Debug)]
+pub enum ShaderCompileError {
+    Preprocess(ShaderPreprocessError),
+    /// GLSL to SPIR-V compilation failed, holding the compiler's messages.
+    Compile(String),
+    Module(Validated<VulkanError>),
+}
+
+impl fmt::Display for ShaderCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderCompileError::Preprocess(e) => write!(f, "failed to preprocess shader: {e}"),
+            ShaderCompileError::Compile(e) => write!(f, "failed to compile shader: {e}"),
+            ShaderCompileError::Module(e) => write!(f, "failed to create shader module: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ShaderCompileError {}
+
+impl From<ShaderPreprocessError> for ShaderCompileError {
+    fn from(e: ShaderPreprocessError) -> Self {
+        ShaderCompileError::Preprocess(e)
+    }
+}
+
+/// Compiles GLSL `source` into a shader module at runtime. `file_name` is used in error
+/// messages.
+pub fn compile_glsl(
+    device: Arc<Device>,
+    source: &str,
+    kind: ShaderKind,
+    file_name: &str,
+) -> Result<Arc<ShaderModule>, ShaderCompileError> {
+    let compiler = shaderc::Compiler::new()
+        .ok_or_else(|| ShaderCompileError::Compile("failed to create compiler".to_string()))?;
+    let mut options = shaderc::CompileOptions::new()
+        .ok_or_else(|| ShaderCompileError::Compile("failed to create options".to_string()))?;
+    options.set_target_env(
+        shaderc::TargetEnv::Vulkan,
+        shaderc::EnvVersion::Vulkan1_0 as u32,
+    );
+    let artifact = compiler
+        .compile_into_spirv(source, kind, file_name, "main", Some(&options))
+        .map_err(|e| ShaderCompileError::Compile(e.to_string()))?;
+    // Safety: the SPIR-V was just produced by shaderc from GLSL
+    unsafe { ShaderModule::new(device, ShaderModuleCreateInfo::new(artifact.as_binary())) }
+        .map_err(ShaderCompileError::Module)
+}
+
+/// Preprocesses the shader at `path` with `preprocessor` and compiles it at runtime.
+pub fn compile_glsl_file(
+    device: Arc<Device>,
+    preprocessor: &ShaderPreprocessor,
+    path: impl AsRef<Path>,
+    kind: ShaderKind,
+) -> Result<Arc<ShaderModule>, ShaderCompileError> {
+    let path = path.as_ref();
+    let source = preprocessor.preprocess_file(path)?;
+    compile_glsl(device, &source, kind, &path.to_string_lossy())
+}
@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+
+use bevy::{
+    app::{App, Last, Plugin, PreUpdate},
+    log::{error, warn},
+    prelude::{Entity, Event, EventReader, EventWriter, IntoSystemConfigs, NonSendMut},
+    tasks::{futures_lite::future, IoTaskPool, Task},
+};
+use image::ImageFormat;
+
+use crate::{system::present_window_group, BevyVulkanoWindows, FrameCapture};
+
+/// Send to save the next presented frame of `window` as a PNG at `path`, handled by the
+/// [`ScreenshotPlugin`]. [`ScreenshotSaved`] is sent once the file is written.
+#[derive(Event, Debug, Clone)]
+pub struct ScreenshotRequested {
+    pub window: Entity,
+    pub path: PathBuf,
+}
+
+/// Sent when a [`ScreenshotRequested`] has been handled. On failure, `error` holds the reason.
+#[derive(Event, Debug, Clone)]
+pub struct ScreenshotSaved {
+    pub window: Entity,
+    pub path: PathBuf,
+    pub error: Option<String>,
+}
+
+/// Screenshots waiting for their frame or being written.
+#[derive(Default)]
+struct Screenshots {
+    pending: Vec<(ScreenshotRequested, FrameCapture)>,
+    saving: Vec<(ScreenshotRequested, Task<Result<(), String>>)>,
+}
+
+/// Saves window screenshots on [`ScreenshotRequested`] events. The final swapchain image is copied
+/// after present and written as a PNG on the [`IoTaskPool`], so requires bevy's `TaskPoolPlugin`.
+///
+/// ```ignore
+/// fn screenshot_on_f12(
+///     keys: Res<Input<KeyCode>>,
+///     windows: Query<Entity, With<PrimaryWindow>>,
+///     mut screenshots: EventWriter<ScreenshotRequested>,
+/// ) {
+///     if keys.just_pressed(KeyCode::F12) {
+///         screenshots.send(ScreenshotRequested {
+///             window: windows.single(),
+///             path: "screenshot.png".into(),
+///         });
+///     }
+/// }
+/// ```
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_non_send_resource::<Screenshots>()
+            .add_event::<ScreenshotRequested>()
+            .add_event::<ScreenshotSaved>()
+            .add_systems(PreUpdate, request_screenshots)
+            .add_systems(Last, save_screenshots.after(present_window_group));
+    }
+}
+
+/// Requests the next frame of windows for which [`ScreenshotRequested`] is received.
+fn request_screenshots(
+    mut requests: EventReader<ScreenshotRequested>,
+    mut screenshots: NonSendMut<Screenshots>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    mut saved_events: EventWriter<ScreenshotSaved>,
+) {
+    for request in requests.read() {
+        let Some(vulkano_window) = vulkano_windows.get_vulkano_window_mut(request.window) else {
+            warn!(
+                "Can't take a screenshot of {:?}, it has no renderer",
+                request.window
+            );
+            saved_events.send(ScreenshotSaved {
+                window: request.window,
+                path: request.path.clone(),
+                error: Some("the window has no renderer".to_string()),
+            });
+            continue;
+        };
+        let capture = vulkano_window.capture_next_frame();
+        screenshots.pending.push((request.clone(), capture));
+    }
+}
+
+/// Starts writing presented screenshots and sends [`ScreenshotSaved`] for finished ones.
+fn save_screenshots(
+    mut screenshots: NonSendMut<Screenshots>,
+    mut saved_events: EventWriter<ScreenshotSaved>,
+) {
+    let screenshots = &mut *screenshots;
+    let mut saved = |request: ScreenshotRequested, result: Result<(), String>| {
+        if let Err(e) = &result {
+            error!("Failed to save screenshot {:?}: {}", request.path, e);
+        }
+        saved_events.send(ScreenshotSaved {
+            window: request.window,
+            path: request.path,
+            error: result.err(),
+        });
+    };
+
+    let mut pending = vec![];
+    for (request, capture) in screenshots.pending.drain(..) {
+        match capture.try_take() {
+            Ok(Some(image)) => {
+                let path = request.path.clone();
+                let task = IoTaskPool::get().spawn(async move {
+                    image
+                        .save_with_format(&path, ImageFormat::Png)
+                        .map_err(|e| e.to_string())
+                });
+                screenshots.saving.push((request, task));
+            }
+            // The window wasn't presented this frame
+            Ok(None) => pending.push((request, capture)),
+            Err(e) => saved(request, Err(e.to_string())),
+        }
+    }
+    screenshots.pending = pending;
+
+    screenshots.saving.retain_mut(|(request, task)| {
+        match future::block_on(future::poll_once(task)) {
+            Some(result) => {
+                saved(request.clone(), result);
+                false
+            }
+            None => true,
+        }
+    });
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+/// Errors from [`ShaderPreprocessor`].
+#[derive(Debug)]
+pub enum ShaderPreprocessError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// `#include` at `line` (1-based) of `file` could not be found relative to the file nor in the
+    /// include directories.
+    IncludeNotFound {
+        include: String,
+        file: PathBuf,
+        line: usize,
+    },
+    /// The file includes itself, directly or through other includes.
+    RecursiveInclude(PathBuf),
+}
+
+impl fmt::Display for ShaderPreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderPreprocessError::Io {
+                path,
+                error,
+            } => write!(f, "failed to read {path:?}: {error}"),
+            ShaderPreprocessError::IncludeNotFound {
+                include,
+                file,
+                line,
+            } => write!(f, "{file:?}:{line}: include {include:?} not found"),
+            ShaderPreprocessError::RecursiveInclude(path) => {
+                write!(f, "{path:?} includes itself")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShaderPreprocessError {}
+
+/// A small GLSL preprocessor resolving `#include "file"` directives and injecting `#define`s,
+/// so shaders can be split into files shared between pipelines and configured from Rust
+/// constants. The output is plain GLSL for the runtime shader compiler.
+///
+/// Includes are resolved relative to the including file first, then in the include directories
+/// in order. Files containing `#pragma once` are included only once.
+///
+/// ```ignore
+/// let source = ShaderPreprocessor::default()
+///     .include_dir("assets/shaders/common")
+///     .define("LOCAL_SIZE", 8)
+///     .define("USE_SHADOWS", true as u32)
+///     .preprocess_file("assets/shaders/lighting.comp")?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ShaderPreprocessor {
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<(String, String)>,
+}
+
+impl ShaderPreprocessor {
+    /// Adds a directory searched for includes.
+    pub fn include_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    /// Defines `name` as `value`, inserted right after the `#version` directive.
+    pub fn define(&mut self, name: impl Into<String>, value: impl Display) -> &mut Self {
+        let name = name.into();
+        let value = value.to_string();
+        match self.defines.iter_mut().find(|(n, _)| *n == name) {
+            Some(define) => define.1 = value,
+            None => self.defines.push((name, value)),
+        }
+        self
+    }
+
+    /// Reads and preprocesses the shader at `path`.
+    pub fn preprocess_file(&self, path: impl AsRef<Path>) -> Result<String, ShaderPreprocessError> {
+        let path = path.as_ref();
+        let source = read_source(path)?;
+        self.preprocess(&source, path)
+    }
+
+    /// Preprocesses `source`, whose includes are resolved relative to `path`.
+    pub fn preprocess(&self, source: &str, path: &Path) -> Result<String, ShaderPreprocessError> {
+        let mut output = String::with_capacity(source.len());
+        self.resolve_includes(
+            source,
+            path,
+            &mut Vec::new(),
+            &mut HashSet::new(),
+            &mut output,
+        )?;
+        Ok(self.inject_defines(&output))
+    }
+
+    fn resolve_includes(
+        &self,
+        source: &str,
+        path: &Path,
+        stack: &mut Vec<PathBuf>,
+        included_once: &mut HashSet<PathBuf>,
+        output: &mut String,
+    ) -> Result<(), ShaderPreprocessError> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if stack.contains(&canonical) {
+            return Err(ShaderPreprocessError::RecursiveInclude(path.to_path_buf()));
+        }
+        stack.push(canonical.clone());
+        for (i, line) in source.lines().enumerate() {
+            let directive = line.trim();
+            if directive == "#pragma once" {
+                included_once.insert(canonical.clone());
+                continue;
+            }
+            let Some(include) = directive
+                .strip_prefix("#include")
+                .and_then(|rest| parse_include_name(rest.trim()))
+            else {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            };
+            let include_path = self.find_include(include, path).ok_or_else(|| {
+                ShaderPreprocessError::IncludeNotFound {
+                    include: include.to_string(),
+                    file: path.to_path_buf(),
+                    line: i + 1,
+                }
+            })?;
+            let include_canonical = include_path
+                .canonicalize()
+                .unwrap_or_else(|_| include_path.clone());
+            if included_once.contains(&include_canonical) {
+                continue;
+            }
+            let include_source = read_source(&include_path)?;
+            self.resolve_includes(&include_source, &include_path, stack, included_once, output)?;
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    fn find_include(&self, include: &str, from: &Path) -> Option<PathBuf> {
+        from.parent()
+            .into_iter()
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(include))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Inserts the defines after the `#version` directive, which must come first in GLSL.
+    fn inject_defines(&self, source: &str) -> String {
+        if self.defines.is_empty() {
+            return source.to_string();
+        }
+        let defines = self
+            .defines
+            .iter()
+            .map(|(name, value)| format!("#define {name} {value}\n"))
+            .collect::<String>();
+        let version_end = source
+            .find("#version")
+            .and_then(|start| source[start..].find('\n').map(|end| start + end + 1));
+        match version_end {
+            Some(end) => format!("{}{}{}", &source[..end], defines, &source[end..]),
+            None => format!("{defines}{source}"),
+        }
+    }
+}
+
+/// Parses `"file"` or `<file>`.
+fn parse_include_name(directive: &str) -> Option<&str> {
+    directive
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .or_else(|| {
+            directive
+                .strip_prefix('<')
+                .and_then(|rest| rest.strip_suffix('>'))
+        })
+}
+
+fn read_source(path: &Path) -> Result<String, ShaderPreprocessError> {
+    std::fs::read_to_string(path).map_err(|error| ShaderPreprocessError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
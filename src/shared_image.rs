@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use bevy::log::warn;
+use vulkano::{
+    device::Queue,
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+    sync::{future::FenceSignalFuture, GpuFuture, Sharing},
+};
+
+use crate::{trace_frame::trace_vk, QueueSharing};
+
+/// A device image rendered in one window's pass and displayed in other windows' passes, e.g. a
+/// viewport or panel of an editor dragged out into its own OS window.
+///
+/// The window writing the image starts its pass with [`SharedImage::before_write`] and finishes it
+/// with [`SharedImage::publish`]. Windows reading it start their passes with
+/// [`SharedImage::after_write`], which orders them after the write, and finish them with
+/// [`SharedImage::read_done`], so that the next write waits for the reads. When the windows'
+/// queues belong to different queue families, the image must be shared concurrently between them,
+/// see [`SharedImage::new`].
+///
+/// ```ignore
+/// // In the window rendering the viewport
+/// let before = shared.before_write(before, &renderer.graphics_queue());
+/// let after = viewport_pass.render(before, &[], shared.view(), None::<()>);
+/// let after = shared.publish(after);
+/// // In the window the viewport was dragged to
+/// let before = shared.after_write(before, &other_renderer.graphics_queue());
+/// let after = fullscreen.render(before, &[shared.view()], other_renderer.swapchain_image_view(), None::<()>);
+/// let after = shared.read_done(after);
+/// ```
+pub struct SharedImage {
+    view: Arc<ImageView>,
+    /// Fence of the last pass writing the image.
+    written: Option<SharedImageFence>,
+    /// Fences of the passes reading the last write.
+    reads: Vec<SharedImageFence>,
+}
+
+type SharedImageFence = Arc<FenceSignalFuture<Box<dyn GpuFuture>>>;
+
+impl SharedImage {
+    /// Creates an image usable on all `queues`, shared concurrently when they belong to different
+    /// queue families so that no ownership transfer is needed between windows.
+    pub fn new(
+        allocator: Arc<StandardMemoryAllocator>,
+        queues: &[Arc<Queue>],
+        extent: [u32; 2],
+        format: Format,
+        usage: ImageUsage,
+    ) -> SharedImage {
+        let image = Image::new(
+            allocator,
+            QueueSharing::Concurrent.image_create_info(queues, ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent: [extent[0], extent[1], 1],
+                usage,
+                ..Default::default()
+            }),
+            AllocationCreateInfo::default(),
+        )
+        .unwrap();
+        SharedImage::from_view(ImageView::new_default(image).unwrap())
+    }
+
+    /// Shares an existing image. Its sharing mode must allow all queues it's used on, see
+    /// [`SharedImage::is_usable_on`].
+    pub fn from_view(view: Arc<ImageView>) -> SharedImage {
+        SharedImage {
+            view,
+            written: None,
+            reads: vec![],
+        }
+    }
+
+    pub fn view(&self) -> Arc<ImageView> {
+        self.view.clone()
+    }
+
+    /// Whether the image can be accessed on `queue` without a queue family ownership transfer.
+    /// Images created with exclusive sharing are only usable on the queue family they were first
+    /// used on, which is assumed to be the family of the queue that wrote the image.
+    pub fn is_usable_on(&self, queue: &Arc<Queue>, writer: &Arc<Queue>) -> bool {
+        match self.view.image().sharing() {
+            Sharing::Exclusive => queue.queue_family_index() == writer.queue_family_index(),
+            Sharing::Concurrent(families) => {
+                families.contains(&queue.queue_family_index())
+                    && families.contains(&writer.queue_family_index())
+            }
+        }
+    }
+
+    /// Joins `before_future` of a pass on `queue` writing the image with the passes reading the
+    /// previous write, so the write doesn't overwrite the image while it's read. Reads on another
+    /// queue are waited for on the CPU, like in [`SharedImage::after_write`].
+    pub fn before_write(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        queue: &Arc<Queue>,
+    ) -> Box<dyn GpuFuture> {
+        let mut future = before_future;
+        for read in self.reads.drain(..) {
+            if read.queue().is_some_and(|reader| reader != *queue) {
+                if let Err(e) = read.wait(None) {
+                    warn!("Failed to wait for shared image read: {e}");
+                }
+            } else {
+                future = future.join(read).boxed();
+            }
+        }
+        future
+    }
+
+    /// Signals a fence after `after_write`, the future of a pass writing the image, and returns a
+    /// future the writing window can continue its frame with. Passes of other windows reading the
+    /// image wait for the write with [`SharedImage::after_write`].
+    ///
+    /// The fence is submitted with the rest of the frame, or by the first pass waiting for it, so
+    /// the frame's future keeps waiting on the swapchain image acquire.
+    pub fn publish(&mut self, after_write: Box<dyn GpuFuture>) -> Box<dyn GpuFuture> {
+        let fence = Arc::new(after_write.then_signal_fence());
+        self.written = Some(fence.clone());
+        fence.boxed()
+    }
+
+    /// Signals a fence after `after_read`, the future of a pass reading the image, which the next
+    /// [`SharedImage::before_write`] waits for. Returns a future the reading window can continue its
+    /// frame with.
+    pub fn read_done(&mut self, after_read: Box<dyn GpuFuture>) -> Box<dyn GpuFuture> {
+        self.reads
+            .retain(|read| !read.is_signaled().unwrap_or(false));
+        let fence = Arc::new(after_read.then_signal_fence());
+        self.reads.push(fence.clone());
+        fence.boxed()
+    }
+
+    /// Joins `before_future` of a pass on `queue` with the last write of the image, so the pass can
+    /// read it. A flushed write gives no GPU ordering with submissions on another queue, so when the
+    /// image was written on a different queue this blocks until the write finished.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image isn't usable on `queue`, see [`SharedImage::is_usable_on`].
+    pub fn after_write(
+        &self,
+        before_future: Box<dyn GpuFuture>,
+        queue: &Arc<Queue>,
+    ) -> Box<dyn GpuFuture> {
+        let Some(written) = &self.written else {
+            return before_future;
+        };
+        if let Some(writer) = written.queue() {
+            assert!(
+                self.is_usable_on(queue, &writer),
+                "shared image written on queue family {} can't be used on queue family {} without \
+                 concurrent sharing, create it with SharedImage::new including both queues",
+                writer.queue_family_index(),
+                queue.queue_family_index()
+            );
+            trace_vk!(
+                "share image {:?} from queue family {} to {}",
+                self.view.image().extent(),
+                writer.queue_family_index(),
+                queue.queue_family_index()
+            );
+            if writer != *queue {
+                if let Err(e) = written.wait(None) {
+                    warn!("Failed to wait for shared image write: {e}");
+                }
+                return before_future;
+            }
+        }
+        before_future.join(written.clone()).boxed()
+    }
+}
@@ -0,0 +1,133 @@
+use bevy::{
+    prelude::{Component, Query, Res},
+    time::Time,
+};
+
+/// Plays a range of frames of a texture atlas at a fixed rate, for animating 2D sprites without
+/// `bevy_sprite`, which doesn't work with this backend. The plugin advances it every update in
+/// `PreUpdate` (requires bevy's `TimePlugin`); read [`SpriteAnimation::frame`] or
+/// [`SpriteAnimation::atlas_uv`] when drawing the sprite.
+///
+/// ```ignore
+/// commands.spawn((Player, SpriteAnimation::new(8, 15, 12.0)));
+///
+/// fn draw_sprites(
+///     sprites: Query<(&Transform, &SpriteAnimation)>,
+///     mut batch: ResMut<MySpriteBatch>,
+/// ) {
+///     for (transform, animation) in &sprites {
+///         let (uv_offset, uv_size) = animation.atlas_uv(8, 4);
+///         batch.push(transform, uv_offset, uv_size);
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct SpriteAnimation {
+    /// Index of the first frame in the atlas, counting row by row.
+    pub first: u32,
+    /// Index of the last frame in the atlas, inclusive.
+    pub last: u32,
+    /// Frames per second.
+    pub fps: f32,
+    /// Whether to start over after the last frame. Otherwise the animation stops on it.
+    pub looping: bool,
+    /// Whether the animation advances. Set to `false` to pause.
+    pub playing: bool,
+    frame: u32,
+    /// Time since the current frame was shown.
+    elapsed: f32,
+    finished: bool,
+}
+
+impl SpriteAnimation {
+    /// A looping animation from frame `first` to `last` at `fps` frames per second.
+    pub fn new(first: u32, last: u32, fps: f32) -> SpriteAnimation {
+        SpriteAnimation {
+            first,
+            last: last.max(first),
+            fps,
+            looping: true,
+            playing: true,
+            frame: first,
+            elapsed: 0.0,
+            finished: false,
+        }
+    }
+
+    /// Plays the animation once, stopping on the last frame.
+    pub fn once(mut self) -> SpriteAnimation {
+        self.looping = false;
+        self
+    }
+
+    /// The current frame index in the atlas.
+    #[inline]
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Whether an animation which doesn't loop reached its last frame.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Number of frames in the animation.
+    #[inline]
+    pub fn frame_count(&self) -> u32 {
+        self.last - self.first + 1
+    }
+
+    /// Starts the animation over from its first frame.
+    pub fn restart(&mut self) {
+        self.frame = self.first;
+        self.elapsed = 0.0;
+        self.finished = false;
+        self.playing = true;
+    }
+
+    /// Advances the animation by `delta` seconds, skipping frames if more than one frame elapsed.
+    pub fn tick(&mut self, delta: f32) {
+        if !self.playing || self.finished || self.fps <= 0.0 {
+            return;
+        }
+        self.elapsed += delta;
+        let frame_duration = 1.0 / self.fps;
+        let frames = (self.elapsed / frame_duration) as u32;
+        if frames == 0 {
+            return;
+        }
+        self.elapsed -= frames as f32 * frame_duration;
+        let position = self.frame.clamp(self.first, self.last) - self.first + frames;
+        if self.looping {
+            self.frame = self.first + position % self.frame_count();
+        } else if position >= self.frame_count() - 1 {
+            self.frame = self.last;
+            self.finished = true;
+        } else {
+            self.frame = self.first + position;
+        }
+    }
+
+    /// Offset and size of the current frame in normalized texture coordinates of an atlas with
+    /// `columns` by `rows` equally sized frames.
+    pub fn atlas_uv(&self, columns: u32, rows: u32) -> ([f32; 2], [f32; 2]) {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        let size = [1.0 / columns as f32, 1.0 / rows as f32];
+        let offset = [
+            (self.frame % columns) as f32 * size[0],
+            (self.frame / columns % rows) as f32 * size[1],
+        ];
+        (offset, size)
+    }
+}
+
+/// Advances all [`SpriteAnimation`]s by the frame's delta time.
+pub(crate) fn animate_sprites(time: Res<Time>, mut animations: Query<&mut SpriteAnimation>) {
+    let delta = time.delta_seconds();
+    for mut animation in &mut animations {
+        if animation.playing && !animation.finished {
+            animation.tick(delta);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+
+use bevy::prelude::{info, Entity, EventReader, NonSend, Res, ResMut, Resource};
+use vulkano::{
+    device::QueueFlags,
+    format::Format,
+    swapchain::{ColorSpace, PresentMode},
+    Version,
+};
+
+use crate::{BevyVulkanoContext, BevyVulkanoWindows, GpuInfo, WindowRendererReady};
+
+/// A queue family of the physical device in use.
+#[derive(Debug, Clone)]
+pub struct QueueFamilyReport {
+    pub index: u32,
+    pub queue_count: u32,
+    pub flags: QueueFlags,
+}
+
+/// Surface capabilities of a window, added to the [`StartupReport`] when its renderer is created.
+#[derive(Debug, Clone)]
+pub struct WindowSurfaceReport {
+    pub window: Entity,
+    pub surface_formats: Vec<(Format, ColorSpace)>,
+    pub present_modes: Vec<PresentMode>,
+    pub swapchain_format: Format,
+    pub swapchain_color_space: ColorSpace,
+    pub present_mode: PresentMode,
+}
+
+/// What the instance and device were created with and what the surfaces of the windows support,
+/// inserted as a resource by [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin) and logged at
+/// startup. Attach it to bug reports, or check it in tests to catch missing features early.
+///
+/// Windows are added as their renderers are created, see [`WindowRendererReady`].
+#[derive(Resource, Debug, Clone)]
+pub struct StartupReport {
+    pub instance_version: Version,
+    pub enabled_layers: Vec<String>,
+    pub enabled_instance_extensions: Vec<&'static str>,
+    pub gpu: GpuInfo,
+    pub device_extensions: Vec<&'static str>,
+    pub enabled_features: Vec<&'static str>,
+    pub queue_families: Vec<QueueFamilyReport>,
+    pub graphics_queue_family: u32,
+    pub compute_queue_family: u32,
+    pub windows: Vec<WindowSurfaceReport>,
+}
+
+impl StartupReport {
+    pub fn new(context: &BevyVulkanoContext) -> StartupReport {
+        let instance = context.context.instance();
+        let device = context.context.device();
+        let physical_device = device.physical_device();
+        StartupReport {
+            instance_version: instance.api_version(),
+            enabled_layers: instance.enabled_layers().to_vec(),
+            enabled_instance_extensions: enabled_names(*instance.enabled_extensions()),
+            gpu: GpuInfo::from_physical_device(physical_device),
+            device_extensions: enabled_names(*device.enabled_extensions()),
+            enabled_features: enabled_names(*device.enabled_features()),
+            queue_families: physical_device
+                .queue_family_properties()
+                .iter()
+                .enumerate()
+                .map(|(index, properties)| QueueFamilyReport {
+                    index: index as u32,
+                    queue_count: properties.queue_count,
+                    flags: properties.queue_flags,
+                })
+                .collect(),
+            graphics_queue_family: context.context.graphics_queue().queue_family_index(),
+            compute_queue_family: context.context.compute_queue().queue_family_index(),
+            windows: vec![],
+        }
+    }
+
+    pub fn window(&self, window: Entity) -> Option<&WindowSurfaceReport> {
+        self.windows.iter().find(|report| report.window == window)
+    }
+}
+
+fn enabled_names(names: impl IntoIterator<Item = (&'static str, bool)>) -> Vec<&'static str> {
+    names
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect()
+}
+
+impl fmt::Display for QueueFamilyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "family {}: {} queues, {:?}",
+            self.index, self.queue_count, self.flags
+        )
+    }
+}
+
+impl fmt::Display for WindowSurfaceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Window {:?}: {:?} {:?}, {:?}",
+            self.window, self.swapchain_format, self.swapchain_color_space, self.present_mode
+        )?;
+        writeln!(f, "  Present modes: {:?}", self.present_modes)?;
+        write!(f, "  Surface formats:")?;
+        for (format, color_space) in &self.surface_formats {
+            write!(f, "\n    {format:?} {color_space:?}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for StartupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Vulkan instance {}", self.instance_version)?;
+        writeln!(f, "  Layers: {}", self.enabled_layers.join(", "))?;
+        writeln!(
+            f,
+            "  Instance extensions: {}",
+            self.enabled_instance_extensions.join(", ")
+        )?;
+        writeln!(
+            f,
+            "Device {} ({:?}), driver {}",
+            self.gpu.device_name,
+            self.gpu.device_type,
+            self.gpu.driver_version_string()
+        )?;
+        writeln!(f, "  Extensions: {}", self.device_extensions.join(", "))?;
+        writeln!(f, "  Features: {}", self.enabled_features.join(", "))?;
+        writeln!(
+            f,
+            "  Graphics queue family {}, compute queue family {}",
+            self.graphics_queue_family, self.compute_queue_family
+        )?;
+        for queue_family in &self.queue_families {
+            writeln!(f, "  {queue_family}")?;
+        }
+        for window in &self.windows {
+            writeln!(f, "{window}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds the surface capabilities of windows whose renderer was created to the [`StartupReport`].
+pub(crate) fn report_window_surfaces(
+    mut ready_events: EventReader<WindowRendererReady>,
+    mut report: ResMut<StartupReport>,
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+    context: Res<BevyVulkanoContext>,
+) {
+    let physical_device = context.context.device().physical_device();
+    for ready in ready_events.read() {
+        let Some(vulkano_window) = vulkano_windows.get_vulkano_window(ready.window) else {
+            continue;
+        };
+        let renderer = &vulkano_window.renderer;
+        let surface = renderer.surface();
+        let window_report = WindowSurfaceReport {
+            window: ready.window,
+            surface_formats: physical_device
+                .surface_formats(&surface, Default::default())
+                .unwrap_or_default(),
+            present_modes: physical_device
+                .surface_present_modes(&surface, Default::default())
+                .map(|modes| modes.into_iter().collect())
+                .unwrap_or_default(),
+            swapchain_format: renderer.swapchain_format(),
+            swapchain_color_space: renderer.swapchain_color_space(),
+            present_mode: renderer.present_mode(),
+        };
+        info!("{window_report}");
+        report
+            .windows
+            .retain(|report| report.window != ready.window);
+        report.windows.push(window_report);
+    }
+}
@@ -0,0 +1,162 @@
+use bevy::{
+    ecs::system::SystemId,
+    log::{debug, error},
+    prelude::{App, IntoSystem, NextState, PostUpdate, Resource, State, States, World},
+    utils::{HashMap, HashSet},
+};
+
+/// Render systems registered per [`States`] value, e.g. a loading screen, the game and an editor
+/// each rendering their own way. Register them with [`StateRenderAppExt`].
+///
+/// Exactly one render system runs per frame in [`PostUpdate`], chosen by the state at the start of
+/// the frame's rendering, so a state change never mixes two renderers within a frame. Warm-up
+/// systems (e.g. creating pipelines and render targets) run once before a state's first frame. When
+/// the next state is already queued in [`NextState`] during rendering, its warm-up runs in that
+/// frame while the current state still renders, so the first frame of the new state doesn't hitch.
+///
+/// ```ignore
+/// app.add_state_warm_up(AppState::InGame, create_game_pipelines)
+///     .add_state_renderer(AppState::Loading, render_loading_screen)
+///     .add_state_renderer(AppState::InGame, render_game);
+/// ```
+#[derive(Resource)]
+pub struct StateRenderers<S: States> {
+    render: HashMap<S, SystemId>,
+    warm_up: HashMap<S, SystemId>,
+    warmed_up: HashSet<S>,
+    active: Option<S>,
+}
+
+impl<S: States> Default for StateRenderers<S> {
+    fn default() -> Self {
+        StateRenderers {
+            render: HashMap::default(),
+            warm_up: HashMap::default(),
+            warmed_up: HashSet::default(),
+            active: None,
+        }
+    }
+}
+
+impl<S: States> StateRenderers<S> {
+    /// The state rendered last frame.
+    pub fn active(&self) -> Option<&S> {
+        self.active.as_ref()
+    }
+
+    /// Whether the warm-up of `state` has run.
+    pub fn is_warmed_up(&self, state: &S) -> bool {
+        self.warmed_up.contains(state)
+    }
+
+    /// Runs the warm-up of `state` again before its next frame, e.g. after its resources were
+    /// freed.
+    pub fn reset_warm_up(&mut self, state: &S) {
+        self.warmed_up.remove(state);
+    }
+
+    /// Marks `state` as warmed up, returning its warm-up system if it hadn't run yet.
+    fn take_warm_up(&mut self, state: &S) -> Option<SystemId> {
+        if !self.warmed_up.insert(state.clone()) {
+            return None;
+        }
+        self.warm_up.get(state).copied()
+    }
+}
+
+/// Registers render and warm-up systems for [`StateRenderers`].
+pub trait StateRenderAppExt {
+    /// Renders with `render` while in `state`, replacing a previously registered renderer.
+    fn add_state_renderer<S: States, M>(
+        &mut self,
+        state: S,
+        render: impl IntoSystem<(), (), M> + 'static,
+    ) -> &mut Self;
+
+    /// Runs `warm_up` once before the first frame rendered in `state`.
+    fn add_state_warm_up<S: States, M>(
+        &mut self,
+        state: S,
+        warm_up: impl IntoSystem<(), (), M> + 'static,
+    ) -> &mut Self;
+}
+
+impl StateRenderAppExt for App {
+    fn add_state_renderer<S: States, M>(
+        &mut self,
+        state: S,
+        render: impl IntoSystem<(), (), M> + 'static,
+    ) -> &mut Self {
+        init_state_renderers::<S>(self);
+        let id = self.world.register_system(render);
+        let previous = self
+            .world
+            .resource_mut::<StateRenderers<S>>()
+            .render
+            .insert(state, id);
+        if let Some(previous) = previous {
+            let _ = self.world.remove_system(previous);
+        }
+        self
+    }
+
+    fn add_state_warm_up<S: States, M>(
+        &mut self,
+        state: S,
+        warm_up: impl IntoSystem<(), (), M> + 'static,
+    ) -> &mut Self {
+        init_state_renderers::<S>(self);
+        let id = self.world.register_system(warm_up);
+        let previous = self
+            .world
+            .resource_mut::<StateRenderers<S>>()
+            .warm_up
+            .insert(state, id);
+        if let Some(previous) = previous {
+            let _ = self.world.remove_system(previous);
+        }
+        self
+    }
+}
+
+fn init_state_renderers<S: States>(app: &mut App) {
+    if !app.world.contains_resource::<StateRenderers<S>>() {
+        app.init_resource::<StateRenderers<S>>()
+            .add_systems(PostUpdate, run_state_renderers::<S>);
+    }
+}
+
+/// Warms up the current and queued states and runs the current state's renderer.
+fn run_state_renderers<S: States>(world: &mut World) {
+    let Some(state) = world.get_resource::<State<S>>().map(|s| s.get().clone()) else {
+        return;
+    };
+    let next = world
+        .get_resource::<NextState<S>>()
+        .and_then(|next| next.0.clone());
+
+    let (warm_ups, render) = {
+        let mut renderers = world.resource_mut::<StateRenderers<S>>();
+        let mut warm_ups = vec![];
+        warm_ups.extend(renderers.take_warm_up(&state));
+        if let Some(next) = &next {
+            warm_ups.extend(renderers.take_warm_up(next));
+        }
+        if renderers.active.as_ref() != Some(&state) {
+            debug!("Switching renderer to state {:?}", state);
+            renderers.active = Some(state.clone());
+        }
+        (warm_ups, renderers.render.get(&state).copied())
+    };
+
+    for warm_up in warm_ups {
+        if let Err(e) = world.run_system(warm_up) {
+            error!("Failed to run render warm-up: {e:?}");
+        }
+    }
+    if let Some(render) = render {
+        if let Err(e) = world.run_system(render) {
+            error!("Failed to run renderer of state {:?}: {e:?}", state);
+        }
+    }
+}
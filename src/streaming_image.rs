@@ -0,0 +1,375 @@
+use std::{cmp::Ordering, collections::BinaryHeap, sync::Arc};
+
+use bevy::{log::warn, utils::HashSet};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, BufferImageCopy,
+        CommandBufferExecFuture, CommandBufferUsage, CopyBufferToImageInfo,
+    },
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    sync::{future::FenceSignalFuture, GpuFuture},
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk};
+
+type UploadFence = Arc<FenceSignalFuture<CommandBufferExecFuture<Box<dyn GpuFuture>>>>;
+
+/// Errors from creating a [`StreamingImage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamingImageError {
+    /// The tile size is 0 in x or y.
+    ZeroTileSize,
+    /// The format is block compressed, tiles are uploaded as tightly packed texels.
+    CompressedFormat(Format),
+}
+
+impl std::fmt::Display for StreamingImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamingImageError::ZeroTileSize => write!(f, "the tile size must not be 0"),
+            StreamingImageError::CompressedFormat(format) => {
+                write!(f, "compressed format {format:?} can't be streamed")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StreamingImageError {}
+
+/// Limits on how much [`StreamingImage`] uploads per frame.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamingBudget {
+    /// Maximum number of tiles uploaded per call to [`StreamingImage::stream`].
+    pub tiles_per_frame: usize,
+    /// Number of reusable staging buffers. Each holds one tile. Uploads stall (are deferred to a
+    /// later frame) when all staging buffers are still in use by the GPU.
+    pub staging_buffers: usize,
+}
+
+impl Default for StreamingBudget {
+    fn default() -> Self {
+        StreamingBudget {
+            tiles_per_frame: 4,
+            staging_buffers: 8,
+        }
+    }
+}
+
+/// Pending tile upload. Higher priority uploads first, equal priorities upload in request order.
+struct TileRequest {
+    priority: i32,
+    sequence: u64,
+    tile: [u32; 2],
+    data: Vec<u8>,
+}
+
+impl PartialEq for TileRequest {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TileRequest {}
+
+impl PartialOrd for TileRequest {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TileRequest {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+struct StagingSlot {
+    buffer: Subbuffer<[u8]>,
+    in_flight: Option<UploadFence>,
+}
+
+impl StagingSlot {
+    /// Whether the GPU has finished with the last upload from this slot.
+    fn is_free(&mut self) -> bool {
+        match &self.in_flight {
+            None => true,
+            Some(fence) => {
+                if fence.is_signaled().unwrap_or(false) {
+                    // Waiting on a signaled fence returns immediately and releases the resources
+                    // held by the submission.
+                    let _ = fence.wait(None);
+                    self.in_flight = None;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+/// A large 2D image split into tiles which are uploaded from the CPU over multiple frames.
+///
+/// Request tiles with [`StreamingImage::request_tile`] and call [`StreamingImage::stream`] once per
+/// frame. Each call uploads at most [`StreamingBudget::tiles_per_frame`] of the highest priority
+/// tiles through a small set of reused staging buffers, so the whole image never needs to be
+/// staged in host memory at once.
+///
+/// Streaming only bounds the host memory used for uploads. The whole image is allocated on the
+/// device when created, as no sparse binding is used, so it must fit into device memory. Tiles
+/// that have not been uploaded yet contain undefined data, use [`StreamingImage::is_resident`] to
+/// check which tiles are ready.
+pub struct StreamingImage {
+    queue: Arc<Queue>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    image: Arc<ImageView>,
+    tile_size: [u32; 2],
+    budget: StreamingBudget,
+    pending: BinaryHeap<TileRequest>,
+    staging: Vec<StagingSlot>,
+    resident: HashSet<[u32; 2]>,
+    /// Tiles whose upload was submitted, but hasn't finished yet.
+    uploading: Vec<(UploadFence, Vec<[u32; 2]>)>,
+    sequence: u64,
+}
+
+impl StreamingImage {
+    /// Creates the image, allocating all of its device memory, and staging buffers. Returns an
+    /// error if `tile_size` is 0 in x or y, or `format` is compressed.
+    pub fn new(
+        allocator: Arc<StandardMemoryAllocator>,
+        queue: Arc<Queue>,
+        format: Format,
+        extent: [u32; 2],
+        tile_size: [u32; 2],
+        budget: StreamingBudget,
+    ) -> Result<StreamingImage, StreamingImageError> {
+        if tile_size[0] == 0 || tile_size[1] == 0 {
+            return Err(StreamingImageError::ZeroTileSize);
+        }
+        // Tile sizes and data lengths are computed with the block size as bytes per texel
+        if format.compression().is_some() {
+            return Err(StreamingImageError::CompressedFormat(format));
+        }
+        let image = ImageView::new_default(
+            Image::new(
+                allocator.clone(),
+                ImageCreateInfo {
+                    image_type: ImageType::Dim2d,
+                    format,
+                    extent: [extent[0], extent[1], 1],
+                    usage: ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST,
+                    ..Default::default()
+                },
+                AllocationCreateInfo::default(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let tile_bytes = tile_size[0] as u64 * tile_size[1] as u64 * format.block_size();
+        let staging = (0..budget.staging_buffers.max(1))
+            .map(|_| StagingSlot {
+                buffer: Buffer::new_slice::<u8>(
+                    allocator.clone(),
+                    BufferCreateInfo {
+                        usage: BufferUsage::TRANSFER_SRC,
+                        ..Default::default()
+                    },
+                    AllocationCreateInfo {
+                        memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                            | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                        ..Default::default()
+                    },
+                    tile_bytes,
+                )
+                .unwrap(),
+                in_flight: None,
+            })
+            .collect();
+        Ok(StreamingImage {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                queue.device().clone(),
+                Default::default(),
+            ),
+            queue,
+            image,
+            tile_size,
+            budget,
+            pending: BinaryHeap::new(),
+            staging,
+            resident: HashSet::default(),
+            uploading: vec![],
+            sequence: 0,
+        })
+    }
+
+    /// The streamed image. Sample from this in your pipelines.
+    pub fn image(&self) -> Arc<ImageView> {
+        self.image.clone()
+    }
+
+    /// Number of tiles in x and y.
+    pub fn tile_count(&self) -> [u32; 2] {
+        let extent = self.image.image().extent();
+        [
+            extent[0].div_ceil(self.tile_size[0]),
+            extent[1].div_ceil(self.tile_size[1]),
+        ]
+    }
+
+    /// Extent of a tile in pixels. Tiles at the right and bottom edges may be smaller than the tile
+    /// size.
+    pub fn tile_extent(&self, tile: [u32; 2]) -> [u32; 2] {
+        tile_extent(self.image.image().extent(), self.tile_size, tile)
+    }
+
+    /// Queues tightly packed pixel `data` of a tile for upload. Returns false if the tile is out of
+    /// bounds or the data length does not match the tile extent.
+    pub fn request_tile(&mut self, tile: [u32; 2], priority: i32, data: Vec<u8>) -> bool {
+        let count = self.tile_count();
+        if tile[0] >= count[0] || tile[1] >= count[1] {
+            warn!("Streaming tile {:?} is out of bounds {:?}", tile, count);
+            return false;
+        }
+        let tile_extent = self.tile_extent(tile);
+        let expected =
+            tile_extent[0] as u64 * tile_extent[1] as u64 * self.image.format().block_size();
+        if data.len() as u64 != expected {
+            warn!(
+                "Streaming tile {:?} data is {} bytes, expected {}",
+                tile,
+                data.len(),
+                expected
+            );
+            return false;
+        }
+        self.sequence += 1;
+        self.pending.push(TileRequest {
+            priority,
+            sequence: self.sequence,
+            tile,
+            data,
+        });
+        true
+    }
+
+    /// Whether the upload of the tile has finished on the GPU. Updated by
+    /// [`StreamingImage::stream`].
+    pub fn is_resident(&self, tile: [u32; 2]) -> bool {
+        self.resident.contains(&tile)
+    }
+
+    /// Number of tiles waiting for upload.
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Marks the tiles of finished uploads as resident.
+    fn update_residency(&mut self) {
+        let resident = &mut self.resident;
+        self.uploading.retain(|(fence, tiles)| {
+            if fence.is_signaled().unwrap_or(false) {
+                resident.extend(tiles.iter().copied());
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Uploads the highest priority pending tiles within the budget after `before_future`. Returns
+    /// a future which you should continue your frame from so that sampling the image waits on the
+    /// uploads. The future is not flushed, submit it with the rest of your frame. Tiles become
+    /// resident once a later call sees that their upload finished.
+    pub fn stream(&mut self, before_future: Box<dyn GpuFuture>) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::stream_tiles");
+        self.update_residency();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let mut used_slots = vec![];
+        let mut tiles = vec![];
+        for (slot_index, slot) in self.staging.iter_mut().enumerate() {
+            if used_slots.len() >= self.budget.tiles_per_frame {
+                break;
+            }
+            if !slot.is_free() {
+                continue;
+            }
+            let Some(request) = self.pending.pop() else {
+                break;
+            };
+            match slot.buffer.write() {
+                Ok(mut write) => write[..request.data.len()].copy_from_slice(&request.data),
+                Err(e) => {
+                    warn!("Staging buffer for streaming tile is not writable: {e}");
+                    self.pending.push(request);
+                    continue;
+                }
+            }
+            let tile_extent =
+                tile_extent(self.image.image().extent(), self.tile_size, request.tile);
+            builder
+                .copy_buffer_to_image(CopyBufferToImageInfo {
+                    regions: [BufferImageCopy {
+                        image_subresource: self.image.image().subresource_layers(),
+                        image_offset: [
+                            request.tile[0] * self.tile_size[0],
+                            request.tile[1] * self.tile_size[1],
+                            0,
+                        ],
+                        image_extent: [tile_extent[0], tile_extent[1], 1],
+                        ..Default::default()
+                    }]
+                    .into(),
+                    ..CopyBufferToImageInfo::buffer_image(
+                        slot.buffer.clone(),
+                        self.image.image().clone(),
+                    )
+                })
+                .unwrap();
+            tiles.push(request.tile);
+            used_slots.push(slot_index);
+        }
+
+        if used_slots.is_empty() {
+            return before_future;
+        }
+
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit streaming image upload of {} tiles on queue family {}",
+            used_slots.len(),
+            self.queue.queue_family_index()
+        );
+        let fence: UploadFence = Arc::new(
+            before_future
+                .then_execute(self.queue.clone(), command_buffer)
+                .unwrap()
+                .then_signal_fence(),
+        );
+        for slot_index in used_slots {
+            self.staging[slot_index].in_flight = Some(fence.clone());
+        }
+        self.uploading.push((fence.clone(), tiles));
+        fence.boxed()
+    }
+}
+
+/// Extent of `tile` of an image of `extent`, clamped at the right and bottom edges.
+fn tile_extent(extent: [u32; 3], tile_size: [u32; 2], tile: [u32; 2]) -> [u32; 2] {
+    [
+        tile_size[0].min(extent[0] - tile[0] * tile_size[0]),
+        tile_size[1].min(extent[1] - tile[1] * tile_size[1]),
+    ]
+}
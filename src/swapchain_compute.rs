@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::BufferContents,
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::{DeviceOwned, Queue},
+    image::view::ImageView,
+    pipeline::{
+        compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    shader::EntryPoint,
+    sync::GpuFuture,
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk, CommandStats};
+
+/// Dispatches a compute shader writing the swapchain image directly, saving a fullscreen copy for
+/// renderers which produce their final image in compute.
+///
+/// Requires `STORAGE` in
+/// [`BevyVulkanoSettings::swapchain_image_usage`](crate::BevyVulkanoSettings::swapchain_image_usage)
+/// and a surface supporting it, check
+/// [`VulkanoWindowRenderer::supports_storage_writes`](crate::VulkanoWindowRenderer::supports_storage_writes).
+/// Storage swapchain images are usually UNORM, so the shader must encode sRGB itself.
+///
+/// The shader receives the target as `layout(set = 0, binding = 0, rgba8) uniform writeonly
+/// image2D` and is dispatched to cover the whole target with its `local_size`. Image layout
+/// transitions to and from the present layout are recorded automatically.
+///
+/// ```ignore
+/// let before = renderer.acquire().unwrap();
+/// let after = pass.dispatch(before, renderer.swapchain_image_view(), None::<()>);
+/// renderer.present(after, true);
+/// ```
+pub struct SwapchainComputePass {
+    queue: Arc<Queue>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    pipeline: Arc<ComputePipeline>,
+    local_size: [u32; 2],
+}
+
+impl SwapchainComputePass {
+    /// Creates the pass for `compute_shader`, whose work group size is `local_size`.
+    pub fn new(
+        queue: Arc<Queue>,
+        compute_shader: EntryPoint,
+        local_size: [u32; 2],
+    ) -> SwapchainComputePass {
+        let device = queue.device().clone();
+        let stage = PipelineShaderStageCreateInfo::new(compute_shader);
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+        let pipeline = ComputePipeline::new(
+            device.clone(),
+            None,
+            ComputePipelineCreateInfo::stage_layout(stage, layout),
+        )
+        .unwrap();
+        SwapchainComputePass {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
+                device,
+                Default::default(),
+            ),
+            queue,
+            pipeline,
+            local_size: [local_size[0].max(1), local_size[1].max(1)],
+        }
+    }
+
+    /// The compute pipeline, e.g. for binding additional descriptor sets with the same layout.
+    pub fn pipeline(&self) -> Arc<ComputePipeline> {
+        self.pipeline.clone()
+    }
+
+    /// Dispatches the shader over `target`, usually
+    /// [`VulkanoWindowRenderer::swapchain_image_view`](crate::VulkanoWindowRenderer::swapchain_image_view).
+    pub fn dispatch<P: BufferContents>(
+        &self,
+        before_future: Box<dyn GpuFuture>,
+        target: Arc<ImageView>,
+        push_constants: Option<P>,
+    ) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::swapchain_compute");
+        let extent = target.image().extent();
+        let layout = self.pipeline.layout().clone();
+        let set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts()[0].clone(),
+            [WriteDescriptorSet::image_view(0, target)],
+            [],
+        )
+        .unwrap();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .bind_pipeline_compute(self.pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, set)
+            .unwrap();
+        if let Some(push_constants) = push_constants {
+            builder.push_constants(layout, 0, push_constants).unwrap();
+        }
+        builder
+            .dispatch([
+                extent[0].div_ceil(self.local_size[0]),
+                extent[1].div_ceil(self.local_size[1]),
+                1,
+            ])
+            .unwrap();
+        CommandStats::record_pipeline_bind();
+        CommandStats::record_dispatches(1);
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit swapchain compute dispatch over {:?} on queue family {}",
+            extent,
+            self.queue.queue_family_index()
+        );
+        before_future
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
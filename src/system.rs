@@ -1,22 +1,21 @@
 // Same as in `bevy_winit`, but modified to allow inserting vulkano renderer that holds the window
 use bevy::{
-    log::{error, info, warn},
+    log::info,
     prelude::{
-        Changed, Commands, Component, Entity, EventWriter, Mut, NonSend, NonSendMut, Query,
-        RemovedComponents, Res, Resource, Window,
+        Commands, Entity, EventWriter, Mut, NonSend, NonSendMut, Query, RemovedComponents, Res,
+        Resource, Window,
     },
     utils::HashMap,
     window::{RawHandleWrapper, WindowClosed, WindowCreated},
 };
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
-use winit::{
-    dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
-    event_loop::EventLoopWindowTarget,
-};
+use winit::event_loop::EventLoopWindowTarget;
 
 use crate::{
-    config::BevyVulkanoSettings, converters, converters::convert_window_level, get_best_videomode,
-    get_fitting_videomode, vulkano_windows::attempt_grab, BevyVulkanoContext, BevyVulkanoWindows,
+    compat::CachedWindow,
+    config::{BevyVulkanoSettings, VulkanoWindowConfig},
+    BevyVulkanoContext, BevyVulkanoWindows, CursorGrabFailed, FramePresented, PresentModeFallback,
+    RenderExtent, SwapchainInfo, WindowRendererReady,
 };
 
 /// System responsible for creating new windows whenever a `Window` component is added
@@ -27,13 +26,21 @@ use crate::{
 pub(crate) fn create_window<'a>(
     mut commands: Commands,
     event_loop: &EventLoopWindowTarget<()>,
-    created_windows: impl Iterator<Item = (Entity, Mut<'a, Window>)>,
+    created_windows: impl Iterator<
+        Item = (
+            Entity,
+            Mut<'a, Window>,
+            Option<&'a VulkanoWindowConfig>,
+            bool,
+        ),
+    >,
     mut event_writer: EventWriter<WindowCreated>,
+    mut ready_writer: EventWriter<WindowRendererReady>,
     mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
     context: Res<BevyVulkanoContext>,
     settings: NonSend<BevyVulkanoSettings>,
 ) {
-    for (entity, mut window) in created_windows {
+    for (entity, mut window, window_config, no_gui) in created_windows {
         if vulkano_windows.get_vulkano_window(entity).is_some() {
             continue;
         }
@@ -44,16 +51,47 @@ pub(crate) fn create_window<'a>(
             entity
         );
 
-        let vulkano_window =
-            vulkano_windows.create_window(event_loop, entity, &window, &context.context, &settings);
+        let window_config = window_config.cloned().unwrap_or_default();
+        #[cfg(feature = "gui")]
+        let with_gui = !no_gui && settings.create_gui;
+        #[cfg(not(feature = "gui"))]
+        let with_gui = !no_gui;
+        let (winit_window, swapchain_state) = if settings.deferred_renderers_per_frame.is_some() {
+            let winit_window = vulkano_windows.create_window_deferred(
+                event_loop,
+                entity,
+                &window,
+                &window_config,
+                with_gui,
+                &settings,
+            );
+            (winit_window, None)
+        } else {
+            let vulkano_window = vulkano_windows.create_window(
+                event_loop,
+                entity,
+                &window,
+                &window_config,
+                with_gui,
+                &context.context,
+                &settings,
+            );
+            (
+                vulkano_window.window(),
+                Some((
+                    vulkano_window.renderer.render_extent(),
+                    vulkano_window.renderer.swapchain_info(),
+                )),
+            )
+        };
         window
             .resolution
-            .set_scale_factor(vulkano_window.window().scale_factor());
+            .set_scale_factor(winit_window.scale_factor());
         commands
             .entity(entity)
             .insert(RawHandleWrapper {
-                window_handle: vulkano_window.window().raw_window_handle(),
-                display_handle: vulkano_window.window().raw_display_handle(),
+                window_handle: winit_window.raw_window_handle(),
+                display_handle: winit_window.raw_display_handle(),
             })
             .insert(CachedWindow {
                 window: window.clone(),
@@ -62,6 +100,53 @@ pub(crate) fn create_window<'a>(
         event_writer.send(WindowCreated {
             window: entity,
         });
+        if let Some((render_extent, swapchain_info)) = swapchain_state {
+            commands
+                .entity(entity)
+                .insert((render_extent, swapchain_info));
+            ready_writer.send(WindowRendererReady {
+                window: entity,
+            });
+        }
+    }
+}
+
+/// Creates renderers of windows whose renderer creation was deferred with
+/// [`BevyVulkanoSettings::deferred_renderers_per_frame`].
+pub(crate) fn create_pending_renderers(
+    mut commands: Commands,
+    event_loop: &EventLoopWindowTarget<()>,
+    mut windows: Query<&mut Window>,
+    mut ready_writer: EventWriter<WindowRendererReady>,
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    context: Res<BevyVulkanoContext>,
+    settings: NonSend<BevyVulkanoSettings>,
+) {
+    let Some(per_frame) = settings.deferred_renderers_per_frame else {
+        return;
+    };
+    if vulkano_windows.pending_len() == 0 {
+        return;
+    }
+    let ready = vulkano_windows.create_pending_renderers(
+        event_loop,
+        &context.context,
+        &settings,
+        per_frame.max(1),
+    );
+    for entity in ready {
+        let vulkano_window = vulkano_windows.get_vulkano_window(entity).unwrap();
+        commands.entity(entity).insert((
+            vulkano_window.renderer.render_extent(),
+            vulkano_window.renderer.swapchain_info(),
+        ));
+        // Apply changes to the window made while its renderer was pending
+        if let Ok(mut window) = windows.get_mut(entity) {
+            window.set_changed();
+        }
+        ready_writer.send(WindowRendererReady {
+            window: entity,
+        });
     }
 }
 
@@ -88,198 +173,87 @@ pub(crate) fn despawn_window(
     }
 }
 
-/// The cached state of the window so we can check which properties were changed from within the app.
-#[derive(Debug, Clone, Component)]
-pub struct CachedWindow {
-    pub window: Window,
+/// Sends [`PresentModeFallback`] events for windows whose requested present mode was substituted.
+pub(crate) fn present_mode_fallback_events(
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    mut fallback_events: EventWriter<PresentModeFallback>,
+) {
+    for (window, vulkano_window) in vulkano_windows.iter_mut() {
+        if let Some((requested, selected)) = vulkano_window.renderer.take_present_mode_fallback() {
+            fallback_events.send(PresentModeFallback {
+                window,
+                requested,
+                selected,
+            });
+        }
+    }
 }
 
-// Detect changes to the window and update the winit window accordingly.
-//
-// Notes:
-// - [`Window::present_mode`] and [`Window::composite_alpha_mode`] updating should be handled in the bevy render crate.
-// - [`Window::transparent`] currently cannot be updated after startup for winit.
-// - [`Window::canvas`] currently cannot be updated after startup, not entirely sure if it would work well with the
-//   event channel stuff.
-pub(crate) fn changed_window(
-    mut changed_windows: Query<(Entity, &mut Window, &mut CachedWindow), Changed<Window>>,
-    vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+/// Sends [`CursorGrabFailed`] events for cursor grabs which failed when creating windows.
+pub(crate) fn cursor_grab_failed_events(
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    mut grab_failed_events: EventWriter<CursorGrabFailed>,
 ) {
-    for (entity, mut window, mut cache) in &mut changed_windows {
-        if let Some(vulkano_window) = vulkano_windows.get_vulkano_window(entity) {
-            if window.title != cache.window.title {
-                vulkano_window.window().set_title(window.title.as_str());
-            }
-
-            if window.mode != cache.window.mode {
-                let new_mode = match window.mode {
-                    bevy::window::WindowMode::BorderlessFullscreen => {
-                        Some(winit::window::Fullscreen::Borderless(None))
-                    }
-                    bevy::window::WindowMode::Fullscreen => {
-                        Some(winit::window::Fullscreen::Exclusive(get_best_videomode(
-                            &vulkano_window.window().current_monitor().unwrap(),
-                        )))
-                    }
-                    bevy::window::WindowMode::SizedFullscreen => {
-                        Some(winit::window::Fullscreen::Exclusive(get_fitting_videomode(
-                            &vulkano_window.window().current_monitor().unwrap(),
-                            window.width() as u32,
-                            window.height() as u32,
-                        )))
-                    }
-                    bevy::window::WindowMode::Windowed => None,
-                };
-
-                if vulkano_window.window().fullscreen() != new_mode {
-                    vulkano_window.window().set_fullscreen(new_mode);
-                }
-            }
-            if window.resolution != cache.window.resolution {
-                let physical_size = PhysicalSize::new(
-                    window.resolution.physical_width(),
-                    window.resolution.physical_height(),
-                );
-                vulkano_window.window().set_inner_size(physical_size);
-            }
-
-            if window.physical_cursor_position() != cache.window.physical_cursor_position() {
-                if let Some(physical_position) = window.physical_cursor_position() {
-                    let inner_size = vulkano_window.window().inner_size();
-
-                    let position = PhysicalPosition::new(
-                        physical_position.x,
-                        // Flip the coordinate space back to winit's context.
-                        inner_size.height as f32 - physical_position.y,
-                    );
-
-                    if let Err(err) = vulkano_window.window().set_cursor_position(position) {
-                        error!("could not set cursor position: {:?}", err);
-                    }
-                }
-            }
-
-            if window.cursor.icon != cache.window.cursor.icon {
-                vulkano_window
-                    .window()
-                    .set_cursor_icon(converters::convert_cursor_icon(window.cursor.icon));
-            }
-
-            if window.cursor.grab_mode != cache.window.cursor.grab_mode {
-                attempt_grab(vulkano_window.window(), window.cursor.grab_mode);
-            }
-
-            if window.cursor.visible != cache.window.cursor.visible {
-                vulkano_window
-                    .window()
-                    .set_cursor_visible(window.cursor.visible);
-            }
-
-            if window.cursor.hit_test != cache.window.cursor.hit_test {
-                if let Err(err) = vulkano_window
-                    .window()
-                    .set_cursor_hittest(window.cursor.hit_test)
-                {
-                    window.cursor.hit_test = cache.window.cursor.hit_test;
-                    warn!(
-                        "Could not set cursor hit test for window {:?}: {:?}",
-                        window.title, err
-                    );
-                }
-            }
-
-            if window.decorations != cache.window.decorations
-                && window.decorations != vulkano_window.window().is_decorated()
-            {
-                vulkano_window.window().set_decorations(window.decorations);
-            }
-
-            if window.resizable != cache.window.resizable
-                && window.resizable != vulkano_window.window().is_resizable()
-            {
-                vulkano_window.window().set_resizable(window.resizable);
-            }
-
-            if window.resize_constraints != cache.window.resize_constraints {
-                let constraints = window.resize_constraints.check_constraints();
-                let min_inner_size = LogicalSize {
-                    width: constraints.min_width,
-                    height: constraints.min_height,
-                };
-                let max_inner_size = LogicalSize {
-                    width: constraints.max_width,
-                    height: constraints.max_height,
-                };
-
-                vulkano_window
-                    .window()
-                    .set_min_inner_size(Some(min_inner_size));
-                if constraints.max_width.is_finite() && constraints.max_height.is_finite() {
-                    vulkano_window
-                        .window()
-                        .set_max_inner_size(Some(max_inner_size));
-                }
-            }
-
-            if window.position != cache.window.position {
-                if let Some(position) = crate::winit_window_position(
-                    &window.position,
-                    &window.resolution,
-                    vulkano_window.window().available_monitors(),
-                    vulkano_window.window().primary_monitor(),
-                    vulkano_window.window().current_monitor(),
-                ) {
-                    let should_set = match vulkano_window.window().outer_position() {
-                        Ok(current_position) => current_position != position,
-                        _ => true,
-                    };
-
-                    if should_set {
-                        vulkano_window.window().set_outer_position(position);
-                    }
-                }
-            }
-
-            if let Some(maximized) = window.internal.take_maximize_request() {
-                vulkano_window.window().set_maximized(maximized);
-            }
-
-            if let Some(minimized) = window.internal.take_minimize_request() {
-                vulkano_window.window().set_minimized(minimized);
-            }
-
-            if window.focused != cache.window.focused && window.focused {
-                vulkano_window.window().focus_window();
-            }
-
-            if window.window_level != cache.window.window_level {
-                vulkano_window
-                    .window()
-                    .set_window_level(convert_window_level(window.window_level));
-            }
-
-            // Currently unsupported changes
-            if window.transparent != cache.window.transparent {
-                window.transparent = cache.window.transparent;
-                warn!(
-                    "Winit does not currently support updating transparency after window creation."
-                );
-            }
+    for failure in vulkano_windows.take_grab_failures() {
+        grab_failed_events.send(failure);
+    }
+}
 
-            if window.ime_enabled != cache.window.ime_enabled {
-                vulkano_window.window().set_ime_allowed(window.ime_enabled);
-            }
+/// Presents the windows whose presents were deferred to present as a
+/// [`PresentGroup`](crate::PresentGroup).
+pub(crate) fn present_window_group(mut vulkano_windows: NonSendMut<BevyVulkanoWindows>) {
+    let submit_first = vulkano_windows.iter().any(|(_, vulkano_window)| {
+        vulkano_window
+            .renderer
+            .present_group()
+            .is_some_and(|group| group.submit_first)
+    });
+    if submit_first {
+        for (_, vulkano_window) in vulkano_windows.iter_mut() {
+            vulkano_window.renderer.submit_deferred_rendering();
+        }
+    }
+    for (_, vulkano_window) in vulkano_windows.iter_mut() {
+        vulkano_window.renderer.present_deferred();
+    }
+}
 
-            if window.ime_position != cache.window.ime_position {
-                vulkano_window
-                    .window()
-                    .set_ime_position(LogicalPosition::new(
-                        window.ime_position.x,
-                        window.ime_position.y,
-                    ));
-            }
+pub(crate) fn frame_presented_events(
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    mut presented_events: EventWriter<FramePresented>,
+) {
+    for (window, vulkano_window) in vulkano_windows.iter_mut() {
+        for stats in vulkano_window.renderer.take_presented_frames() {
+            presented_events.send(FramePresented {
+                window,
+                frame_index: stats.frame_index,
+                cpu_time: stats.cpu_time,
+                acquire_wait: stats.acquire_wait,
+                suboptimal: stats.suboptimal,
+                presented_at: stats.presented_at,
+            });
+        }
+    }
+}
 
-            cache.window = window.clone();
+/// Recreates swapchains invalidated by resizes before the frame's systems run and updates the
+/// [`RenderExtent`] and [`SwapchainInfo`] of each window to match.
+pub(crate) fn update_render_extents(
+    mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
+    mut extents: Query<(&mut RenderExtent, &mut SwapchainInfo)>,
+) {
+    for (window, vulkano_window) in vulkano_windows.iter_mut() {
+        vulkano_window.renderer.recreate_swapchain_if_needed();
+        let Ok((mut extent, mut info)) = extents.get_mut(window) else {
+            continue;
+        };
+        let render_extent = vulkano_window.renderer.render_extent();
+        if *extent != render_extent {
+            *extent = render_extent;
+        }
+        let swapchain_info = vulkano_window.renderer.swapchain_info();
+        if *info != swapchain_info {
+            *info = swapchain_info;
         }
     }
 }
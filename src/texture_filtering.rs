@@ -0,0 +1,103 @@
+use bevy::prelude::Resource;
+use vulkano::{
+    device::{Device, Features},
+    image::sampler::{Filter, SamplerCreateInfo, SamplerMipmapMode},
+};
+
+/// Device features required for anisotropic filtering.
+pub fn sampler_anisotropy_features() -> Features {
+    Features {
+        sampler_anisotropy: true,
+        ..Features::empty()
+    }
+}
+
+/// Global texture filtering quality, e.g. wired to a graphics settings menu. Inserted as a
+/// resource from [`BevyVulkanoSettings::texture_filtering`](crate::BevyVulkanoSettings::texture_filtering)
+/// and used for the samplers of the crate sampling user textures, like the thumbnails of the
+/// [`RenderTargetInspector`](crate::RenderTargetInspector). Post process passes keep their own
+/// filters, as they sample their inputs at a fixed scale.
+///
+/// Create samplers for your own textures with [`TextureFiltering::sampler_create_info`] and
+/// recreate them when the resource changes:
+///
+/// ```ignore
+/// fn update_sampler(filtering: Res<TextureFiltering>, context: Res<BevyVulkanoContext>, mut material: ResMut<Material>) {
+///     if filtering.is_changed() {
+///         let device = context.context.device();
+///         material.sampler = Sampler::new(device.clone(), filtering.sampler_create_info(device)).unwrap();
+///     }
+/// }
+/// ```
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct TextureFiltering {
+    /// Maximum anisotropy, e.g. `16.0`. `None` disables anisotropic filtering. Requires
+    /// [`BevyVulkanoSettings::sampler_anisotropy`](crate::BevyVulkanoSettings::sampler_anisotropy)
+    /// and is clamped to the `max_sampler_anisotropy` limit of the device.
+    pub anisotropy: Option<f32>,
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_mode: SamplerMipmapMode,
+}
+
+impl Default for TextureFiltering {
+    fn default() -> Self {
+        TextureFiltering {
+            anisotropy: None,
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            mipmap_mode: SamplerMipmapMode::Linear,
+        }
+    }
+}
+
+impl TextureFiltering {
+    /// Nearest filtering without anisotropy, e.g. for pixel art.
+    pub fn nearest() -> TextureFiltering {
+        TextureFiltering {
+            anisotropy: None,
+            mag_filter: Filter::Nearest,
+            min_filter: Filter::Nearest,
+            mipmap_mode: SamplerMipmapMode::Nearest,
+        }
+    }
+
+    /// Linear filtering with the given anisotropy.
+    pub fn anisotropic(anisotropy: f32) -> TextureFiltering {
+        TextureFiltering {
+            anisotropy: Some(anisotropy),
+            ..Default::default()
+        }
+    }
+
+    /// The anisotropy samplers are created with on `device`. `None` if anisotropy is disabled or
+    /// the `sampler_anisotropy` feature isn't enabled, clamped to the device limit otherwise.
+    pub fn device_anisotropy(&self, device: &Device) -> Option<f32> {
+        let anisotropy = self.anisotropy.filter(|anisotropy| *anisotropy > 1.0)?;
+        if !device.enabled_features().sampler_anisotropy {
+            return None;
+        }
+        Some(anisotropy.min(device.physical_device().properties().max_sampler_anisotropy))
+    }
+
+    /// A sampler with the filtering settings and repeating address mode for `device`.
+    pub fn sampler_create_info(&self, device: &Device) -> SamplerCreateInfo {
+        self.apply(device, SamplerCreateInfo::default())
+    }
+
+    /// Overrides the filters and anisotropy of `sampler_create_info`, keeping its address modes
+    /// and level of detail settings.
+    pub fn apply(
+        &self,
+        device: &Device,
+        sampler_create_info: SamplerCreateInfo,
+    ) -> SamplerCreateInfo {
+        SamplerCreateInfo {
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_mode: self.mipmap_mode,
+            anisotropy: self.device_anisotropy(device),
+            ..sampler_create_info
+        }
+    }
+}
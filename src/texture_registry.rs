@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use bevy::{prelude::Resource, utils::HashMap};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        CopyBufferToImageInfo,
+    },
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    sync::{self, GpuFuture},
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk, GpuMemoryBudget};
+
+/// Identifies a texture in the [`TextureRegistry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ImageTextureId(u64);
+
+/// Device local textures shared by all windows, so a texture sampled in several windows (e.g. in
+/// a multi-window editor) is uploaded and stored once.
+///
+/// Textures uploaded with a key are deduplicated: uploading the same key again returns the
+/// existing id without a copy. With the `gui` feature, use
+/// [`VulkanoWindow::gui_texture_id`](crate::VulkanoWindow::gui_texture_id) to get a window's egui
+/// texture id for a registered texture. Large textures can be uploaded over several frames with
+/// [`TextureUploads`](crate::TextureUploads).
+#[derive(Resource, Default)]
+pub struct TextureRegistry {
+    textures: HashMap<ImageTextureId, Arc<ImageView>>,
+    keys: HashMap<String, ImageTextureId>,
+    next_id: u64,
+}
+
+impl TextureRegistry {
+    /// Registers an existing image view.
+    pub fn insert(&mut self, view: Arc<ImageView>) -> ImageTextureId {
+        let id = self.reserve_id();
+        self.textures.insert(id, view);
+        id
+    }
+
+    /// Registers an existing image view under `key`, unless a texture with the key exists.
+    pub fn insert_keyed(&mut self, key: &str, view: Arc<ImageView>) -> ImageTextureId {
+        if let Some(id) = self.id_for_key(key) {
+            return id;
+        }
+        let id = self.insert(view);
+        self.keys.insert(key.to_string(), id);
+        id
+    }
+
+    /// Reserves an id for a texture registered later with [`TextureRegistry::insert_reserved`].
+    pub(crate) fn reserve_id(&mut self) -> ImageTextureId {
+        let id = ImageTextureId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    pub(crate) fn insert_reserved(&mut self, id: ImageTextureId, key: &str, view: Arc<ImageView>) {
+        self.textures.insert(id, view);
+        self.keys.insert(key.to_string(), id);
+    }
+
+    /// Uploads `data` into a new sampled device local image of `extent` and `format` registered
+    /// under `key`. Returns the id and the upload future, which must complete before the texture is
+    /// sampled. If `key` is already registered, the existing id is returned without uploading.
+    pub fn upload(
+        &mut self,
+        key: &str,
+        queue: Arc<Queue>,
+        allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: &StandardCommandBufferAllocator,
+        extent: [u32; 2],
+        format: Format,
+        data: &[u8],
+    ) -> (ImageTextureId, Option<Box<dyn GpuFuture>>) {
+        profile_scope!("bevy_vulkano::texture_upload");
+        if let Some(id) = self.id_for_key(key) {
+            return (id, None);
+        }
+        let staging = Buffer::from_iter(
+            allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            data.iter().copied(),
+        )
+        .unwrap();
+        let image = Image::new(
+            allocator,
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent: [extent[0], extent[1], 1],
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap();
+        GpuMemoryBudget::track_buffer(staging.buffer());
+        GpuMemoryBudget::track_image(&image);
+        let mut builder = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(staging, image.clone()))
+            .unwrap();
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit texture upload {key:?}: {:?} {:?} on queue family {}",
+            extent,
+            format,
+            queue.queue_family_index()
+        );
+        let future = sync::now(queue.device().clone())
+            .then_execute(queue, command_buffer)
+            .unwrap()
+            .boxed();
+        let id = self.insert_keyed(key, ImageView::new_default(image).unwrap());
+        (id, Some(future))
+    }
+
+    /// The image view of a texture.
+    pub fn get(&self, id: ImageTextureId) -> Option<Arc<ImageView>> {
+        self.textures.get(&id).cloned()
+    }
+
+    /// The id of the texture registered under `key`.
+    pub fn id_for_key(&self, key: &str) -> Option<ImageTextureId> {
+        self.keys.get(key).copied()
+    }
+
+    /// Removes a texture. The image is freed once no window or command buffer uses it anymore.
+    pub fn remove(&mut self, id: ImageTextureId) -> Option<Arc<ImageView>> {
+        self.keys.retain(|_, key_id| *key_id != id);
+        self.textures.remove(&id)
+    }
+
+    /// Removes the textures for which `f` returns `false`.
+    pub fn retain(&mut self, mut f: impl FnMut(ImageTextureId, &Arc<ImageView>) -> bool) {
+        self.textures.retain(|id, view| f(*id, view));
+        let textures = &self.textures;
+        self.keys.retain(|_, id| textures.contains_key(id));
+    }
+
+    /// Removes the textures no longer used outside the registry, i.e. whose image view is only
+    /// referenced by the registry, and returns their ids. Call it e.g. after a level unloaded to
+    /// free its textures. Textures registered with a window's gui are in use until released with
+    /// [`VulkanoWindow::release_gui_texture`](crate::VulkanoWindow::release_gui_texture).
+    pub fn collect_garbage(&mut self) -> Vec<ImageTextureId> {
+        let mut removed = vec![];
+        self.retain(|id, view| {
+            let used = Arc::strong_count(view) > 1;
+            if !used {
+                removed.push(id);
+            }
+            used
+        });
+        removed
+    }
+
+    /// Iterate registered textures.
+    pub fn iter(&self) -> impl Iterator<Item = (ImageTextureId, &Arc<ImageView>)> {
+        self.textures.iter().map(|(id, view)| (*id, view))
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+}
@@ -0,0 +1,293 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use bevy::{
+    log::warn,
+    prelude::{Event, EventWriter, Res, ResMut, Resource},
+};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, BufferImageCopy,
+        CommandBufferExecFuture, CommandBufferUsage, CopyBufferToImageInfo,
+    },
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    sync::{self, future::FenceSignalFuture, GpuFuture},
+};
+
+use crate::{
+    profiling::profile_scope, trace_frame::trace_vk, BevyVulkanoContext, GpuMemoryBudget,
+    ImageTextureId, QueueSharing, TextureRegistry,
+};
+
+type SliceFence = FenceSignalFuture<CommandBufferExecFuture<sync::future::NowFuture>>;
+
+/// Progress of a texture queued with [`TextureUploads::queue`], sent whenever more of it has been
+/// uploaded. The texture is registered in the [`TextureRegistry`] when `uploaded_bytes` reaches
+/// `total_bytes`.
+#[derive(Event, Debug, Clone)]
+pub struct TextureUploadProgress {
+    pub id: ImageTextureId,
+    pub key: String,
+    pub uploaded_bytes: u64,
+    pub total_bytes: u64,
+}
+
+impl TextureUploadProgress {
+    /// Uploaded fraction in `0.0..=1.0`, e.g. for a loading bar.
+    pub fn fraction(&self) -> f32 {
+        self.uploaded_bytes as f32 / self.total_bytes.max(1) as f32
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.uploaded_bytes >= self.total_bytes
+    }
+}
+
+struct SlicedUpload {
+    id: ImageTextureId,
+    key: String,
+    image: Arc<Image>,
+    data: Vec<u8>,
+    /// Bytes of a row of texel blocks.
+    row_bytes: u64,
+    /// Texel block rows submitted so far.
+    submitted_rows: u32,
+    block_rows: u32,
+    /// Submitted slices with their size, in submission order.
+    in_flight: VecDeque<(u64, SliceFence)>,
+    uploaded_bytes: u64,
+}
+
+impl SlicedUpload {
+    fn total_bytes(&self) -> u64 {
+        self.data.len() as u64
+    }
+}
+
+/// Uploads large textures into the [`TextureRegistry`] a slice of rows per frame, so loading e.g.
+/// 8k world textures does not stall a frame, and sends [`TextureUploadProgress`] events for
+/// loading screens.
+///
+/// Slices are submitted on the compute queue of the context, which is a separate queue when the
+/// device has one, so uploads don't delay rendering on the graphics queue. Textures upload one at
+/// a time in the order they were queued.
+#[derive(Resource)]
+pub struct TextureUploads {
+    /// Maximum bytes submitted per frame. At least one row is submitted per frame.
+    pub bytes_per_frame: u64,
+    queued: VecDeque<SlicedUpload>,
+    command_buffer_allocator: Option<StandardCommandBufferAllocator>,
+}
+
+impl Default for TextureUploads {
+    fn default() -> Self {
+        TextureUploads {
+            bytes_per_frame: 16 * 1024 * 1024,
+            queued: VecDeque::new(),
+            command_buffer_allocator: None,
+        }
+    }
+}
+
+impl TextureUploads {
+    /// Queues tightly packed texel `data` of a sampled 2D texture of `extent` and `format` for a
+    /// time-sliced upload under `key`. Returns the id the texture gets in the registry once
+    /// uploaded. If `key` is already registered or queued, its id is returned without uploading.
+    pub fn queue(
+        &mut self,
+        registry: &mut TextureRegistry,
+        context: &BevyVulkanoContext,
+        key: &str,
+        extent: [u32; 2],
+        format: Format,
+        data: Vec<u8>,
+    ) -> Option<ImageTextureId> {
+        if let Some(id) = registry.id_for_key(key) {
+            return Some(id);
+        }
+        if let Some(upload) = self.queued.iter().find(|upload| upload.key == key) {
+            return Some(upload.id);
+        }
+        let block_extent = format.block_extent();
+        let row_bytes = extent[0].div_ceil(block_extent[0]) as u64 * format.block_size();
+        let block_rows = extent[1].div_ceil(block_extent[1]);
+        if data.len() as u64 != row_bytes * block_rows as u64 {
+            warn!(
+                "Texture upload {key:?} data is {} bytes, expected {}",
+                data.len(),
+                row_bytes * block_rows as u64
+            );
+            return None;
+        }
+        let image = create_upload_image(
+            context.context.memory_allocator().clone(),
+            context.context.graphics_queue(),
+            context.context.compute_queue(),
+            extent,
+            format,
+        );
+        let id = registry.reserve_id();
+        self.queued.push_back(SlicedUpload {
+            id,
+            key: key.to_string(),
+            image,
+            data,
+            row_bytes,
+            submitted_rows: 0,
+            block_rows,
+            in_flight: VecDeque::new(),
+            uploaded_bytes: 0,
+        });
+        Some(id)
+    }
+
+    /// Number of textures waiting for their upload to finish.
+    pub fn pending(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Whether a texture queued under `key` is still uploading.
+    pub fn is_uploading(&self, key: &str) -> bool {
+        self.queued.iter().any(|upload| upload.key == key)
+    }
+}
+
+fn create_upload_image(
+    allocator: Arc<StandardMemoryAllocator>,
+    graphics_queue: &Arc<Queue>,
+    upload_queue: &Arc<Queue>,
+    extent: [u32; 2],
+    format: Format,
+) -> Arc<Image> {
+    let image = Image::new(
+        allocator,
+        QueueSharing::Concurrent.image_create_info(
+            &[graphics_queue.clone(), upload_queue.clone()],
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent: [extent[0], extent[1], 1],
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+        ),
+        AllocationCreateInfo::default(),
+    )
+    .unwrap();
+    GpuMemoryBudget::track_image(&image);
+    image
+}
+
+/// Submits the next slice of the current [`TextureUploads`] upload, reports progress of finished
+/// slices and registers completed textures.
+pub(crate) fn upload_texture_slices(
+    mut uploads: ResMut<TextureUploads>,
+    mut registry: ResMut<TextureRegistry>,
+    context: Res<BevyVulkanoContext>,
+    mut progress_events: EventWriter<TextureUploadProgress>,
+) {
+    profile_scope!("bevy_vulkano::texture_upload_slices");
+    let uploads = &mut *uploads;
+    let Some(upload) = uploads.queued.front_mut() else {
+        return;
+    };
+    let queue = context.context.compute_queue().clone();
+
+    if upload.submitted_rows < upload.block_rows {
+        let rows_per_frame = (uploads.bytes_per_frame / upload.row_bytes.max(1)).max(1) as u32;
+        let rows = rows_per_frame.min(upload.block_rows - upload.submitted_rows);
+        let start = (upload.submitted_rows as u64 * upload.row_bytes) as usize;
+        let end = start + (rows as u64 * upload.row_bytes) as usize;
+        let staging = Buffer::from_iter(
+            context.context.memory_allocator().clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            upload.data[start..end].iter().copied(),
+        )
+        .unwrap();
+        GpuMemoryBudget::track_buffer(staging.buffer());
+        let command_buffer_allocator = uploads.command_buffer_allocator.get_or_insert_with(|| {
+            StandardCommandBufferAllocator::new(queue.device().clone(), Default::default())
+        });
+        let mut builder = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let extent = upload.image.extent();
+        let block_height = upload.image.format().block_extent()[1];
+        let offset_y = upload.submitted_rows * block_height;
+        builder
+            .copy_buffer_to_image(CopyBufferToImageInfo {
+                regions: [BufferImageCopy {
+                    image_subresource: upload.image.subresource_layers(),
+                    image_offset: [0, offset_y, 0],
+                    image_extent: [
+                        extent[0],
+                        (rows * block_height).min(extent[1] - offset_y),
+                        1,
+                    ],
+                    ..Default::default()
+                }]
+                .into(),
+                ..CopyBufferToImageInfo::buffer_image(staging, upload.image.clone())
+            })
+            .unwrap();
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit texture upload slice {:?}: rows {}..{} of {} on queue family {}",
+            upload.key,
+            upload.submitted_rows,
+            upload.submitted_rows + rows,
+            upload.block_rows,
+            queue.queue_family_index()
+        );
+        let fence = sync::now(queue.device().clone())
+            .then_execute(queue.clone(), command_buffer)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap();
+        upload.submitted_rows += rows;
+        upload.in_flight.push_back(((end - start) as u64, fence));
+    }
+
+    let mut progressed = false;
+    while let Some((bytes, fence)) = upload.in_flight.front() {
+        if !fence.is_signaled().unwrap_or(false) {
+            break;
+        }
+        upload.uploaded_bytes += *bytes;
+        // Waiting on a signaled fence returns immediately and releases the slice's staging buffer
+        let _ = fence.wait(None);
+        upload.in_flight.pop_front();
+        progressed = true;
+    }
+    if !progressed {
+        return;
+    }
+    progress_events.send(TextureUploadProgress {
+        id: upload.id,
+        key: upload.key.clone(),
+        uploaded_bytes: upload.uploaded_bytes,
+        total_bytes: upload.total_bytes(),
+    });
+    if upload.uploaded_bytes >= upload.total_bytes() {
+        let upload = uploads.queued.pop_front().unwrap();
+        registry.insert_reserved(
+            upload.id,
+            &upload.key,
+            ImageView::new_default(upload.image).unwrap(),
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use bevy::{
+    app::{App, Plugin, PreUpdate},
+    prelude::{resource_exists, IntoSystemConfigs},
+    time::Time,
+};
+
+use crate::{
+    sprite_animation::animate_sprites, texture_upload::upload_texture_slices,
+    TextureUploadProgress, TextureUploads,
+};
+
+/// Registers the [`TextureUploads`] and animates [`SpriteAnimation`](crate::SpriteAnimation)s.
+/// Added by [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin) with feature `texture_utils`.
+pub struct VulkanoTextureUtilsPlugin;
+
+impl Plugin for VulkanoTextureUtilsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TextureUploads>()
+            .add_event::<TextureUploadProgress>()
+            .add_systems(
+                PreUpdate,
+                (
+                    upload_texture_slices,
+                    animate_sprites.run_if(resource_exists::<Time>()),
+                ),
+            );
+    }
+}
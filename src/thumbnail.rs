@@ -0,0 +1,199 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::log::warn;
+use image::RgbaImage;
+use vulkano::{
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, BlitImageInfo,
+        CommandBufferUsage,
+    },
+    format::{Format, FormatFeatures, NumericFormat},
+    image::{sampler::Filter, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+    sync::GpuFuture,
+};
+
+use crate::{
+    capture::ImageReadback,
+    frame_hooks::{FrameHook, FrameHookContext},
+    profiling::profile_scope,
+    trace_frame::trace_vk,
+};
+
+#[derive(Default)]
+struct ThumbnailState {
+    /// Readback of the last thumbnail and its capture time, until the GPU has finished it.
+    pending: Option<(ImageReadback, Instant)>,
+    latest: Option<(RgbaImage, Instant)>,
+}
+
+/// Thumbnails generated by a [`ThumbnailHook`]. Clones share the same thumbnails.
+#[derive(Clone, Default)]
+pub struct Thumbnails {
+    state: Arc<Mutex<ThumbnailState>>,
+}
+
+impl Thumbnails {
+    /// The most recent thumbnail which the GPU has finished, with the time it was captured.
+    pub fn latest(&self) -> Option<(RgbaImage, Instant)> {
+        let mut state = self.state.lock().unwrap();
+        if let Some((readback, captured_at)) = &state.pending {
+            if let Some(image) = readback.to_rgba_image() {
+                state.latest = Some((image, *captured_at));
+                state.pending = None;
+            }
+        }
+        state.latest.clone()
+    }
+}
+
+/// A [`FrameHook`] downscaling the swapchain image into a small thumbnail every `interval`, e.g.
+/// for save game thumbnails or window previews. The downscale is a linear blit on the GPU, so
+/// only the thumbnail is copied to the host.
+///
+/// ```ignore
+/// let (hook, thumbnails) = ThumbnailHook::new([256, 144], Duration::from_secs(5));
+/// vulkano_window.renderer.add_frame_hook(hook);
+/// // Later
+/// if let Some((thumbnail, _)) = thumbnails.latest() {
+///     thumbnail.save("save_game.png")?;
+/// }
+/// ```
+pub struct ThumbnailHook {
+    extent: [u32; 2],
+    interval: Duration,
+    last_capture: Option<Instant>,
+    target: Option<Arc<Image>>,
+    command_buffer_allocator: Option<StandardCommandBufferAllocator>,
+    thumbnails: Thumbnails,
+}
+
+impl ThumbnailHook {
+    /// Creates a hook generating thumbnails of `extent` every `interval`, and the handle to read
+    /// them from.
+    pub fn new(extent: [u32; 2], interval: Duration) -> (ThumbnailHook, Thumbnails) {
+        let thumbnails = Thumbnails::default();
+        (
+            ThumbnailHook {
+                extent: [extent[0].max(1), extent[1].max(1)],
+                interval,
+                last_capture: None,
+                target: None,
+                command_buffer_allocator: None,
+                thumbnails: thumbnails.clone(),
+            },
+            thumbnails,
+        )
+    }
+
+    /// Target image of the blit, keeping the sRGB encoding of the swapchain so the thumbnail isn't
+    /// converted between color spaces.
+    fn target(
+        &mut self,
+        allocator: Arc<StandardMemoryAllocator>,
+        source_format: Format,
+    ) -> Arc<Image> {
+        let format = if source_format.numeric_format_color() == Some(NumericFormat::SRGB) {
+            Format::R8G8B8A8_SRGB
+        } else {
+            Format::R8G8B8A8_UNORM
+        };
+        if let Some(target) = self.target.as_ref().filter(|t| t.format() == format) {
+            return target.clone();
+        }
+        let target = Image::new(
+            allocator,
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent: [self.extent[0], self.extent[1], 1],
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap();
+        self.target = Some(target.clone());
+        target
+    }
+}
+
+impl FrameHook for ThumbnailHook {
+    fn before_present(
+        &mut self,
+        context: &FrameHookContext,
+        future: Box<dyn GpuFuture>,
+    ) -> Box<dyn GpuFuture> {
+        if self
+            .last_capture
+            .is_some_and(|last| last.elapsed() < self.interval)
+        {
+            return future;
+        }
+        profile_scope!("bevy_vulkano::thumbnail");
+        let now = Instant::now();
+        self.last_capture = Some(now);
+        let source = context.swapchain_image_view.image().clone();
+        let blit_src = context
+            .graphics_queue
+            .device()
+            .physical_device()
+            .format_properties(source.format())
+            .map(|p| {
+                p.optimal_tiling_features
+                    .intersects(FormatFeatures::BLIT_SRC)
+            })
+            .unwrap_or(false);
+        if !source.usage().intersects(ImageUsage::TRANSFER_SRC) || !blit_src {
+            warn!(
+                "Swapchain image of window {:?} can't be downscaled to a thumbnail (format {:?}, \
+                 usage {:?})",
+                context.window.id(),
+                source.format(),
+                source.usage()
+            );
+            return future;
+        }
+        let target = self.target(context.memory_allocator.clone(), source.format());
+        let readback = ImageReadback::new(
+            context.memory_allocator.clone(),
+            self.extent,
+            target.format(),
+        );
+        let command_buffer_allocator = self.command_buffer_allocator.get_or_insert_with(|| {
+            StandardCommandBufferAllocator::new(
+                context.graphics_queue.device().clone(),
+                Default::default(),
+            )
+        });
+        let mut builder = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            context.graphics_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .blit_image(BlitImageInfo {
+                filter: Filter::Linear,
+                ..BlitImageInfo::images(source, target.clone())
+            })
+            .unwrap();
+        readback.record(&mut builder, target).unwrap();
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit thumbnail of window {:?}, image {} ({:?}) on queue family {}",
+            context.window.id(),
+            context.image_index,
+            self.extent,
+            context.graphics_queue.queue_family_index()
+        );
+        self.thumbnails.state.lock().unwrap().pending = Some((readback, now));
+        future
+            .then_execute(context.graphics_queue.clone(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
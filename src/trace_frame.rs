@@ -0,0 +1,94 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use bevy::{
+    log::{info, warn},
+    prelude::{ResMut, Resource},
+};
+
+static TRACING: AtomicBool = AtomicBool::new(false);
+static TRACED_FRAME: AtomicU64 = AtomicU64::new(0);
+static CORRELATION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Logs submissions, presents and swapchain operations made through the crate's APIs for a single
+/// frame, a lightweight alternative to API dump layers when debugging synchronization issues
+/// remotely. Only available in debug builds.
+///
+/// Each logged operation gets a correlation id, which related operations refer to, e.g. a present
+/// names the acquire of its image.
+///
+/// ```ignore
+/// fn trace_on_f12(keys: Res<Input<KeyCode>>, mut trace: ResMut<TraceFrame>) {
+///     if keys.just_pressed(KeyCode::F12) {
+///         trace.request();
+///     }
+/// }
+/// ```
+#[derive(Resource, Default)]
+pub struct TraceFrame {
+    requested: bool,
+    frames_traced: u64,
+}
+
+impl TraceFrame {
+    /// Traces the next frame.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Whether a frame is currently being traced.
+    pub fn is_tracing() -> bool {
+        cfg!(debug_assertions) && TRACING.load(Ordering::Relaxed)
+    }
+
+    /// Number of frames traced so far.
+    pub fn frames_traced(&self) -> u64 {
+        self.frames_traced
+    }
+}
+
+/// Logs a traced operation, returning its correlation id.
+pub(crate) fn trace(args: fmt::Arguments) -> u64 {
+    let id = CORRELATION_ID.fetch_add(1, Ordering::Relaxed);
+    info!(
+        "[trace frame {} #{}] {}",
+        TRACED_FRAME.load(Ordering::Relaxed),
+        id,
+        args
+    );
+    id
+}
+
+/// Logs an operation when a frame is being traced, evaluating to its correlation id.
+macro_rules! trace_vk {
+    ($($arg:tt)*) => {
+        if $crate::TraceFrame::is_tracing() {
+            Some($crate::trace_frame::trace(format_args!($($arg)*)))
+        } else {
+            None
+        }
+    };
+}
+
+pub(crate) use trace_vk;
+
+/// Ends the previous traced frame and starts tracing if requested.
+pub(crate) fn begin_trace_frame(mut trace_frame: ResMut<TraceFrame>) {
+    if TRACING.swap(false, Ordering::Relaxed) {
+        info!("[trace frame {}] end", TRACED_FRAME.load(Ordering::Relaxed));
+    }
+    if !trace_frame.requested {
+        return;
+    }
+    trace_frame.requested = false;
+    if !cfg!(debug_assertions) {
+        warn!("Frame tracing is only available in debug builds");
+        return;
+    }
+    trace_frame.frames_traced += 1;
+    TRACED_FRAME.store(trace_frame.frames_traced, Ordering::Relaxed);
+    TRACING.store(true, Ordering::Relaxed);
+    info!("[trace frame {}] begin", trace_frame.frames_traced);
+}
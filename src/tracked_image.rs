@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use vulkano::{
+    image::{view::ImageView, ImageLayout, ImageUsage},
+    sync::GpuFuture,
+};
+
+use crate::trace_frame::trace_vk;
+
+/// How a pass accesses an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageAccess {
+    /// Not accessed yet, the contents are undefined.
+    Undefined,
+    /// Sampled in a shader, e.g. as input of a [`crate::FullscreenPass`].
+    Sampled,
+    /// Read or written as a storage image, e.g. by a [`crate::ComputePass`].
+    Storage,
+    /// Rendered to, e.g. as target of a [`crate::FullscreenPass`] or of the gui.
+    ColorAttachment,
+    /// Copied or blitted from, e.g. by [`crate::ImageReadback`].
+    TransferSrc,
+    /// Copied or blitted to, e.g. by uploads.
+    TransferDst,
+}
+
+impl ImageAccess {
+    /// Layout the image is in during the access.
+    pub fn layout(self) -> ImageLayout {
+        match self {
+            ImageAccess::Undefined => ImageLayout::Undefined,
+            ImageAccess::Sampled => ImageLayout::ShaderReadOnlyOptimal,
+            ImageAccess::Storage => ImageLayout::General,
+            ImageAccess::ColorAttachment => ImageLayout::ColorAttachmentOptimal,
+            ImageAccess::TransferSrc => ImageLayout::TransferSrcOptimal,
+            ImageAccess::TransferDst => ImageLayout::TransferDstOptimal,
+        }
+    }
+
+    /// Usage the image must have been created with for the access.
+    pub fn required_usage(self) -> ImageUsage {
+        match self {
+            ImageAccess::Undefined => ImageUsage::empty(),
+            ImageAccess::Sampled => ImageUsage::SAMPLED,
+            ImageAccess::Storage => ImageUsage::STORAGE,
+            ImageAccess::ColorAttachment => ImageUsage::COLOR_ATTACHMENT,
+            ImageAccess::TransferSrc => ImageUsage::TRANSFER_SRC,
+            ImageAccess::TransferDst => ImageUsage::TRANSFER_DST,
+        }
+    }
+}
+
+/// A user created image passed between the crate's passes, recording its last known access and
+/// layout.
+///
+/// Vulkano inserts the layout transitions and barriers between passes as long as each pass is
+/// submitted after the future of the previous one. [`TrackedImage::pass`] makes sure of that,
+/// checks that the image has the usage the next access needs before the pass fails validation, and
+/// traces the layout transition with [`crate::TraceFrame`].
+///
+/// ```ignore
+/// let mut scene = TrackedImage::new(scene_view);
+/// let after = scene.pass(before, ImageAccess::Storage, |before, view| {
+///     compute.set_image("output", view);
+///     compute.dispatch(before, extent, None::<()>)
+/// });
+/// let after = scene.pass(after, ImageAccess::Sampled, |before, view| {
+///     fullscreen.render(before, &[view], swapchain_view, None::<()>)
+/// });
+/// ```
+pub struct TrackedImage {
+    view: Arc<ImageView>,
+    last_access: ImageAccess,
+}
+
+impl TrackedImage {
+    pub fn new(view: Arc<ImageView>) -> TrackedImage {
+        TrackedImage {
+            view,
+            last_access: ImageAccess::Undefined,
+        }
+    }
+
+    pub fn view(&self) -> Arc<ImageView> {
+        self.view.clone()
+    }
+
+    /// The last access recorded with [`TrackedImage::pass`] or [`TrackedImage::set_access`].
+    pub fn last_access(&self) -> ImageAccess {
+        self.last_access
+    }
+
+    /// Layout of the image after its last access.
+    pub fn layout(&self) -> ImageLayout {
+        self.last_access.layout()
+    }
+
+    /// Records an access made outside of [`TrackedImage::pass`], e.g. in the user's own command
+    /// buffer.
+    pub fn set_access(&mut self, access: ImageAccess) {
+        self.last_access = access;
+    }
+
+    /// Runs `pass` accessing the image with `access` after `before_future`, which must include the
+    /// future of the image's previous pass, and returns the future of the pass.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image wasn't created with the usage `access` requires.
+    pub fn pass(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        access: ImageAccess,
+        pass: impl FnOnce(Box<dyn GpuFuture>, Arc<ImageView>) -> Box<dyn GpuFuture>,
+    ) -> Box<dyn GpuFuture> {
+        let usage = self.view.usage();
+        assert!(
+            usage.contains(access.required_usage()),
+            "image with usage {usage:?} can't be accessed as {access:?}, which requires {:?}",
+            access.required_usage()
+        );
+        if access != self.last_access {
+            trace_vk!(
+                "transition tracked image {:?} from {:?} to {:?}",
+                self.view.image().extent(),
+                self.last_access.layout(),
+                access.layout()
+            );
+        }
+        self.last_access = access;
+        pass(before_future, self.view.clone())
+    }
+}
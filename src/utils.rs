@@ -0,0 +1,110 @@
+use vulkano::{
+    device::physical::PhysicalDevice,
+    format::{Format, FormatFeatures},
+    image::ImageUsage,
+};
+
+/// 8 bit color formats, widely supported as color attachments and sampled images.
+pub const DEFAULT_COLOR_FORMATS: &[Format] = &[
+    Format::R8G8B8A8_UNORM,
+    Format::B8G8R8A8_UNORM,
+    Format::R8G8B8A8_SRGB,
+    Format::B8G8R8A8_SRGB,
+];
+
+/// Floating point color formats for HDR render targets.
+pub const DEFAULT_HDR_COLOR_FORMATS: &[Format] = &[
+    Format::R16G16B16A16_SFLOAT,
+    Format::B10G11R11_UFLOAT_PACK32,
+    Format::R32G32B32A32_SFLOAT,
+];
+
+/// Depth formats in order of precision. At least one of `D32_SFLOAT` and `D24_UNORM_S8_UINT` is
+/// guaranteed to be supported as a depth attachment.
+pub const DEFAULT_DEPTH_FORMATS: &[Format] = &[
+    Format::D32_SFLOAT,
+    Format::D32_SFLOAT_S8_UINT,
+    Format::D24_UNORM_S8_UINT,
+    Format::D16_UNORM,
+];
+
+/// Formats for storage images written by compute shaders. `R8G8B8A8_UNORM` is not supported for
+/// storage on some GPUs, while the float formats are required by the spec.
+pub const DEFAULT_STORAGE_FORMATS: &[Format] = &[
+    Format::R8G8B8A8_UNORM,
+    Format::R16G16B16A16_SFLOAT,
+    Format::R32G32B32A32_SFLOAT,
+];
+
+/// Format features an optimally tiled image needs for `usage`.
+pub fn format_features_for_usage(usage: ImageUsage) -> FormatFeatures {
+    let mut features = FormatFeatures::empty();
+    if usage.intersects(ImageUsage::TRANSFER_SRC) {
+        features |= FormatFeatures::TRANSFER_SRC;
+    }
+    if usage.intersects(ImageUsage::TRANSFER_DST) {
+        features |= FormatFeatures::TRANSFER_DST;
+    }
+    if usage.intersects(ImageUsage::SAMPLED) {
+        features |= FormatFeatures::SAMPLED_IMAGE;
+    }
+    if usage.intersects(ImageUsage::STORAGE) {
+        features |= FormatFeatures::STORAGE_IMAGE;
+    }
+    if usage.intersects(ImageUsage::COLOR_ATTACHMENT) {
+        features |= FormatFeatures::COLOR_ATTACHMENT;
+    }
+    if usage.intersects(ImageUsage::DEPTH_STENCIL_ATTACHMENT) {
+        features |= FormatFeatures::DEPTH_STENCIL_ATTACHMENT;
+    }
+    features
+}
+
+/// Whether optimally tiled images of `format` can be created with `usage` on the device.
+pub fn supports_format(
+    physical_device: &PhysicalDevice,
+    format: Format,
+    usage: ImageUsage,
+) -> bool {
+    physical_device
+        .format_properties(format)
+        .map(|properties| {
+            properties
+                .optimal_tiling_features
+                .contains(format_features_for_usage(usage))
+        })
+        .unwrap_or(false)
+}
+
+/// The first of `candidates` supporting `usage`, or `None` if none do.
+pub fn try_first_supported_format(
+    physical_device: &PhysicalDevice,
+    candidates: &[Format],
+    usage: ImageUsage,
+) -> Option<Format> {
+    candidates
+        .iter()
+        .copied()
+        .find(|format| supports_format(physical_device, *format, usage))
+}
+
+/// The first of `candidates` supporting `usage`. Use this instead of hard coding formats, as
+/// images with unsupported format and usage combinations may silently render black on some GPUs.
+///
+/// # Panics
+///
+/// Panics if none of the candidates are supported.
+pub fn first_supported_format(
+    physical_device: &PhysicalDevice,
+    candidates: &[Format],
+    usage: ImageUsage,
+) -> Format {
+    try_first_supported_format(physical_device, candidates, usage).unwrap_or_else(|| {
+        panic!(
+            "None of the formats {:?} support usage {:?} on {}",
+            candidates,
+            usage,
+            physical_device.properties().device_name
+        )
+    })
+}
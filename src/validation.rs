@@ -0,0 +1,221 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    log::{error, info, warn},
+    prelude::{Res, Resource},
+};
+use vulkano::{
+    instance::{
+        debug::{
+            DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessengerCallback,
+            DebugUtilsMessengerCreateInfo,
+        },
+        InstanceExtensions,
+    },
+    VulkanLibrary,
+};
+use vulkano_util::context::VulkanoConfig;
+
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// Environment variable listing extra instance layers to enable, separated by commas, e.g.
+/// `VK_LAYER_LUNARG_api_dump,VK_LAYER_LUNARG_gfxreconstruct`.
+pub const INSTANCE_LAYERS_ENV: &str = "BEVY_VULKANO_INSTANCE_LAYERS";
+
+/// How validation errors reported by `VK_LAYER_KHRONOS_validation` are handled, see
+/// [`BevyVulkanoSettings::validation`](crate::BevyVulkanoSettings::validation).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// Log validation messages.
+    Log,
+    /// Log validation messages and panic at the end of a frame which had validation errors, e.g.
+    /// to fail tests running render systems under validation in CI.
+    Panic,
+    /// Log validation messages and collect errors into [`ValidationErrors`] for tests to inspect.
+    Collect,
+}
+
+/// A validation error reported during the app's lifetime.
+#[derive(Debug, Clone)]
+pub struct ValidationMessage {
+    pub id_name: Option<String>,
+    pub message: String,
+}
+
+/// Validation errors reported since they were last taken. Inserted when
+/// [`BevyVulkanoSettings::validation`](crate::BevyVulkanoSettings::validation) is set.
+#[derive(Resource, Clone)]
+pub struct ValidationErrors {
+    mode: ValidationMode,
+    errors: Arc<Mutex<Vec<ValidationMessage>>>,
+}
+
+impl ValidationErrors {
+    pub fn mode(&self) -> ValidationMode {
+        self.mode
+    }
+
+    /// Returns the errors reported since the last call, clearing them.
+    pub fn take(&self) -> Vec<ValidationMessage> {
+        std::mem::take(&mut *self.errors.lock().unwrap())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.lock().unwrap().is_empty()
+    }
+
+    /// Panics with the reported errors if there are any.
+    pub fn assert_empty(&self) {
+        let errors = self.take();
+        if !errors.is_empty() {
+            panic!("{}", describe_errors(&errors));
+        }
+    }
+}
+
+fn describe_errors(errors: &[ValidationMessage]) -> String {
+    let mut description = format!("{} Vulkan validation errors:", errors.len());
+    for error in errors {
+        description.push_str(&format!(
+            "\n- [{}] {}",
+            error.id_name.as_deref().unwrap_or("unknown"),
+            error.message
+        ));
+    }
+    description
+}
+
+/// Names of the instance layers installed on the system.
+fn available_layers(library: &VulkanLibrary) -> Vec<String> {
+    library
+        .layer_properties()
+        .map(|layers| layers.map(|layer| layer.name().to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// Enables the layers listed in `extra_layers` and [`INSTANCE_LAYERS_ENV`] in `config`. All
+/// requested layers, including those already in `config.instance_create_info.enabled_layers`, are
+/// checked against the installed layers, and missing ones are dropped with a warning instead of
+/// failing instance creation.
+pub(crate) fn enable_instance_layers(config: &mut VulkanoConfig, extra_layers: &[String]) {
+    let env_layers = std::env::var(INSTANCE_LAYERS_ENV).unwrap_or_default();
+    let requested = config
+        .instance_create_info
+        .enabled_layers
+        .iter()
+        .cloned()
+        .chain(extra_layers.iter().cloned())
+        .chain(
+            env_layers
+                .split(',')
+                .map(|layer| layer.trim().to_string())
+                .filter(|layer| !layer.is_empty()),
+        )
+        .collect::<Vec<_>>();
+    if requested.is_empty() {
+        return;
+    }
+    let Ok(library) = VulkanLibrary::new() else {
+        return;
+    };
+    let available = available_layers(&library);
+    let mut enabled: Vec<String> = vec![];
+    for layer in requested {
+        if enabled.contains(&layer) {
+            continue;
+        }
+        if available.contains(&layer) {
+            info!("Enabling instance layer {layer}");
+            enabled.push(layer);
+        } else {
+            warn!(
+                "Instance layer {layer} is not installed and won't be enabled. Available layers: \
+                 {available:?}"
+            );
+        }
+    }
+    config.instance_create_info.enabled_layers = enabled;
+}
+
+/// Enables the validation layer and a debug messenger in `config`, returning the resource the
+/// messenger reports errors to. Validation is skipped with a warning when the layer isn't
+/// installed.
+pub(crate) fn enable_validation(
+    config: &mut VulkanoConfig,
+    mode: ValidationMode,
+) -> Option<ValidationErrors> {
+    let library = VulkanLibrary::new().ok()?;
+    if !available_layers(&library)
+        .iter()
+        .any(|layer| layer == VALIDATION_LAYER)
+    {
+        warn!(
+            "Validation requested, but {VALIDATION_LAYER} is not installed. Install the Vulkan \
+             SDK or your distribution's validation layers package"
+        );
+        return None;
+    }
+    if !config
+        .instance_create_info
+        .enabled_layers
+        .iter()
+        .any(|layer| layer == VALIDATION_LAYER)
+    {
+        config
+            .instance_create_info
+            .enabled_layers
+            .push(VALIDATION_LAYER.to_string());
+    }
+    config.instance_create_info.enabled_extensions = config
+        .instance_create_info
+        .enabled_extensions
+        .union(&InstanceExtensions {
+            ext_debug_utils: true,
+            ..InstanceExtensions::empty()
+        });
+
+    let errors = Arc::new(Mutex::new(vec![]));
+    let callback_errors = errors.clone();
+    // Safety: the callback doesn't make any Vulkan calls.
+    let callback = unsafe {
+        DebugUtilsMessengerCallback::new(move |severity, message_type, data| {
+            let id_name = data.message_id_name.map(str::to_string);
+            let message = data.message.to_string();
+            if severity.intersects(DebugUtilsMessageSeverity::ERROR) {
+                error!("[vulkan {:?}] {}", message_type, message);
+                callback_errors.lock().unwrap().push(ValidationMessage {
+                    id_name,
+                    message,
+                });
+            } else if severity.intersects(DebugUtilsMessageSeverity::WARNING) {
+                warn!("[vulkan {:?}] {}", message_type, message);
+            } else {
+                info!("[vulkan {:?}] {}", message_type, message);
+            }
+        })
+    };
+    config.debug_create_info = Some(DebugUtilsMessengerCreateInfo {
+        message_severity: DebugUtilsMessageSeverity::ERROR | DebugUtilsMessageSeverity::WARNING,
+        message_type: DebugUtilsMessageType::GENERAL
+            | DebugUtilsMessageType::VALIDATION
+            | DebugUtilsMessageType::PERFORMANCE,
+        ..DebugUtilsMessengerCreateInfo::user_callback(callback)
+    });
+    info!("Enabled {VALIDATION_LAYER} ({mode:?})");
+    Some(ValidationErrors {
+        mode,
+        errors,
+    })
+}
+
+/// Panics at the end of a frame which had validation errors in [`ValidationMode::Panic`], and
+/// discards errors in [`ValidationMode::Log`].
+pub(crate) fn check_validation_errors(validation_errors: Res<ValidationErrors>) {
+    match validation_errors.mode {
+        ValidationMode::Log => {
+            validation_errors.take();
+        }
+        ValidationMode::Panic => validation_errors.assert_empty(),
+        ValidationMode::Collect => {}
+    }
+}
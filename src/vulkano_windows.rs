@@ -2,44 +2,194 @@
 
 #![allow(clippy::field_reassign_with_default)]
 
+use std::{
+    any::{Any, TypeId},
+    sync::Arc,
+};
+
 use bevy::{
     log::warn,
-    prelude::Entity,
+    prelude::{Entity, Event},
     utils::HashMap,
-    window::{PresentMode, Window, WindowMode, WindowPosition, WindowResolution},
+    window::{CursorGrabMode, Window, WindowMode, WindowPosition, WindowResolution},
 };
 #[cfg(feature = "gui")]
-use egui_winit_vulkano::{Gui, GuiConfig};
-use vulkano_util::{
-    context::VulkanoContext,
-    renderer::VulkanoWindowRenderer,
-    window::{
-        WindowDescriptor as VulkanoWindowDescriptor,
-        WindowResizeConstraints as VulkanoWindowResizeConstraints,
-    },
-};
+use egui_winit_vulkano::{egui, Gui, GuiConfig};
+#[cfg(feature = "gui")]
+use vulkano::image::sampler::SamplerCreateInfo;
+use vulkano::{format::Format, swapchain::ColorSpace};
+use vulkano_util::context::VulkanoContext;
 use winit::{
     dpi::{LogicalSize, PhysicalPosition},
     monitor::MonitorHandle,
 };
 
-use crate::{config::BevyVulkanoSettings, converters::convert_window_level};
+use crate::{
+    compat::convert_window_level,
+    config::{BevyVulkanoSettings, CursorGrabFallback, VulkanoWindowConfig},
+    hdr::hdr_formats,
+    renderer::{SwapchainPreferences, VulkanoWindowRenderer},
+};
+#[cfg(feature = "gui")]
+use crate::{ImageTextureId, TextureRegistry};
 
 pub struct VulkanoWindow {
+    /// Per-window data of the app, dropped with the window before its renderer.
+    user_data: HashMap<TypeId, Box<dyn Any>>,
     pub renderer: VulkanoWindowRenderer,
+    /// The egui integration of the window. `None` for windows with [`NoGui`](crate::NoGui).
+    #[cfg(feature = "gui")]
+    pub gui: Option<Gui>,
+    /// Shared textures registered with this window's gui.
     #[cfg(feature = "gui")]
-    pub gui: Gui,
+    gui_textures: HashMap<ImageTextureId, egui::TextureId>,
 }
 
 impl VulkanoWindow {
     pub fn window(&self) -> &winit::window::Window {
         self.renderer.window()
     }
+
+    /// The `(format, color space)` pairs supported by the window's surface, e.g. to offer an HDR
+    /// option only on HDR capable displays, see
+    /// [`VulkanoWindowConfig::preferred_color_space`].
+    pub fn supported_surface_formats(&self) -> Vec<(Format, ColorSpace)> {
+        self.renderer.supported_surface_formats()
+    }
+
+    /// Captures the next presented frame of the window, see
+    /// [`VulkanoWindowRenderer::capture_next_frame`].
+    ///
+    /// ```ignore
+    /// let capture = vulkano_window.capture_next_frame();
+    /// // In a later frame
+    /// if let Ok(Some(image)) = capture.try_take() {
+    ///     image.save("frame.png").unwrap();
+    /// }
+    /// ```
+    #[cfg(feature = "capture")]
+    pub fn capture_next_frame(&mut self) -> crate::FrameCapture {
+        self.renderer.capture_next_frame()
+    }
+
+    /// Stores per-window data of type `T`, e.g. framebuffers, depth images or pipelines of the
+    /// window, returning the previous value. The data is dropped when the window closes, so it
+    /// doesn't need to be tracked in maps keyed by window entity.
+    pub fn insert_user_data<T: 'static>(&mut self, data: T) -> Option<T> {
+        self.user_data
+            .insert(TypeId::of::<T>(), Box::new(data))
+            .and_then(|previous| previous.downcast().ok())
+            .map(|previous| *previous)
+    }
+
+    pub fn get_user_data<T: 'static>(&self) -> Option<&T> {
+        self.user_data.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    pub fn get_user_data_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.user_data.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    /// The data of type `T`, inserted with `create` first if the window has none, e.g. to create
+    /// per-window resources lazily in a render system.
+    pub fn user_data_or_insert_with<T: 'static>(&mut self, create: impl FnOnce() -> T) -> &mut T {
+        self.user_data
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(create()))
+            .downcast_mut()
+            .unwrap()
+    }
+
+    pub fn remove_user_data<T: 'static>(&mut self) -> Option<T> {
+        self.user_data
+            .remove(&TypeId::of::<T>())
+            .and_then(|data| data.downcast().ok())
+            .map(|data| *data)
+    }
+
+    /// The egui texture id of a [`TextureRegistry`] texture for this window. The shared image is
+    /// registered with the window's gui on first use without copying it.
+    #[cfg(feature = "gui")]
+    pub fn gui_texture_id(
+        &mut self,
+        registry: &TextureRegistry,
+        id: ImageTextureId,
+        sampler_create_info: SamplerCreateInfo,
+    ) -> Option<egui::TextureId> {
+        if let Some(texture_id) = self.gui_textures.get(&id) {
+            return Some(*texture_id);
+        }
+        let view = registry.get(id)?;
+        let texture_id = self
+            .gui
+            .as_mut()?
+            .register_user_image_view(view, sampler_create_info);
+        self.gui_textures.insert(id, texture_id);
+        Some(texture_id)
+    }
+
+    /// Unregisters a shared texture from this window's gui, e.g. after removing it from the
+    /// [`TextureRegistry`].
+    #[cfg(feature = "gui")]
+    pub fn release_gui_texture(&mut self, id: ImageTextureId) {
+        if let Some(texture_id) = self.gui_textures.remove(&id) {
+            if let Some(gui) = &mut self.gui {
+                gui.unregister_user_image(texture_id);
+            }
+        }
+    }
+}
+
+/// Sent when the renderer of a window has been created. With
+/// [`BevyVulkanoSettings::deferred_renderers_per_frame`], this can be several frames after
+/// [`WindowCreated`](bevy::window::WindowCreated), and the window has no [`VulkanoWindow`] until
+/// then.
+#[derive(Event, Debug, Clone)]
+pub struct WindowRendererReady {
+    pub window: Entity,
+}
+
+/// Sent when the cursor grab mode requested for a window could not be set. With
+/// [`CursorGrabFallback::Substitute`], the other grab mode may have been used instead, e.g. so an
+/// FPS game can fall back to re-centering the cursor itself when it's only confined.
+#[derive(Event, Debug, Clone)]
+pub struct CursorGrabFailed {
+    pub window: Entity,
+    pub requested: CursorGrabMode,
+    /// Grab mode set instead of the requested one, if any.
+    pub substituted: Option<CursorGrabMode>,
+    pub error: String,
+}
+
+/// Sent when a resolution set on a window's `Window` component was zero, not finite or larger than
+/// the maximum image dimension of the device. The window is resized to `applied` instead and the
+/// component's resolution is corrected to match.
+#[derive(Event, Debug, Clone)]
+pub struct WindowResolutionClamped {
+    pub window: Entity,
+    /// Requested physical size.
+    pub requested: [u32; 2],
+    /// Physical size the window was resized to.
+    pub applied: [u32; 2],
+}
+
+/// A window whose renderer has not been created yet.
+struct PendingWindow {
+    entity: Entity,
+    winit_window: winit::window::Window,
+    preferences: SwapchainPreferences,
+    with_gui: bool,
 }
 
 #[derive(Default)]
 pub struct BevyVulkanoWindows {
     pub(crate) windows: HashMap<winit::window::WindowId, VulkanoWindow>,
+    /// Windows waiting for their renderer, in creation order.
+    pending: Vec<PendingWindow>,
+    /// Cursor grabs which failed when creating windows.
+    grab_failures: Vec<CursorGrabFailed>,
+    /// Window used as the main render window, see [`crate::MainRenderWindow`].
+    pub(crate) main_window: Option<Entity>,
     /// Maps entities to `winit` window identifiers.
     pub(crate) entity_to_winit: HashMap<Entity, winit::window::WindowId>,
     /// Maps `winit` window identifiers to entities.
@@ -51,136 +201,202 @@ pub struct BevyVulkanoWindows {
 }
 
 impl BevyVulkanoWindows {
+    /// Creates the winit window and renderer of `window`. `with_gui` is ignored without feature
+    /// `gui`.
     pub fn create_window(
         &mut self,
         event_loop: &winit::event_loop::EventLoopWindowTarget<()>,
         entity: Entity,
         window: &Window,
+        window_config: &VulkanoWindowConfig,
+        with_gui: bool,
         vulkano_context: &VulkanoContext,
-        _settings: &BevyVulkanoSettings,
+        settings: &BevyVulkanoSettings,
     ) -> &VulkanoWindow {
-        let mut winit_window_builder = winit::window::WindowBuilder::new();
-
-        winit_window_builder = match window.mode {
-            WindowMode::BorderlessFullscreen => winit_window_builder.with_fullscreen(Some(
-                winit::window::Fullscreen::Borderless(event_loop.primary_monitor()),
-            )),
-            WindowMode::Fullscreen => {
-                winit_window_builder.with_fullscreen(Some(winit::window::Fullscreen::Exclusive(
-                    get_best_videomode(&event_loop.primary_monitor().unwrap()),
-                )))
-            }
-            WindowMode::SizedFullscreen => winit_window_builder.with_fullscreen(Some(
-                winit::window::Fullscreen::Exclusive(get_fitting_videomode(
-                    &event_loop.primary_monitor().unwrap(),
-                    window.width() as u32,
-                    window.height() as u32,
-                )),
-            )),
-            WindowMode::Windowed => {
-                if let Some(position) = winit_window_position(
-                    &window.position,
-                    &window.resolution,
-                    event_loop.available_monitors(),
-                    event_loop.primary_monitor(),
-                    None,
-                ) {
-                    winit_window_builder = winit_window_builder.with_position(position);
-                }
-
-                let logical_size = LogicalSize::new(window.width(), window.height());
-                if let Some(sf) = window.resolution.scale_factor_override() {
-                    winit_window_builder.with_inner_size(logical_size.to_physical::<f64>(sf))
-                } else {
-                    winit_window_builder.with_inner_size(logical_size)
-                }
-            }
-        };
+        let winit_window = build_winit_window(event_loop, window);
+        self.initial_grab(entity, &winit_window, window, settings);
+        let preferences = swapchain_preferences(window, window_config, settings);
+        self.insert_renderer(
+            event_loop,
+            entity,
+            Arc::new(winit_window),
+            &preferences,
+            with_gui,
+            vulkano_context,
+            settings,
+        )
+    }
 
-        winit_window_builder = winit_window_builder
-            .with_window_level(convert_window_level(window.window_level))
-            .with_resizable(window.resizable)
-            .with_decorations(window.decorations)
-            .with_transparent(window.transparent);
+    /// Creates the winit window of `window`, deferring the creation of its renderer to
+    /// [`BevyVulkanoWindows::create_pending_renderers`].
+    pub fn create_window_deferred(
+        &mut self,
+        event_loop: &winit::event_loop::EventLoopWindowTarget<()>,
+        entity: Entity,
+        window: &Window,
+        window_config: &VulkanoWindowConfig,
+        with_gui: bool,
+        settings: &BevyVulkanoSettings,
+    ) -> &winit::window::Window {
+        let winit_window = build_winit_window(event_loop, window);
+        self.initial_grab(entity, &winit_window, window, settings);
+        self.entity_to_winit.insert(entity, winit_window.id());
+        self.winit_to_entity.insert(winit_window.id(), entity);
+        self.pending.push(PendingWindow {
+            entity,
+            winit_window,
+            preferences: swapchain_preferences(window, window_config, settings),
+            with_gui,
+        });
+        &self.pending.last().unwrap().winit_window
+    }
 
-        let constraints = window.resize_constraints.check_constraints();
-        let min_inner_size = LogicalSize {
-            width: constraints.min_width,
-            height: constraints.min_height,
-        };
-        let max_inner_size = LogicalSize {
-            width: constraints.max_width,
-            height: constraints.max_height,
-        };
+    /// Creates the renderers of up to `max_count` windows created with
+    /// [`BevyVulkanoWindows::create_window_deferred`], returning their entities.
+    pub fn create_pending_renderers(
+        &mut self,
+        event_loop: &winit::event_loop::EventLoopWindowTarget<()>,
+        vulkano_context: &VulkanoContext,
+        settings: &BevyVulkanoSettings,
+        max_count: usize,
+    ) -> Vec<Entity> {
+        let count = max_count.min(self.pending.len());
+        let pending = self.pending.drain(..count).collect::<Vec<_>>();
+        pending
+            .into_iter()
+            .map(|pending| {
+                self.insert_renderer(
+                    event_loop,
+                    pending.entity,
+                    Arc::new(pending.winit_window),
+                    &pending.preferences,
+                    pending.with_gui,
+                    vulkano_context,
+                    settings,
+                );
+                pending.entity
+            })
+            .collect()
+    }
 
-        let winit_window_builder =
-            if constraints.max_width.is_finite() && constraints.max_height.is_finite() {
-                winit_window_builder
-                    .with_min_inner_size(min_inner_size)
-                    .with_max_inner_size(max_inner_size)
-            } else {
-                winit_window_builder.with_min_inner_size(min_inner_size)
-            };
+    /// Drops the renderers and guis of all windows and creates new ones on `vulkano_context`,
+    /// keeping the winit windows. Per-window user data is dropped too, as it may hold resources of
+    /// the previous device. Returns the entities of the recreated windows.
+    pub(crate) fn recreate_renderers(
+        &mut self,
+        event_loop: &winit::event_loop::EventLoopWindowTarget<()>,
+        vulkano_context: &VulkanoContext,
+        settings: &BevyVulkanoSettings,
+        preferences: &HashMap<Entity, SwapchainPreferences>,
+    ) -> Vec<Entity> {
+        let windows = std::mem::take(&mut self.windows);
+        windows
+            .into_iter()
+            .filter_map(|(winit_id, vulkano_window)| {
+                let entity = self.winit_to_entity.get(&winit_id).copied()?;
+                #[cfg(feature = "gui")]
+                let with_gui = vulkano_window.gui.is_some();
+                #[cfg(not(feature = "gui"))]
+                let with_gui = false;
+                let winit_window = vulkano_window.renderer.shared_window();
+                // The swapchain of the previous renderer must be gone before creating a new one
+                drop(vulkano_window);
+                self.insert_renderer(
+                    event_loop,
+                    entity,
+                    winit_window,
+                    &preferences.get(&entity).cloned().unwrap_or_default(),
+                    with_gui,
+                    vulkano_context,
+                    settings,
+                );
+                Some(entity)
+            })
+            .collect()
+    }
 
-        let winit_window = winit_window_builder
-            .with_title(window.title.as_str())
-            .build(event_loop)
-            .unwrap();
+    /// Returns cursor grabs which failed when creating windows, clearing them. Used to emit
+    /// [`CursorGrabFailed`] events.
+    pub(crate) fn take_grab_failures(&mut self) -> Vec<CursorGrabFailed> {
+        std::mem::take(&mut self.grab_failures)
+    }
 
+    fn initial_grab(
+        &mut self,
+        entity: Entity,
+        winit_window: &winit::window::Window,
+        window: &Window,
+        settings: &BevyVulkanoSettings,
+    ) {
         // Do not set the grab mode on window creation if it's none, this can fail on mobile
-        if window.cursor.grab_mode != bevy::window::CursorGrabMode::None {
-            attempt_grab(&winit_window, window.cursor.grab_mode);
+        if window.cursor.grab_mode != CursorGrabMode::None {
+            if let Some(failure) = attempt_grab(
+                entity,
+                winit_window,
+                window.cursor.grab_mode,
+                settings.cursor_grab_fallback,
+            ) {
+                self.grab_failures.push(failure);
+            }
         }
+    }
 
-        winit_window.set_cursor_visible(window.cursor.visible);
+    /// Whether the renderer of a window has not been created yet.
+    pub fn is_pending(&self, entity: Entity) -> bool {
+        self.pending.iter().any(|pending| pending.entity == entity)
+    }
 
-        // Do not set the cursor hittest on window creation if it's false, as it will always fail on some
-        // platforms and log an unfixable warning.
-        if !window.cursor.hit_test {
-            if let Err(err) = winit_window.set_cursor_hittest(window.cursor.hit_test) {
-                warn!(
-                    "Could not set cursor hit test for window {:?}: {:?}",
-                    window.title, err
-                );
-            }
-        }
+    /// Number of windows waiting for their renderer.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
 
+    fn insert_renderer(
+        &mut self,
+        event_loop: &winit::event_loop::EventLoopWindowTarget<()>,
+        entity: Entity,
+        winit_window: Arc<winit::window::Window>,
+        preferences: &SwapchainPreferences,
+        with_gui: bool,
+        vulkano_context: &VulkanoContext,
+        settings: &BevyVulkanoSettings,
+    ) -> &VulkanoWindow {
         let vulkano_window = {
-            let pos = winit_window
-                .inner_position()
-                .ok()
-                .map(|p| [p.x as f32, p.y as f32]);
-            let window_renderer = VulkanoWindowRenderer::new(
+            let mut window_renderer = VulkanoWindowRenderer::with_shared_window(
                 vulkano_context,
                 winit_window,
-                &window_descriptor_to_vulkano_window_descriptor(window, pos),
-                move |ci| {
-                    ci.image_format = vulkano::format::Format::B8G8R8A8_SRGB;
-                    ci.min_image_count = ci.min_image_count.max(2);
-                },
+                preferences,
             );
+            window_renderer.set_driver_workarounds(settings.driver_workarounds.unwrap_or_default());
+            window_renderer.set_present_group(settings.present_group);
 
             #[cfg(feature = "gui")]
             {
-                let gui = Gui::new(
-                    event_loop,
-                    window_renderer.surface(),
-                    window_renderer.graphics_queue(),
-                    vulkano::format::Format::B8G8R8A8_SRGB,
-                    GuiConfig {
-                        is_overlay: _settings.is_gui_overlay,
-                        allow_srgb_render_target: true,
-                        ..Default::default()
-                    },
-                );
+                let gui = with_gui.then(|| {
+                    Gui::new(
+                        event_loop,
+                        window_renderer.surface(),
+                        window_renderer.graphics_queue(),
+                        window_renderer.swapchain_format(),
+                        GuiConfig {
+                            is_overlay: settings.is_gui_overlay,
+                            allow_srgb_render_target: true,
+                            ..Default::default()
+                        },
+                    )
+                });
                 VulkanoWindow {
+                    user_data: HashMap::default(),
                     renderer: window_renderer,
                     gui,
+                    gui_textures: HashMap::default(),
                 }
             }
             #[cfg(not(feature = "gui"))]
             {
+                let _ = (event_loop, with_gui);
                 VulkanoWindow {
+                    user_data: HashMap::default(),
                     renderer: window_renderer,
                 }
             }
@@ -218,16 +434,190 @@ impl BevyVulkanoWindows {
             .and_then(|winit_id| self.windows.get_mut(winit_id))
     }
 
+    /// Entity of the main render window, the window marked as [`crate::MainRenderWindow`] or
+    /// otherwise the primary window.
+    pub fn main_window_entity(&self) -> Option<Entity> {
+        self.main_window
+    }
+
+    /// Get the main render window, see [`BevyVulkanoWindows::main_window_entity`].
+    pub fn main_window(&self) -> Option<&VulkanoWindow> {
+        self.get_vulkano_window(self.main_window?)
+    }
+
+    /// Get the main render window mutably, see [`BevyVulkanoWindows::main_window_entity`].
+    pub fn main_window_mut(&mut self) -> Option<&mut VulkanoWindow> {
+        self.get_vulkano_window_mut(self.main_window?)
+    }
+
+    /// Iterate all windows with their entities.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &VulkanoWindow)> {
+        let winit_to_entity = &self.winit_to_entity;
+        self.windows
+            .iter()
+            .filter_map(|(winit_id, vulkano_window)| {
+                winit_to_entity
+                    .get(winit_id)
+                    .map(|entity| (*entity, vulkano_window))
+            })
+    }
+
+    /// Iterate all windows mutably with their entities.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut VulkanoWindow)> {
+        let winit_to_entity = &self.winit_to_entity;
+        self.windows
+            .iter_mut()
+            .filter_map(|(winit_id, vulkano_window)| {
+                winit_to_entity
+                    .get(winit_id)
+                    .map(|entity| (*entity, vulkano_window))
+            })
+    }
+
+    /// Entities of all windows with a renderer, like [`BevyVulkanoWindows::iter`]. Windows whose
+    /// renderer is still [pending](BevyVulkanoWindows::is_pending) are excluded.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.iter().map(|(entity, _)| entity)
+    }
+
+    /// Number of windows.
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
     /// Remove a window from winit.
     ///
     /// This should mostly just be called when the window is closing.
     pub fn remove_window(&mut self, entity: Entity) -> Option<VulkanoWindow> {
+        self.pending.retain(|pending| pending.entity != entity);
+        if self.main_window == Some(entity) {
+            self.main_window = None;
+        }
         let winit_id = self.entity_to_winit.remove(&entity)?;
         // Don't remove from winit_to_window_id, to track that we used to know about this winit window
         self.windows.remove(&winit_id)
     }
 }
 
+/// Builds the winit window of `window`.
+fn build_winit_window(
+    event_loop: &winit::event_loop::EventLoopWindowTarget<()>,
+    window: &Window,
+) -> winit::window::Window {
+    let mut winit_window_builder = winit::window::WindowBuilder::new();
+
+    winit_window_builder = match window.mode {
+        WindowMode::BorderlessFullscreen => winit_window_builder.with_fullscreen(Some(
+            winit::window::Fullscreen::Borderless(event_loop.primary_monitor()),
+        )),
+        WindowMode::Fullscreen => {
+            winit_window_builder.with_fullscreen(Some(winit::window::Fullscreen::Exclusive(
+                get_best_videomode(&event_loop.primary_monitor().unwrap()),
+            )))
+        }
+        WindowMode::SizedFullscreen => winit_window_builder.with_fullscreen(Some(
+            winit::window::Fullscreen::Exclusive(get_fitting_videomode(
+                &event_loop.primary_monitor().unwrap(),
+                window.width() as u32,
+                window.height() as u32,
+            )),
+        )),
+        WindowMode::Windowed => {
+            if let Some(position) = winit_window_position(
+                &window.position,
+                &window.resolution,
+                event_loop.available_monitors(),
+                event_loop.primary_monitor(),
+                None,
+            ) {
+                winit_window_builder = winit_window_builder.with_position(position);
+            }
+
+            let logical_size = LogicalSize::new(window.width(), window.height());
+            if let Some(sf) = window.resolution.scale_factor_override() {
+                winit_window_builder.with_inner_size(logical_size.to_physical::<f64>(sf))
+            } else {
+                winit_window_builder.with_inner_size(logical_size)
+            }
+        }
+    };
+
+    winit_window_builder = winit_window_builder
+        .with_window_level(convert_window_level(window.window_level))
+        .with_resizable(window.resizable)
+        .with_decorations(window.decorations)
+        .with_transparent(window.transparent);
+
+    let constraints = window.resize_constraints.check_constraints();
+    let min_inner_size = LogicalSize {
+        width: constraints.min_width,
+        height: constraints.min_height,
+    };
+    let max_inner_size = LogicalSize {
+        width: constraints.max_width,
+        height: constraints.max_height,
+    };
+
+    let winit_window_builder =
+        if constraints.max_width.is_finite() && constraints.max_height.is_finite() {
+            winit_window_builder
+                .with_min_inner_size(min_inner_size)
+                .with_max_inner_size(max_inner_size)
+        } else {
+            winit_window_builder.with_min_inner_size(min_inner_size)
+        };
+
+    let winit_window = winit_window_builder
+        .with_title(window.title.as_str())
+        .build(event_loop)
+        .unwrap();
+
+    winit_window.set_cursor_visible(window.cursor.visible);
+
+    // Do not set the cursor hittest on window creation if it's false, as it will always fail on some
+    // platforms and log an unfixable warning.
+    if !window.cursor.hit_test {
+        if let Err(err) = winit_window.set_cursor_hittest(window.cursor.hit_test) {
+            warn!(
+                "Could not set cursor hit test for window {:?}: {:?}",
+                window.title, err
+            );
+        }
+    }
+
+    winit_window
+}
+
+pub(crate) fn swapchain_preferences(
+    window: &Window,
+    window_config: &VulkanoWindowConfig,
+    settings: &BevyVulkanoSettings,
+) -> SwapchainPreferences {
+    let image_color_space = window_config
+        .preferred_color_space
+        .unwrap_or(ColorSpace::SrgbNonLinear);
+    SwapchainPreferences {
+        present_mode: window.present_mode,
+        allow_tearing: settings.allow_tearing,
+        image_format: window_config
+            .image_format
+            .unwrap_or_else(|| hdr_formats(image_color_space)[0]),
+        image_color_space,
+        image_array_layers: window_config.image_array_layers,
+        format_selector: window_config.format_selector.clone(),
+        image_usage: settings.swapchain_image_usage,
+        min_image_count: window_config
+            .min_image_count
+            .or(settings.min_swapchain_image_count),
+        present_queue: settings.present_queue,
+        composite_alpha: window_config.composite_alpha,
+    }
+}
+
 /// Gets the "best" video mode which fits the given dimensions.
 ///
 /// The heuristic for "best" prioritizes width, height, and refresh rate in that order.
@@ -284,30 +674,59 @@ pub fn get_best_videomode(monitor: &winit::monitor::MonitorHandle) -> winit::mon
     modes.first().unwrap().clone()
 }
 
+/// Sets the cursor grab mode of `winit_window`. When the requested mode fails, the other grab
+/// mode is tried unless `fallback` is [`CursorGrabFallback::Strict`]. Returns the failure to report
+/// as [`CursorGrabFailed`].
 pub(crate) fn attempt_grab(
+    entity: Entity,
     winit_window: &winit::window::Window,
-    grab_mode: bevy::window::CursorGrabMode,
-) {
-    let grab_result = match grab_mode {
-        bevy::window::CursorGrabMode::None => {
-            winit_window.set_cursor_grab(winit::window::CursorGrabMode::None)
-        }
-        bevy::window::CursorGrabMode::Confined => winit_window
-            .set_cursor_grab(winit::window::CursorGrabMode::Confined)
-            .or_else(|_e| winit_window.set_cursor_grab(winit::window::CursorGrabMode::Locked)),
-        bevy::window::CursorGrabMode::Locked => winit_window
-            .set_cursor_grab(winit::window::CursorGrabMode::Locked)
-            .or_else(|_e| winit_window.set_cursor_grab(winit::window::CursorGrabMode::Confined)),
+    grab_mode: CursorGrabMode,
+    fallback: CursorGrabFallback,
+) -> Option<CursorGrabFailed> {
+    let (requested, substitute) = match grab_mode {
+        CursorGrabMode::None => (winit::window::CursorGrabMode::None, None),
+        CursorGrabMode::Confined => (
+            winit::window::CursorGrabMode::Confined,
+            Some((
+                CursorGrabMode::Locked,
+                winit::window::CursorGrabMode::Locked,
+            )),
+        ),
+        CursorGrabMode::Locked => (
+            winit::window::CursorGrabMode::Locked,
+            Some((
+                CursorGrabMode::Confined,
+                winit::window::CursorGrabMode::Confined,
+            )),
+        ),
     };
-
-    if let Err(err) = grab_result {
-        let err_desc = match grab_mode {
-            bevy::window::CursorGrabMode::Confined | bevy::window::CursorGrabMode::Locked => "grab",
-            bevy::window::CursorGrabMode::None => "ungrab",
-        };
-
-        bevy::utils::tracing::error!("Unable to {} cursor: {}", err_desc, err);
+    let err = winit_window.set_cursor_grab(requested).err()?;
+    let substituted = match (fallback, substitute) {
+        (CursorGrabFallback::Substitute, Some((substitute, winit_substitute))) => winit_window
+            .set_cursor_grab(winit_substitute)
+            .ok()
+            .map(|_| substitute),
+        _ => None,
+    };
+    match substituted {
+        Some(substitute) => warn!(
+            "Unable to grab cursor with {:?}, using {:?} instead: {}",
+            grab_mode, substitute, err
+        ),
+        None => {
+            let err_desc = match grab_mode {
+                CursorGrabMode::Confined | CursorGrabMode::Locked => "grab",
+                CursorGrabMode::None => "ungrab",
+            };
+            bevy::utils::tracing::error!("Unable to {} cursor: {}", err_desc, err);
+        }
     }
+    Some(CursorGrabFailed {
+        window: entity,
+        requested: grab_mode,
+        substituted,
+        error: err.to_string(),
+    })
 }
 
 pub fn winit_window_position(
@@ -367,44 +786,3 @@ pub fn winit_window_position(
         }
     }
 }
-
-fn window_descriptor_to_vulkano_window_descriptor(
-    wd: &Window,
-    position: Option<[f32; 2]>,
-) -> VulkanoWindowDescriptor {
-    let mut window_descriptor = VulkanoWindowDescriptor::default();
-    window_descriptor.width = wd.width();
-    window_descriptor.height = wd.height();
-    window_descriptor.position = position;
-    window_descriptor.resize_constraints = VulkanoWindowResizeConstraints {
-        min_width: wd.resize_constraints.min_width,
-        min_height: wd.resize_constraints.min_height,
-        max_width: wd.resize_constraints.max_width,
-        max_height: wd.resize_constraints.max_height,
-    };
-    window_descriptor.scale_factor_override = wd.resolution.scale_factor_override();
-    window_descriptor.title = wd.title.clone();
-    window_descriptor.present_mode = match wd.present_mode {
-        PresentMode::Fifo => vulkano::swapchain::PresentMode::Fifo,
-        PresentMode::Immediate => vulkano::swapchain::PresentMode::Immediate,
-        PresentMode::Mailbox => vulkano::swapchain::PresentMode::Mailbox,
-        PresentMode::AutoNoVsync => vulkano::swapchain::PresentMode::Immediate,
-        PresentMode::AutoVsync => vulkano::swapchain::PresentMode::FifoRelaxed,
-        PresentMode::FifoRelaxed => vulkano::swapchain::PresentMode::FifoRelaxed,
-    };
-    window_descriptor.resizable = wd.resizable;
-    window_descriptor.decorations = wd.decorations;
-    window_descriptor.cursor_visible = wd.cursor.visible;
-    window_descriptor.cursor_locked = match wd.cursor.grab_mode {
-        bevy::window::CursorGrabMode::Locked => true,
-        _ => false,
-    };
-    window_descriptor.mode = match wd.mode {
-        WindowMode::Windowed => vulkano_util::window::WindowMode::Windowed,
-        WindowMode::Fullscreen => vulkano_util::window::WindowMode::Fullscreen,
-        WindowMode::BorderlessFullscreen => vulkano_util::window::WindowMode::BorderlessFullscreen,
-        WindowMode::SizedFullscreen => vulkano_util::window::WindowMode::SizedFullscreen,
-    };
-    window_descriptor.transparent = wd.transparent;
-    window_descriptor
-}
@@ -0,0 +1,180 @@
+use std::{
+    fmt::Write as _,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    log::error,
+    prelude::{Entity, Event, EventWriter, NonSend, Res, ResMut, Resource},
+    utils::HashSet,
+};
+
+use crate::{BevyVulkanoWindows, GpuInfo};
+
+/// Sent by the [`FrameWatchdog`] when a window's frame has not finished on the GPU within the
+/// threshold after its swapchain image was acquired.
+#[derive(Event, Debug, Clone)]
+pub struct FrameHang {
+    pub window: Entity,
+    /// Time since the frame's image was acquired.
+    pub elapsed: Duration,
+}
+
+/// Detects stuck frames and GPU hangs. Insert this resource to enable the watchdog.
+///
+/// Each frame, the fence of every window's last presented frame is checked. Frames pending for
+/// longer than `threshold` are logged with device and swapchain state and reported with a
+/// [`FrameHang`] event. A background thread additionally logs when the main thread has not
+/// completed an update within `threshold`, e.g. while blocked waiting on a hung GPU.
+#[derive(Resource)]
+pub struct FrameWatchdog {
+    pub threshold: Duration,
+    /// Directory to write a diagnostics report to on each hang.
+    pub diagnostics_dir: Option<PathBuf>,
+    /// Windows whose current hang has already been reported.
+    reported: HashSet<Entity>,
+    heartbeat: Arc<Heartbeat>,
+}
+
+struct Heartbeat {
+    start: Instant,
+    /// Milliseconds since `start` at the last completed update.
+    last_update_ms: AtomicU64,
+    thread_started: AtomicBool,
+}
+
+impl Default for FrameWatchdog {
+    fn default() -> Self {
+        FrameWatchdog::new(Duration::from_secs(2))
+    }
+}
+
+impl FrameWatchdog {
+    pub fn new(threshold: Duration) -> FrameWatchdog {
+        FrameWatchdog {
+            threshold,
+            diagnostics_dir: None,
+            reported: HashSet::default(),
+            heartbeat: Arc::new(Heartbeat {
+                start: Instant::now(),
+                last_update_ms: AtomicU64::new(0),
+                thread_started: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Writes a diagnostics report into `dir` on each hang.
+    pub fn with_diagnostics_dir(mut self, dir: impl Into<PathBuf>) -> FrameWatchdog {
+        self.diagnostics_dir = Some(dir.into());
+        self
+    }
+
+    fn beat(&self) {
+        let elapsed = self.heartbeat.start.elapsed().as_millis() as u64;
+        self.heartbeat
+            .last_update_ms
+            .store(elapsed, Ordering::Relaxed);
+        if !self.heartbeat.thread_started.swap(true, Ordering::Relaxed) {
+            spawn_heartbeat_thread(self.heartbeat.clone(), self.threshold);
+        }
+    }
+}
+
+fn spawn_heartbeat_thread(heartbeat: Arc<Heartbeat>, threshold: Duration) {
+    let interval = (threshold / 2).max(Duration::from_millis(10));
+    thread::Builder::new()
+        .name("bevy_vulkano frame watchdog".to_string())
+        .spawn(move || {
+            let mut reported = false;
+            // Stop once the watchdog resource has been dropped
+            while Arc::strong_count(&heartbeat) > 1 {
+                thread::sleep(interval);
+                let now = heartbeat.start.elapsed().as_millis() as u64;
+                let stalled = Duration::from_millis(
+                    now.saturating_sub(heartbeat.last_update_ms.load(Ordering::Relaxed)),
+                );
+                if stalled > threshold && !reported {
+                    error!(
+                        "No frame has completed for {:?}, the main thread may be blocked on a \
+                         hung GPU",
+                        stalled
+                    );
+                }
+                reported = stalled > threshold;
+            }
+        })
+        .unwrap();
+}
+
+/// Checks pending frames of all windows and reports hangs.
+pub(crate) fn frame_watchdog(
+    mut watchdog: ResMut<FrameWatchdog>,
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+    gpu_info: Res<GpuInfo>,
+    mut hang_events: EventWriter<FrameHang>,
+) {
+    watchdog.beat();
+    let threshold = watchdog.threshold;
+    for (window, vulkano_window) in vulkano_windows.iter() {
+        let renderer = &vulkano_window.renderer;
+        let elapsed = match renderer.pending_frame_duration() {
+            Some(elapsed) if elapsed > threshold => elapsed,
+            _ => {
+                watchdog.reported.remove(&window);
+                continue;
+            }
+        };
+        if !watchdog.reported.insert(window) {
+            continue;
+        }
+
+        let mut report = String::new();
+        let _ = writeln!(report, "Frame of window {window:?} pending for {elapsed:?}");
+        let _ = writeln!(
+            report,
+            "Device: {} ({:?}), driver {}, api {}",
+            gpu_info.device_name,
+            gpu_info.vendor,
+            gpu_info.driver_version_string(),
+            gpu_info.api_version
+        );
+        let _ = writeln!(
+            report,
+            "Queues: graphics family {}, compute family {}",
+            renderer.graphics_queue().queue_family_index(),
+            renderer.compute_queue().queue_family_index()
+        );
+        let _ = writeln!(
+            report,
+            "Swapchain: {:?} {:?}, present mode {:?}, image {}",
+            renderer.swapchain_image_size(),
+            renderer.swapchain_format(),
+            renderer.present_mode(),
+            renderer.image_index()
+        );
+        error!("Possible GPU hang detected:\n{report}");
+
+        if let Some(dir) = &watchdog.diagnostics_dir {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|t| t.as_secs())
+                .unwrap_or_default();
+            let path = dir.join(format!("frame_hang_{timestamp}_{}.txt", window.index()));
+            if let Err(e) = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, report))
+            {
+                error!("Failed to write hang diagnostics to {:?}: {e}", path);
+            }
+        }
+
+        hang_events.send(FrameHang {
+            window,
+            elapsed,
+        });
+    }
+}
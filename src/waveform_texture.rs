@@ -0,0 +1,232 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, BufferImageCopy,
+        CommandBufferExecFuture, CommandBufferUsage, CopyBufferToImageInfo,
+    },
+    device::{DeviceOwned, Queue},
+    format::Format,
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    sync::{future::FenceSignalFuture, GpuFuture},
+};
+
+use crate::{profiling::profile_scope, trace_frame::trace_vk};
+
+type UploadFence = Arc<FenceSignalFuture<CommandBufferExecFuture<Box<dyn GpuFuture>>>>;
+
+struct WaveformSlot {
+    buffer: Subbuffer<[f32]>,
+    in_flight: Option<UploadFence>,
+}
+
+impl WaveformSlot {
+    /// Whether the GPU has finished with the last upload from this slot.
+    fn is_free(&mut self) -> bool {
+        match &self.in_flight {
+            None => true,
+            Some(fence) => {
+                if fence.is_signaled().unwrap_or(false) {
+                    let _ = fence.wait(None);
+                    self.in_flight = None;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+/// A `R32_SFLOAT` texture fed from a CPU-side sample ring, e.g. audio samples or any other
+/// `f32` signal, for visualizers.
+///
+/// Push samples as they arrive with [`WaveformTexture::push_samples`] and call
+/// [`WaveformTexture::upload`] once per frame. Each upload writes the latest `width` samples
+/// (oldest first) into one row of the texture through a small ring of staging buffers. With more
+/// than one row, rows are written in turn, so the texture holds a scrolling history, e.g. for a
+/// spectrogram; [`WaveformTexture::latest_row`] is the row written last. When all staging buffers
+/// are still in use by the GPU, the upload is skipped instead of stalling.
+///
+/// ```ignore
+/// let mut waveform = WaveformTexture::new(queue, allocator, 1024, 1, 3);
+/// waveform.push_samples(&audio_samples);
+/// let after = waveform.upload(before);
+/// // Sample `waveform.image()` with `texture(waveform, vec2(x, 0.5)).r`
+/// ```
+pub struct WaveformTexture {
+    queue: Arc<Queue>,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    image: Arc<ImageView>,
+    samples: VecDeque<f32>,
+    slots: Vec<WaveformSlot>,
+    next_slot: usize,
+    latest_row: Option<u32>,
+    skipped: u64,
+}
+
+impl WaveformTexture {
+    /// Creates a texture of `width` samples per row and `rows` rows, uploaded through
+    /// `staging_slots` staging buffers. Two or three slots avoid skipping uploads.
+    pub fn new(
+        queue: Arc<Queue>,
+        allocator: Arc<StandardMemoryAllocator>,
+        width: u32,
+        rows: u32,
+        staging_slots: usize,
+    ) -> WaveformTexture {
+        let width = width.max(1);
+        let image = ImageView::new_default(
+            Image::new(
+                allocator.clone(),
+                ImageCreateInfo {
+                    image_type: ImageType::Dim2d,
+                    format: Format::R32_SFLOAT,
+                    extent: [width, rows.max(1), 1],
+                    usage: ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST,
+                    ..Default::default()
+                },
+                AllocationCreateInfo::default(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let slots = (0..staging_slots.max(1))
+            .map(|_| WaveformSlot {
+                buffer: Buffer::new_slice::<f32>(
+                    allocator.clone(),
+                    BufferCreateInfo {
+                        usage: BufferUsage::TRANSFER_SRC,
+                        ..Default::default()
+                    },
+                    AllocationCreateInfo {
+                        memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                            | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                        ..Default::default()
+                    },
+                    width as u64,
+                )
+                .unwrap(),
+                in_flight: None,
+            })
+            .collect();
+        WaveformTexture {
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                queue.device().clone(),
+                Default::default(),
+            ),
+            queue,
+            image,
+            samples: VecDeque::with_capacity(width as usize),
+            slots,
+            next_slot: 0,
+            latest_row: None,
+            skipped: 0,
+        }
+    }
+
+    /// The texture. Sample from this in your pipelines.
+    pub fn image(&self) -> Arc<ImageView> {
+        self.image.clone()
+    }
+
+    /// Samples per row.
+    pub fn width(&self) -> u32 {
+        self.image.image().extent()[0]
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.image.image().extent()[1]
+    }
+
+    /// Row written by the latest upload, `None` before the first upload.
+    pub fn latest_row(&self) -> Option<u32> {
+        self.latest_row
+    }
+
+    /// Number of uploads skipped because every staging buffer was in use.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Appends samples to the ring, dropping the oldest samples beyond the texture width.
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        let width = self.width() as usize;
+        let samples = &samples[samples.len().saturating_sub(width)..];
+        let overflow = (self.samples.len() + samples.len()).saturating_sub(width);
+        self.samples.drain(..overflow);
+        self.samples.extend(samples);
+    }
+
+    /// Clears the sample ring, e.g. when playback stops.
+    pub fn clear_samples(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Uploads the sample ring into the next row after `before_future`. Missing samples are zero.
+    /// Returns a future which you should continue your frame from so that sampling the texture
+    /// waits on the upload.
+    pub fn upload(&mut self, before_future: Box<dyn GpuFuture>) -> Box<dyn GpuFuture> {
+        profile_scope!("bevy_vulkano::waveform_upload");
+        let width = self.width();
+        let row = self.latest_row.map_or(0, |row| (row + 1) % self.rows());
+        let slot = &mut self.slots[self.next_slot];
+        if !slot.is_free() {
+            self.skipped += 1;
+            return before_future;
+        }
+        match slot.buffer.write() {
+            Ok(mut write) => {
+                let padding = write.len() - self.samples.len();
+                write[..padding].fill(0.0);
+                for (target, sample) in write[padding..].iter_mut().zip(&self.samples) {
+                    *target = *sample;
+                }
+            }
+            Err(_) => {
+                self.skipped += 1;
+                return before_future;
+            }
+        }
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .copy_buffer_to_image(CopyBufferToImageInfo {
+                regions: [BufferImageCopy {
+                    image_subresource: self.image.image().subresource_layers(),
+                    image_offset: [0, row, 0],
+                    image_extent: [width, 1, 1],
+                    ..Default::default()
+                }]
+                .into(),
+                ..CopyBufferToImageInfo::buffer_image(
+                    slot.buffer.clone(),
+                    self.image.image().clone(),
+                )
+            })
+            .unwrap();
+        let command_buffer = builder.build().unwrap();
+        trace_vk!(
+            "submit waveform upload of row {} on queue family {}",
+            row,
+            self.queue.queue_family_index()
+        );
+        let fence: UploadFence = Arc::new(
+            before_future
+                .then_execute(self.queue.clone(), command_buffer)
+                .unwrap()
+                .then_signal_fence_and_flush()
+                .unwrap(),
+        );
+        slot.in_flight = Some(fence.clone());
+        self.next_slot = (self.next_slot + 1) % self.slots.len();
+        self.latest_row = Some(row);
+        fence.boxed()
+    }
+}
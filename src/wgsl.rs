@@ -0,0 +1,80 @@
+use std::{fmt, path::Path, sync::Arc};
+
+use naga::{
+    back::spv,
+    front::wgsl,
+    valid::{Capabilities, ValidationFlags, Validator},
+};
+use vulkano::{
+    device::Device,
+    shader::{ShaderModule, ShaderModuleCreateInfo},
+    Validated, VulkanError,
+};
+
+/// Errors from translating WGSL shaders at runtime.
+#[derive(Debug)]
+pub enum WgslCompileError {
+    Io(std::io::Error),
+    /// The source failed to parse, holding the formatted diagnostic.
+    Parse(String),
+    Validation(String),
+    /// Writing SPIR-V failed.
+    Translate(String),
+    Module(Validated<VulkanError>),
+}
+
+impl fmt::Display for WgslCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WgslCompileError::Io(e) => write!(f, "failed to read shader: {e}"),
+            WgslCompileError::Parse(e) => write!(f, "failed to parse WGSL: {e}"),
+            WgslCompileError::Validation(e) => write!(f, "invalid WGSL shader: {e}"),
+            WgslCompileError::Translate(e) => write!(f, "failed to translate WGSL to SPIR-V: {e}"),
+            WgslCompileError::Module(e) => write!(f, "failed to create shader module: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for WgslCompileError {}
+
+/// Translates WGSL `source` to SPIR-V with naga. All entry points of the source are kept and can
+/// be selected by name with [`ShaderModule::entry_point`].
+///
+/// Like wgpu, naga flips the Y axis of vertex outputs, so shaders shared with a wgpu project
+/// render the same way.
+pub fn wgsl_to_spirv(source: &str) -> Result<Vec<u32>, WgslCompileError> {
+    let module =
+        wgsl::parse_str(source).map_err(|e| WgslCompileError::Parse(e.emit_to_string(source)))?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| WgslCompileError::Validation(e.to_string()))?;
+    spv::write_vec(&module, &info, &spv::Options::default(), None)
+        .map_err(|e| WgslCompileError::Translate(e.to_string()))
+}
+
+/// Translates WGSL `source` into a shader module at runtime, e.g. to compile with the
+/// [`PipelineCompiler`](crate::PipelineCompiler).
+///
+/// ```ignore
+/// let module = compile_wgsl(device.clone(), include_str!("blit.wgsl"))?;
+/// let vs = module.entry_point("vs_main").unwrap();
+/// let fs = module.entry_point("fs_main").unwrap();
+/// ```
+pub fn compile_wgsl(
+    device: Arc<Device>,
+    source: &str,
+) -> Result<Arc<ShaderModule>, WgslCompileError> {
+    let words = wgsl_to_spirv(source)?;
+    // Safety: the SPIR-V was just produced by naga from validated WGSL
+    unsafe { ShaderModule::new(device, ShaderModuleCreateInfo::new(&words)) }
+        .map_err(WgslCompileError::Module)
+}
+
+/// Reads the WGSL shader at `path` and translates it at runtime.
+pub fn compile_wgsl_file(
+    device: Arc<Device>,
+    path: impl AsRef<Path>,
+) -> Result<Arc<ShaderModule>, WgslCompileError> {
+    let source = std::fs::read_to_string(path).map_err(WgslCompileError::Io)?;
+    compile_wgsl(device, &source)
+}
@@ -0,0 +1,33 @@
+use bevy::{
+    app::{App, First, Last, Plugin},
+    prelude::IntoSystemConfigs,
+};
+
+use crate::{
+    focused_window::update_focused_window,
+    present_mode_bench::run_present_mode_benchmark,
+    render_on_demand::request_on_demand_redraws,
+    system::{frame_presented_events, present_window_group},
+    FocusedWindow, PresentModeBenchFinished, PresentModeBenchmark,
+};
+
+/// Registers the [`FocusedWindow`], [`RenderOnDemand`](crate::RenderOnDemand) windows and the
+/// [`PresentModeBenchmark`]. Added by [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin) with
+/// feature `windowing`.
+pub struct VulkanoWindowingPlugin;
+
+impl Plugin for VulkanoWindowingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FocusedWindow>()
+            .init_resource::<PresentModeBenchmark>()
+            .add_event::<PresentModeBenchFinished>()
+            .add_systems(First, update_focused_window)
+            .add_systems(
+                Last,
+                (
+                    request_on_demand_redraws.after(present_window_group),
+                    run_present_mode_benchmark.after(frame_presented_events),
+                ),
+            );
+    }
+}